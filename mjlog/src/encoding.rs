@@ -0,0 +1,116 @@
+//! # encoding
+//!
+//! Detection of the text encoding of mjlog files, and decoding to UTF-8 for the parser.
+//!
+//! Tenhou writes UTF-8 without a BOM, but some older tools re-save the files with a BOM or in Shift_JIS.
+//! The encoding is taken from the BOM, then from the encoding attribute of the XML declaration.
+//! Without either, the text is UTF-8, or Shift_JIS if it is not valid UTF-8.
+//!
+//! Shift_JIS is decoded by `encoding_rs` with the Windows extensions (CP932), which Japanese Windows tools write.
+//! Malformed bytes fail with `InvalidEncoding` rather than being replaced.
+
+use crate::parser::{MjlogError, MjlogResult};
+use encoding_rs::SHIFT_JIS;
+use std::borrow::Cow;
+
+/// Text encoding of an mjlog file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MjlogEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    ShiftJis,
+}
+
+const BOM_UTF8: [u8; 3] = [0xef, 0xbb, 0xbf];
+const BOM_UTF16LE: [u8; 2] = [0xff, 0xfe];
+const BOM_UTF16BE: [u8; 2] = [0xfe, 0xff];
+
+// Value of the encoding attribute of the XML declaration at the start of the text.
+fn declared_encoding(bytes: &[u8]) -> Option<String> {
+    let decl = bytes.strip_prefix(b"<?xml")?;
+    let decl = String::from_utf8_lossy(&decl[..decl.windows(2).position(|x| x == b"?>")?]).to_string();
+    let rest = decl[decl.find("encoding")? + "encoding".len()..].trim_start().strip_prefix('=')?.trim_start();
+    let quote = rest.chars().next().filter(|&x| x == '"' || x == '\'')?;
+    let value = &rest[1..];
+    Some(value[..value.find(quote)?].to_string())
+}
+
+/// Detects the encoding of the text. See the module documentation.
+pub fn detect_encoding(bytes: &[u8]) -> MjlogResult<MjlogEncoding> {
+    if bytes.starts_with(&BOM_UTF8) {
+        return Ok(MjlogEncoding::Utf8);
+    }
+    if bytes.starts_with(&BOM_UTF16LE) {
+        return Ok(MjlogEncoding::Utf16Le);
+    }
+    if bytes.starts_with(&BOM_UTF16BE) {
+        return Ok(MjlogEncoding::Utf16Be);
+    }
+    match declared_encoding(bytes) {
+        Some(x) => match x.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" | "us-ascii" | "ascii" => Ok(MjlogEncoding::Utf8),
+            "shift_jis" | "shift-jis" | "sjis" | "x-sjis" | "windows-31j" | "cp932" | "ms932" => Ok(MjlogEncoding::ShiftJis),
+            _ => Err(MjlogError::UnsupportedEncoding(x)),
+        },
+        None if std::str::from_utf8(bytes).is_ok() => Ok(MjlogEncoding::Utf8),
+        None => Ok(MjlogEncoding::ShiftJis),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], to_u16: fn([u8; 2]) -> u16) -> Option<String> {
+    let units = bytes.chunks_exact(2);
+    if !units.remainder().is_empty() {
+        return None;
+    }
+    char::decode_utf16(units.map(|x| to_u16([x[0], x[1]]))).collect::<Result<String, _>>().ok()
+}
+
+/// Decodes the bytes of an mjlog file to UTF-8 in the detected encoding, without the BOM.
+pub fn decode_mjlog(bytes: &[u8]) -> MjlogResult<Cow<'_, str>> {
+    let encoding = detect_encoding(bytes)?;
+    let invalid = || MjlogError::InvalidEncoding(format!("{:?}", encoding));
+    match encoding {
+        MjlogEncoding::Utf8 => std::str::from_utf8(bytes.strip_prefix(&BOM_UTF8).unwrap_or(bytes)).map(Cow::Borrowed).map_err(|_| invalid()),
+        MjlogEncoding::Utf16Le => decode_utf16(&bytes[2..], u16::from_le_bytes).map(Cow::Owned).ok_or_else(invalid),
+        MjlogEncoding::Utf16Be => decode_utf16(&bytes[2..], u16::from_be_bytes).map(Cow::Owned).ok_or_else(invalid),
+        MjlogEncoding::ShiftJis => SHIFT_JIS.decode_without_bom_handling_and_without_replacement(bytes).ok_or_else(invalid),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_mjlogs, parse_mjlogs_bytes};
+
+    #[test]
+    fn test_decode_mjlog() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?><mjloggm ver="2.3"><GO type="169" lobby="0"/></mjloggm>"#;
+        let mjlogs = parse_mjlogs(xml).unwrap();
+
+        let bom: Vec<u8> = BOM_UTF8.iter().chain(xml.as_bytes()).copied().collect();
+        assert_eq!(parse_mjlogs_bytes(&bom).unwrap(), mjlogs);
+
+        let utf16: Vec<u8> = BOM_UTF16LE.into_iter().chain(xml.encode_utf16().flat_map(|x| x.to_le_bytes())).collect();
+        assert_eq!(detect_encoding(&utf16).unwrap(), MjlogEncoding::Utf16Le);
+        assert_eq!(parse_mjlogs_bytes(&utf16).unwrap(), mjlogs);
+
+        // "<!--ｱ-->" in Shift_JIS, declared or not.
+        let to_bytes = |x: String| -> Vec<u8> { x.chars().map(|x| x as u32 as u8).collect() };
+        let sjis = to_bytes(xml.replace("UTF-8", "Shift_JIS").replace("<GO", "<!--\u{b1}--><GO"));
+        assert_eq!(detect_encoding(&sjis).unwrap(), MjlogEncoding::ShiftJis);
+        assert!(decode_mjlog(&sjis).unwrap().contains("<!--ｱ-->"));
+        assert_eq!(parse_mjlogs_bytes(&sjis).unwrap(), mjlogs);
+        assert_eq!(detect_encoding(&sjis[sjis.iter().position(|&x| x == b'>').unwrap() + 1..]).unwrap(), MjlogEncoding::ShiftJis);
+
+        // "<!--漢-->", and "<!--①-->" of the CP932 extensions. A lead byte without the trail byte fails.
+        let kanji = to_bytes(xml.replace("UTF-8", "Shift_JIS").replace("<GO", "<!--\u{8a}\u{bf}--><!--\u{87}\u{40}--><GO"));
+        assert!(decode_mjlog(&kanji).unwrap().contains("<!--漢--><!--①-->"));
+        assert_eq!(parse_mjlogs_bytes(&kanji).unwrap(), mjlogs);
+        assert!(matches!(decode_mjlog(b"<?xml version=\"1.0\" encoding=\"Shift_JIS\"?>\x8a"), Err(MjlogError::InvalidEncoding(_))));
+        assert!(matches!(decode_mjlog(b"<?xml version=\"1.0\" encoding=\"Shift_JIS\"?>\xff"), Err(MjlogError::InvalidEncoding(_))));
+
+        assert!(matches!(detect_encoding(br#"<?xml version="1.0" encoding='EUC-JP'?>"#), Err(MjlogError::UnsupportedEncoding(x)) if x == "EUC-JP"));
+        assert!(matches!(decode_mjlog(b"<?xml version=\"1.0\" encoding=\"utf-8\"?>\xff"), Err(MjlogError::InvalidEncoding(_))));
+    }
+}
@@ -0,0 +1,223 @@
+//! # exporter
+//!
+//! Writes `Mjlog` back to mjlog XML, in the same attributes as tenhou.net.
+//!
+//! `parse_mjlogs(&export_mjlog(x))` gives `x` again. Names are percent-encoded byte by byte like tenhou,
+//! so the text is not the original file byte for byte, e.g. for attribute order of old logs.
+//! Tags unknown to the parser, kept as `Action::Unknown` (see `ParseOptions::keep_unknown_tags`), are written back as they were.
+
+use crate::model::*;
+use std::fmt::Write;
+
+fn join<T: ToString>(v: &[T]) -> String {
+    v.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn join_hais(v: &[Hai]) -> String {
+    join(&v.iter().map(|x| x.to_u8()).collect::<Vec<_>>())
+}
+
+fn encode_percent(s: &str) -> String {
+    s.bytes().map(|b| format!("%{:02X}", b)).collect()
+}
+
+fn escape(s: &str) -> String {
+    quick_xml::escape::escape(s).to_string()
+}
+
+fn export_go_type(settings: &GameSettings) -> u32 {
+    let room = settings.room as u32;
+    (settings.vs_human as u32) | (settings.no_red as u32) << 1 | (settings.no_kuitan as u32) << 2 | (settings.hanchan as u32) << 3 | (settings.sanma as u32) << 4 | (room & 2) << 4 | (settings.soku as u32) << 6 | (room & 1) << 7
+}
+
+fn export_lenient<T: ToString>(v: &[Option<T>]) -> String {
+    v.iter().map(|x| x.as_ref().map(|x| x.to_string()).unwrap_or_default()).collect::<Vec<_>>().join(",")
+}
+
+/// Encodes the meld to the "m" attribute of N and AGARI, the inverse of the parser.
+pub fn export_meld(meld: &Meld) -> u16 {
+    match meld {
+        Meld::Chii { combination, called_position } => {
+            let (h_min, h_mid, h_max) = combination;
+            let pict_type = h_min.kind() as u16;
+            let pattern = ((pict_type / 9) * 7 + pict_type % 9) * 3 + *called_position as u16;
+            let offset = |h: &Hai| (h.to_u8() % 4) as u16;
+            pattern << 10 | offset(h_max) << 7 | offset(h_mid) << 5 | offset(h_min) << 3 | 0x04 | Direction::Kamicha as u16
+        }
+        Meld::Pon { dir, combination, called, unused } => export_pon_or_kakan(*dir, combination, called, unused, 0x08),
+        Meld::Kakan { dir, combination, called, added } => export_pon_or_kakan(*dir, combination, called, added, 0x10),
+        Meld::Daiminkan { dir, hai } => (hai.to_u8() as u16) << 8 | *dir as u16,
+        Meld::Ankan { hai } => (hai.to_u8() as u16) << 8,
+    }
+}
+
+fn export_pon_or_kakan(dir: Direction, combination: &(Hai, Hai, Hai), called: &Hai, fourth: &Hai, flag: u16) -> u16 {
+    let called_index = [combination.0, combination.1, combination.2].iter().position(|x| x == called).unwrap_or_default() as u16;
+    let pattern = called.kind() as u16 * 3 + called_index;
+    pattern << 9 | ((fourth.to_u8() % 4) as u16) << 5 | flag | dir as u16
+}
+
+fn export_owari(owari: &Option<(Vec<GamePoint>, Vec<f64>)>) -> String {
+    match owari {
+        Some((points, results)) => {
+            let pairs: Vec<String> = points.iter().zip(results).map(|(p, r)| format!("{},{}", p, r)).collect();
+            format!(" owari=\"{}\"", pairs.join(","))
+        }
+        None => String::new(),
+    }
+}
+
+fn export_sc(before_points: &[GamePoint], delta_points: &[GamePoint]) -> String {
+    join(&before_points.iter().zip(delta_points).flat_map(|(b, d)| [*b, *d]).collect::<Vec<_>>())
+}
+
+fn export_ryuukyoku_reason(reason: ExtraRyuukyokuReason) -> &'static str {
+    match reason {
+        ExtraRyuukyokuReason::KyuusyuKyuuhai => "yao9",
+        ExtraRyuukyokuReason::SuuchaRiichi => "reach4",
+        ExtraRyuukyokuReason::SanchaHoura => "ron3",
+        ExtraRyuukyokuReason::SuukanSanra => "kan4",
+        ExtraRyuukyokuReason::SuufuuRenda => "kaze4",
+        ExtraRyuukyokuReason::NagashiMangan => "nm",
+    }
+}
+
+fn export_agari(x: &ActionAGARI) -> String {
+    let mut s = format!("<AGARI ba=\"{},{}\" hai=\"{}\"", x.honba, x.kyoutaku, join_hais(&x.hai));
+    if !x.m.is_empty() {
+        write!(s, " m=\"{}\"", join(&x.m.iter().map(export_meld).collect::<Vec<_>>())).unwrap();
+    }
+    write!(s, " machi=\"{}\" ten=\"{},{},{}\"", x.machi.to_u8(), x.ten.fu, x.ten.total, x.ten.limit as u8).unwrap();
+    if !x.yaku.is_empty() {
+        write!(s, " yaku=\"{}\"", join(&x.yaku.iter().flat_map(|&(y, han)| [y.to_u8(), han]).collect::<Vec<_>>())).unwrap();
+    }
+    if !x.yakuman.is_empty() {
+        write!(s, " yakuman=\"{}\"", join(&x.yakuman.iter().map(|y| y.to_u8()).collect::<Vec<_>>())).unwrap();
+    }
+    write!(s, " doraHai=\"{}\"", join_hais(&x.dora_hai)).unwrap();
+    if !x.dora_hai_ura.is_empty() {
+        write!(s, " doraHaiUra=\"{}\"", join_hais(&x.dora_hai_ura)).unwrap();
+    }
+    write!(s, " who=\"{}\" fromWho=\"{}\"", x.who.to_u8(), x.from_who.to_u8()).unwrap();
+    if let Some(pao_who) = x.pao_who {
+        write!(s, " paoWho=\"{}\"", pao_who.to_u8()).unwrap();
+    }
+    write!(s, " sc=\"{}\"{}/>", export_sc(&x.before_points, &x.delta_points), export_owari(&x.owari)).unwrap();
+    s
+}
+
+fn export_ryuukyoku(x: &ActionRYUUKYOKU) -> String {
+    let mut s = format!("<RYUUKYOKU ba=\"{},{}\" sc=\"{}\"", x.honba, x.kyoutaku, export_sc(&x.before_points, &x.delta_points));
+    for (i, hai) in [&x.hai0, &x.hai1, &x.hai2, &x.hai3].into_iter().enumerate() {
+        if let Some(hai) = hai {
+            write!(s, " hai{}=\"{}\"", i, join_hais(hai)).unwrap();
+        }
+    }
+    if let Some(reason) = x.reason {
+        write!(s, " type=\"{}\"", export_ryuukyoku_reason(reason)).unwrap();
+    }
+    write!(s, "{}/>", export_owari(&x.owari)).unwrap();
+    s
+}
+
+fn export_action(action: &Action) -> String {
+    match action {
+        Action::SHUFFLE(x) => format!("<SHUFFLE seed=\"{}\" ref=\"\"/>", escape(&x.seed)),
+        Action::GO(x) => format!("<GO type=\"{}\" lobby=\"{}\"/>", export_go_type(&x.settings), x.lobby),
+        Action::UN1(x) => {
+            let names: String = x.names.iter().enumerate().map(|(i, n)| format!(" n{}=\"{}\"", i, encode_percent(n))).collect();
+            let mut s = format!("<UN{} dan=\"{}\" rate=\"{}\"", names, export_lenient(&x.dan.iter().map(|d| d.map(|d| d as u8)).collect::<Vec<_>>()), export_lenient(&x.rate));
+            if !x.sx.is_empty() {
+                write!(s, " sx=\"{}\"", escape(&x.sx.join(","))).unwrap();
+            }
+            s + "/>"
+        }
+        Action::UN2(x) => format!("<UN n{}=\"{}\"/>", x.who.to_u8(), encode_percent(&x.name)),
+        Action::BYE(x) => format!("<BYE who=\"{}\"/>", x.who.to_u8()),
+        Action::TAIKYOKU(x) => format!("<TAIKYOKU oya=\"{}\"/>", x.oya.to_u8()),
+        Action::INIT(x) => {
+            let seed = &x.seed;
+            let mut s = format!(
+                "<INIT seed=\"{},{},{},{},{},{}\" ten=\"{}\" oya=\"{}\"",
+                seed.kyoku,
+                seed.honba,
+                seed.kyoutaku,
+                seed.dice.0,
+                seed.dice.1,
+                seed.dora_hyouji.to_u8(),
+                join(&x.ten),
+                x.oya.to_u8()
+            );
+            for (i, hai) in x.hai.iter().enumerate() {
+                write!(s, " hai{}=\"{}\"", i, join_hais(hai)).unwrap();
+            }
+            s + "/>"
+        }
+        Action::REACH1(x) => format!("<REACH who=\"{}\" step=\"1\"/>", x.who.to_u8()),
+        Action::REACH2(x) => format!("<REACH who=\"{}\" ten=\"{}\" step=\"2\"/>", x.who.to_u8(), join(&x.ten)),
+        Action::N(x) => format!("<N who=\"{}\" m=\"{}\"/>", x.who.to_u8(), export_meld(&x.m)),
+        Action::DORA(x) => format!("<DORA hai=\"{}\"/>", x.hai.to_u8()),
+        Action::AGARI(x) => export_agari(x),
+        Action::RYUUKYOKU(x) => export_ryuukyoku(x),
+        Action::DRAW(x) => format!("<{}{}/>", ['T', 'U', 'V', 'W'][x.who.to_u8() as usize % 4], x.hai.to_u8()),
+        Action::DISCARD(x) => format!("<{}{}/>", ['D', 'E', 'F', 'G'][x.who.to_u8() as usize % 4], x.hai.to_u8()),
+        Action::Unknown(x) => {
+            let attrs: String = x.attrs.iter().map(|(k, v)| format!(" {}=\"{}\"", k, escape(v))).collect();
+            format!("<{}{}/>", x.name, attrs)
+        }
+    }
+}
+
+/// mjlog XML of the game, one mjloggm tag.
+pub fn export_mjlog(mjlog: &Mjlog) -> String {
+    let mut s = format!("<mjloggm ver=\"{}\">", mjlog.ver);
+    for action in &mjlog.actions {
+        s += &export_action(action);
+    }
+    s + "</mjloggm>"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{conv_meld_from_u16, parse_mjlogs, parse_mjlogs_with, ParseOptions};
+
+    #[test]
+    fn test_export_meld() {
+        for m in 0..=u16::MAX {
+            // Pei nuki is not supported, and some bits of the others are unused.
+            let Ok(meld) = conv_meld_from_u16(m) else {
+                continue;
+            };
+            assert_eq!(conv_meld_from_u16(export_meld(&meld)).unwrap(), meld, "m={}", m);
+        }
+    }
+
+    #[test]
+    fn test_export_mjlog() {
+        let xml = r#"<mjloggm ver="2.3"><SHUFFLE seed="a&amp;b" ref=""/><GO type="169" lobby="0"/><UN n0="%E3%81%82" n1="%2C" n2="b" n3="c" dan="16,,18,0" rate="2000.5,1500,,1500" sx="M,F,C,M"/><TAIKYOKU oya="0"/><INIT seed="0,0,0,2,3,52" ten="250,250,250,250" oya="0" hai0="0,1,2,3,4,5,6,7,8,9,10,11,12" hai1="13,14,15,16,17,18,19,20,21,22,23,24,25" hai2="26,27,28,29,30,31,32,33,34,35,36,37,38" hai3="39,40,41,42,43,44,45,46,47,48,49,50,51"/><T53/><D53/><N who="1" m="19495"/><RYUUKYOKU ba="0,0" sc="250,0,250,0,250,0,250,0" type="yao9" owari="250,5.0,250,-5.0,250,10.0,250,-10.0"/></mjloggm>"#;
+        let mjlog = parse_mjlogs(xml).unwrap().remove(0);
+        let exported = export_mjlog(&mjlog);
+        assert_eq!(parse_mjlogs(&exported).unwrap().remove(0), mjlog);
+    }
+
+    #[test]
+    fn test_export_unknown_yaku() {
+        let xml = r#"<mjloggm ver="2.3"><AGARI ba="0,0" hai="0,1,2,3,4,5,6,7,8,9,10,11,12,13" machi="13" ten="30,1000,0" yaku="1,1,60,2" doraHai="52" who="0" fromWho="1" sc="250,10,250,-10,250,0,250,0"/></mjloggm>"#;
+        assert!(parse_mjlogs(xml).is_err());
+        let mjlog = parse_mjlogs_with(xml, &ParseOptions { lenient: true, ..Default::default() }).unwrap().remove(0);
+        let agari = mjlog.actions[0].as_agari().unwrap();
+        assert_eq!(agari.yaku, [(Yaku::Riichi, 1), (Yaku::Unknown(60), 2)]);
+        assert_eq!(export_mjlog(&mjlog), xml);
+    }
+
+    #[test]
+    fn test_export_unknown_tag() {
+        let xml = r#"<mjloggm ver="2.3"><GO type="169" lobby="0"/><FUTURE z="1" a="&lt;&amp;&quot;"/><EMPTY/></mjloggm>"#;
+        assert!(parse_mjlogs(xml).is_err());
+        let options = ParseOptions { keep_unknown_tags: true, ..Default::default() };
+        let mjlog = parse_mjlogs_with(xml, &options).unwrap().remove(0);
+        assert_eq!(mjlog.actions[1].as_unknown().unwrap().attrs[1], ("a".to_string(), "<&\"".to_string()));
+        assert_eq!(export_mjlog(&mjlog), xml);
+    }
+}
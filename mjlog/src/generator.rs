@@ -0,0 +1,787 @@
+//! # generator
+//!
+//! Builds random games from a seed, for tests and fixtures without real user data.
+//!
+//! The games follow the flow of tenhou: draws and discards, riichi, calls, kans with rinshan draws and new dora,
+//! wins including multiple ron, and exhaustive draws with noten payments, until the owari of the last round.
+//! Four-player walls come from the SHUFFLE seed, so `verify_wall` accepts the games.
+//!
+//! Yaku are not judged. A win has riichi, menzen tsumo, tanyao (or haku for open hands without kuitan),
+//! plus dora, aka dora and ura dora, with the points of the table. Furiten and kuikae are not checked either.
+//!
+//! # Usage
+//!
+//! ```ignore
+//! let options = GeneratorOptions { settings: GameSettings { sanma: true, ..GeneratorOptions::default().settings }, ..Default::default() };
+//! let mjlog = generate_mjlog(42, &options);
+//! ```
+
+use crate::model::*;
+use crate::replay::meld_hais;
+use crate::uma_oka::{final_results, placements, UmaOka};
+use crate::wall::WallGenerator;
+use base64::Engine;
+use num_traits::FromPrimitive;
+
+const NAMES: [&str; 8] = ["あいうえお", "Bob", "C,D", "<&\"'>", "%41", "ＮＡＮＡ", " space ", "名無し"];
+const SEED_PREFIX: &str = "mt19937ar-sha512-n288-base64,";
+const SEED_BYTES: usize = 624 * 4;
+const RED_FIVES: [u8; 3] = [16, 52, 88];
+const NUM_KINDS: usize = 34;
+
+type TileCounts = [u8; NUM_KINDS];
+
+/// Options of `generate_mjlog`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratorOptions {
+    /// Rules of the GO tag. `sanma`, `no_red` and `no_kuitan` change the play, the others are only recorded.
+    pub settings: GameSettings,
+    /// Rounds to play. The game ends earlier if a player goes below 0.
+    pub rounds: usize,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        GeneratorOptions {
+            settings: GameSettings {
+                vs_human: true,
+                hanchan: true,
+                ..GameSettings::default()
+            },
+            rounds: 8,
+        }
+    }
+}
+
+// xorshift64*, enough for test data and stable across platforms.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // splitmix64 of the seed, so that close seeds give different games and the state is never 0.
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Rng((z ^ (z >> 31)) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    // True with the probability 1/n.
+    fn one_in(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+}
+
+fn is_yaochuu(kind: usize) -> bool {
+    kind >= 27 || matches!(kind % 9, 0 | 8)
+}
+
+// The tiles are all melds, where the tiles before `kind` are already taken.
+fn is_melds(counts: &mut TileCounts, kind: usize) -> bool {
+    let Some(i) = (kind..NUM_KINDS).find(|&i| counts[i] > 0) else {
+        return true;
+    };
+    if counts[i] >= 3 {
+        counts[i] -= 3;
+        let ok = is_melds(counts, i);
+        counts[i] += 3;
+        if ok {
+            return true;
+        }
+    }
+    if i < 27 && i % 9 <= 6 && counts[i + 1] > 0 && counts[i + 2] > 0 {
+        (i..i + 3).for_each(|k| counts[k] -= 1);
+        let ok = is_melds(counts, i);
+        (i..i + 3).for_each(|k| counts[k] += 1);
+        return ok;
+    }
+    false
+}
+
+// Complete hand of melds and a pair, chiitoitsu or kokushi musou.
+fn is_complete(counts: &TileCounts) -> bool {
+    let num: usize = counts.iter().map(|&x| x as usize).sum();
+    if num % 3 != 2 {
+        return false;
+    }
+    if num == 14 && (counts.iter().filter(|&&x| x == 2).count() == 7 || (0..NUM_KINDS).all(|k| (counts[k] > 0) == is_yaochuu(k))) {
+        return true;
+    }
+    let mut counts = *counts;
+    (0..NUM_KINDS).any(|pair| {
+        if counts[pair] < 2 {
+            return false;
+        }
+        counts[pair] -= 2;
+        let ok = is_melds(&mut counts, 0);
+        counts[pair] += 2;
+        ok
+    })
+}
+
+fn is_tenpai(counts: &TileCounts) -> bool {
+    let mut counts = *counts;
+    // Waits are next to the tiles of the hand, except for kokushi musou.
+    let near = |counts: &TileCounts, k: usize| is_yaochuu(k) || (k.saturating_sub(2)..(k + 3).min(27)).any(|x| x / 9 == k / 9 && counts[x] > 0);
+    let waits: Vec<usize> = (0..NUM_KINDS).filter(|&k| near(&counts, k)).collect();
+    waits.into_iter().any(|k| {
+        counts[k] += 1;
+        let ok = is_complete(&counts);
+        counts[k] -= 1;
+        ok
+    })
+}
+
+// Tiles of the hand by the number of the same and nearby tiles, a cheap stand-in for the shanten number.
+fn usefulness(counts: &TileCounts, kind: usize) -> usize {
+    let near = [(-2, 1), (-1, 2), (1, 2), (2, 1)]
+        .into_iter()
+        .filter_map(|(d, weight)| {
+            let k = kind.checked_add_signed(d)?;
+            (kind < 27 && k < 27 && k / 9 == kind / 9).then(|| counts[k] as usize * weight)
+        })
+        .sum::<usize>();
+    (counts[kind] as usize - 1) * 3 + near
+}
+
+#[derive(Debug, Clone, Default)]
+struct Seat {
+    hand: Vec<Hai>,
+    melds: Vec<Meld>,
+    riichi: bool,
+}
+
+impl Seat {
+    fn counts(&self, extra: Option<Hai>) -> TileCounts {
+        let mut counts = [0; NUM_KINDS];
+        for h in self.hand.iter().chain(extra.iter()) {
+            counts[h.kind() as usize] += 1;
+        }
+        counts
+    }
+
+    fn is_agari(&self, extra: Option<Hai>) -> bool {
+        is_complete(&self.counts(extra))
+    }
+
+    fn is_tenpai(&self) -> bool {
+        is_tenpai(&self.counts(None))
+    }
+
+    fn is_closed(&self) -> bool {
+        self.melds.iter().all(|m| matches!(m, Meld::Ankan { .. }))
+    }
+
+    fn count_kind(&self, kind: u8) -> usize {
+        self.hand.iter().filter(|h| h.kind() == kind).count()
+    }
+
+    fn take(&mut self, hai: Hai) {
+        let pos = self.hand.iter().position(|&x| x == hai).expect("tile in the hand");
+        self.hand.remove(pos);
+    }
+
+    // Discards keeping the hand tenpai, for riichi.
+    fn tenpai_discards(&self) -> Vec<Hai> {
+        let mut ret: Vec<Hai> = vec![];
+        for &h in &self.hand {
+            if ret.iter().any(|x| x.kind() == h.kind()) {
+                continue;
+            }
+            let mut counts = self.counts(None);
+            counts[h.kind() as usize] -= 1;
+            if is_tenpai(&counts) {
+                ret.push(h);
+            }
+        }
+        ret
+    }
+
+    fn least_useful(&self, rng: &mut Rng) -> Hai {
+        let counts = self.counts(None);
+        let least = self.hand.iter().map(|h| usefulness(&counts, h.kind() as usize)).min().unwrap_or_default();
+        let candidates: Vec<Hai> = self.hand.iter().copied().filter(|h| usefulness(&counts, h.kind() as usize) == least).collect();
+        candidates[rng.below(candidates.len())]
+    }
+}
+
+// Pon in the same form as the parser gives, where the combination is the kind without the unused tile.
+fn pon(dir: Direction, called: Hai, unused: Hai) -> Meld {
+    let base = called.kind() * 4;
+    let mut hais = [Hai::new(base), Hai::new(base + 1), Hai::new(base + 2), Hai::new(base + 3)];
+    hais.swap(3, (unused.to_u8() % 4) as usize);
+    Meld::Pon {
+        dir,
+        combination: (hais[0], hais[1], hais[2]),
+        called,
+        unused,
+    }
+}
+
+// Base points of the hand and its limit.
+fn base_points(fu: u8, han: u8) -> (i32, ScoreRank) {
+    match han {
+        13.. => (8000, ScoreRank::Yakuman),
+        11.. => (6000, ScoreRank::Sanbaiman),
+        8.. => (4000, ScoreRank::Baiman),
+        6.. => (3000, ScoreRank::Haneman),
+        _ => match (fu as i32) << (han + 2) {
+            x if x >= 2000 => (2000, ScoreRank::Mangan),
+            x => (x, ScoreRank::Normal),
+        },
+    }
+}
+
+fn ceil_to_100(x: i32) -> i32 {
+    (x + 99) / 100 * 100
+}
+
+fn sorted(hais: &[Hai]) -> Vec<Hai> {
+    let mut v = hais.to_vec();
+    v.sort_by_key(|h| h.to_u8());
+    v
+}
+
+// Tiles of a round in the order of `Wall::tiles`: 14 tiles of the dead wall first, and the hands dealt from the end.
+struct Yama {
+    hands: Vec<Vec<Hai>>,
+    draws: Vec<Hai>,
+    rinshan: [Hai; 4],
+    dora_indicators: [Hai; 5],
+    ura_dora_indicators: [Hai; 5],
+    dice: (u8, u8),
+}
+
+impl Yama {
+    // Same layout as `Wall`. Three-player games leave out 2m to 8m.
+    fn new(tiles: &[Hai], dice: (u8, u8), oya: Player, num_players: u8) -> Self {
+        let n = num_players as usize;
+        let mut hands = vec![vec![]; n];
+        let mut next = tiles.iter().rev().copied();
+        for turn in 0..4 {
+            let count = if turn < 3 { 4 } else { 1 };
+            for p in 0..n {
+                hands[(oya.to_u8() as usize + p) % n].extend(next.by_ref().take(count));
+            }
+        }
+        Yama {
+            hands,
+            draws: tiles[14..tiles.len() - 13 * n].iter().rev().copied().collect(),
+            rinshan: [1, 0, 3, 2].map(|i| tiles[i]),
+            dora_indicators: [5, 7, 9, 11, 13].map(|i| tiles[i]),
+            ura_dora_indicators: [4, 6, 8, 10, 12].map(|i| tiles[i]),
+            dice,
+        }
+    }
+}
+
+// A round in progress.
+struct Round {
+    oya: Player,
+    seats: Vec<Seat>,
+    yama: Yama,
+    drawn: usize,
+    kans: usize,
+    dora: Vec<Hai>,
+}
+
+impl Round {
+    fn wall_left(&self) -> usize {
+        self.yama.draws.len() - self.kans - self.drawn
+    }
+}
+
+// How the round ended, for the next dealer and honba.
+enum RoundEnd {
+    Win { oya_won: bool },
+    Draw { oya_tenpai: bool },
+}
+
+// Progress of the game between rounds. Points are in units of 100, 4 seats even in three-player games like tenhou.
+struct Game {
+    rng: Rng,
+    walls: WallGenerator,
+    settings: GameSettings,
+    num_players: u8,
+    actions: Vec<Action>,
+    scores: Vec<GamePoint>,
+    kyoku: u8,
+    honba: u8,
+    kyoutaku: u8,
+}
+
+impl Game {
+    fn push(&mut self, action: Action) {
+        self.actions.push(action);
+    }
+
+    fn seat_range(&self) -> std::ops::Range<usize> {
+        0..self.num_players as usize
+    }
+
+    // Direction of the discarder from the caller. Three-player games have no toimen.
+    fn direction(&self, caller: Player, discarder: Player) -> Direction {
+        match (discarder.distance_from(caller, self.num_players), self.num_players) {
+            (1, _) => Direction::Shimocha,
+            (2, 3) | (3, _) => Direction::Kamicha,
+            (2, _) => Direction::Toimen,
+            _ => Direction::SelfSeat,
+        }
+    }
+
+    fn start_round(&mut self) -> Round {
+        let oya = Player::new(self.kyoku % self.num_players);
+        let wall = self.walls.next_wall();
+        let tiles: Vec<Hai> = wall.tiles.iter().copied().filter(|h| !self.settings.sanma || !(1..=7).contains(&h.kind())).collect();
+        let yama = Yama::new(&tiles, wall.dice, oya, self.num_players);
+        let mut hai = vec![vec![]; 4];
+        for (i, hand) in yama.hands.iter().enumerate() {
+            hai[i] = sorted(hand);
+        }
+
+        self.push(Action::INIT(ActionINIT {
+            seed: InitSeed {
+                kyoku: self.kyoku,
+                honba: self.honba,
+                kyoutaku: self.kyoutaku,
+                dice: yama.dice,
+                dora_hyouji: yama.dora_indicators[0],
+            },
+            ten: self.scores.clone(),
+            oya,
+            hai: hai.clone(),
+        }));
+
+        Round {
+            oya,
+            seats: hai.into_iter().take(self.num_players as usize).map(|hand| Seat { hand, ..Seat::default() }).collect(),
+            dora: vec![yama.dora_indicators[0]],
+            yama,
+            drawn: 0,
+            kans: 0,
+        }
+    }
+
+    fn kan(&mut self, round: &mut Round, who: Player, m: Meld) {
+        round.seats[who.to_u8() as usize].melds.push(m.clone());
+        self.push(Action::N(ActionN { who, m }));
+        round.kans += 1;
+        let hai = round.yama.dora_indicators[round.kans];
+        round.dora.push(hai);
+        self.push(Action::DORA(ActionDORA { hai }));
+    }
+
+    // Ankan or kakan after a draw, if the player chooses to.
+    fn try_kan(&mut self, round: &mut Round, who: Player) -> bool {
+        let seat = &round.seats[who.to_u8() as usize];
+        if round.kans >= 4 || seat.riichi || round.wall_left() == 0 {
+            return false;
+        }
+        if let Some(kind) = (0..NUM_KINDS as u8).find(|&k| seat.count_kind(k) == 4) {
+            if self.rng.one_in(2) {
+                round.seats[who.to_u8() as usize].hand.retain(|h| h.kind() != kind);
+                self.kan(round, who, Meld::Ankan { hai: Hai::new(kind * 4) });
+                return true;
+            }
+        }
+        let kakan = seat.melds.iter().position(|m| matches!(m, Meld::Pon { unused, .. } if seat.hand.contains(unused)));
+        if let Some(i) = kakan {
+            if self.rng.one_in(2) {
+                let seat = &mut round.seats[who.to_u8() as usize];
+                let Meld::Pon { dir, combination, called, unused } = seat.melds.remove(i) else { unreachable!() };
+                seat.take(unused);
+                self.kan(round, who, Meld::Kakan { dir, combination, called, added: unused });
+                return true;
+            }
+        }
+        false
+    }
+
+    // Pon, chii or daiminkan of the discard. Returns the caller and whether it was a kan.
+    fn try_call(&mut self, round: &mut Round, discarder: Player, hai: Hai) -> Option<(Player, bool)> {
+        if round.wall_left() == 0 {
+            return None;
+        }
+        let kind = hai.kind();
+        for distance in 1..self.num_players {
+            let who = discarder.offset(distance, self.num_players);
+            let seat = &round.seats[who.to_u8() as usize];
+            if seat.riichi {
+                continue;
+            }
+            let dir = self.direction(who, discarder);
+            let same: Vec<Hai> = seat.hand.iter().copied().filter(|h| h.kind() == kind).collect();
+
+            if same.len() == 3 && round.kans < 4 && self.rng.one_in(4) {
+                round.seats[who.to_u8() as usize].hand.retain(|h| h.kind() != kind);
+                self.kan(round, who, Meld::Daiminkan { dir, hai });
+                return Some((who, true));
+            }
+            if same.len() >= 2 && self.rng.one_in(3) {
+                let unused = (kind * 4..kind * 4 + 4).map(Hai::new).find(|x| *x != hai && *x != same[0] && *x != same[1]).unwrap();
+                let m = pon(dir, hai, unused);
+                let seat = &mut round.seats[who.to_u8() as usize];
+                seat.take(same[0]);
+                seat.take(same[1]);
+                seat.melds.push(m.clone());
+                self.push(Action::N(ActionN { who, m }));
+                return Some((who, false));
+            }
+        }
+
+        // Chii from the kamicha, only in four-player games.
+        let who = discarder.offset(1, self.num_players);
+        let seat = &round.seats[who.to_u8() as usize];
+        if self.settings.sanma || kind >= 27 || seat.riichi || !self.rng.one_in(3) {
+            return None;
+        }
+        let find = |k: u8| seat.hand.iter().copied().find(|h| h.kind() == k);
+        let number = kind % 9;
+        let (called_position, a, b) = (number.saturating_sub(2)..=number.min(6)).find_map(|n| {
+            let min = kind - number + n;
+            let others: Vec<u8> = (min..min + 3).filter(|&k| k != kind).collect();
+            Some((CalledPosition::from_u8(kind - min)?, find(others[0])?, find(others[1])?))
+        })?;
+        let mut combination = sorted(&[a, b]);
+        combination.insert(called_position as usize, hai);
+        let m = Meld::Chii {
+            combination: (combination[0], combination[1], combination[2]),
+            called_position,
+        };
+        let seat = &mut round.seats[who.to_u8() as usize];
+        seat.take(a);
+        seat.take(b);
+        seat.melds.push(m.clone());
+        self.push(Action::N(ActionN { who, m }));
+        Some((who, false))
+    }
+
+    // AGARI of the player. Honba and riichi sticks go to the first winner only.
+    fn win(&mut self, round: &Round, who: Player, from_who: Player, machi: Hai, first: bool) {
+        let seat = &round.seats[who.to_u8() as usize];
+        let tsumo = who == from_who;
+        let mut hai = seat.hand.clone();
+        if !tsumo {
+            hai.push(machi);
+        }
+        let all: Vec<Hai> = hai.iter().copied().chain(seat.melds.iter().flat_map(meld_hais)).collect();
+
+        let mut yaku = vec![];
+        if seat.riichi {
+            yaku.push((Yaku::Riichi, 1));
+        }
+        if seat.is_closed() && tsumo {
+            yaku.push((Yaku::MenzenTsumo, 1));
+        }
+        if yaku.is_empty() {
+            let kuitan = !seat.is_closed() && self.settings.no_kuitan;
+            yaku.push((if kuitan { Yaku::YakuhaiHaku } else { Yaku::Tanyao }, 1));
+        }
+        let dora = count_dora_with(&all, &round.dora, self.settings.sanma);
+        if dora > 0 {
+            yaku.push((Yaku::Dora, dora));
+        }
+        let aka = all.iter().filter(|h| RED_FIVES.contains(&h.to_u8())).count() as u8;
+        if !self.settings.no_red && aka > 0 {
+            yaku.push((Yaku::AkaDora, aka));
+        }
+        let dora_hai_ura = if seat.riichi { round.yama.ura_dora_indicators[..round.dora.len()].to_vec() } else { vec![] };
+        if seat.riichi {
+            // Tenhou records ura dora even if 0.
+            yaku.push((Yaku::UraDora, count_dora_with(&all, &dora_hai_ura, self.settings.sanma)));
+        }
+
+        let han = yaku.iter().map(|&(_, x)| x).sum();
+        let fu = [30, 40][self.rng.below(2)];
+        let (base, limit) = base_points(fu, han);
+        let oya = round.oya.to_u8() as usize;
+        let (honba, sticks) = if first { (self.honba as GamePoint, self.kyoutaku as GamePoint) } else { (0, 0) };
+
+        let w = who.to_u8() as usize;
+        let mut delta_points = vec![0; 4];
+        let mut total = 0;
+        for p in self.seat_range().filter(|&p| p != w) {
+            let pay = match (tsumo, w == oya || p == oya) {
+                (false, _) if p != from_who.to_u8() as usize => continue,
+                (false, _) => ceil_to_100(base * if w == oya { 6 } else { 4 }),
+                (true, true) => ceil_to_100(base * 2),
+                (true, false) => ceil_to_100(base),
+            };
+            total += pay;
+            let pay = pay / 100 + honba * if tsumo { 1 } else { 3 };
+            delta_points[p] -= pay;
+            delta_points[w] += pay;
+        }
+        delta_points[w] += sticks * 10;
+
+        let before_points = self.scores.clone();
+        for (s, d) in self.scores.iter_mut().zip(&delta_points) {
+            *s += d;
+        }
+        self.push(Action::AGARI(ActionAGARI {
+            honba: self.honba,
+            kyoutaku: self.kyoutaku,
+            hai: sorted(&hai),
+            m: seat.melds.clone(),
+            machi,
+            ten: AgariTen { fu, total: total as u32, limit },
+            yaku,
+            yakuman: vec![],
+            dora_hai: round.dora.clone(),
+            dora_hai_ura,
+            who,
+            from_who,
+            pao_who: None,
+            before_points,
+            delta_points,
+            owari: None,
+        }));
+    }
+
+    // Exhaustive draw with the noten payments, 3000 points in total.
+    fn ryuukyoku(&mut self, round: &Round) -> RoundEnd {
+        let tenpai: Vec<bool> = round.seats.iter().map(Seat::is_tenpai).collect();
+        let num_tenpai = tenpai.iter().filter(|&&x| x).count() as GamePoint;
+        let num_noten = self.num_players as GamePoint - num_tenpai;
+        let mut delta_points = vec![0; 4];
+        if num_tenpai > 0 && num_noten > 0 {
+            for (d, &t) in delta_points.iter_mut().zip(&tenpai) {
+                *d = if t { 30 / num_tenpai } else { -30 / num_noten };
+            }
+        }
+        let mut hands: Vec<Option<Vec<Hai>>> = round.seats.iter().zip(&tenpai).map(|(s, &t)| t.then(|| sorted(&s.hand))).collect();
+        hands.resize(4, None);
+
+        let before_points = self.scores.clone();
+        for (s, d) in self.scores.iter_mut().zip(&delta_points) {
+            *s += d;
+        }
+        self.push(Action::RYUUKYOKU(ActionRYUUKYOKU {
+            honba: self.honba,
+            kyoutaku: self.kyoutaku,
+            before_points,
+            delta_points,
+            hai0: hands[0].clone(),
+            hai1: hands[1].clone(),
+            hai2: hands[2].clone(),
+            hai3: hands[3].clone(),
+            reason: None,
+            owari: None,
+        }));
+        RoundEnd::Draw {
+            oya_tenpai: tenpai[round.oya.to_u8() as usize],
+        }
+    }
+
+    fn discard_choice(&mut self, round: &Round, who: Player, after_draw: bool) -> (Hai, bool) {
+        let seat = &round.seats[who.to_u8() as usize];
+        if seat.riichi {
+            return (*seat.hand.last().unwrap(), false);
+        }
+        // Riichi whenever possible, but not every turn to keep some dama.
+        if seat.is_closed() && after_draw && self.scores[who.to_u8() as usize] >= 10 && round.wall_left() >= self.num_players as usize && self.rng.one_in(2) {
+            let candidates = seat.tenpai_discards();
+            if !candidates.is_empty() {
+                return (candidates[self.rng.below(candidates.len())], true);
+            }
+        }
+        if self.rng.one_in(8) {
+            (seat.hand[self.rng.below(seat.hand.len())], false)
+        } else {
+            (seat.least_useful(&mut self.rng), false)
+        }
+    }
+
+    fn play_round(&mut self) -> RoundEnd {
+        let mut round = self.start_round();
+        let mut who = round.oya;
+        // Some(rinshan) to draw, None to discard after a call.
+        let mut draw = Some(false);
+
+        loop {
+            if let Some(rinshan) = draw {
+                let hai = if rinshan {
+                    round.yama.rinshan[round.kans - 1]
+                } else {
+                    if round.wall_left() == 0 {
+                        return self.ryuukyoku(&round);
+                    }
+                    round.drawn += 1;
+                    round.yama.draws[round.drawn - 1]
+                };
+                self.push(Action::DRAW(ActionDRAW { who, hai }));
+                round.seats[who.to_u8() as usize].hand.push(hai);
+
+                if round.seats[who.to_u8() as usize].is_agari(None) && self.rng.one_in(2) {
+                    self.win(&round, who, who, hai, true);
+                    return RoundEnd::Win { oya_won: who == round.oya };
+                }
+                if self.try_kan(&mut round, who) {
+                    draw = Some(true);
+                    continue;
+                }
+            }
+
+            let (hai, declare) = self.discard_choice(&round, who, draw.is_some());
+            if declare {
+                self.push(Action::REACH1(ActionREACH1 { who }));
+            }
+            round.seats[who.to_u8() as usize].take(hai);
+            self.push(Action::DISCARD(ActionDISCARD { who, hai }));
+
+            if self.rng.one_in(200) {
+                let p = Player::new(self.rng.below(self.num_players as usize) as u8);
+                let name = self.actions.iter().find_map(|a| a.as_un1()).map(|x| x.names[p.to_u8() as usize].clone()).unwrap_or_default();
+                self.push(Action::BYE(ActionBYE { who: p }));
+                self.push(Action::UN2(ActionUN2 { who: p, name }));
+            }
+
+            // Ron is always taken, and multiple ron is allowed, in turn order from the discarder.
+            let winners: Vec<Player> = (1..self.num_players).map(|d| who.offset(d, self.num_players)).filter(|p| round.seats[p.to_u8() as usize].is_agari(Some(hai))).collect();
+            if !winners.is_empty() {
+                for (i, &winner) in winners.iter().enumerate() {
+                    self.win(&round, winner, who, hai, i == 0);
+                }
+                return RoundEnd::Win { oya_won: winners.contains(&round.oya) };
+            }
+
+            if declare {
+                round.seats[who.to_u8() as usize].riichi = true;
+                self.scores[who.to_u8() as usize] -= 10;
+                self.kyoutaku += 1;
+                self.push(Action::REACH2(ActionREACH2 { who, ten: self.scores.clone() }));
+            }
+
+            match self.try_call(&mut round, who, hai) {
+                Some((caller, kan)) => {
+                    who = caller;
+                    draw = kan.then_some(true);
+                }
+                None => {
+                    who = who.offset(1, self.num_players);
+                    draw = Some(false);
+                }
+            }
+        }
+    }
+
+    // Owari of the last result, where the riichi sticks left go to the top.
+    fn finish(&mut self) {
+        let n = self.num_players as usize;
+        let mut points = self.scores[..n].to_vec();
+        if let Some(top) = placements(&points).iter().position(|&x| x == 0) {
+            points[top] += self.kyoutaku as GamePoint * 10;
+        }
+        let mut results = final_results(&points, &UmaOka::for_players(self.settings.sanma));
+        points.resize(4, 0);
+        results.resize(4, 0.0);
+        let owari = Some((points, results));
+        match self.actions.last_mut() {
+            Some(Action::AGARI(x)) => x.owari = owari,
+            Some(Action::RYUUKYOKU(x)) => x.owari = owari,
+            _ => {}
+        }
+    }
+}
+
+/// A random game from the seed. The same seed and options always give the same game.
+pub fn generate_mjlog(seed: u64, options: &GeneratorOptions) -> Mjlog {
+    let mut rng = Rng::new(seed);
+    let settings = options.settings.clone();
+    let num_players: u8 = if settings.sanma { 3 } else { 4 };
+
+    let key: Vec<u8> = (0..SEED_BYTES).map(|_| rng.next() as u8).collect();
+    let shuffle_seed = format!("{}{}", SEED_PREFIX, base64::engine::general_purpose::STANDARD.encode(key));
+    let walls = WallGenerator::from_seed(&shuffle_seed).expect("valid seed");
+
+    // The fourth seat of three-player games is empty, same as tenhou.
+    let seat = |i: usize| i < num_players as usize;
+    let names = (0..4).map(|i| if seat(i) { NAMES[rng.below(NAMES.len())].to_string() } else { String::new() }).collect();
+    let dan = (0..4).map(|i| TenhouRank::from_u8(if seat(i) { rng.below(21) as u8 } else { 0 })).collect();
+    let rate = (0..4).map(|i| Some(if seat(i) { 1200.0 + rng.below(12000) as f64 / 10.0 } else { 0.0 })).collect();
+    let sx = (0..4).map(|i| if seat(i) { ["M", "F"][rng.below(2)] } else { "C" }.to_string()).collect();
+    let start: GamePoint = if settings.sanma { 350 } else { 250 };
+
+    let mut game = Game {
+        rng,
+        walls,
+        num_players,
+        actions: vec![
+            Action::SHUFFLE(ActionSHUFFLE { seed: shuffle_seed }),
+            Action::GO(ActionGO { settings: settings.clone(), lobby: 0 }),
+            Action::UN1(ActionUN1 { names, dan, rate, sx }),
+            Action::TAIKYOKU(ActionTAIKYOKU { oya: Player::new(0) }),
+        ],
+        settings,
+        scores: (0..4).map(|i| if seat(i) { start } else { 0 }).collect(),
+        kyoku: 0,
+        honba: 0,
+        kyoutaku: 0,
+    };
+
+    for i in 0..options.rounds.max(1) {
+        let end = game.play_round();
+        if i + 1 >= options.rounds || game.scores.iter().any(|&x| x < 0) {
+            break;
+        }
+        match end {
+            RoundEnd::Win { oya_won } => {
+                game.kyoutaku = 0;
+                if oya_won {
+                    game.honba += 1;
+                } else {
+                    game.honba = 0;
+                    game.kyoku += 1;
+                }
+            }
+            RoundEnd::Draw { oya_tenpai } => {
+                game.honba += 1;
+                if !oya_tenpai {
+                    game.kyoku += 1;
+                }
+            }
+        }
+    }
+    game.finish();
+
+    Mjlog {
+        ver: 2.3,
+        actions: game.actions,
+        incomplete: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::GameState;
+    use crate::wall::verify_wall;
+
+    #[test]
+    fn test_generate_mjlog() {
+        let options = GeneratorOptions::default();
+        let mjlog = generate_mjlog(1, &options);
+        assert_eq!(mjlog, generate_mjlog(1, &options));
+        assert!(verify_wall(&mjlog).unwrap().is_empty());
+        assert!(!GameState::from_actions(&mjlog.actions).unwrap().in_round);
+
+        let sanma = GeneratorOptions {
+            settings: GameSettings { sanma: true, ..options.settings },
+            ..options
+        };
+        let mjlog = generate_mjlog(1, &sanma);
+        let state = GameState::from_actions(&mjlog.actions).unwrap();
+        assert_eq!(state.players.len(), 3);
+        assert!(mjlog.actions.iter().all(|a| !matches!(a, Action::N(ActionN { m: Meld::Chii { .. }, .. }))));
+    }
+}
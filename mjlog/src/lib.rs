@@ -4,7 +4,9 @@
 //!
 //! # Usage
 //!
-//! ```
+//! ```ignore
+//! use mjlog::prelude::*;
+//!
 //! let content :: String = std::fs::read_to_string("/your/xml/path")?;
 //!
 //! // You can read xml contains multiple mjloggm tags.
@@ -13,9 +15,17 @@
 //!
 //! # Install
 //!
-//! ```
+//! ```text
 //! cargo add mjlog
 //! ```
 
+pub mod encoding;
+pub mod exporter;
+pub mod generator;
 pub mod model;
 pub mod parser;
+pub mod prelude;
+pub mod replay;
+pub mod uma_oka;
+pub mod visitor;
+pub mod wall;
@@ -8,7 +8,7 @@
 
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
-use serde_derive::{Serialize, Deserialize};
+use serde_derive::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// Occurs when there is no corresponding identifier.
@@ -28,7 +28,7 @@ pub enum ParseError {
 ///
 /// When red 5 is enabled, it is assigned to the tile where mod 4 == 0. (16,52,88)
 ///
-/// ```
+/// ```text
 /// order:
 /// 1111..0555..9999m 1111..0555..9999p 1111..0555..9999s 1111..7777z
 /// (0m == red 5m)
@@ -41,6 +41,8 @@ pub struct Hai(u8);
 pub struct Player(u8);
 
 /// GamePoint represents each player's score, which usually starts at 25,000 or 30,000.
+///
+/// In units of 100, same as the XML. Widen to `i64` before multiplying by 100.
 pub type GamePoint = i32;
 
 /// Represents the relative direction of a player based on the current player’s perspective.
@@ -123,13 +125,37 @@ pub struct InitSeed {
     pub dora_hyouji: Hai,
 }
 
+/// Represents which tile of a chii is the called one, in the ascending order of the combination.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, FromPrimitive)]
+pub enum CalledPosition {
+    /// e.g. 3 of 345
+    #[default]
+    Min,
+    /// e.g. 4 of 345
+    Mid,
+    /// e.g. 5 of 345
+    Max,
+}
+
+impl CalledPosition {
+    /// Reorders the ascending combination to the placement order on the board, the called tile first.
+    pub fn board_order<T>(self, (min, mid, max): (T, T, T)) -> (T, T, T) {
+        match self {
+            CalledPosition::Min => (min, mid, max),
+            CalledPosition::Mid => (mid, min, max),
+            CalledPosition::Max => (max, min, mid),
+        }
+    }
+}
+
 /// Represents the details of a call (meld).
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Meld {
     Chii {
         combination: (Hai, Hai, Hai),
-        // 0 == min, 1 == mid, 2 == max
-        called_position: u8,
+        // Min, Mid or Max of the combination
+        called_position: CalledPosition,
     },
     Pon {
         dir: Direction,
@@ -189,9 +215,19 @@ pub enum ScoreRank {
     Yakuman,
 }
 
+/// The "ten" attribute of AGARI, e.g. `ten="30,7700,0"`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgariTen {
+    /// Hand value points in mahjong scoring, e.g. 20, 30.
+    pub fu: u8,
+    /// Total winning points, excluding honba and kyoutaku.
+    pub total: u32,
+    /// Limit of the hand such as Mangan, or `Normal` below mangan.
+    pub limit: ScoreRank,
+}
+
 /// Represents the name of a Yaku (winning hand combination).
-#[repr(u8)]
-#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, FromPrimitive)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
 pub enum Yaku {
     #[default]
     MenzenTsumo,
@@ -249,10 +285,86 @@ pub enum Yaku {
     Dora,
     UraDora,
     AkaDora,
+    /// Yaku ID unknown to this version, kept by the lenient parser.
+    Unknown(u8),
+}
+
+impl Yaku {
+    /// Known yaku in the order of their IDs.
+    pub const ALL: [Yaku; 55] = [
+        Yaku::MenzenTsumo,
+        Yaku::Riichi,
+        Yaku::Ippatsu,
+        Yaku::Chankan,
+        Yaku::Rinshankaihou,
+        Yaku::HaiteiTsumo,
+        Yaku::HouteiRon,
+        Yaku::Pinfu,
+        Yaku::Tanyao,
+        Yaku::Iipeikou,
+        Yaku::PlayerWindTon,
+        Yaku::PlayerWindNan,
+        Yaku::PlayerWindSha,
+        Yaku::PlayerWindPei,
+        Yaku::FieldWindTon,
+        Yaku::FieldWindNan,
+        Yaku::FieldWindSha,
+        Yaku::FieldWindPei,
+        Yaku::YakuhaiHaku,
+        Yaku::YakuhaiHatsu,
+        Yaku::YakuhaiChun,
+        Yaku::DoubleRiichi,
+        Yaku::Chiitoitsu,
+        Yaku::Chanta,
+        Yaku::Ikkitsuukan,
+        Yaku::SansyokuDoujun,
+        Yaku::SanshokuDoukou,
+        Yaku::Sankantsu,
+        Yaku::Toitoi,
+        Yaku::Sanannkou,
+        Yaku::Shousangen,
+        Yaku::Honroutou,
+        Yaku::Ryanpeikou,
+        Yaku::Junchan,
+        Yaku::Honiisou,
+        Yaku::Chiniisou,
+        Yaku::Renhou,
+        Yaku::Tenhou,
+        Yaku::Chiihou,
+        Yaku::Daisangen,
+        Yaku::Suuankou,
+        Yaku::SuuankouTanki,
+        Yaku::Tsuuiisou,
+        Yaku::Ryuuiisou,
+        Yaku::Chinroutou,
+        Yaku::Tyuurenpoutou,
+        Yaku::Tyuurenpoutou9,
+        Yaku::Kokushimusou,
+        Yaku::Kokushimusou13,
+        Yaku::Daisuushii,
+        Yaku::Syousuushii,
+        Yaku::Suukantsu,
+        Yaku::Dora,
+        Yaku::UraDora,
+        Yaku::AkaDora,
+    ];
+
+    /// Yaku of the ID, or `None` if the ID is unknown to this version.
+    pub fn from_u8(x: u8) -> Option<Yaku> {
+        Yaku::ALL.get(x as usize).copied()
+    }
+
+    /// ID of the yaku in the AGARI tag.
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            Yaku::Unknown(x) => *x,
+            _ => Yaku::ALL.iter().position(|x| x == self).unwrap() as u8,
+        }
+    }
 }
 
 /// Corresponds to the AGARI tag.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ActionAGARI {
     /// Bonus points for consecutive draws or dealer wins.
     pub honba: u8,
@@ -269,14 +381,8 @@ pub struct ActionAGARI {
     /// Winning tiles at the time of completion.
     pub machi: Hai,
 
-    /// Hand value points in mahjong scoring, e.g. 20, 30.
-    pub fu: u8,
-
-    /// Total winning points.
-    pub net_score: u32,
-
-    /// Detailed information on winning points.
-    pub score_rank: ScoreRank,
+    /// Fu, total points and limit of the hand.
+    pub ten: AgariTen,
 
     /// List of winning Yakus (hand combinations).
     ///
@@ -324,7 +430,7 @@ pub struct ActionAGARI {
 }
 
 /// Corresponds to the RYUUKYOKU tag.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ActionRYUUKYOKU {
     /// Bonus points for consecutive draws or dealer wins.
     pub honba: u8,
@@ -362,13 +468,13 @@ pub struct ActionRYUUKYOKU {
 }
 
 /// Corresponds to the SHUFFLE tag.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ActionSHUFFLE {
     pub seed: String,
 }
 
 /// Corresponds to the GO tag.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ActionGO {
     /// In the original XML, this is named "type", but it has been chaned to avoid conflicts with Rust reserved keywords.
     pub settings: GameSettings,
@@ -379,37 +485,40 @@ pub struct ActionGO {
 ///
 /// In the original XML, the initial state and reconnection share the UN tag.
 /// However, since user utilize them differently, they are intentionally separated into two.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ActionUN1 {
     pub names: Vec<String>,
-    pub dan: Vec<TenhouRank>,
-    pub rate: Vec<f64>,
+    /// `None` for seats without a valid rank, e.g. AI or observer entries in event logs.
+    pub dan: Vec<Option<TenhouRank>>,
+    /// `None` for seats without a valid rate, same as `dan`.
+    pub rate: Vec<Option<f64>>,
+    /// Kept as is. May be empty for AI or observer entries.
     pub sx: Vec<String>,
 }
 
 /// Corresponds to the UN tag in the case of reconnection.
 ///
 /// In the original XML, it is expressed as options from n0 to n3, but since that is confusing, it has been reorganized.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ActionUN2 {
     pub who: Player,
     pub name: String,
 }
 
 /// Corresponds to the BYE tag.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ActionBYE {
     pub who: Player,
 }
 
 /// Corresponds to the TAIKYOKU tag.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ActionTAIKYOKU {
     pub oya: Player,
 }
 
 /// Corresponds to the INIT tag.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ActionINIT {
     pub seed: InitSeed,
     pub ten: Vec<GamePoint>,
@@ -423,27 +532,27 @@ pub struct ActionINIT {
 /// we split the enum into two since they are usually handled separately.
 /// At step 1, a riichi declaration is made.
 /// Afterwards, a tile is discarded, and if no ron occurs, step is set to 2.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ActionREACH1 {
     pub who: Player,
 }
 
 /// Corresponds to the REACH tag after a tile is discarded (step 2).
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ActionREACH2 {
     pub who: Player,
     pub ten: Vec<GamePoint>,
 }
 
 /// Corresponds to the N tag, represents a call (meld).
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ActionN {
     pub who: Player,
     pub m: Meld,
 }
 
 /// Corresponds to the DORA tag, represents a new Dora indicator.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ActionDORA {
     pub hai: Hai,
 }
@@ -452,7 +561,7 @@ pub struct ActionDORA {
 ///
 /// Tsumo actions are represented by the T, U, V, and W tags,
 /// but since they share common properties, they are unified into a single structure.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ActionDRAW {
     pub who: Player,
     pub hai: Hai,
@@ -462,14 +571,23 @@ pub struct ActionDRAW {
 ///
 /// Discard actions are represented by the D, E, F, and G tags,
 /// but since they share common properties, they are unified into a single structure.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ActionDISCARD {
     pub who: Player,
     pub hai: Hai,
 }
 
+/// A tag unknown to this version, kept by the lenient parser.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActionUnknown {
+    /// Tag name.
+    pub name: String,
+    /// Attributes in the order of the tag, with unescaped values.
+    pub attrs: Vec<(String, String)>,
+}
+
 /// Corresponds to each tag within ```mgloggm```.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Action {
     SHUFFLE(ActionSHUFFLE),
     GO(ActionGO),
@@ -486,13 +604,17 @@ pub enum Action {
     RYUUKYOKU(ActionRYUUKYOKU),
     DRAW(ActionDRAW),
     DISCARD(ActionDISCARD),
+    Unknown(ActionUnknown),
 }
 
 /// Corresponds to the entire mjloggm tag.
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct Mjlog {
     pub ver: f64,
     pub actions: Vec<Action>,
+    /// The log ends before the closing mjloggm tag, e.g. of a disconnected download. See `ParseOptions::allow_truncated`.
+    #[serde(default)]
+    pub incomplete: bool,
 }
 
 impl Hai {
@@ -512,6 +634,43 @@ impl Hai {
         let number = (pict_index % 9) + 1;
         pict_type <= 2 && number == 5
     }
+
+    /// Tile kind (0..34), ignoring red fives.
+    ///
+    /// ```text
+    /// 0..9 m, 9..18 p, 18..27 s, 27..34 z
+    /// ```
+    pub fn kind(&self) -> u8 {
+        self.0 / 4
+    }
+
+    /// Kind of the dora indicated by this tile.
+    ///
+    /// 9 → 1 for number tiles, 北 → 東 for winds, and 中 → 白 for dragons. For three-player games, see `next_for_dora_with`.
+    pub fn next_for_dora(&self) -> u8 {
+        self.next_for_dora_with(false)
+    }
+
+    /// Same as `next_for_dora`. In three-player games, which have no 2m to 8m, 1m indicates 9m.
+    pub fn next_for_dora_with(&self, sanma: bool) -> u8 {
+        match self.kind() {
+            0 if sanma => 8,
+            k @ (8 | 17 | 26) => k - 8,
+            30 => 27,
+            33 => 31,
+            k => k + 1,
+        }
+    }
+}
+
+/// Number of dora in the tiles, counted once per indicator. Red fives count as normal fives; aka dora are not included.
+pub fn count_dora(hand: &[Hai], indicators: &[Hai]) -> u8 {
+    count_dora_with(hand, indicators, false)
+}
+
+/// Same as `count_dora`, with the dora of three-player games. See `Hai::next_for_dora_with`.
+pub fn count_dora_with(hand: &[Hai], indicators: &[Hai], sanma: bool) -> u8 {
+    indicators.iter().map(|i| i.next_for_dora_with(sanma)).map(|d| hand.iter().filter(|h| h.kind() == d).count() as u8).sum()
 }
 
 impl Player {
@@ -522,6 +681,23 @@ impl Player {
     pub fn to_u8(&self) -> u8 {
         self.0
     }
+
+    /// The player `distance` seats after this one in turn order.
+    pub fn offset(&self, distance: u8, num_players: u8) -> Player {
+        Player((self.0 + distance) % num_players)
+    }
+
+    /// Seats from `from` to this player in turn order: 1 for shimocha, 2 for toimen, 3 for kamicha, and 0 for `from` itself.
+    pub fn distance_from(&self, from: Player, num_players: u8) -> u8 {
+        (self.0 % num_players + num_players - from.0 % num_players) % num_players
+    }
+}
+
+/// Resolves atamahane (head bump): index of the first of `winners` in turn order from the discarder `from`.
+///
+/// Tenhou allows multiple ron, but honba and kyoutaku go to this winner only.
+pub fn atamahane(from: Player, winners: &[Player], num_players: u8) -> Option<usize> {
+    (0..winners.len()).min_by_key(|&i| winners[i].distance_from(from, num_players))
 }
 
 impl ActionAGARI {
@@ -636,6 +812,13 @@ impl Action {
         }
     }
 
+    pub fn as_unknown(&self) -> Option<&ActionUnknown> {
+        match self {
+            Action::Unknown(x) => Some(x),
+            _ => None,
+        }
+    }
+
     pub fn is_shuffle(&self) -> bool {
         self.as_shuffle().is_some()
     }
@@ -695,6 +878,10 @@ impl Action {
     pub fn is_discard(&self) -> bool {
         self.as_discard().is_some()
     }
+
+    pub fn is_unknown(&self) -> bool {
+        self.as_unknown().is_some()
+    }
 }
 
 impl std::str::FromStr for Hai {
@@ -743,3 +930,26 @@ impl Default for Meld {
         Meld::Ankan { hai: Hai::default() }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_dora() {
+        let hais = |xs: &[u8]| xs.iter().map(|&x| Hai::new(x)).collect::<Vec<_>>();
+        // 9m -> 1m, 北 -> 東, 中 -> 白
+        assert_eq!(Hai::new(32).next_for_dora(), 0);
+        assert_eq!(Hai::new(120).next_for_dora(), 27);
+        assert_eq!(Hai::new(132).next_for_dora(), 31);
+        // Two 5m (one red) for each of the two 4m indicators.
+        assert_eq!(count_dora(&hais(&[16, 17, 12]), &hais(&[13, 14])), 4);
+
+        // 1m indicates 9m in three-player games, and 2m otherwise.
+        assert_eq!(Hai::new(0).next_for_dora(), 1);
+        assert_eq!(Hai::new(0).next_for_dora_with(true), 8);
+        assert_eq!(Hai::new(32).next_for_dora_with(true), 0);
+        assert_eq!(count_dora_with(&hais(&[32, 33, 4]), &hais(&[0]), true), 2);
+        assert_eq!(count_dora(&hais(&[32, 33, 4]), &hais(&[0])), 1);
+    }
+}
@@ -1,8 +1,11 @@
+use crate::encoding::decode_mjlog;
 use crate::model::*;
 use num_traits::FromPrimitive;
 use percent_encoding::percent_decode_str;
+use quick_xml::events::attributes::Attribute;
 use quick_xml::events::{BytesStart, Event};
 use quick_xml::reader::Reader;
+use std::borrow::Cow;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -34,7 +37,9 @@ pub enum MjlogError {
     #[error("Invalid yaku number: {0}")]
     InvalidYakuNum(u8),
     #[error("Invalid agari rank: {0}")]
-    InvalidScoreRank(u8),
+    InvalidScoreRank(u32),
+    #[error("Invalid fu: {0}")]
+    InvalidFu(u32),
     #[error("Invalid owari")]
     InvalidOwari,
     #[error("Process instruction is not supported.")]
@@ -49,74 +54,209 @@ pub enum MjlogError {
     UnexpectedEof,
     #[error("Unexpected tag: {0}")]
     UnexpectedTag(String),
+    #[error("Unsupported encoding: {0}")]
+    UnsupportedEncoding(String),
+    #[error("Invalid text in the encoding: {0}")]
+    InvalidEncoding(String),
+    #[error("{position}: {source}")]
+    At { position: MjlogPosition, source: Box<MjlogError> },
 }
 
 pub type MjlogResult<T> = Result<T, MjlogError>;
 
+/// Position of a parse error in the text, at the start of the tag (or text) being parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MjlogPosition {
+    /// Byte offset from the start of the text.
+    pub offset: usize,
+    /// 1-origin line.
+    pub line: usize,
+    /// 1-origin column, in characters.
+    pub column: usize,
+    /// 0-origin index of the mjloggm tag being parsed, or of the next one outside mjloggm tags.
+    pub game: usize,
+}
+
+impl MjlogPosition {
+    // The reader offset is before the spaces trimmed from the event, so they are skipped here.
+    fn new(text: &str, offset: u64, game: usize) -> Self {
+        let mut offset = (offset as usize).min(text.len());
+        while !text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset += text[offset..].len() - text[offset..].trim_start().len();
+        let before = &text[..offset];
+        MjlogPosition {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[before.rfind('\n').map_or(0, |x| x + 1)..].chars().count() + 1,
+            game,
+        }
+    }
+}
+
+impl std::fmt::Display for MjlogPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {} (byte {}, game {})", self.line, self.column, self.offset, self.game)
+    }
+}
+
+impl MjlogError {
+    /// Position of the error. The parse functions give it for every error.
+    pub fn position(&self) -> Option<MjlogPosition> {
+        match self {
+            MjlogError::At { position, .. } => Some(*position),
+            _ => None,
+        }
+    }
+
+    /// The error without the position.
+    pub fn kind(&self) -> &MjlogError {
+        match self {
+            MjlogError::At { source, .. } => source.kind(),
+            x => x,
+        }
+    }
+
+    // Syntax errors are reported by the reader at the start of the broken markup, the others at the start of the event.
+    fn at(self, text: &str, reader: &Reader<&[u8]>, start: u64, game: usize) -> MjlogError {
+        let offset = if matches!(self, MjlogError::XmlError(_)) { reader.error_position() } else { start };
+        MjlogError::At {
+            position: MjlogPosition::new(text, offset, game),
+            source: Box::new(self),
+        }
+    }
+}
+
+/// What the lenient parser accepted or skipped instead of failing. `game` is the 0-origin index of the mjloggm tag.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum MjlogWarning {
+    #[error("game {game}: unknown tag {name}, kept as is")]
+    UnknownTag { game: usize, name: String },
+    #[error("game {game}: unknown attribute {attr} of {tag}, ignored")]
+    UnknownAttribute { game: usize, tag: String, attr: String },
+    #[error("game {game}: unknown yaku id {id}")]
+    UnknownYaku { game: usize, id: u8 },
+    #[error("game {game}: invalid {name} tag ({error}), kept as unknown")]
+    InvalidTag { game: usize, name: String, error: String },
+    #[error("game {game}: ignored: {error}")]
+    Ignored { game: usize, error: String },
+    #[error("game {game}: skipped the rest of the text: {error}")]
+    Skipped { game: usize, error: String },
+    #[error("game {game}: truncated, kept {actions} actions")]
+    Truncated { game: usize, actions: usize },
+}
+
+/// Options of the parser.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParseOptions {
+    /// Keeps yaku IDs unknown to this version as `Yaku::Unknown`, and tags unknown to this version or invalid as `Action::Unknown`,
+    /// instead of failing, for logs newer than the parser. Text and nested tags in mjloggm are ignored.
+    pub lenient: bool,
+    /// Keeps tags unknown to this version as `Action::Unknown` instead of failing, so that exporting the game writes them back.
+    /// The lenient parser always keeps them.
+    pub keep_unknown_tags: bool,
+    /// Returns the actions parsed so far of a log without the closing mjloggm tag, flagged as `Mjlog::incomplete`,
+    /// instead of failing. A tag cut in the middle is dropped. The lenient parser always allows it.
+    pub allow_truncated: bool,
+}
+
 fn get_partition_even_odd<T: Clone>(v: &[T]) -> (Vec<T>, Vec<T>) {
     (v.iter().step_by(2).cloned().collect(), v.iter().skip(1).step_by(2).cloned().collect())
 }
 
-fn parse_vec<T: std::str::FromStr>(v: &[String]) -> Result<Vec<T>, T::Err> {
-    v.iter().map(|x| x.parse()).collect()
+// Numbers are parsed from the raw bytes of the attributes, without unescaping or allocating, as tenhou never escapes them.
+fn parse_bytes<T: std::str::FromStr>(x: &[u8]) -> Option<T> {
+    std::str::from_utf8(x).ok()?.parse().ok()
 }
 
-fn parse_csv<T: std::str::FromStr>(x: &str) -> Result<Vec<T>, T::Err> {
-    x.split(',').map(|x| x.parse()).collect()
+fn parse_error(x: &[u8]) -> MjlogError {
+    MjlogError::ParseError(String::from_utf8_lossy(x).to_string())
+}
+
+// An empty attribute is an empty list, e.g. hai3 of INIT in three-player games.
+fn split_csv(x: &[u8]) -> impl Iterator<Item = &[u8]> {
+    (!x.is_empty()).then_some(x).into_iter().flat_map(|x| x.split(|&b| b == b','))
+}
+
+// The vector is allocated once for the number of values, instead of growing while collecting.
+fn parse_csv<T: std::str::FromStr>(x: &[u8]) -> Option<Vec<T>> {
+    let mut values = Vec::with_capacity(x.iter().filter(|&&b| b == b',').count() + 1);
+    for value in split_csv(x) {
+        values.push(parse_bytes(value)?);
+    }
+    Some(values)
 }
 
 fn decode_percent_encoding(s: &str) -> String {
     percent_decode_str(s).decode_utf8_lossy().to_string()
 }
 
-fn try_get_attribute_str(e: &BytesStart, attr_name: &str) -> MjlogResult<Option<String>> {
-    let attr_opt = e.try_get_attribute(attr_name)?;
-    if attr_opt.is_none() {
-        return Ok(None);
+// Attributes of a tag, read once. Most tags look up all of their attributes, so each lookup searches
+// the keys instead of reading the tag again.
+struct TagAttributes<'a>(Vec<Attribute<'a>>);
+
+impl<'a> TagAttributes<'a> {
+    fn new(e: &'a BytesStart) -> MjlogResult<Self> {
+        Ok(TagAttributes(e.attributes().with_checks(false).collect::<Result<_, _>>()?))
     }
-    let attr = attr_opt.unwrap();
-    let unescaped_value = attr.unescape_value()?;
-    Ok(Some(unescaped_value.to_string()))
+
+    fn find(&self, attr_name: &str) -> Option<&Attribute<'a>> {
+        self.0.iter().find(|x| x.key.as_ref() == attr_name.as_bytes())
+    }
+}
+
+// Raw value of the attribute, borrowed from the tag.
+fn try_get_attribute_raw<'a>(e: &'a TagAttributes, attr_name: &str) -> Option<&'a [u8]> {
+    e.find(attr_name).map(|x| x.value.as_ref())
 }
 
-fn try_get_attribute_value<T: std::str::FromStr>(e: &BytesStart, attr_name: &str) -> MjlogResult<Option<T>> {
-    let s_opt = try_get_attribute_str(e, attr_name)?;
-    if s_opt.is_none() {
-        return Ok(None);
+// Unescaped value of the attribute, borrowed from the tag unless it has escapes.
+fn try_get_attribute_str<'a>(e: &TagAttributes<'a>, attr_name: &str) -> MjlogResult<Option<Cow<'a, str>>> {
+    match e.find(attr_name) {
+        Some(attr) => Ok(Some(attr.unescape_value()?)),
+        None => Ok(None),
     }
-    let s = s_opt.unwrap();
-    let value = s.parse::<T>().map_err(|_| MjlogError::ParseError(s))?;
-    Ok(Some(value))
 }
 
-fn try_get_attribute_csv<T: std::str::FromStr>(e: &BytesStart, attr_name: &str) -> MjlogResult<Option<Vec<T>>> {
-    let s_opt = try_get_attribute_str(e, attr_name)?;
-    if s_opt.is_none() {
-        return Ok(None);
+fn try_get_attribute_value<T: std::str::FromStr>(e: &TagAttributes, attr_name: &str) -> MjlogResult<Option<T>> {
+    match try_get_attribute_raw(e, attr_name) {
+        Some(x) => Ok(Some(parse_bytes(x).ok_or_else(|| parse_error(x))?)),
+        None => Ok(None),
     }
-    let s = s_opt.unwrap();
-    let csv = parse_csv(&s).map_err(|_| MjlogError::ParseError(s))?;
-    Ok(Some(csv))
 }
 
-fn get_attribute_str(e: &BytesStart, attr_name: &str) -> MjlogResult<String> {
+fn try_get_attribute_csv<T: std::str::FromStr>(e: &TagAttributes, attr_name: &str) -> MjlogResult<Option<Vec<T>>> {
+    match try_get_attribute_raw(e, attr_name) {
+        Some(x) => Ok(Some(parse_csv(x).ok_or_else(|| parse_error(x))?)),
+        None => Ok(None),
+    }
+}
+
+// Some event logs have AI or observer entries with empty or unusual values (e.g. `dan="10,,11,12"`).
+// Such entries become `None` instead of failing the whole log. A missing attribute is an empty list.
+fn get_attribute_csv_lenient<T: std::str::FromStr>(e: &TagAttributes, attr_name: &str) -> Vec<Option<T>> {
+    split_csv(try_get_attribute_raw(e, attr_name).unwrap_or_default()).map(parse_bytes).collect()
+}
+
+fn get_attribute_str<'a>(e: &TagAttributes<'a>, attr_name: &str) -> MjlogResult<Cow<'a, str>> {
     try_get_attribute_str(e, attr_name)?.ok_or(MjlogError::AttributeNotFound(attr_name.to_string()))
 }
 
-fn get_attribute_value<T: std::str::FromStr>(e: &BytesStart, attr_name: &str) -> MjlogResult<T> {
+fn get_attribute_value<T: std::str::FromStr>(e: &TagAttributes, attr_name: &str) -> MjlogResult<T> {
     try_get_attribute_value(e, attr_name)?.ok_or(MjlogError::AttributeNotFound(attr_name.to_string()))
 }
 
-fn get_attribute_csv<T: std::str::FromStr>(e: &BytesStart, attr_name: &str) -> MjlogResult<Vec<T>> {
+fn get_attribute_csv<T: std::str::FromStr>(e: &TagAttributes, attr_name: &str) -> MjlogResult<Vec<T>> {
     try_get_attribute_csv(e, attr_name)?.ok_or(MjlogError::AttributeNotFound(attr_name.to_string()))
 }
 
-fn conv_shuffle(e: &BytesStart) -> MjlogResult<Action> {
-    let seed = get_attribute_str(e, "seed")?;
+fn conv_shuffle(e: &TagAttributes) -> MjlogResult<Action> {
+    let seed = get_attribute_str(e, "seed")?.into_owned();
     Ok(Action::SHUFFLE(ActionSHUFFLE { seed }))
 }
 
-fn conv_go(e: &BytesStart) -> MjlogResult<Action> {
+fn conv_go(e: &TagAttributes) -> MjlogResult<Action> {
     let t: u32 = get_attribute_value(e, "type")?;
     let lobby = get_attribute_value(e, "lobby")?;
     let room_type_index = (t & 0x20) >> 4 | (t & 0x80) >> 7;
@@ -134,7 +274,7 @@ fn conv_go(e: &BytesStart) -> MjlogResult<Action> {
     Ok(Action::GO(ActionGO { settings, lobby }))
 }
 
-fn conv_uv(e: &BytesStart) -> MjlogResult<Action> {
+fn conv_uv(e: &TagAttributes) -> MjlogResult<Action> {
     let names = [
         try_get_attribute_str(e, "n0")?.map(|s| decode_percent_encoding(&s)),
         try_get_attribute_str(e, "n1")?.map(|s| decode_percent_encoding(&s)),
@@ -146,9 +286,9 @@ fn conv_uv(e: &BytesStart) -> MjlogResult<Action> {
     if name_num == 4 {
         // In the initial state, all values from n0 to n3 are valid.
         // Even in a three-player game, n3 is an empty string.
-        let dan = get_attribute_csv(e, "dan")?;
-        let rate = get_attribute_csv(e, "rate")?;
-        let sx = get_attribute_csv(e, "sx")?;
+        let dan = get_attribute_csv_lenient(e, "dan");
+        let rate = get_attribute_csv_lenient(e, "rate");
+        let sx = try_get_attribute_csv(e, "sx")?.unwrap_or_default();
 
         Ok(Action::UN1(ActionUN1 {
             names: names.iter().map(|x| x.clone().unwrap()).collect(),
@@ -168,19 +308,19 @@ fn conv_uv(e: &BytesStart) -> MjlogResult<Action> {
     }
 }
 
-fn conv_bye(e: &BytesStart) -> MjlogResult<Action> {
+fn conv_bye(e: &TagAttributes) -> MjlogResult<Action> {
     let who = get_attribute_value(e, "who")?;
 
     Ok(Action::BYE(ActionBYE { who }))
 }
 
-fn conv_taikyoku(e: &BytesStart) -> MjlogResult<Action> {
+fn conv_taikyoku(e: &TagAttributes) -> MjlogResult<Action> {
     let oya = get_attribute_value(e, "oya")?;
 
     Ok(Action::TAIKYOKU(ActionTAIKYOKU { oya }))
 }
 
-fn conv_init(e: &BytesStart) -> MjlogResult<Action> {
+fn conv_init(e: &TagAttributes) -> MjlogResult<Action> {
     let seed: Vec<u8> = get_attribute_csv(e, "seed")?;
     let ten = get_attribute_csv(e, "ten")?;
     let oya = get_attribute_value(e, "oya")?;
@@ -203,7 +343,7 @@ fn conv_init(e: &BytesStart) -> MjlogResult<Action> {
     }))
 }
 
-fn conv_reach(e: &BytesStart) -> MjlogResult<Action> {
+fn conv_reach(e: &TagAttributes) -> MjlogResult<Action> {
     let step = get_attribute_value(e, "step")?;
     let who = get_attribute_value(e, "who")?;
 
@@ -217,7 +357,7 @@ fn conv_reach(e: &BytesStart) -> MjlogResult<Action> {
     }
 }
 
-fn conv_meld_from_u16(m: u16) -> MjlogResult<Meld> {
+pub(crate) fn conv_meld_from_u16(m: u16) -> MjlogResult<Meld> {
     // who called?
     let dir = Direction::from_u8((m & 0x3) as u8).unwrap();
 
@@ -234,7 +374,7 @@ fn conv_meld_from_u16(m: u16) -> MjlogResult<Meld> {
         // 0 == min
         // 1 == mid
         // 2 == max
-        let called_position = pattern % 3;
+        let called_position = CalledPosition::from_u8(pattern % 3).unwrap();
 
         // 0..6 (1..7)
         let min_number = (pattern / 3) % 7;
@@ -297,7 +437,7 @@ fn conv_meld_from_u16(m: u16) -> MjlogResult<Meld> {
         }
     } else if m & 0x20 != 0 {
         // North(not supported currently)
-        return Err(MjlogError::UnexpectedPeiNuki);
+        Err(MjlogError::UnexpectedPeiNuki)
     } else {
         // Daiminkan or Ankan
         let hai = Hai::new(((m & 0xff00) >> 8) as u8);
@@ -309,48 +449,62 @@ fn conv_meld_from_u16(m: u16) -> MjlogResult<Meld> {
     }
 }
 
-fn conv_n(e: &BytesStart) -> MjlogResult<Action> {
+fn conv_n(e: &TagAttributes) -> MjlogResult<Action> {
     let who = get_attribute_value(e, "who")?;
     let m = get_attribute_value(e, "m")?;
     Ok(Action::N(ActionN { who, m: conv_meld_from_u16(m)? }))
 }
 
-fn conv_dora(e: &BytesStart) -> MjlogResult<Action> {
+fn conv_dora(e: &TagAttributes) -> MjlogResult<Action> {
     let hai = get_attribute_value(e, "hai")?;
     Ok(Action::DORA(ActionDORA { hai }))
 }
 
-fn conv_owari(e: &BytesStart) -> MjlogResult<Option<(Vec<GamePoint>, Vec<f64>)>> {
-    let owari_csv_opt: Option<Vec<String>> = try_get_attribute_csv(e, "owari")?;
-
-    if let Some(owari_csv) = owari_csv_opt {
+fn conv_owari(e: &TagAttributes) -> MjlogResult<Option<(Vec<GamePoint>, Vec<f64>)>> {
+    if let Some(owari) = try_get_attribute_raw(e, "owari") {
+        let owari_csv: Vec<&[u8]> = split_csv(owari).collect();
         let (final_points_str, final_results_str) = get_partition_even_odd(&owari_csv);
-        let final_points = parse_vec(&final_points_str).map_err(|_| MjlogError::InvalidOwari)?;
-        let final_results = parse_vec(&final_results_str).map_err(|_| MjlogError::InvalidOwari)?;
+        let final_points = final_points_str.iter().map(|x| parse_bytes(x)).collect::<Option<_>>().ok_or(MjlogError::InvalidOwari)?;
+        let final_results = final_results_str.iter().map(|x| parse_bytes(x)).collect::<Option<_>>().ok_or(MjlogError::InvalidOwari)?;
         Ok(Some((final_points, final_results)))
     } else {
         Ok(None)
     }
 }
 
-fn conv_yaku(x: u8) -> MjlogResult<Yaku> {
-    Yaku::from_u8(x).ok_or(MjlogError::InvalidYakuNum(x))
+fn conv_yaku(x: u8, options: &ParseOptions) -> MjlogResult<Yaku> {
+    match Yaku::from_u8(x) {
+        Some(yaku) => Ok(yaku),
+        None if options.lenient => Ok(Yaku::Unknown(x)),
+        None => Err(MjlogError::InvalidYakuNum(x)),
+    }
+}
+
+fn conv_score_rank(x: u32) -> MjlogResult<ScoreRank> {
+    u8::try_from(x).ok().and_then(ScoreRank::from_u8).ok_or(MjlogError::InvalidScoreRank(x))
 }
 
-fn conv_score_rank(x: u8) -> MjlogResult<ScoreRank> {
-    ScoreRank::from_u8(x).ok_or(MjlogError::InvalidScoreRank(x))
+fn conv_agari_ten(ten: &[u32]) -> MjlogResult<AgariTen> {
+    match *ten {
+        [fu, total, limit] => Ok(AgariTen {
+            fu: u8::try_from(fu).map_err(|_| MjlogError::InvalidFu(fu))?,
+            total,
+            limit: conv_score_rank(limit)?,
+        }),
+        _ => Err(MjlogError::InvalidTenLength(ten.len())),
+    }
 }
 
-fn conv_yaku_pair(chunk: &[u8]) -> MjlogResult<(Yaku, u8)> {
+fn conv_yaku_pair(chunk: &[u8], options: &ParseOptions) -> MjlogResult<(Yaku, u8)> {
     assert_eq!(chunk.len(), 2);
 
-    let yaku = Yaku::from_u8(chunk[0]).ok_or(MjlogError::InvalidYakuNum(chunk[0]))?;
+    let yaku = conv_yaku(chunk[0], options)?;
     let han = chunk[1];
 
     Ok((yaku, han))
 }
 
-fn conv_agari(e: &BytesStart) -> MjlogResult<Action> {
+fn conv_agari(e: &TagAttributes, options: &ParseOptions) -> MjlogResult<Action> {
     let ba = get_attribute_csv(e, "ba")?;
     let hai = get_attribute_csv(e, "hai")?;
     let m_vec: Vec<u16> = try_get_attribute_csv(e, "m")?.unwrap_or(vec![]);
@@ -370,14 +524,10 @@ fn conv_agari(e: &BytesStart) -> MjlogResult<Action> {
         return Err(MjlogError::InvalidBaLength(ba.len()));
     }
 
-    if ten.len() != 3 {
-        return Err(MjlogError::InvalidTenLength(ten.len()));
-    }
-
     let m = m_vec.into_iter().map(conv_meld_from_u16).collect::<MjlogResult<Vec<Meld>>>()?;
-    let score_rank = conv_score_rank(ten[2] as u8)?;
-    let yaku = yaku_vec.chunks_exact(2).map(conv_yaku_pair).collect::<MjlogResult<Vec<(Yaku, u8)>>>()?;
-    let yakuman = yakuman_vec.into_iter().map(conv_yaku).collect::<MjlogResult<Vec<Yaku>>>()?;
+    let ten = conv_agari_ten(&ten)?;
+    let yaku = yaku_vec.chunks_exact(2).map(|x| conv_yaku_pair(x, options)).collect::<MjlogResult<Vec<(Yaku, u8)>>>()?;
+    let yakuman = yakuman_vec.into_iter().map(|x| conv_yaku(x, options)).collect::<MjlogResult<Vec<Yaku>>>()?;
 
     let agari = ActionAGARI {
         honba: ba[0],
@@ -385,9 +535,7 @@ fn conv_agari(e: &BytesStart) -> MjlogResult<Action> {
         hai,
         m,
         machi,
-        fu: ten[0] as u8,
-        net_score: ten[1],
-        score_rank,
+        ten,
         yaku,
         yakuman,
         dora_hai,
@@ -403,14 +551,14 @@ fn conv_agari(e: &BytesStart) -> MjlogResult<Action> {
     Ok(Action::AGARI(agari))
 }
 
-fn conv_ryuukyoku(e: &BytesStart) -> MjlogResult<Action> {
+fn conv_ryuukyoku(e: &TagAttributes) -> MjlogResult<Action> {
     let ba = get_attribute_csv(e, "ba")?;
     let hai0 = try_get_attribute_csv(e, "hai0")?;
     let hai1 = try_get_attribute_csv(e, "hai1")?;
     let hai2 = try_get_attribute_csv(e, "hai2")?;
     let hai3 = try_get_attribute_csv(e, "hai3")?;
     let (before_points, delta_points) = get_partition_even_odd(&get_attribute_csv(e, "sc")?);
-    let type_str_opt: Option<String> = try_get_attribute_str(e, "type")?;
+    let type_str_opt = try_get_attribute_str(e, "type")?;
     let owari = conv_owari(e)?;
 
     if ba.len() != 2 {
@@ -453,61 +601,196 @@ fn parse_hai_tag(n: &[u8]) -> Option<Action> {
     }
 }
 
-fn conv_action(e: &BytesStart) -> MjlogResult<Action> {
+fn conv_action(e: &BytesStart, options: &ParseOptions) -> MjlogResult<Action> {
+    // Draws and discards are most of the tags, and have no attributes.
+    if let Some(action) = parse_hai_tag(e.name().as_ref()) {
+        return Ok(action);
+    }
+    let attrs = || TagAttributes::new(e);
     let event = match e.name().as_ref() {
-        b"SHUFFLE" => conv_shuffle(e)?,
-        b"GO" => conv_go(e)?,
-        b"UN" => conv_uv(e)?,
-        b"BYE" => conv_bye(e)?,
-        b"TAIKYOKU" => conv_taikyoku(e)?,
-        b"INIT" => conv_init(e)?,
-        b"REACH" => conv_reach(e)?,
-        b"N" => conv_n(e)?,
-        b"DORA" => conv_dora(e)?,
-        b"AGARI" => conv_agari(e)?,
-        b"RYUUKYOKU" => conv_ryuukyoku(e)?,
-        x => parse_hai_tag(x).ok_or(MjlogError::UnexpectedTag(String::from_utf8_lossy(e.name().as_ref()).to_string()))?,
+        b"SHUFFLE" => conv_shuffle(&attrs()?)?,
+        b"GO" => conv_go(&attrs()?)?,
+        b"UN" => conv_uv(&attrs()?)?,
+        b"BYE" => conv_bye(&attrs()?)?,
+        b"TAIKYOKU" => conv_taikyoku(&attrs()?)?,
+        b"INIT" => conv_init(&attrs()?)?,
+        b"REACH" => conv_reach(&attrs()?)?,
+        b"N" => conv_n(&attrs()?)?,
+        b"DORA" => conv_dora(&attrs()?)?,
+        b"AGARI" => conv_agari(&attrs()?, options)?,
+        b"RYUUKYOKU" => conv_ryuukyoku(&attrs()?)?,
+        _ if options.keep_unknown_tags => conv_unknown(e)?,
+        _ => return Err(MjlogError::UnexpectedTag(String::from_utf8_lossy(e.name().as_ref()).to_string())),
     };
     Ok(event)
 }
 
-fn conv_mjloggm<R: std::io::BufRead>(reader: &mut Reader<R>, e: &BytesStart) -> MjlogResult<Mjlog> {
-    let ver = get_attribute_value(e, "ver")?;
+// Attributes read by the parser for each known tag. The lenient parser reports the others.
+fn known_attributes(name: &[u8]) -> Option<&'static [&'static str]> {
+    match name {
+        b"SHUFFLE" => Some(&["seed", "ref"]),
+        b"GO" => Some(&["type", "lobby"]),
+        b"UN" => Some(&["n0", "n1", "n2", "n3", "dan", "rate", "sx"]),
+        b"BYE" => Some(&["who"]),
+        b"TAIKYOKU" => Some(&["oya"]),
+        b"INIT" => Some(&["seed", "ten", "oya", "hai0", "hai1", "hai2", "hai3"]),
+        b"REACH" => Some(&["who", "step", "ten"]),
+        b"N" => Some(&["who", "m"]),
+        b"DORA" => Some(&["hai"]),
+        b"AGARI" => Some(&["ba", "hai", "m", "machi", "ten", "yaku", "yakuman", "doraHai", "doraHaiUra", "who", "fromWho", "paoWho", "sc", "owari"]),
+        b"RYUUKYOKU" => Some(&["ba", "hai0", "hai1", "hai2", "hai3", "sc", "type", "owari"]),
+        x if parse_hai_tag(x).is_some() => Some(&[]),
+        _ => None,
+    }
+}
+
+fn conv_unknown(e: &BytesStart) -> MjlogResult<Action> {
+    let mut attrs = Vec::new();
+    for attr in e.attributes() {
+        let attr = attr?;
+        attrs.push((String::from_utf8_lossy(attr.key.as_ref()).to_string(), attr.unescape_value()?.to_string()));
+    }
+    Ok(Action::Unknown(ActionUnknown {
+        name: String::from_utf8_lossy(e.name().as_ref()).to_string(),
+        attrs,
+    }))
+}
+
+// Unknown and invalid tags are kept as `Action::Unknown`, and unknown attributes are ignored, with warnings.
+fn conv_action_lenient(e: &BytesStart, options: &ParseOptions, game: usize, warnings: &mut Vec<MjlogWarning>) -> MjlogResult<Action> {
+    let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
+    let Some(known) = known_attributes(e.name().as_ref()) else {
+        warnings.push(MjlogWarning::UnknownTag { game, name });
+        return conv_unknown(e);
+    };
+    match conv_action(e, options) {
+        Ok(action) => {
+            for attr in e.attributes() {
+                let key = String::from_utf8_lossy(attr?.key.as_ref()).to_string();
+                if !known.contains(&key.as_str()) {
+                    warnings.push(MjlogWarning::UnknownAttribute { game, tag: name.clone(), attr: key });
+                }
+            }
+            Ok(action)
+        }
+        Err(error) => {
+            warnings.push(MjlogWarning::InvalidTag { game, name, error: error.to_string() });
+            conv_unknown(e)
+        }
+    }
+}
+
+// `start` is set to the offset of each event, for the position of the error.
+fn conv_mjloggm(reader: &mut Reader<&[u8]>, e: &BytesStart, options: &ParseOptions, game: usize, warnings: &mut Vec<MjlogWarning>, start: &mut u64) -> MjlogResult<Mjlog> {
+    let ver = get_attribute_value(&TagAttributes::new(e)?, "ver")?;
 
     let mut actions = Vec::new();
-    let mut buf = Vec::new();
     loop {
-        match reader.read_event_into(&mut buf)? {
+        *start = reader.buffer_position();
+        // Syntax errors are unclosed markup at the end of the text.
+        let event = match reader.read_event() {
+            Ok(Event::Eof) | Err(quick_xml::errors::Error::Syntax(_)) if options.allow_truncated => {
+                if options.lenient {
+                    warnings.push(MjlogWarning::Truncated { game, actions: actions.len() });
+                }
+                return Ok(Mjlog { ver, actions, incomplete: true });
+            }
+            x => x?,
+        };
+        match event {
             Event::Decl(_) => continue,
             Event::DocType(_) => continue,
             Event::Comment(_) => continue,
             Event::Eof => return Err(MjlogError::UnexpectedEof),
+            Event::PI(_) if options.lenient => warnings.push(MjlogWarning::Ignored {
+                game,
+                error: MjlogError::UnexpectedPI.to_string(),
+            }),
+            Event::CData(_) if options.lenient => warnings.push(MjlogWarning::Ignored {
+                game,
+                error: MjlogError::UnexpectedCData.to_string(),
+            }),
+            Event::Text(_) if options.lenient => warnings.push(MjlogWarning::Ignored {
+                game,
+                error: MjlogError::UnexpectedText.to_string(),
+            }),
+            Event::Start(e) if options.lenient => {
+                let name = e.name().as_ref().to_vec();
+                warnings.push(MjlogWarning::Ignored {
+                    game,
+                    error: MjlogError::UnexpectedTag(String::from_utf8_lossy(&name).to_string()).to_string(),
+                });
+                reader.read_to_end(quick_xml::name::QName(&name))?;
+            }
             Event::PI(_) => return Err(MjlogError::UnexpectedPI),
             Event::CData(_) => return Err(MjlogError::UnexpectedCData),
             Event::Text(_) => return Err(MjlogError::UnexpectedText),
             Event::Start(e) => return Err(MjlogError::UnexpectedTag(String::from_utf8_lossy(e.name().as_ref()).to_string())),
-            Event::Empty(e) => actions.push(conv_action(&e)?),
-            Event::End(e) if e.as_ref() == b"mjloggm" => return Ok(Mjlog { ver, actions }),
+            Event::Empty(e) if options.lenient => actions.push(conv_action_lenient(&e, options, game, warnings)?),
+            Event::Empty(e) => actions.push(conv_action(&e, options)?),
+            Event::End(e) if e.as_ref() == b"mjloggm" => return Ok(Mjlog { ver, actions, incomplete: false }),
             Event::End(e) => return Err(MjlogError::UnexpectedTag(String::from_utf8_lossy(e.name().as_ref()).to_string())),
         }
     }
 }
 
 pub fn parse_mjlogs(text: &str) -> MjlogResult<Vec<Mjlog>> {
+    parse_mjlogs_with(text, &ParseOptions::default())
+}
+
+/// Same as `parse_mjlogs`, but decodes the bytes of a file in the detected encoding first. See `encoding`.
+pub fn parse_mjlogs_bytes(bytes: &[u8]) -> MjlogResult<Vec<Mjlog>> {
+    parse_mjlogs(&decode_mjlog(bytes)?)
+}
+
+pub fn parse_mjlogs_with(text: &str, options: &ParseOptions) -> MjlogResult<Vec<Mjlog>> {
+    let mut mjlogs = Vec::new();
+    parse_mjlogs_into(text, options, &mut mjlogs, &mut Vec::new())?;
+    Ok(mjlogs)
+}
+
+/// Parses in lenient mode (see `ParseOptions::lenient`), and never fails. What is accepted or skipped is in the warnings.
+///
+/// A truncated game is kept (see `ParseOptions::allow_truncated`). Any other broken XML stops the parse,
+/// where the game being parsed and the rest of the text are skipped with a warning.
+pub fn parse_mjlogs_lenient(text: &str) -> (Vec<Mjlog>, Vec<MjlogWarning>) {
+    let mut mjlogs = Vec::new();
+    let mut warnings = Vec::new();
+    if let Err(e) = parse_mjlogs_into(
+        text,
+        &ParseOptions {
+            lenient: true,
+            allow_truncated: true,
+            ..Default::default()
+        },
+        &mut mjlogs,
+        &mut warnings,
+    ) {
+        warnings.push(MjlogWarning::Skipped { game: mjlogs.len(), error: e.to_string() });
+    }
+    (mjlogs, warnings)
+}
+
+// Pushes the games parsed so far, so that the lenient parser keeps them on failure.
+fn parse_mjlogs_into(text: &str, options: &ParseOptions, mjlogs: &mut Vec<Mjlog>, warnings: &mut Vec<MjlogWarning>) -> MjlogResult<()> {
     let mut reader = Reader::from_reader(text.as_ref());
 
     // Ignore spaces for xmllint
     reader.config_mut().trim_text(true);
 
-    // Convert all event types
-    let mut mjlogs = Vec::new();
-    let mut buf = Vec::new();
+    let mut start = 0;
+    conv_events(&mut reader, options, mjlogs, warnings, &mut start).map_err(|e| e.at(text, &reader, start, mjlogs.len()))
+}
+
+fn conv_events(reader: &mut Reader<&[u8]>, options: &ParseOptions, mjlogs: &mut Vec<Mjlog>, warnings: &mut Vec<MjlogWarning>, start: &mut u64) -> MjlogResult<()> {
+    // Convert all event types. Events borrow the text, without copying.
     loop {
-        match reader.read_event_into(&mut buf)? {
+        *start = reader.buffer_position();
+        match reader.read_event()? {
             Event::Decl(_) => continue,
             Event::DocType(_) => continue,
             Event::Comment(_) => continue,
-            Event::Eof => return Ok(mjlogs),
+            Event::Eof => return Ok(()),
             Event::PI(_) => return Err(MjlogError::UnexpectedPI),
             Event::CData(_) => return Err(MjlogError::UnexpectedCData),
             Event::Text(_) => return Err(MjlogError::UnexpectedText),
@@ -516,10 +799,103 @@ pub fn parse_mjlogs(text: &str) -> MjlogResult<Vec<Mjlog>> {
                     return Err(MjlogError::UnexpectedTag(String::from_utf8_lossy(e.name().as_ref()).to_string()));
                 }
 
-                mjlogs.push(conv_mjloggm(&mut reader, &e)?);
+                let game = mjlogs.len();
+                let mjlog = conv_mjloggm(reader, &e, options, game, warnings, start)?;
+                let yaku = mjlog.actions.iter().filter_map(|x| x.as_agari()).flat_map(|x| x.yaku.iter().map(|&(y, _)| y).chain(x.yakuman.iter().copied()));
+                for y in yaku {
+                    if let Yaku::Unknown(id) = y {
+                        warnings.push(MjlogWarning::UnknownYaku { game, id });
+                    }
+                }
+                mjlogs.push(mjlog);
             }
             Event::Empty(e) => return Err(MjlogError::UnexpectedTag(String::from_utf8_lossy(e.name().as_ref()).to_string())),
             Event::End(e) => return Err(MjlogError::UnexpectedTag(String::from_utf8_lossy(e.name().as_ref()).to_string())),
         }
     }
 }
+
+/// Splits the XML into the text of each mjloggm tag, without parsing the contents.
+pub fn split_mjlogs(text: &str) -> MjlogResult<Vec<&str>> {
+    let mut reader = Reader::from_reader(text.as_ref());
+    reader.config_mut().trim_text(true);
+
+    let mut ret = Vec::new();
+    let mut start = 0;
+    split_events(text, &mut reader, &mut ret, &mut start).map_err(|e| e.at(text, &reader, start, ret.len()))?;
+    Ok(ret)
+}
+
+fn split_events<'a>(text: &'a str, reader: &mut Reader<&[u8]>, ret: &mut Vec<&'a str>, start: &mut u64) -> MjlogResult<()> {
+    loop {
+        *start = reader.buffer_position();
+        match reader.read_event()? {
+            Event::Eof => return Ok(()),
+            Event::Start(e) if e.name().as_ref() == b"mjloggm" => {
+                let start = text[..reader.buffer_position() as usize].rfind("<mjloggm").unwrap_or(0);
+                let name = e.name().as_ref().to_vec();
+                reader.read_to_end(quick_xml::name::QName(&name))?;
+                ret.push(&text[start..reader.buffer_position() as usize]);
+            }
+            Event::Decl(_) | Event::DocType(_) | Event::Comment(_) => continue,
+            Event::Start(e) | Event::Empty(e) => return Err(MjlogError::UnexpectedTag(String::from_utf8_lossy(e.name().as_ref()).to_string())),
+            Event::End(e) => return Err(MjlogError::UnexpectedTag(String::from_utf8_lossy(e.name().as_ref()).to_string())),
+            Event::PI(_) => return Err(MjlogError::UnexpectedPI),
+            Event::CData(_) => return Err(MjlogError::UnexpectedCData),
+            Event::Text(_) => return Err(MjlogError::UnexpectedText),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_position() {
+        let xml = "<mjloggm ver=\"2.3\"></mjloggm>\n<mjloggm ver=\"2.3\">\n  <GO type=\"169\" lobby=\"0\"/>\n  <FOO/></mjloggm>";
+        let e = parse_mjlogs(xml).unwrap_err();
+        assert!(matches!(e.kind(), MjlogError::UnexpectedTag(x) if x == "FOO"));
+        assert_eq!(
+            e.position(),
+            Some(MjlogPosition {
+                offset: xml.find("<FOO").unwrap(),
+                line: 4,
+                column: 3,
+                game: 1
+            })
+        );
+        assert_eq!(e.to_string(), format!("line 4, column 3 (byte {}, game 1): Unexpected tag: FOO", xml.find("<FOO").unwrap()));
+
+        let e = split_mjlogs("<mjloggm ver=\"2.3\"></mjloggm>\n<mjloggm ver=\"2.3\"><GO").unwrap_err();
+        assert!(matches!(e.kind(), MjlogError::XmlError(_)));
+        assert_eq!(e.position().map(|x| (x.line, x.column, x.game)), Some((2, 20, 1)));
+    }
+
+    #[test]
+    fn test_parse_mjlogs_lenient() {
+        let xml = r#"<mjloggm ver="2.3"><GO type="169" lobby="0" rule="x"/><FUTURE a="1" b="&lt;"/><TAIKYOKU oya="9z"/>text</mjloggm><mjloggm ver="2.3"></FOO>"#;
+        assert!(parse_mjlogs(xml).is_err());
+
+        let (mjlogs, warnings) = parse_mjlogs_lenient(xml);
+        assert_eq!(mjlogs.len(), 1);
+        let actions = &mjlogs[0].actions;
+        assert!(actions[0].is_go());
+        assert_eq!(actions[1].as_unknown().unwrap().attrs, vec![("a".to_string(), "1".to_string()), ("b".to_string(), "<".to_string())]);
+        assert_eq!(actions[2].as_unknown().unwrap().name, "TAIKYOKU");
+
+        assert_eq!(warnings.len(), 5);
+        assert_eq!(
+            warnings[0],
+            MjlogWarning::UnknownAttribute {
+                game: 0,
+                tag: "GO".to_string(),
+                attr: "rule".to_string()
+            }
+        );
+        assert_eq!(warnings[1], MjlogWarning::UnknownTag { game: 0, name: "FUTURE".to_string() });
+        assert!(matches!(&warnings[2], MjlogWarning::InvalidTag { name, .. } if name == "TAIKYOKU"));
+        assert!(matches!(warnings[3], MjlogWarning::Ignored { game: 0, .. }));
+        assert!(matches!(warnings[4], MjlogWarning::Skipped { game: 1, .. }));
+    }
+}
@@ -0,0 +1,21 @@
+//! # prelude
+//!
+//! The stable public API of this crate.
+//!
+//! Items exported here follow semantic versioning. Everything else may change in minor versions.
+//!
+//! ```ignore
+//! use mjlog::prelude::*;
+//! ```
+
+pub use crate::encoding::{decode_mjlog, detect_encoding, MjlogEncoding};
+pub use crate::exporter::{export_meld, export_mjlog};
+pub use crate::generator::{generate_mjlog, GeneratorOptions};
+pub use crate::model::{
+    atamahane, count_dora, count_dora_with, Action, ActionAGARI, ActionBYE, ActionDISCARD, ActionDORA, ActionDRAW, ActionGO, ActionINIT, ActionN, ActionREACH1, ActionREACH2, ActionRYUUKYOKU, ActionSHUFFLE, ActionTAIKYOKU, ActionUN1, ActionUN2,
+    ActionUnknown, AgariTen, CalledPosition, Direction, ExtraRyuukyokuReason, GamePoint, GameSettings, Hai, InitSeed, Meld, Mjlog, ParseError, Player, ScoreRank, TenhouRank, TenhouRoom, Yaku,
+};
+pub use crate::parser::{parse_mjlogs, parse_mjlogs_bytes, parse_mjlogs_lenient, parse_mjlogs_with, split_mjlogs, MjlogError, MjlogPosition, MjlogResult, MjlogWarning, ParseOptions};
+pub use crate::replay::{DiscardedTile, GameState, PlayerState, ReplayError, ReplayResult};
+pub use crate::visitor::{visit_mjlog, ActionVisitor};
+pub use crate::wall::{verify_wall, Wall, WallCheck, WallError, WallGenerator, WallMismatch, WallResult};
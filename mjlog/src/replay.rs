@@ -0,0 +1,360 @@
+//! # replay
+//!
+//! Reconstructs the game state by applying actions one by one.
+//!
+//! # Usage
+//!
+//! ```ignore
+//! let mut state = GameState::new();
+//! for action in &mjlog.actions {
+//!     state.apply(action)?;
+//!     println!("{:?}", state.players[0].hand);
+//! }
+//! ```
+
+use crate::model::*;
+use thiserror::Error;
+
+/// Number of tiles that can be drawn in a round, excluding the dead wall.
+const WALL_SIZE_4P: u8 = 136 - 14 - 13 * 4;
+const WALL_SIZE_3P: u8 = 108 - 14 - 13 * 3;
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("Action before INIT")]
+    RoundNotStarted,
+    #[error("Invalid player: {0}")]
+    InvalidPlayer(u8),
+    #[error("Hai {hai} is not in the hand of player {who}")]
+    HaiNotInHand { who: u8, hai: u8 },
+    #[error("Wall is empty")]
+    WallEmpty,
+}
+
+pub type ReplayResult<T> = Result<T, ReplayError>;
+
+/// A tile in the river.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiscardedTile {
+    pub hai: Hai,
+    /// Discarded the tile just drawn.
+    pub tsumogiri: bool,
+    /// Discarded with the riichi declaration.
+    pub riichi: bool,
+    /// Called by another player. The tile is still kept in the river.
+    pub called: bool,
+}
+
+/// State of each player.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerState {
+    /// Concealed tiles, in the order drawn.
+    pub hand: Vec<Hai>,
+    pub melds: Vec<Meld>,
+    pub discards: Vec<DiscardedTile>,
+    /// Riichi is declared (REACH step 1), but may not be accepted yet.
+    pub riichi_declared: bool,
+    /// Riichi is accepted (REACH step 2).
+    pub riichi: bool,
+    /// Points in units of 100, same as the XML.
+    pub score: GamePoint,
+}
+
+/// State of the game at a point.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GameState {
+    pub settings: GameSettings,
+    pub kyoku: u8,
+    pub honba: u8,
+    pub kyoutaku: u8,
+    pub oya: Player,
+    pub players: Vec<PlayerState>,
+    /// Dora indicators revealed so far.
+    pub dora_indicators: Vec<Hai>,
+    /// Number of tiles left to draw.
+    pub wall_remaining: u8,
+    /// The player to act next, or the player who acted last.
+    pub current: Player,
+    /// True after INIT until AGARI or RYUUKYOKU.
+    pub in_round: bool,
+    last_draw: Option<Hai>,
+    last_discard: Option<Player>,
+}
+
+fn remove_hai(hand: &mut Vec<Hai>, who: Player, hai: Hai) -> ReplayResult<()> {
+    match hand.iter().position(|&x| x == hai) {
+        Some(pos) => {
+            hand.remove(pos);
+            Ok(())
+        }
+        None => Err(ReplayError::HaiNotInHand { who: who.to_u8(), hai: hai.to_u8() }),
+    }
+}
+
+/// Tiles taken from the hand by the meld.
+pub fn meld_hand_hais(m: &Meld) -> Vec<Hai> {
+    match *m {
+        Meld::Chii { combination: (a, b, c), called_position } => {
+            let mut v = vec![a, b, c];
+            v.remove(called_position as usize);
+            v
+        }
+        Meld::Pon { combination: (a, b, c), called, .. } => [a, b, c].into_iter().filter(|&x| x != called).collect(),
+        Meld::Kakan { added, .. } => vec![added],
+        Meld::Daiminkan { hai, .. } => {
+            let base = hai.to_u8() / 4 * 4;
+            (base..base + 4).map(Hai::new).filter(|&x| x != hai).collect()
+        }
+        Meld::Ankan { hai } => {
+            let base = hai.to_u8() / 4 * 4;
+            (base..base + 4).map(Hai::new).collect()
+        }
+    }
+}
+
+/// All tiles of the meld, including the called one.
+pub fn meld_hais(m: &Meld) -> Vec<Hai> {
+    match *m {
+        Meld::Chii { combination: (a, b, c), .. } | Meld::Pon { combination: (a, b, c), .. } => vec![a, b, c],
+        Meld::Kakan { combination: (a, b, c), added, .. } => vec![a, b, c, added],
+        Meld::Daiminkan { hai, .. } | Meld::Ankan { hai } => {
+            let base = hai.to_u8() / 4 * 4;
+            (base..base + 4).map(Hai::new).collect()
+        }
+    }
+}
+
+impl GameState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replays the actions from the beginning.
+    pub fn from_actions(actions: &[Action]) -> ReplayResult<Self> {
+        let mut state = Self::new();
+        for a in actions {
+            state.apply(a)?;
+        }
+        Ok(state)
+    }
+
+    fn player_mut(&mut self, who: Player) -> ReplayResult<&mut PlayerState> {
+        if !self.in_round {
+            return Err(ReplayError::RoundNotStarted);
+        }
+        self.players.get_mut(who.to_u8() as usize).ok_or(ReplayError::InvalidPlayer(who.to_u8()))
+    }
+
+    fn set_scores(&mut self, points: &[GamePoint]) {
+        for (p, &x) in self.players.iter_mut().zip(points) {
+            p.score = x;
+        }
+    }
+
+    /// Applies an action to the state.
+    pub fn apply(&mut self, action: &Action) -> ReplayResult<()> {
+        match action {
+            Action::GO(x) => self.settings = x.settings.clone(),
+            Action::INIT(x) => {
+                let num_players = if self.settings.sanma { 3 } else { 4 };
+                self.kyoku = x.seed.kyoku;
+                self.honba = x.seed.honba;
+                self.kyoutaku = x.seed.kyoutaku;
+                self.oya = x.oya;
+                self.current = x.oya;
+                self.players = (0..num_players)
+                    .map(|i| PlayerState {
+                        hand: x.hai.get(i).cloned().unwrap_or_default(),
+                        score: x.ten.get(i).copied().unwrap_or_default(),
+                        ..Default::default()
+                    })
+                    .collect();
+                self.dora_indicators = vec![x.seed.dora_hyouji];
+                self.wall_remaining = if self.settings.sanma { WALL_SIZE_3P } else { WALL_SIZE_4P };
+                self.in_round = true;
+                self.last_draw = None;
+                self.last_discard = None;
+            }
+            Action::DRAW(x) => {
+                // The wall is also empty before INIT, so the round is checked first.
+                let remaining = self.wall_remaining.checked_sub(1);
+                let player = self.player_mut(x.who)?;
+                let remaining = remaining.ok_or(ReplayError::WallEmpty)?;
+                player.hand.push(x.hai);
+                self.wall_remaining = remaining;
+                self.current = x.who;
+                self.last_draw = Some(x.hai);
+                self.last_discard = None;
+            }
+            Action::DISCARD(x) => {
+                let tsumogiri = self.last_draw == Some(x.hai);
+                let player = self.player_mut(x.who)?;
+                remove_hai(&mut player.hand, x.who, x.hai)?;
+                let riichi = player.riichi_declared && !player.riichi;
+                player.discards.push(DiscardedTile { hai: x.hai, tsumogiri, riichi, called: false });
+                self.current = x.who;
+                self.last_draw = None;
+                self.last_discard = Some(x.who);
+            }
+            Action::REACH1(x) => self.player_mut(x.who)?.riichi_declared = true,
+            Action::REACH2(x) => {
+                self.player_mut(x.who)?.riichi = true;
+                self.kyoutaku += 1;
+                self.set_scores(&x.ten);
+            }
+            Action::N(x) => {
+                let called_from = match x.m {
+                    Meld::Chii { .. } | Meld::Pon { .. } | Meld::Daiminkan { .. } => self.last_discard,
+                    _ => None,
+                };
+                if let Some(from) = called_from {
+                    if let Some(t) = self.player_mut(from)?.discards.last_mut() {
+                        t.called = true;
+                    }
+                }
+
+                let player = self.player_mut(x.who)?;
+                for hai in meld_hand_hais(&x.m) {
+                    remove_hai(&mut player.hand, x.who, hai)?;
+                }
+                match x.m {
+                    // Kakan upgrades the existing pon.
+                    Meld::Kakan { called, .. } => {
+                        player.melds.retain(|m| !matches!(m, Meld::Pon { called: c, .. } if *c == called));
+                        player.melds.push(x.m.clone());
+                    }
+                    _ => player.melds.push(x.m.clone()),
+                }
+                self.current = x.who;
+                self.last_draw = None;
+                self.last_discard = None;
+            }
+            Action::DORA(x) => self.dora_indicators.push(x.hai),
+            Action::AGARI(x) => {
+                let points: Vec<GamePoint> = x.before_points.iter().zip(&x.delta_points).map(|(a, b)| a + b).collect();
+                self.set_scores(&points);
+                self.kyoutaku = 0;
+                self.in_round = false;
+            }
+            Action::RYUUKYOKU(x) => {
+                let points: Vec<GamePoint> = x.before_points.iter().zip(&x.delta_points).map(|(a, b)| a + b).collect();
+                self.set_scores(&points);
+                self.in_round = false;
+            }
+            Action::SHUFFLE(_) | Action::UN1(_) | Action::UN2(_) | Action::BYE(_) | Action::TAIKYOKU(_) | Action::Unknown(_) => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw(who: u8, hai: u8) -> Action {
+        Action::DRAW(ActionDRAW { who: Player::new(who), hai: Hai::new(hai) })
+    }
+
+    fn discard(who: u8, hai: u8) -> Action {
+        Action::DISCARD(ActionDISCARD { who: Player::new(who), hai: Hai::new(hai) })
+    }
+
+    fn called_count(state: &GameState) -> usize {
+        state.players.iter().flat_map(|x| &x.discards).filter(|t| t.called).count()
+    }
+
+    fn hais(xs: impl IntoIterator<Item = u8>) -> Vec<Hai> {
+        xs.into_iter().map(Hai::new).collect()
+    }
+
+    #[test]
+    fn test_apply_before_init() {
+        assert!(matches!(GameState::new().apply(&draw(0, 0)), Err(ReplayError::RoundNotStarted)));
+        assert!(matches!(GameState::new().apply(&discard(0, 0)), Err(ReplayError::RoundNotStarted)));
+    }
+
+    #[test]
+    fn test_apply() {
+        let pon = Meld::Pon {
+            dir: Direction::Kamicha,
+            combination: (Hai::new(40), Hai::new(41), Hai::new(42)),
+            called: Hai::new(40),
+            unused: Hai::new(43),
+        };
+        let kakan = Meld::Kakan {
+            dir: Direction::Kamicha,
+            combination: (Hai::new(40), Hai::new(41), Hai::new(42)),
+            called: Hai::new(40),
+            added: Hai::new(43),
+        };
+        let init = ActionINIT {
+            seed: InitSeed {
+                kyoku: 0,
+                honba: 0,
+                kyoutaku: 0,
+                dice: (1, 1),
+                dora_hyouji: Hai::new(132),
+            },
+            ten: vec![250; 4],
+            oya: Player::new(0),
+            hai: vec![hais((40..=88).step_by(4)), hais((1..=37).step_by(4).chain([41, 42, 45])), hais(89..=101), hais(102..=114)],
+        };
+        let actions = vec![
+            Action::GO(ActionGO { settings: GameSettings::default(), lobby: 0 }),
+            Action::INIT(init),
+            draw(0, 120),
+            discard(0, 120),
+            draw(1, 121),
+            discard(1, 45),
+            draw(2, 122),
+            Action::REACH1(ActionREACH1 { who: Player::new(2) }),
+            discard(2, 89),
+            Action::REACH2(ActionREACH2 {
+                who: Player::new(2),
+                ten: vec![250, 250, 240, 250],
+            }),
+            draw(3, 123),
+            discard(3, 123),
+            draw(0, 124),
+            discard(0, 40),
+            Action::N(ActionN { who: Player::new(1), m: pon.clone() }),
+            discard(1, 1),
+            draw(2, 125),
+            discard(2, 125),
+            draw(3, 126),
+            discard(3, 126),
+            draw(0, 127),
+            discard(0, 127),
+            draw(1, 43),
+        ];
+
+        let mut state = GameState::from_actions(&actions).unwrap();
+        let p = &state.players;
+
+        // Tsumogiri is a discard of the tile just drawn, not of a tile from the hand or after a call.
+        assert_eq!(p[0].discards.iter().map(|t| t.tsumogiri).collect::<Vec<_>>(), vec![true, false, true]);
+        assert_eq!(p[1].discards.iter().map(|t| t.tsumogiri).collect::<Vec<_>>(), vec![false, false]);
+
+        // Only the called tile is marked in the river.
+        assert_eq!(p[0].discards.iter().map(|t| t.called).collect::<Vec<_>>(), vec![false, true, false]);
+        assert_eq!(called_count(&state), 1);
+        assert_eq!(p[1].melds, vec![pon]);
+        assert!(!p[1].hand.contains(&Hai::new(41)) && !p[1].hand.contains(&Hai::new(42)));
+
+        // Only the declaration tile is the riichi tile, and the deposit is taken on step 2.
+        assert_eq!(p[2].discards.iter().map(|t| t.riichi).collect::<Vec<_>>(), vec![true, false]);
+        assert!(p[2].riichi_declared && p[2].riichi);
+        assert_eq!(p[2].score, 240);
+        assert_eq!(state.kyoutaku, 1);
+
+        // 9 tiles are drawn from the 70 tiles of the live wall.
+        assert_eq!(state.wall_remaining, 61);
+        assert_eq!(p[1].hand.len(), 11);
+
+        // Kakan replaces the pon instead of adding a meld.
+        state.apply(&Action::N(ActionN { who: Player::new(1), m: kakan.clone() })).unwrap();
+        assert_eq!(state.players[1].melds, vec![kakan]);
+        assert_eq!(state.players[1].hand.len(), 10);
+        assert_eq!(called_count(&state), 1);
+    }
+}
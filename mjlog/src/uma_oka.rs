@@ -0,0 +1,82 @@
+//! # uma_oka
+//!
+//! Final results from final points, by the rule of tenhou ranked games.
+//!
+//! * 4 players: 25000 start, 30000 return, uma +20/+10/-10/-20.
+//! * 3 players: 35000 start, 40000 return, uma +20/0/-20.
+//!
+//! The difference from the return points is rounded in thousands, with .5 rounded down (五捨六入),
+//! and the top player takes the rest so that the results sum to zero (oka included).
+
+use crate::model::GamePoint;
+
+/// Points to return and uma by placement. Points are in units of 100, same as mjlog.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UmaOka {
+    pub return_points: GamePoint,
+    pub uma: Vec<i32>,
+}
+
+impl UmaOka {
+    pub fn yonma() -> Self {
+        UmaOka {
+            return_points: 300,
+            uma: vec![20, 10, -10, -20],
+        }
+    }
+
+    pub fn sanma() -> Self {
+        UmaOka { return_points: 400, uma: vec![20, 0, -20] }
+    }
+
+    /// Tenhou's rule for the number of players.
+    pub fn for_players(sanma: bool) -> Self {
+        if sanma {
+            Self::sanma()
+        } else {
+            Self::yonma()
+        }
+    }
+}
+
+/// 0-origin placement of each player. Ties go to the earlier seat, which is the first dealer's side.
+pub fn placements(points: &[GamePoint]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(points[i]));
+    let mut ranks = vec![0; points.len()];
+    for (rank, &who) in order.iter().enumerate() {
+        ranks[who] = rank;
+    }
+    ranks
+}
+
+/// Final results of the players, in seat order.
+pub fn final_results(points: &[GamePoint], rule: &UmaOka) -> Vec<f64> {
+    let ranks = placements(points);
+    let mut results: Vec<i32> = points
+        .iter()
+        .zip(&ranks)
+        // (diff + 400) / 1000 rounded toward negative infinity, in units of 100: .5 down, .6 up.
+        .map(|(&x, &rank)| (x - rule.return_points + 4).div_euclid(10) + rule.uma.get(rank).copied().unwrap_or_default())
+        .collect();
+
+    if let Some(top) = ranks.iter().position(|&x| x == 0) {
+        let others: i32 = results.iter().enumerate().filter(|&(i, _)| i != top).map(|(_, x)| x).sum();
+        results[top] = -others;
+    }
+    results.into_iter().map(f64::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_final_results() {
+        // Same as the owari of the selftest fixture.
+        assert_eq!(final_results(&[280, 240, 263, 217], &UmaOka::yonma()), [38.0, -16.0, 6.0, -28.0]);
+        // Ties go to the earlier seat. +1.5 is rounded to +1, -11.4 to -11 and -11.6 to -12.
+        assert_eq!(final_results(&[315, 315, 184, 186], &UmaOka::yonma()), [42.0, 11.0, -32.0, -21.0]);
+        assert_eq!(final_results(&[500, 380, 170], &UmaOka::sanma()), [45.0, -2.0, -43.0]);
+    }
+}
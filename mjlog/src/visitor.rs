@@ -0,0 +1,128 @@
+//! # visitor
+//!
+//! Callbacks over the actions of a game, with the game state kept by the driver.
+//!
+//! Implement only the callbacks needed. Each callback gets the state after the action is applied,
+//! e.g. the drawn tile is in the hand in `on_draw`, and the scores include the win in `on_agari`.
+//!
+//! # Usage
+//!
+//! ```ignore
+//! struct RiichiCount(usize);
+//!
+//! impl ActionVisitor for RiichiCount {
+//!     fn on_riichi(&mut self, _state: &GameState, _action: &ActionREACH1) {
+//!         self.0 += 1;
+//!     }
+//! }
+//!
+//! let mut count = RiichiCount(0);
+//! visit_mjlog(&mjlog, &mut count)?;
+//! ```
+
+use crate::model::*;
+use crate::replay::*;
+
+/// Callbacks of `visit_mjlog`, one per tag. They do nothing by default.
+#[allow(unused_variables)]
+pub trait ActionVisitor {
+    /// Called for every action before the callback of the tag.
+    fn on_action(&mut self, state: &GameState, action: &Action) {}
+    fn on_shuffle(&mut self, state: &GameState, action: &ActionSHUFFLE) {}
+    fn on_go(&mut self, state: &GameState, action: &ActionGO) {}
+    /// The first UN tag with the names, dan and rate.
+    fn on_players(&mut self, state: &GameState, action: &ActionUN1) {}
+    fn on_taikyoku(&mut self, state: &GameState, action: &ActionTAIKYOKU) {}
+    fn on_init(&mut self, state: &GameState, action: &ActionINIT) {}
+    fn on_draw(&mut self, state: &GameState, action: &ActionDRAW) {}
+    fn on_discard(&mut self, state: &GameState, action: &ActionDISCARD) {}
+    /// Riichi declaration (REACH step 1).
+    fn on_riichi(&mut self, state: &GameState, action: &ActionREACH1) {}
+    /// Riichi accepted after the discard (REACH step 2).
+    fn on_riichi_accepted(&mut self, state: &GameState, action: &ActionREACH2) {}
+    fn on_meld(&mut self, state: &GameState, action: &ActionN) {}
+    fn on_dora(&mut self, state: &GameState, action: &ActionDORA) {}
+    fn on_agari(&mut self, state: &GameState, action: &ActionAGARI) {}
+    fn on_ryuukyoku(&mut self, state: &GameState, action: &ActionRYUUKYOKU) {}
+    fn on_reconnect(&mut self, state: &GameState, action: &ActionUN2) {}
+    fn on_disconnect(&mut self, state: &GameState, action: &ActionBYE) {}
+    /// A tag unknown to this version, kept by the parser. It does not change the state.
+    fn on_unknown(&mut self, state: &GameState, action: &ActionUnknown) {}
+}
+
+/// Replays the game and calls the visitor for each action. Returns the state at the end of the game.
+pub fn visit_mjlog<V: ActionVisitor + ?Sized>(mjlog: &Mjlog, visitor: &mut V) -> ReplayResult<GameState> {
+    let mut state = GameState::new();
+    for action in &mjlog.actions {
+        state.apply(action)?;
+        visitor.on_action(&state, action);
+        match action {
+            Action::SHUFFLE(x) => visitor.on_shuffle(&state, x),
+            Action::GO(x) => visitor.on_go(&state, x),
+            Action::UN1(x) => visitor.on_players(&state, x),
+            Action::UN2(x) => visitor.on_reconnect(&state, x),
+            Action::BYE(x) => visitor.on_disconnect(&state, x),
+            Action::TAIKYOKU(x) => visitor.on_taikyoku(&state, x),
+            Action::INIT(x) => visitor.on_init(&state, x),
+            Action::DRAW(x) => visitor.on_draw(&state, x),
+            Action::DISCARD(x) => visitor.on_discard(&state, x),
+            Action::REACH1(x) => visitor.on_riichi(&state, x),
+            Action::REACH2(x) => visitor.on_riichi_accepted(&state, x),
+            Action::N(x) => visitor.on_meld(&state, x),
+            Action::DORA(x) => visitor.on_dora(&state, x),
+            Action::AGARI(x) => visitor.on_agari(&state, x),
+            Action::RYUUKYOKU(x) => visitor.on_ryuukyoku(&state, x),
+            Action::Unknown(x) => visitor.on_unknown(&state, x),
+        }
+    }
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::*;
+
+    #[derive(Default)]
+    struct Counter {
+        actions: usize,
+        rounds: usize,
+        draws: usize,
+        // Hand size of each draw, which includes the drawn tile.
+        hand_sizes: Vec<usize>,
+        ends: usize,
+    }
+
+    impl ActionVisitor for Counter {
+        fn on_action(&mut self, _state: &GameState, _action: &Action) {
+            self.actions += 1;
+        }
+        fn on_init(&mut self, _state: &GameState, _action: &ActionINIT) {
+            self.rounds += 1;
+        }
+        fn on_draw(&mut self, state: &GameState, action: &ActionDRAW) {
+            self.draws += 1;
+            let player = &state.players[action.who.to_u8() as usize];
+            self.hand_sizes.push(player.hand.len() + player.melds.len() * 3);
+        }
+        fn on_agari(&mut self, _state: &GameState, _action: &ActionAGARI) {
+            self.ends += 1;
+        }
+        fn on_ryuukyoku(&mut self, _state: &GameState, _action: &ActionRYUUKYOKU) {
+            self.ends += 1;
+        }
+    }
+
+    #[test]
+    fn test_visit_mjlog() {
+        let mjlog = generate_mjlog(3, &GeneratorOptions::default());
+        let mut counter = Counter::default();
+        let state = visit_mjlog(&mjlog, &mut counter).unwrap();
+        assert!(!state.in_round);
+        assert_eq!(counter.actions, mjlog.actions.len());
+        assert_eq!(counter.rounds, mjlog.actions.iter().filter(|x| x.is_init()).count());
+        assert!(counter.ends >= counter.rounds);
+        assert_eq!(counter.draws, mjlog.actions.iter().filter(|x| x.is_draw()).count());
+        assert!(counter.hand_sizes.iter().all(|&x| x >= 14));
+    }
+}
@@ -0,0 +1,301 @@
+//! # wall
+//!
+//! Reconstructs the walls of each round from the seed in the SHUFFLE tag.
+//!
+//! The seed is `mt19937ar-sha512-n288-base64,...`: 624 words of MT19937 `init_by_array` key in base64.
+//! For each round, 288 words are drawn from MT19937 and hashed with SHA-512 in 9 blocks of 32 words,
+//! giving 144 random words that shuffle the 136 tiles and roll the dice.
+//!
+//! Only four-player games are supported.
+//!
+//! # Usage
+//!
+//! ```ignore
+//! let mismatches = verify_wall(&mjlog)?;
+//! assert!(mismatches.is_empty());
+//! ```
+
+use crate::model::*;
+use base64::Engine;
+use sha2::{Digest, Sha512};
+use thiserror::Error;
+
+const SEED_PREFIX: &str = "mt19937ar-sha512-n288-base64,";
+const NUM_TILES: usize = 136;
+
+#[derive(Debug, Error)]
+pub enum WallError {
+    #[error("SHUFFLE tag not found")]
+    NoSeed,
+    #[error("Unknown seed format")]
+    UnknownSeedFormat,
+    #[error("Invalid base64 in seed")]
+    InvalidBase64,
+    #[error("Invalid seed length: {0} bytes")]
+    InvalidSeedLength(usize),
+    #[error("Three-player games are not supported")]
+    Sanma,
+}
+
+pub type WallResult<T> = Result<T, WallError>;
+
+const MT_N: usize = 624;
+const MT_M: usize = 397;
+
+/// MT19937 (mt19937ar.c).
+struct Mt19937 {
+    state: [u32; MT_N],
+    index: usize,
+}
+
+impl Mt19937 {
+    fn new(seed: u32) -> Self {
+        let mut state = [0u32; MT_N];
+        state[0] = seed;
+        for i in 1..MT_N {
+            state[i] = 1812433253u32.wrapping_mul(state[i - 1] ^ (state[i - 1] >> 30)).wrapping_add(i as u32);
+        }
+        Mt19937 { state, index: MT_N }
+    }
+
+    fn from_key(key: &[u32]) -> Self {
+        let mut mt = Self::new(19650218);
+        let s = &mut mt.state;
+        let (mut i, mut j) = (1, 0);
+        for _ in 0..MT_N.max(key.len()) {
+            s[i] = (s[i] ^ (s[i - 1] ^ (s[i - 1] >> 30)).wrapping_mul(1664525)).wrapping_add(key[j]).wrapping_add(j as u32);
+            i += 1;
+            j += 1;
+            if i >= MT_N {
+                s[0] = s[MT_N - 1];
+                i = 1;
+            }
+            if j >= key.len() {
+                j = 0;
+            }
+        }
+        for _ in 0..MT_N - 1 {
+            s[i] = (s[i] ^ (s[i - 1] ^ (s[i - 1] >> 30)).wrapping_mul(1566083941)).wrapping_sub(i as u32);
+            i += 1;
+            if i >= MT_N {
+                s[0] = s[MT_N - 1];
+                i = 1;
+            }
+        }
+        s[0] = 0x80000000;
+        mt
+    }
+
+    fn generate(&mut self) {
+        for i in 0..MT_N {
+            let y = (self.state[i] & 0x80000000) | (self.state[(i + 1) % MT_N] & 0x7fffffff);
+            let mag = if y & 1 == 0 { 0 } else { 0x9908b0df };
+            self.state[i] = self.state[(i + MT_M) % MT_N] ^ (y >> 1) ^ mag;
+        }
+        self.index = 0;
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        if self.index >= MT_N {
+            self.generate();
+        }
+        let mut y = self.state[self.index];
+        self.index += 1;
+        y ^= y >> 11;
+        y ^= (y << 7) & 0x9d2c5680;
+        y ^= (y << 15) & 0xefc60000;
+        y ^ (y >> 18)
+    }
+}
+
+/// Tiles of a round in the order of `yama` of tenhou.net.
+///
+/// The initial hands are dealt from the end (`tiles[135]`, `tiles[134]`, ...), 4 tiles at a time to each player
+/// from the dealer for 3 turns and then 1 tile each. Tiles 0..14 are the dead wall.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wall {
+    pub tiles: [Hai; NUM_TILES],
+    /// Dice in 0..6, same as the seed of INIT.
+    pub dice: (u8, u8),
+}
+
+impl Wall {
+    /// Initial hands indexed by seat.
+    pub fn initial_hands(&self, oya: Player) -> [Vec<Hai>; 4] {
+        let mut hands: [Vec<Hai>; 4] = Default::default();
+        let mut next = (0..NUM_TILES).rev().map(|i| self.tiles[i]);
+        for turn in 0..4 {
+            let count = if turn < 3 { 4 } else { 1 };
+            for p in 0..4 {
+                let seat = (oya.to_u8() as usize + p) % 4;
+                hands[seat].extend(next.by_ref().take(count));
+            }
+        }
+        hands
+    }
+
+    /// Tiles of the live wall in the order drawn, after the initial hands.
+    pub fn draws(&self) -> impl Iterator<Item = Hai> + '_ {
+        (14..NUM_TILES - 52).rev().map(|i| self.tiles[i])
+    }
+
+    /// The first dora indicator and the ones revealed by kans, in order.
+    pub fn dora_indicators(&self) -> [Hai; 5] {
+        [5, 7, 9, 11, 13].map(|i| self.tiles[i])
+    }
+
+    /// Ura dora indicators, in the same order as `dora_indicators`.
+    pub fn ura_dora_indicators(&self) -> [Hai; 5] {
+        [4, 6, 8, 10, 12].map(|i| self.tiles[i])
+    }
+
+    /// Tiles drawn after kans, in order.
+    pub fn rinshan(&self) -> [Hai; 4] {
+        [1, 0, 3, 2].map(|i| self.tiles[i])
+    }
+}
+
+/// Generates the wall of each round in turn.
+pub struct WallGenerator {
+    mt: Mt19937,
+}
+
+impl WallGenerator {
+    /// Decodes the seed of the SHUFFLE tag.
+    pub fn from_seed(seed: &str) -> WallResult<Self> {
+        let encoded = seed.strip_prefix(SEED_PREFIX).ok_or(WallError::UnknownSeedFormat)?;
+        let bytes = base64::engine::general_purpose::STANDARD.decode(encoded).map_err(|_| WallError::InvalidBase64)?;
+        if bytes.len() != MT_N * 4 {
+            return Err(WallError::InvalidSeedLength(bytes.len()));
+        }
+        let key: Vec<u32> = bytes.chunks_exact(4).map(|x| u32::from_le_bytes([x[0], x[1], x[2], x[3]])).collect();
+        Ok(WallGenerator { mt: Mt19937::from_key(&key) })
+    }
+
+    /// Generates the wall of the next round.
+    pub fn next_wall(&mut self) -> Wall {
+        let src: Vec<u32> = (0..288).map(|_| self.mt.next_u32()).collect();
+        let mut rnd = Vec::with_capacity(144);
+        for block in src.chunks_exact(32) {
+            let bytes: Vec<u8> = block.iter().flat_map(|x| x.to_le_bytes()).collect();
+            let hash = Sha512::digest(&bytes);
+            rnd.extend(hash.chunks_exact(4).map(|x| u32::from_le_bytes([x[0], x[1], x[2], x[3]])));
+        }
+
+        let mut tiles: [Hai; NUM_TILES] = std::array::from_fn(|i| Hai::new(i as u8));
+        for (i, &r) in rnd.iter().enumerate().take(NUM_TILES - 1) {
+            tiles.swap(i, i + r as usize % (NUM_TILES - i));
+        }
+        let dice = ((rnd[135] % 6) as u8, (rnd[136] % 6) as u8);
+        Wall { tiles, dice }
+    }
+}
+
+/// What was compared with the reconstructed wall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WallCheck {
+    Dice,
+    DoraIndicator,
+    /// Initial hand of the player, compared as a set.
+    InitialHand(u8),
+    Draw(u8),
+}
+
+/// A logged value that does not match the wall. Tiles are `Hai` numbers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WallMismatch {
+    /// Index in `Mjlog::actions`.
+    pub action_index: usize,
+    pub check: WallCheck,
+    pub expected: Vec<u8>,
+    pub actual: Vec<u8>,
+}
+
+fn hai_numbers(hais: &[Hai]) -> Vec<u8> {
+    let mut v: Vec<u8> = hais.iter().map(|x| x.to_u8()).collect();
+    v.sort();
+    v
+}
+
+/// Checks the dice, dora indicators, initial hands and every drawn tile against the walls reconstructed from the seed.
+pub fn verify_wall(mjlog: &Mjlog) -> WallResult<Vec<WallMismatch>> {
+    let mut generator = None;
+    let mut mismatches = vec![];
+    let mut wall: Option<Wall> = None;
+    let (mut draw_index, mut rinshan_index, mut kan_dora_index) = (0, 0, 0);
+    let mut after_kan = false;
+
+    let mut check = |action_index, check, expected: Vec<u8>, actual: Vec<u8>| {
+        if expected != actual {
+            mismatches.push(WallMismatch { action_index, check, expected, actual });
+        }
+    };
+
+    for (i, a) in mjlog.actions.iter().enumerate() {
+        match a {
+            Action::SHUFFLE(x) => generator = Some(WallGenerator::from_seed(&x.seed)?),
+            Action::GO(x) if x.settings.sanma => return Err(WallError::Sanma),
+            Action::INIT(x) => {
+                let w = generator.as_mut().ok_or(WallError::NoSeed)?.next_wall();
+                check(i, WallCheck::Dice, vec![w.dice.0, w.dice.1], vec![x.seed.dice.0, x.seed.dice.1]);
+                check(i, WallCheck::DoraIndicator, vec![w.dora_indicators()[0].to_u8()], vec![x.seed.dora_hyouji.to_u8()]);
+                for (who, (expected, actual)) in w.initial_hands(x.oya).iter().zip(&x.hai).enumerate() {
+                    check(i, WallCheck::InitialHand(who as u8), hai_numbers(expected), hai_numbers(actual));
+                }
+                (draw_index, rinshan_index, kan_dora_index) = (0, 0, 0);
+                after_kan = false;
+                wall = Some(w);
+            }
+            Action::DRAW(x) => {
+                let Some(w) = &wall else { continue };
+                let expected = if after_kan {
+                    rinshan_index += 1;
+                    w.rinshan().get(rinshan_index - 1).copied()
+                } else {
+                    draw_index += 1;
+                    w.draws().nth(draw_index - 1)
+                };
+                check(i, WallCheck::Draw(x.who.to_u8()), expected.map(|h| h.to_u8()).into_iter().collect(), vec![x.hai.to_u8()]);
+                after_kan = false;
+            }
+            Action::N(x) => after_kan = matches!(x.m, Meld::Daiminkan { .. } | Meld::Kakan { .. } | Meld::Ankan { .. }),
+            Action::DORA(x) => {
+                let Some(w) = &wall else { continue };
+                kan_dora_index += 1;
+                let expected = w.dora_indicators().get(kan_dora_index).map(|h| h.to_u8());
+                check(i, WallCheck::DoraIndicator, expected.into_iter().collect(), vec![x.hai.to_u8()]);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(mismatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wall::*;
+
+    #[test]
+    fn test_mt19937() {
+        // First outputs of mt19937ar.c with init_by_array({0x123, 0x234, 0x345, 0x456}).
+        let mut mt = Mt19937::from_key(&[0x123, 0x234, 0x345, 0x456]);
+        assert_eq!([mt.next_u32(), mt.next_u32(), mt.next_u32()], [1067595299, 955945823, 477289528]);
+    }
+
+    #[test]
+    fn test_wall() {
+        let key: Vec<u8> = (0..MT_N * 4).map(|i| (i * 7) as u8).collect();
+        let seed = format!("{}{}", SEED_PREFIX, base64::engine::general_purpose::STANDARD.encode(key));
+        let wall = WallGenerator::from_seed(&seed).ok().unwrap().next_wall();
+
+        let mut tiles: Vec<u8> = wall.tiles.iter().map(|x| x.to_u8()).collect();
+        tiles.sort();
+        assert_eq!(tiles, (0..136).collect::<Vec<u8>>());
+        assert_eq!(wall.initial_hands(Player::new(1)).map(|x| x.len()), [13; 4]);
+        assert_eq!(wall.initial_hands(Player::new(1))[1][0], wall.tiles[135]);
+        assert_eq!(wall.draws().count(), 70);
+
+        assert!(matches!(WallGenerator::from_seed("mt19937ar-sha512-n288-base64,AAAA"), Err(WallError::InvalidSeedLength(3))));
+    }
+}
@@ -1,110 +1,307 @@
+use crate::divergence::*;
+use crate::json_diff::*;
+use flate2::read::GzDecoder;
 use futures::stream::{FuturesOrdered, StreamExt};
 use glob::glob;
+use mjlog::model::{Action, Mjlog};
 use mjlog::parser::*;
 use mjlog2json_core::conv::*;
+use mjlog2json_core::validate::{validate_scores, ScoreMismatch};
 use serde_json::{to_string_pretty, Value};
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use tenhou_json::exporter::*;
 use tenhou_json::model::*;
 use tenhou_json::parser::*;
+use thiserror::Error;
 
-fn replace_extension(x: &Path) -> PathBuf {
-    let mut r = x.to_path_buf();
-    r.set_extension("json");
-    r
+// The official JSON next to the XML: foo.json, or compressed as foo.json.gz or foo.json.zst.
+fn json_path(x: &Path) -> PathBuf {
+    ["json", "json.gz", "json.zst"].iter().map(|ext| x.with_extension(ext)).find(|p| p.exists()).unwrap_or_else(|| x.with_extension("json"))
 }
 
+// Decompresses gzip and zstd by the magic number, and reads anything else as plain text.
+fn decode_json(path: PathBuf, bytes: Vec<u8>) -> Result<String, CheckError> {
+    let invalid = |e| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+    let bytes = if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut out = vec![];
+        GzDecoder::new(bytes.as_slice()).read_to_end(&mut out).map(|_| out)
+    } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        zstd::decode_all(bytes.as_slice())
+    } else {
+        Ok(bytes)
+    };
+    bytes.and_then(|x| String::from_utf8(x).map_err(invalid)).map_err(|e| CheckError::Io(path, e))
+}
+
+// Differing fields printed for a file. All of them are in the diff files.
+const MAX_PRINTED_FIELDS: usize = 10;
+
 enum TaskResult {
-    Same,
-    Diff(String, String),
+    /// Converted correctly, with the payments that do not match the recomputed ones.
+    Same(Vec<ScoreMismatch>),
+    /// Expected and actual text for the diff files, and the lines to print.
+    Diff(String, String, Vec<String>),
 }
 
+/// Why a file could not be checked. The check goes on with the other files, except for `Pattern` and the output directory.
+#[derive(Debug, Error)]
+pub enum CheckError {
+    #[error("{0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("XML: {0}")]
+    Xml(#[from] MjlogError),
+    #[error("XML: no game in the file")]
+    NoGame,
+    #[error("JSON: {0}")]
+    Json(TenhouJsonError),
+    #[error("Conversion: {0}")]
+    Conv(#[from] ConvError),
+    #[error("Export: {0}")]
+    Export(TenhouJsonError),
+    #[error(transparent)]
+    Pattern(#[from] glob::PatternError),
+}
+
+// Falls back to the text as it is, so that a diff is still written.
 fn to_string_pretty_from_str(s: &str) -> String {
-    let value: Value = serde_json::from_str(s).unwrap();
-    to_string_pretty(&value).unwrap()
+    serde_json::from_str::<Value>(s).ok().and_then(|x| to_string_pretty(&x).ok()).unwrap_or_else(|| s.to_string())
 }
 
-fn verify(content_xml: String, content_json: String) -> TaskResult {
-    let mjlog = &parse_mjlogs(&content_xml).unwrap()[0];
-    let tenhou_json = parse_tenhou_json(&content_json).unwrap();
-    let converted_tenhou_json = TenhouJson {
+// Payments are recomputed independently of the converter, which passes pao_who and the deltas through as they are.
+fn score_mismatches(mjlog: &Mjlog) -> Vec<ScoreMismatch> {
+    let sanma = mjlog.actions.iter().any(|a| matches!(a, Action::GO(x) if x.settings.sanma));
+    if sanma {
+        return vec![];
+    }
+    validate_scores(mjlog).unwrap_or_default()
+}
+
+fn verify(content_xml: String, content_json: String, ignore: &[IgnoredField]) -> Result<TaskResult, CheckError> {
+    let mjlogs = parse_mjlogs(&content_xml)?;
+    let mjlog = mjlogs.first().ok_or(CheckError::NoGame)?;
+    let tenhou_json = parse_tenhou_json(&content_json).map_err(CheckError::Json)?;
+    let mut converted_tenhou_json = TenhouJson {
         reference: tenhou_json.reference.clone(), // same as filebase
-        ..conv_to_tenhou_json(mjlog).unwrap()
+        ..conv_to_tenhou_json(mjlog)?
     };
+    copy_ignored_fields(&tenhou_json, &mut converted_tenhou_json, ignore);
 
     if tenhou_json != converted_tenhou_json {
-        return TaskResult::Diff(std::format!("{:#?}", tenhou_json), std::format!("{:#?}", converted_tenhou_json));
+        // One line per differing field, so that a diff tool lines up the two files by path.
+        let diffs = diff_tenhou_json(&tenhou_json, &converted_tenhou_json);
+        let expected = diffs.iter().map(|x| format!("{}: {}\n", x.path, x.expected)).collect();
+        let actual = diffs.iter().map(|x| format!("{}: {}\n", x.path, x.actual)).collect();
+
+        let mut lines: Vec<String> = diffs.iter().take(MAX_PRINTED_FIELDS).map(|x| x.to_string()).collect();
+        if diffs.len() > MAX_PRINTED_FIELDS {
+            lines.push(format!("... and {} more fields", diffs.len() - MAX_PRINTED_FIELDS));
+        }
+        // Replaying both documents pinpoints the first turn that differs, which is hard to find from the fields alone.
+        if let Some(d) = find_divergence(mjlog, &tenhou_json) {
+            lines.push(format!("first divergence: {}", d));
+        }
+        return Ok(TaskResult::Diff(expected, actual, lines));
     }
 
-    let exported_json = export_tenhou_json(&converted_tenhou_json).unwrap();
+    // Official files escape non-ASCII characters, so that they can be compared byte by byte.
+    let options = ExportOptions {
+        ascii_escape: content_json.is_ascii(),
+        ..Default::default()
+    };
+    let exported_json = export_tenhou_json_with(&converted_tenhou_json, &options).map_err(CheckError::Export)?;
     if content_json != exported_json {
-        return TaskResult::Diff(to_string_pretty_from_str(&content_json), to_string_pretty_from_str(&exported_json));
+        return Ok(TaskResult::Diff(to_string_pretty_from_str(&content_json), to_string_pretty_from_str(&exported_json), vec![]));
     }
 
-    TaskResult::Same
+    Ok(TaskResult::Same(score_mismatches(mjlog)))
 }
 
-fn sync_check_xml(path_xml: PathBuf) -> (PathBuf, TaskResult) {
-    let content_xml = std::fs::read_to_string(&path_xml).unwrap();
-    let content_json = std::fs::read_to_string(replace_extension(&path_xml)).unwrap();
-
-    (path_xml, verify(content_xml, content_json))
+fn write_file(path: PathBuf, contents: String) -> Result<(), CheckError> {
+    std::fs::write(&path, contents).map_err(|e| CheckError::Io(path, e))
 }
 
-pub fn sync_check_glob(pattern: &str) {
-    for entry in glob(pattern).expect("Failed to read glob pattern") {
-        let path_xml = entry.unwrap();
+/// Options of the checker.
+#[derive(Debug, Clone)]
+pub struct CheckOptions {
+    pub output: DiffOutput,
+    /// Fields excluded from the comparison, e.g. ones that legitimately differ by lobby.
+    pub ignore: Vec<IgnoredField>,
+}
 
-        // print log before check in sync mode
-        println!("{}", path_xml.to_string_lossy().into_owned());
+/// Where the differences are written.
+#[derive(Debug, Clone)]
+pub enum DiffOutput {
+    /// `expected.txt` and `actual.txt` of the first difference, then stop checking.
+    First,
+    /// `<file stem>.expected.txt` and `<file stem>.actual.txt` of every difference into the directory,
+    /// then print the number of passed and failed files.
+    All(PathBuf),
+}
 
-        match sync_check_xml(path_xml) {
-            (_, TaskResult::Same) => {}
-            (path_xml, TaskResult::Diff(expected, actual)) => {
-                println!("detect difference: {}", path_xml.to_string_lossy());
-                std::fs::write("expected.txt", expected).unwrap();
-                std::fs::write("actual.txt", actual).unwrap();
-                return;
-            }
+fn report_diff(path_xml: &Path, expected: String, actual: String, lines: Vec<String>, output: &DiffOutput) -> Result<(), CheckError> {
+    println!("detect difference: {}", path_xml.to_string_lossy());
+    for x in lines {
+        println!("{}", x);
+    }
+    match output {
+        DiffOutput::First => {
+            write_file(PathBuf::from("expected.txt"), expected)?;
+            write_file(PathBuf::from("actual.txt"), actual)
+        }
+        DiffOutput::All(dir) => {
+            let stem = path_xml.file_stem().unwrap_or_default().to_string_lossy();
+            write_file(dir.join(format!("{}.expected.txt", stem)), expected)?;
+            write_file(dir.join(format!("{}.actual.txt", stem)), actual)
         }
     }
+}
 
-    // succeeded all test
-    std::fs::write("expected.txt", "SUCCESS!").unwrap();
-    std::fs::write("actual.txt", "SUCCESS!").unwrap();
+fn report_score_mismatches(path_xml: &Path, mismatches: &[ScoreMismatch]) {
+    for m in mismatches {
+        println!("score mismatch: {}: {}", path_xml.to_string_lossy(), m);
+    }
 }
 
-async fn async_check_xml(path_xml: PathBuf) -> (PathBuf, TaskResult) {
-    let content_xml = async_std::fs::read_to_string(&path_xml).await.unwrap();
-    let content_json = async_std::fs::read_to_string(replace_extension(&path_xml)).await.unwrap();
+fn read_file(path: PathBuf) -> Result<String, CheckError> {
+    std::fs::read_to_string(&path).map_err(|e| CheckError::Io(path, e))
+}
 
-    (path_xml, verify(content_xml, content_json))
+fn read_json_file(path: PathBuf) -> Result<String, CheckError> {
+    match std::fs::read(&path) {
+        Ok(x) => decode_json(path, x),
+        Err(e) => Err(CheckError::Io(path, e)),
+    }
 }
 
-pub async fn async_check_glob(pattern: &str) {
-    let mut tasks = FuturesOrdered::new();
+fn sync_check_xml(path_xml: PathBuf, ignore: &[IgnoredField]) -> (PathBuf, Result<TaskResult, CheckError>) {
+    let result = read_file(path_xml.clone()).and_then(|xml| Ok((xml, read_json_file(json_path(&path_xml))?))).and_then(|(xml, json)| verify(xml, json, ignore));
+    (path_xml, result)
+}
 
-    println!("Registering tasks...");
-    for entry in glob(pattern).expect("Failed to read glob pattern") {
-        tasks.push_back(async_std::task::spawn(async_check_xml(entry.unwrap())));
+struct Tally<'a> {
+    output: &'a DiffOutput,
+    passed: usize,
+    failed: usize,
+    /// Files that could not be checked, listed again at the end.
+    errors: Vec<(PathBuf, CheckError)>,
+}
+
+impl<'a> Tally<'a> {
+    fn new(output: &'a DiffOutput) -> Result<Self, CheckError> {
+        if let DiffOutput::All(dir) = output {
+            std::fs::create_dir_all(dir).map_err(|e| CheckError::Io(dir.clone(), e))?;
+        }
+        Ok(Tally { output, passed: 0, failed: 0, errors: vec![] })
     }
 
-    while let Some(ret) = tasks.next().await {
-        match ret {
-            (path_xml, TaskResult::Same) => {
-                // print log after check in async mode
+    /// Returns false if checking should stop.
+    fn record(&mut self, path_xml: &Path, result: Result<TaskResult, CheckError>) -> bool {
+        let result = match result {
+            Ok(TaskResult::Same(mismatches)) => {
+                report_score_mismatches(path_xml, &mismatches);
+                self.passed += 1;
+                return true;
+            }
+            Ok(TaskResult::Diff(expected, actual, lines)) => {
+                self.failed += 1;
+                report_diff(path_xml, expected, actual, lines, self.output).map(|_| matches!(self.output, DiffOutput::All(_)))
+            }
+            Err(e) => Err(e),
+        };
+        result.unwrap_or_else(|e| {
+            println!("error: {}: {}", path_xml.to_string_lossy(), e);
+            self.errors.push((path_xml.to_path_buf(), e));
+            true
+        })
+    }
+
+    fn finish(self) {
+        match self.output {
+            DiffOutput::First if self.errors.is_empty() => {
+                // succeeded all test
+                let _ = std::fs::write("expected.txt", "SUCCESS!");
+                let _ = std::fs::write("actual.txt", "SUCCESS!");
+            }
+            DiffOutput::First => println!("passed: {}, errors: {}", self.passed, self.errors.len()),
+            DiffOutput::All(_) => println!("passed: {}, failed: {}, errors: {}", self.passed, self.failed, self.errors.len()),
+        }
+        for (path_xml, e) in &self.errors {
+            println!("error: {}: {}", path_xml.to_string_lossy(), e);
+        }
+    }
+}
+
+/// Checks the files one by one. Errors of a file are reported in the summary and do not stop the check.
+pub fn sync_check_glob(pattern: &str, options: &CheckOptions) -> Result<(), CheckError> {
+    let paths = glob(pattern)?;
+    let mut tally = Tally::new(&options.output)?;
+    for entry in paths {
+        let (path_xml, result) = match entry {
+            Ok(path_xml) => {
+                // print log before check in sync mode
                 println!("{}", path_xml.to_string_lossy().into_owned());
+                sync_check_xml(path_xml, &options.ignore)
             }
-            (path_xml, TaskResult::Diff(expected, actual)) => {
-                println!("detect difference: {}", path_xml.to_string_lossy());
-                std::fs::write("expected.txt", expected).unwrap();
-                std::fs::write("actual.txt", actual).unwrap();
-                return;
+            Err(e) => (e.path().to_path_buf(), Err(CheckError::Io(e.path().to_path_buf(), e.into_error()))),
+        };
+        if !tally.record(&path_xml, result) {
+            return Ok(());
+        }
+    }
+    tally.finish();
+    Ok(())
+}
+
+async fn async_read_file(path: PathBuf) -> Result<String, CheckError> {
+    async_std::fs::read_to_string(&path).await.map_err(|e| CheckError::Io(path, e))
+}
+
+async fn async_read_json_file(path: PathBuf) -> Result<String, CheckError> {
+    match async_std::fs::read(&path).await {
+        Ok(x) => decode_json(path, x),
+        Err(e) => Err(CheckError::Io(path, e)),
+    }
+}
+
+async fn async_check_xml(path_xml: PathBuf, ignore: Vec<IgnoredField>) -> (PathBuf, Result<TaskResult, CheckError>) {
+    let content_xml = match async_read_file(path_xml.clone()).await {
+        Ok(x) => x,
+        Err(e) => return (path_xml, Err(e)),
+    };
+    let content_json = match async_read_json_file(json_path(&path_xml)).await {
+        Ok(x) => x,
+        Err(e) => return (path_xml, Err(e)),
+    };
+
+    (path_xml, verify(content_xml, content_json, &ignore))
+}
+
+/// Checks the files in parallel, same as `sync_check_glob` except that the files are printed after checking.
+pub async fn async_check_glob(pattern: &str, options: &CheckOptions) -> Result<(), CheckError> {
+    let mut tasks = FuturesOrdered::new();
+
+    println!("Registering tasks...");
+    for entry in glob(pattern)? {
+        match entry {
+            Ok(path_xml) => tasks.push_back(async_std::task::spawn(async_check_xml(path_xml, options.ignore.clone()))),
+            Err(e) => {
+                let path_xml = e.path().to_path_buf();
+                tasks.push_back(async_std::task::spawn(async move { (path_xml.clone(), Err(CheckError::Io(path_xml, e.into_error()))) }));
             }
         }
     }
 
-    // succeeded all test
-    std::fs::write("expected.txt", "SUCCESS!").unwrap();
-    std::fs::write("actual.txt", "SUCCESS!").unwrap();
+    let mut tally = Tally::new(&options.output)?;
+    while let Some((path_xml, result)) = tasks.next().await {
+        if let Ok(TaskResult::Same(_)) = result {
+            // print log after check in async mode
+            println!("{}", path_xml.to_string_lossy().into_owned());
+        }
+        if !tally.record(&path_xml, result) {
+            return Ok(());
+        }
+    }
+    tally.finish();
+    Ok(())
 }
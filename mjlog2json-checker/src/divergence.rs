@@ -0,0 +1,309 @@
+//! Replays the mjlog-XML and the tenhou-JSON of the same game independently,
+//! and finds the earliest point where the reconstructed player states disagree.
+
+use mjlog::model::{Action, Hai, InitSeed, Meld, Mjlog};
+use std::fmt;
+use tenhou_json::model::*;
+
+/// State of a player after one of their own actions (draw, call, kan or discard).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PlayerState {
+    /// Concealed tiles in tenhou-json tile numbers, sorted.
+    pub hand: Vec<u8>,
+    pub discards: Vec<u8>,
+    pub melds: usize,
+    pub riichi: bool,
+}
+
+/// The earliest disagreement between the two replays.
+#[derive(Debug)]
+pub struct Divergence {
+    /// 0-origin round index.
+    pub round: usize,
+    pub kyoku: u8,
+    pub honba: u8,
+    pub who: usize,
+    /// 0-origin index of the action in the player's own action sequence.
+    pub step: usize,
+    /// Index in `Mjlog::actions` of the action that produced the XML state, if any.
+    pub action_index: Option<usize>,
+    pub xml_state: Option<PlayerState>,
+    pub json_state: Option<PlayerState>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "round {} (kyoku {}, honba {}), player {}, step {}", self.round, self.kyoku, self.honba, self.who, self.step)?;
+        if let Some(i) = self.action_index {
+            writeln!(f, "  xml action index: {}", i)?;
+        }
+        writeln!(f, "  xml:  {:?}", self.xml_state)?;
+        write!(f, "  json: {:?}", self.json_state)
+    }
+}
+
+// Tile number of tenhou-json (11..47, 51..53 for red fives).
+fn hai_to_code(hai: Hai) -> u8 {
+    let n = hai.to_u8();
+    match n {
+        16 => 51,
+        52 => 52,
+        88 => 53,
+        _ => (n / 36 + 1) * 10 + (n / 4 % 9) + 1,
+    }
+}
+
+fn to_black(code: u8) -> u8 {
+    if code > 50 {
+        (code - 50) * 10 + 5
+    } else {
+        code
+    }
+}
+
+fn remove_tile(hand: &mut Vec<u8>, code: u8) {
+    if let Some(pos) = hand.iter().position(|&x| x == code) {
+        hand.remove(pos);
+    }
+}
+
+fn remove_kind(hand: &mut Vec<u8>, code: u8) {
+    hand.retain(|&x| to_black(x) != to_black(code));
+}
+
+fn snapshot(state: &PlayerState) -> PlayerState {
+    let mut s = state.clone();
+    s.hand.sort_by_key(|&x| (to_black(x), x));
+    s
+}
+
+type XmlRound = (InitSeed, [Vec<(usize, PlayerState)>; 4]);
+
+fn replay_xml(mjlog: &Mjlog) -> Vec<XmlRound> {
+    let mut rounds: Vec<XmlRound> = vec![];
+    let mut states: [PlayerState; 4] = Default::default();
+    let mut riichi_declared = [false; 4];
+
+    for (i, a) in mjlog.actions.iter().enumerate() {
+        if let Action::INIT(x) = a {
+            for (p, h) in x.hai.iter().enumerate().take(4) {
+                states[p] = PlayerState {
+                    hand: h.iter().map(|&x| hai_to_code(x)).collect(),
+                    ..Default::default()
+                };
+            }
+            riichi_declared = [false; 4];
+            rounds.push((x.seed.clone(), Default::default()));
+            continue;
+        }
+        let Some(round) = rounds.last_mut() else { continue };
+
+        let who = match a {
+            Action::DRAW(x) => {
+                let who = x.who.to_u8() as usize % 4;
+                states[who].hand.push(hai_to_code(x.hai));
+                who
+            }
+            Action::DISCARD(x) => {
+                let who = x.who.to_u8() as usize % 4;
+                let code = hai_to_code(x.hai);
+                remove_tile(&mut states[who].hand, code);
+                states[who].discards.push(code);
+                states[who].riichi |= riichi_declared[who];
+                who
+            }
+            Action::REACH1(x) => {
+                riichi_declared[x.who.to_u8() as usize % 4] = true;
+                continue;
+            }
+            Action::N(x) => {
+                let who = x.who.to_u8() as usize % 4;
+                let state = &mut states[who];
+                match x.m {
+                    Meld::Chii { combination: (a, b, c), called_position } => {
+                        let mut v = vec![a, b, c];
+                        v.remove(called_position as usize);
+                        v.into_iter().for_each(|h| remove_tile(&mut state.hand, hai_to_code(h)));
+                        state.melds += 1;
+                    }
+                    Meld::Pon { combination: (a, b, c), called, .. } => {
+                        [a, b, c].into_iter().filter(|&h| h != called).for_each(|h| remove_tile(&mut state.hand, hai_to_code(h)));
+                        state.melds += 1;
+                    }
+                    Meld::Kakan { added, .. } => remove_tile(&mut state.hand, hai_to_code(added)),
+                    Meld::Daiminkan { hai, .. } | Meld::Ankan { hai } => {
+                        remove_kind(&mut state.hand, hai_to_code(hai));
+                        state.melds += 1;
+                    }
+                }
+                who
+            }
+            _ => continue,
+        };
+        round.1[who].push((i, snapshot(&states[who])));
+    }
+
+    rounds
+}
+
+fn tile_code(t: &Tile) -> u8 {
+    t.to_u8()
+}
+
+// Called tile position of pon in the board order. See conv.rs of mjlog2json-core.
+fn called_position(dir: Direction) -> usize {
+    match dir {
+        Direction::Kamicha => 0,
+        Direction::Toimen => 1,
+        _ => 2,
+    }
+}
+
+fn replay_json_player(player: &RoundPlayer) -> Vec<PlayerState> {
+    let mut state = PlayerState {
+        hand: player.hand.iter().map(tile_code).collect(),
+        ..Default::default()
+    };
+    let mut states = vec![];
+    let mut last_draw = None;
+
+    for i in 0..player.incoming.len().max(player.outgoing.len()) {
+        if let Some(x) = player.incoming.get(i) {
+            last_draw = None;
+            match x {
+                IncomingTile::Tsumo(t) => {
+                    state.hand.push(tile_code(t));
+                    last_draw = Some(tile_code(t));
+                }
+                IncomingTile::Chii { combination: (_, b, c) } => {
+                    remove_tile(&mut state.hand, tile_code(b));
+                    remove_tile(&mut state.hand, tile_code(c));
+                    state.melds += 1;
+                }
+                IncomingTile::Pon { combination: (a, b, c), dir } => {
+                    let mut v = vec![a, b, c];
+                    v.remove(called_position(*dir));
+                    v.into_iter().for_each(|t| remove_tile(&mut state.hand, tile_code(t)));
+                    state.melds += 1;
+                }
+                IncomingTile::Daiminkan { combination: (a, ..), .. } => {
+                    remove_kind(&mut state.hand, tile_code(a));
+                    state.melds += 1;
+                }
+            }
+            states.push(snapshot(&state));
+        }
+
+        if let Some(x) = player.outgoing.get(i) {
+            let discarded = match x {
+                OutgoingTile::Discard(t) => Some((tile_code(t), false)),
+                OutgoingTile::Riichi(t) => Some((tile_code(t), true)),
+                OutgoingTile::Tsumogiri => last_draw.map(|t| (t, false)),
+                OutgoingTile::TsumogiriRiichi => last_draw.map(|t| (t, true)),
+                OutgoingTile::Ankan(t) => {
+                    remove_kind(&mut state.hand, tile_code(t));
+                    state.melds += 1;
+                    None
+                }
+                OutgoingTile::Kakan { added, .. } => {
+                    remove_tile(&mut state.hand, tile_code(added));
+                    None
+                }
+                OutgoingTile::Dummy => continue,
+            };
+            if let Some((code, riichi)) = discarded {
+                remove_tile(&mut state.hand, code);
+                state.discards.push(code);
+                state.riichi |= riichi;
+            }
+            states.push(snapshot(&state));
+        }
+    }
+
+    states
+}
+
+/// Replays both representations and returns the earliest divergence, or `None` if every player state agrees.
+pub fn find_divergence(mjlog: &Mjlog, tenhou_json: &TenhouJson) -> Option<Divergence> {
+    let xml_rounds = replay_xml(mjlog);
+    let num_rounds = xml_rounds.len().max(tenhou_json.rounds.len());
+
+    for round in 0..num_rounds {
+        let (kyoku, honba) = match (xml_rounds.get(round), tenhou_json.rounds.get(round)) {
+            (Some((seed, _)), _) => (seed.kyoku, seed.honba),
+            (None, Some(r)) => (r.settings.kyoku, r.settings.honba),
+            (None, None) => unreachable!(),
+        };
+
+        // (action index, divergence) of each player; the smallest action index is the earliest.
+        let mut found: Vec<(usize, Divergence)> = vec![];
+        for who in 0..4 {
+            let empty = vec![];
+            let xml_states = xml_rounds.get(round).map_or(&empty, |(_, x)| &x[who]);
+            let json_states = tenhou_json.rounds.get(round).and_then(|r| r.players.get(who)).map(replay_json_player).unwrap_or_default();
+
+            for step in 0..xml_states.len().max(json_states.len()) {
+                let xml_state = xml_states.get(step);
+                let json_state = json_states.get(step);
+                if xml_state.map(|(_, s)| s) != json_state {
+                    let action_index = xml_state.map(|(i, _)| *i);
+                    found.push((
+                        action_index.unwrap_or(usize::MAX),
+                        Divergence {
+                            round,
+                            kyoku,
+                            honba,
+                            who,
+                            step,
+                            action_index,
+                            xml_state: xml_state.map(|(_, s)| s.clone()),
+                            json_state: json_state.cloned(),
+                        },
+                    ));
+                    break;
+                }
+            }
+        }
+
+        if let Some((_, d)) = found.into_iter().min_by_key(|(i, _)| *i) {
+            return Some(d);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mjlog::generator::*;
+    use mjlog2json_core::conv::conv_to_tenhou_json;
+
+    fn is_discard(x: &OutgoingTile) -> bool {
+        matches!(x, OutgoingTile::Discard(_) | OutgoingTile::Riichi(_) | OutgoingTile::Tsumogiri | OutgoingTile::TsumogiriRiichi)
+    }
+
+    #[test]
+    fn test_find_divergence() {
+        let mjlog = generate_mjlog(1, &GeneratorOptions::default());
+        let mut tenhou_json = conv_to_tenhou_json(&mjlog).unwrap();
+        assert!(find_divergence(&mjlog, &tenhou_json).is_none());
+
+        // Replace the first hand discard of player 2 in the first round by another tile.
+        let outgoing = &mut tenhou_json.rounds[0].players[2].outgoing;
+        let j = outgoing.iter().position(|x| matches!(x, OutgoingTile::Discard(_))).unwrap();
+        let OutgoingTile::Discard(tile) = outgoing[j] else { unreachable!() };
+        let other = if tile.to_u8() == 11 { 12 } else { 11 };
+        outgoing[j] = OutgoingTile::Discard(Tile::from_u8(other).ok().unwrap());
+        let nth = outgoing[..j].iter().filter(|x| is_discard(x)).count();
+
+        // The same discard in the XML: the `nth` discard of player 2 after the first INIT.
+        let expected_index = mjlog.actions.iter().enumerate().filter(|(_, a)| matches!(a, Action::DISCARD(x) if x.who.to_u8() == 2)).nth(nth).map(|(i, _)| i);
+
+        let d = find_divergence(&mjlog, &tenhou_json).unwrap();
+        assert_eq!((d.round, d.who), (0, 2));
+        assert_eq!(d.action_index, expected_index);
+        assert_eq!(d.xml_state.unwrap().discards.last(), Some(&tile.to_u8()));
+        assert_eq!(d.json_state.unwrap().discards.last(), Some(&other));
+    }
+}
@@ -0,0 +1,215 @@
+//! Compares two tenhou-JSON documents field by field,
+//! and reports only the differing fields with their paths, e.g. `log[3].players[1].outgoing[14]: Riichi(Tile(26)) vs Discard(Tile(26))`.
+
+use std::fmt::{self, Debug};
+use tenhou_json::model::*;
+
+/// A field that differs between the two documents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDiff {
+    pub path: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl fmt::Display for FieldDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} vs {}", self.path, self.expected, self.actual)
+    }
+}
+
+// Shown for the elements only in one of the lists.
+const MISSING: &str = "<missing>";
+
+#[derive(Default)]
+struct Differ {
+    diffs: Vec<FieldDiff>,
+}
+
+impl Differ {
+    fn push(&mut self, path: &str, expected: String, actual: String) {
+        self.diffs.push(FieldDiff { path: path.to_string(), expected, actual });
+    }
+
+    fn leaf<T: PartialEq + Debug>(&mut self, path: &str, expected: &T, actual: &T) {
+        if expected != actual {
+            self.push(path, format!("{:?}", expected), format!("{:?}", actual));
+        }
+    }
+
+    fn list<T: Debug>(&mut self, path: &str, expected: &[T], actual: &[T], mut each: impl FnMut(&mut Self, &str, &T, &T)) {
+        for i in 0..expected.len().max(actual.len()) {
+            let path = format!("{}[{}]", path, i);
+            match (expected.get(i), actual.get(i)) {
+                (Some(e), Some(a)) => each(self, &path, e, a),
+                (Some(e), None) => self.push(&path, format!("{:?}", e), MISSING.to_string()),
+                (None, Some(a)) => self.push(&path, MISSING.to_string(), format!("{:?}", a)),
+                (None, None) => unreachable!(),
+            }
+        }
+    }
+
+    fn leaves<T: PartialEq + Debug>(&mut self, path: &str, expected: &[T], actual: &[T]) {
+        self.list(path, expected, actual, |d, path, e, a| d.leaf(path, e, a));
+    }
+
+    fn agari(&mut self, path: &str, e: &Agari, a: &Agari) {
+        self.leaves(&format!("{}.delta_points", path), &e.delta_points, &a.delta_points);
+        self.leaf(&format!("{}.who", path), &e.who, &a.who);
+        self.leaf(&format!("{}.from_who", path), &e.from_who, &a.from_who);
+        self.leaf(&format!("{}.pao_who", path), &e.pao_who, &a.pao_who);
+        self.leaf(&format!("{}.ranked_score", path), &e.ranked_score, &a.ranked_score);
+        self.leaves(&format!("{}.yaku", path), &e.yaku, &a.yaku);
+    }
+
+    fn result(&mut self, path: &str, e: &RoundResult, a: &RoundResult) {
+        match (e, a) {
+            (RoundResult::Agari { agari_vec: e }, RoundResult::Agari { agari_vec: a }) => self.list(&format!("{}.agari", path), e, a, Self::agari),
+            (RoundResult::Ryuukyoku { reason: er, delta_points: ed }, RoundResult::Ryuukyoku { reason: ar, delta_points: ad }) => {
+                self.leaf(&format!("{}.reason", path), er, ar);
+                self.leaves(&format!("{}.delta_points", path), ed, ad);
+            }
+            // Whole results would be too long to read.
+            _ => {
+                let name = |x: &RoundResult| if matches!(x, RoundResult::Agari { .. }) { "Agari" } else { "Ryuukyoku" };
+                self.push(path, name(e).to_string(), name(a).to_string());
+            }
+        }
+    }
+
+    fn round(&mut self, path: &str, e: &Round, a: &Round) {
+        let (es, as_) = (&e.settings, &a.settings);
+        self.leaf(&format!("{}.settings.kyoku", path), &es.kyoku, &as_.kyoku);
+        self.leaf(&format!("{}.settings.honba", path), &es.honba, &as_.honba);
+        self.leaf(&format!("{}.settings.kyoutaku", path), &es.kyoutaku, &as_.kyoutaku);
+        self.leaves(&format!("{}.settings.points", path), &es.points, &as_.points);
+        self.leaves(&format!("{}.settings.dora", path), &es.dora, &as_.dora);
+        self.leaves(&format!("{}.settings.ura_dora", path), &es.ura_dora, &as_.ura_dora);
+
+        self.list(&format!("{}.players", path), &e.players, &a.players, |d, path, e, a| {
+            d.leaves(&format!("{}.hand", path), &e.hand, &a.hand);
+            d.leaves(&format!("{}.incoming", path), &e.incoming, &a.incoming);
+            d.leaves(&format!("{}.outgoing", path), &e.outgoing, &a.outgoing);
+        });
+
+        self.result(&format!("{}.result", path), &e.result, &a.result);
+    }
+}
+
+/// Top-level fields that can be excluded from the comparison, named same as the paths.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IgnoredField {
+    Ver,
+    Connection,
+    Ratingc,
+    Rule,
+    Lobby,
+    Dan,
+    Rate,
+    Sx,
+    /// Both the final points and the final results.
+    Sc,
+    Name,
+}
+
+impl std::str::FromStr for IgnoredField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ver" => Ok(IgnoredField::Ver),
+            "connection" => Ok(IgnoredField::Connection),
+            "ratingc" => Ok(IgnoredField::Ratingc),
+            "rule" => Ok(IgnoredField::Rule),
+            "lobby" => Ok(IgnoredField::Lobby),
+            "dan" => Ok(IgnoredField::Dan),
+            "rate" => Ok(IgnoredField::Rate),
+            "sx" => Ok(IgnoredField::Sx),
+            "sc" => Ok(IgnoredField::Sc),
+            "name" => Ok(IgnoredField::Name),
+            _ => Err(format!("unknown field: {} (expected ver, connection, ratingc, rule, lobby, dan, rate, sx, sc or name)", s)),
+        }
+    }
+}
+
+/// Copies the ignored fields of `expected` to `actual`, so that they compare equal, also after exporting.
+pub fn copy_ignored_fields(expected: &TenhouJson, actual: &mut TenhouJson, ignored: &[IgnoredField]) {
+    for field in ignored {
+        match field {
+            IgnoredField::Ver => actual.ver = expected.ver,
+            IgnoredField::Connection => actual.connections = expected.connections.clone(),
+            IgnoredField::Ratingc => actual.ratingc = expected.ratingc.clone(),
+            IgnoredField::Rule => actual.rule = expected.rule.clone(),
+            IgnoredField::Lobby => actual.lobby = expected.lobby,
+            IgnoredField::Dan => actual.dan = expected.dan.clone(),
+            IgnoredField::Rate => actual.rate = expected.rate.clone(),
+            IgnoredField::Sx => actual.sx = expected.sx.clone(),
+            IgnoredField::Sc => {
+                actual.final_points = expected.final_points.clone();
+                actual.final_results = expected.final_results.clone();
+            }
+            IgnoredField::Name => actual.names = expected.names.clone(),
+        }
+    }
+}
+
+/// Differing fields of the two documents, in document order. Empty if they are equal.
+pub fn diff_tenhou_json(expected: &TenhouJson, actual: &TenhouJson) -> Vec<FieldDiff> {
+    let mut d = Differ::default();
+    d.leaf("ver", &expected.ver, &actual.ver);
+    d.leaf("ref", &expected.reference, &actual.reference);
+    d.list("log", &expected.rounds, &actual.rounds, Differ::round);
+    d.leaves("connection", &expected.connections, &actual.connections);
+    d.leaf("ratingc", &expected.ratingc, &actual.ratingc);
+    d.leaf("rule", &expected.rule, &actual.rule);
+    d.leaf("lobby", &expected.lobby, &actual.lobby);
+    d.leaves("dan", &expected.dan, &actual.dan);
+    d.leaves("rate", &expected.rate, &actual.rate);
+    d.leaves("sx", &expected.sx, &actual.sx);
+    d.leaves("sc.points", &expected.final_points, &actual.final_points);
+    // In tenths of a point, so that the float error of the same result is not a difference.
+    d.leaves("sc.results", &expected.final_results_tenths(), &actual.final_results_tenths());
+    d.leaves("name", &expected.names, &actual.names);
+    d.diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_tenhou_json() {
+        let player = |x| RoundPlayer {
+            outgoing: vec![OutgoingTile::Tsumogiri, x],
+            ..Default::default()
+        };
+        let tile = Tile::from_u8(26).ok().unwrap();
+        let expected = TenhouJson {
+            rounds: vec![Round {
+                players: vec![player(OutgoingTile::Riichi(tile))],
+                ..Default::default()
+            }],
+            names: vec!["A".to_string()],
+            ..Default::default()
+        };
+        let actual = TenhouJson {
+            rounds: vec![Round {
+                players: vec![player(OutgoingTile::Discard(tile))],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let diffs: Vec<String> = diff_tenhou_json(&expected, &actual).iter().map(|x| x.to_string()).collect();
+        assert_eq!(diffs, ["log[0].players[0].outgoing[1]: Riichi(Tile(26)) vs Discard(Tile(26))", "name[0]: \"A\" vs <missing>"]);
+        assert!(diff_tenhou_json(&expected, &expected).is_empty());
+    }
+
+    #[test]
+    fn test_diff_final_results() {
+        let results = |x: Vec<f64>| TenhouJson { final_results: x, ..Default::default() };
+        assert!(diff_tenhou_json(&results(vec![38.0, -16.5]), &results(vec![37.99999999, -16.500000001])).is_empty());
+        let diffs: Vec<String> = diff_tenhou_json(&results(vec![38.0, -16.5]), &results(vec![38.1, -16.5])).iter().map(|x| x.to_string()).collect();
+        assert_eq!(diffs, ["sc.results[0]: 380 vs 381"]);
+    }
+}
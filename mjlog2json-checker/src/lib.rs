@@ -0,0 +1,9 @@
+//! # mjlog2json-checker
+//!
+//! Verify that mjlog2json conversion matches the official xml and json.
+//!
+//! The check is also available as `mjlog2json check` of the main CLI, which uses this library.
+
+pub mod checker;
+pub mod divergence;
+pub mod json_diff;
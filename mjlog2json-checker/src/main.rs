@@ -4,27 +4,58 @@
 //!
 //! # Usage
 //!
-//! 1. Download official xml and json to same folder.
+//! 1. Download official xml and json to same folder. The json may be compressed as .json.gz or .json.zst.
 //! 2. Run ```cargo run --release -p mjlog2json-checker async <<folder_name>>```
 //! 3. Check the difference between ```actual.txt``` and ```expected.txt``` using a diff tool.
+//!    The files list only the differing fields, one per line with its path such as ```log[3].players[1].outgoing[14]```.
+//!    The first fields and the first turn where the replayed player states disagree are also printed.
+//!
+//! To check every file instead of stopping at the first difference, add ```--all <<output_folder>>```.
+//! A pair of ```<<name>>.expected.txt``` and ```<<name>>.actual.txt``` is written there for each failing file,
+//! and the numbers of passed and failed files are printed at the end.
+//! Files that cannot be read, parsed or converted are printed as ```error:``` lines and listed again at the end, without stopping the check.
+//!
+//! To exclude fields that legitimately differ, e.g. by lobby, add ```--ignore <<field>>``` for each field:
+//! ```ver```, ```connection```, ```ratingc```, ```rule```, ```lobby```, ```dan```, ```rate```, ```sx```, ```sc``` or ```name```.
+//!
+//! The same check is available as ```mjlog2json check <<folder_name>>``` of the main CLI.
 
-mod checker;
-
-use crate::checker::*;
+use mjlog2json_checker::checker::*;
 use std::env;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[async_std::main]
 async fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+
+    let output = match args.iter().position(|x| x == "--all") {
+        Some(i) if i + 1 < args.len() => DiffOutput::All(PathBuf::from(args.drain(i..=i + 1).nth(1).unwrap())),
+        Some(_) => return println!("--all needs the output folder"),
+        None => DiffOutput::First,
+    };
+
+    let mut ignore = vec![];
+    while let Some(i) = args.iter().position(|x| x == "--ignore") {
+        let Some(field) = args.drain(i..(i + 2).min(args.len())).nth(1) else {
+            return println!("--ignore needs the field name");
+        };
+        match field.parse() {
+            Ok(x) => ignore.push(x),
+            Err(e) => return println!("{}", e),
+        }
+    }
+    let options = CheckOptions { output, ignore };
 
-    let command = args[1].clone(); // "sync" or "async"
+    let command = args.get(1).cloned().unwrap_or_default(); // "sync" or "async"
     let target_dir = if args.len() < 3 { Path::new("data") } else { Path::new(&args[2]) };
     let glob_pattern = target_dir.join("*.xml");
 
-    match command.as_str() {
-        "sync" => sync_check_glob(&glob_pattern.to_string_lossy()),
-        "async" => async_check_glob(&glob_pattern.to_string_lossy()).await,
-        _ => println!("command: sync | async"),
+    let result = match command.as_str() {
+        "sync" => sync_check_glob(&glob_pattern.to_string_lossy(), &options),
+        "async" => async_check_glob(&glob_pattern.to_string_lossy(), &options).await,
+        _ => return println!("command: sync | async [folder] [--all output_folder] [--ignore field]..."),
+    };
+    if let Err(e) = result {
+        println!("{}", e);
     }
 }
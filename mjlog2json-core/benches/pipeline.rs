@@ -0,0 +1,59 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use mjlog::exporter::export_mjlog;
+use mjlog::generator::*;
+use mjlog::model::GameSettings;
+use mjlog::parser::parse_mjlogs;
+use mjlog2json_core::conv::conv_to_tenhou_json;
+use std::hint::black_box;
+use tenhou_json::exporter::export_tenhou_json;
+
+// The logs of each stage: a recorded game, and generated hanchan games of four and three players.
+fn sample_xmls() -> Vec<(&'static str, String)> {
+    let yonma = GeneratorOptions::default();
+    let sanma = GeneratorOptions {
+        settings: GameSettings {
+            sanma: true,
+            ..GeneratorOptions::default().settings
+        },
+        ..Default::default()
+    };
+    vec![
+        ("observer", include_str!("../fixtures/observer.xml").to_string()),
+        ("yonma", export_mjlog(&generate_mjlog(1, &yonma))),
+        ("sanma", export_mjlog(&generate_mjlog(1, &sanma))),
+        // A file of many games, as the files of multiple games and the directories of a corpus.
+        ("yonma batch 20", (0..20).map(|seed| export_mjlog(&generate_mjlog(seed, &yonma))).collect()),
+    ]
+}
+
+fn bench_pipeline(c: &mut Criterion) {
+    let xmls = sample_xmls();
+
+    let mut group = c.benchmark_group("parse_mjlogs");
+    for (name, xml) in &xmls {
+        group.throughput(Throughput::Bytes(xml.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), xml, |b, xml| b.iter(|| parse_mjlogs(black_box(xml)).unwrap()));
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("conv_to_tenhou_json");
+    for (name, xml) in &xmls {
+        let mjlogs = parse_mjlogs(xml).unwrap();
+        group.throughput(Throughput::Elements(mjlogs.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &mjlogs, |b, mjlogs| {
+            b.iter(|| black_box(mjlogs).iter().map(|x| conv_to_tenhou_json(x).unwrap()).collect::<Vec<_>>())
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("export_tenhou_json");
+    for (name, xml) in &xmls {
+        let jsons: Vec<_> = parse_mjlogs(xml).unwrap().iter().map(|x| conv_to_tenhou_json(x).unwrap()).collect();
+        group.throughput(Throughput::Elements(jsons.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &jsons, |b, jsons| b.iter(|| black_box(jsons).iter().map(|x| export_tenhou_json(x).unwrap()).collect::<Vec<_>>()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_pipeline);
+criterion_main!(benches);
@@ -0,0 +1,97 @@
+//! # action_arrow
+//!
+//! The actions of each round as an Arrow record batch, one row per action with the round flattened into the columns,
+//! for research on the play rather than the results of `round_arrow`.
+
+use arrow_array::builder::{StringBuilder, UInt16Builder, UInt32Builder, UInt8Builder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use mjlog::exporter::export_meld;
+use mjlog::model::*;
+use std::sync::Arc;
+
+/// Schema of the action table.
+///
+/// * `hai`: the tile ID of mjlog (0..136), null for calls and riichi. The winning tile for AGARI.
+/// * `meld`: the "m" attribute of N, null for the other actions.
+/// * `from_who`: the discarder for AGARI, null for the other actions.
+pub fn actions_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("ref", DataType::Utf8, false),
+        Field::new("round", DataType::UInt32, false),
+        Field::new("kyoku", DataType::UInt8, false),
+        Field::new("honba", DataType::UInt8, false),
+        Field::new("seq", DataType::UInt32, false),
+        Field::new("kind", DataType::Utf8, false),
+        Field::new("who", DataType::UInt8, true),
+        Field::new("hai", DataType::UInt8, true),
+        Field::new("meld", DataType::UInt16, true),
+        Field::new("from_who", DataType::UInt8, true),
+    ])
+}
+
+/// Action table of the game. Actions before the first round, such as GO and UN, are left out.
+pub fn actions_record_batch(reference: &str, mjlog: &Mjlog) -> Result<RecordBatch, ArrowError> {
+    let mut reference_column = StringBuilder::new();
+    let mut round = UInt32Builder::new();
+    let mut kyoku = UInt8Builder::new();
+    let mut honba = UInt8Builder::new();
+    let mut seq = UInt32Builder::new();
+    let mut kind = StringBuilder::new();
+    let mut who = UInt8Builder::new();
+    let mut hai = UInt8Builder::new();
+    let mut meld = UInt16Builder::new();
+    let mut from_who = UInt8Builder::new();
+
+    let mut current: Option<(u32, &InitSeed)> = None;
+    let mut number = 0;
+    let mut index = 0;
+    for a in &mjlog.actions {
+        let row = match a {
+            Action::INIT(x) => {
+                current = Some((number, &x.seed));
+                number += 1;
+                index = 0;
+                continue;
+            }
+            Action::DRAW(x) => ("draw", Some(x.who), Some(x.hai), None, None),
+            Action::DISCARD(x) => ("discard", Some(x.who), Some(x.hai), None, None),
+            Action::N(x) => ("meld", Some(x.who), None, Some(export_meld(&x.m)), None),
+            Action::REACH1(x) => ("riichi", Some(x.who), None, None, None),
+            Action::REACH2(x) => ("riichi_accepted", Some(x.who), None, None, None),
+            Action::DORA(x) => ("dora", None, Some(x.hai), None, None),
+            Action::AGARI(x) => ("agari", Some(x.who), Some(x.machi), None, Some(x.from_who)),
+            Action::RYUUKYOKU(_) => ("ryuukyoku", None, None, None, None),
+            _ => continue,
+        };
+        let Some((round_number, seed)) = current else {
+            continue;
+        };
+
+        reference_column.append_value(reference);
+        round.append_value(round_number);
+        kyoku.append_value(seed.kyoku);
+        honba.append_value(seed.honba);
+        seq.append_value(index);
+        kind.append_value(row.0);
+        who.append_option(row.1.map(|x| x.to_u8()));
+        hai.append_option(row.2.map(|x| x.to_u8()));
+        meld.append_option(row.3);
+        from_who.append_option(row.4.map(|x| x.to_u8()));
+        index += 1;
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(reference_column.finish()),
+        Arc::new(round.finish()),
+        Arc::new(kyoku.finish()),
+        Arc::new(honba.finish()),
+        Arc::new(seq.finish()),
+        Arc::new(kind.finish()),
+        Arc::new(who.finish()),
+        Arc::new(hai.finish()),
+        Arc::new(meld.finish()),
+        Arc::new(from_who.finish()),
+    ];
+    RecordBatch::try_new(Arc::new(actions_schema()), columns)
+}
@@ -0,0 +1,89 @@
+//! # anonymize
+//!
+//! Removes the account identities from a game, so logs can be shared for research.
+//!
+//! The names become placeholders, the dan and rate become unknown (empty and 0 in tenhou-JSON),
+//! and the SHUFFLE seed is removed. The game ID in the file name is kept, and it still leads to the original log on tenhou.net.
+
+use mjlog::model::*;
+use std::str::FromStr;
+
+/// How to replace the player names.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Anonymize {
+    /// "Player0" to "Player3" by seat.
+    #[default]
+    Seat,
+    /// "Player-" and a hash of the name, e.g. "Player-1b2c3d4e", the same for a player across games.
+    ///
+    /// The hash has no secret, so a guessed name can be checked against it.
+    Hash,
+}
+
+impl FromStr for Anonymize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "seat" => Ok(Anonymize::Seat),
+            "hash" => Ok(Anonymize::Hash),
+            _ => Err(format!("unknown anonymization: {} (expected seat or hash)", s)),
+        }
+    }
+}
+
+// 32-bit FNV-1a, stable across platforms and Rust versions unlike `DefaultHasher`.
+fn fnv1a(s: &str) -> u32 {
+    s.bytes().fold(0x811c9dc5, |h, x| (h ^ x as u32).wrapping_mul(0x01000193))
+}
+
+fn placeholder(mode: Anonymize, who: usize, name: &str) -> String {
+    match mode {
+        Anonymize::Seat => format!("Player{}", who),
+        Anonymize::Hash => format!("Player-{:08x}", fnv1a(name)),
+    }
+}
+
+/// Replaces the names, dan, rate and SHUFFLE seed of the game. See the module documentation.
+///
+/// The names of reconnections (UN tags after the first) are replaced in the same way.
+pub fn anonymize(mjlog: &mut Mjlog, mode: Anonymize) {
+    for action in &mut mjlog.actions {
+        match action {
+            Action::SHUFFLE(x) => x.seed.clear(),
+            Action::UN1(x) => {
+                x.names = x.names.iter().enumerate().map(|(who, name)| placeholder(mode, who, name)).collect();
+                x.dan.iter_mut().for_each(|x| *x = None);
+                x.rate.iter_mut().for_each(|x| *x = None);
+            }
+            Action::UN2(x) => x.name = placeholder(mode, x.who.to_u8() as usize, &x.name),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conv::conv_all;
+    use mjlog::parser::parse_mjlogs;
+
+    #[test]
+    fn test_anonymize() {
+        let mut mjlog = parse_mjlogs(include_str!("../fixtures/observer.xml")).unwrap().remove(0);
+        let names = mjlog.actions.iter().find_map(|x| x.as_un1()).unwrap().names.clone();
+        let mut hashed = mjlog.clone();
+
+        anonymize(&mut mjlog, Anonymize::Seat);
+        let tenhou_json = conv_all(&[mjlog.clone()]).unwrap().remove(0);
+        assert_eq!(tenhou_json.names, ["Player0", "Player1", "Player2", "Player3"]);
+        assert_eq!(tenhou_json.dan, ["", "", "", ""]);
+        assert_eq!(tenhou_json.rate, [0.0, 0.0, 0.0, 0.0]);
+        assert!(mjlog.actions.iter().filter_map(|x| x.as_shuffle()).all(|x| x.seed.is_empty()));
+
+        anonymize(&mut hashed, Anonymize::Hash);
+        let hashed_names = &hashed.actions.iter().find_map(|x| x.as_un1()).unwrap().names;
+        assert_eq!(hashed_names[0], placeholder(Anonymize::Hash, 3, &names[0]));
+        assert_ne!(hashed_names[0], hashed_names[1]);
+    }
+}
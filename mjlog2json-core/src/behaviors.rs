@@ -0,0 +1,94 @@
+//! # behaviors
+//!
+//! Conversion behaviors and heuristics with their status under the options, for dataset consumers
+//! to record which semantics produced their data.
+//!
+//! The IDs are stable: a behavior is never renamed or reused for another meaning, only added.
+//!
+//! ```json
+//! {"version":"0.1.3","behaviors":[{"id":"ankan_red_five","enabled":true,"description":"..."},...]}
+//! ```
+
+use crate::conv::{ConvOptions, IncompleteRound, RedFiveMode};
+use serde_json::json;
+use tenhou_json::exporter::Locale;
+
+/// A behavior of the conversion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Behavior {
+    /// Stable ID in snake case.
+    pub id: &'static str,
+    /// Whether the conversion with the options applies it.
+    pub enabled: bool,
+    pub description: &'static str,
+}
+
+/// Behaviors of the conversion with the options, in a fixed order.
+pub fn behaviors(options: &ConvOptions) -> Vec<Behavior> {
+    let b = |id, enabled, description| Behavior { id, enabled, description };
+    vec![
+        b("red_five_always", options.red_five == RedFiveMode::Always, "hai 16, 52 and 88 are red fives even if the rule of the GO tag has no red fives"),
+        b("red_five_follow_rule", options.red_five == RedFiveMode::FollowRule, "red fives only if the rule of the GO tag has red fives"),
+        b("ankan_red_five", options.red_five != RedFiveMode::Never, "a closed kan of fives is recorded with the red five, where red fives are enabled"),
+        b("hand_sort_red_five", true, "starting hands are sorted as normal five, red five, six"),
+        b(
+            "multiple_ron_separate_results",
+            true,
+            "each winner of a multiple ron has its own result with the payments as recorded, including honba and riichi sticks",
+        ),
+        b("pao_who_defaults_to_winner", true, "pao_who of a result without pao is the winner"),
+        b("ura_dora_zero_omitted", true, "ura dora of 0 han is not listed in the yaku"),
+        b("ryuukyoku_zero_deltas_omitted", true, "an exhaustive draw without payments has no delta points"),
+        b("ryuukyoku_tenpai_reason", true, "an exhaustive draw is all tenpai or no tenpai when all or none of the hands are shown"),
+        b("trailing_dummy_removed", true, "a placeholder discard left at the end of the discards of a player, after an open kan, is removed"),
+        b("unknown_rate_zero", true, "AI or observer seats without a valid rate have rate 0 and an empty dan"),
+        b("unknown_yaku_kept", true, "yaku IDs unknown to this version, accepted with --lenient, are kept as 不明役 followed by the ID"),
+        b("dan_names_english", options.locale == Locale::English, "dan names are in English instead of Japanese"),
+        b("rule_name_english", options.locale == Locale::English, "the rule name (rule.disp) is romanized, e.g. Ippan South Kuitan Red instead of 般南喰赤"),
+        b(
+            "synthesize_final_result",
+            options.synthesize_final_result,
+            "logs without owari get final points from the last scores, with the riichi sticks left going to the top, and results by tenhou's uma and oka",
+        ),
+        b(
+            "incomplete_round_dropped",
+            options.incomplete_round == IncompleteRound::Drop,
+            "a round without a win or draw, e.g. the last round of a truncated log, is dropped",
+        ),
+        b(
+            "incomplete_round_aborted",
+            options.incomplete_round == IncompleteRound::Abort,
+            "a round without a win or draw, e.g. of a game aborted by a disconnection, ends with 中断 without payments",
+        ),
+    ]
+}
+
+/// JSON of the behaviors with the version of mjlog2json-core.
+pub fn behaviors_to_json(behaviors: &[Behavior]) -> String {
+    json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "behaviors": behaviors.iter().map(|x| json!({"id": x.id, "enabled": x.enabled, "description": x.description})).collect::<Vec<_>>(),
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_behaviors() {
+        let enabled = |options: &ConvOptions, id: &str| behaviors(options).iter().find(|x| x.id == id).unwrap().enabled;
+        let default = ConvOptions::default();
+        assert!(enabled(&default, "red_five_always"));
+        assert!(!enabled(&default, "synthesize_final_result"));
+
+        let options = ConvOptions {
+            red_five: RedFiveMode::Never,
+            synthesize_final_result: true,
+            ..ConvOptions::default()
+        };
+        assert!(!enabled(&options, "ankan_red_five"));
+        assert!(enabled(&options, "synthesize_final_result"));
+    }
+}
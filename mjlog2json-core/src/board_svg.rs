@@ -0,0 +1,189 @@
+//! # board_svg
+//!
+//! SVG image of the table at a turn, for blog posts and problem sets cut out of real games.
+//!
+//! The seat at the bottom is the player who just drew, and the others are rotated around the table.
+//! Tiles are drawn as labeled rectangles, so the image needs no fonts or images other than the system ones.
+
+use crate::escape::escape_html;
+use crate::problem::*;
+use mjlog::model::*;
+use mjlog::replay::*;
+use std::fmt::Write;
+
+const SIZE: i32 = 900;
+const CENTER: i32 = SIZE / 2;
+const TILE_W: i32 = 26;
+const TILE_H: i32 = 36;
+// Half the size of the center box with the round information.
+const BOX: i32 = 150;
+const RIVER_COLUMNS: usize = 6;
+const RIVER_Y: i32 = CENTER + BOX + 10;
+const LABEL_Y: i32 = CENTER + BOX - 12;
+const HAND_Y: i32 = SIZE - TILE_H - 24;
+
+const WINDS: [&str; 4] = ["East", "South", "West", "North"];
+const HONORS: [&str; 7] = ["東", "南", "西", "北", "白", "發", "中"];
+const SUITS: [(&str, &str); 3] = [("m", "#222"), ("p", "#1a4fa0"), ("s", "#1d7a2e")];
+
+/// Options of `board_svg`.
+#[derive(Debug, Clone, Default)]
+pub struct BoardSvgOptions {
+    /// Seat at the bottom. The player who drew at the turn if `None`.
+    pub perspective: Option<usize>,
+    /// Shows only the hand at the bottom, and the backs of the other hands.
+    pub hide_hands: bool,
+}
+
+struct TileStyle {
+    hidden: bool,
+    dim: bool,
+    riichi: bool,
+}
+
+const NORMAL: TileStyle = TileStyle { hidden: false, dim: false, riichi: false };
+
+fn write_tile(out: &mut String, x: i32, y: i32, hai: Hai, red: bool, style: TileStyle) {
+    let fill = if style.hidden {
+        "#3b7a57"
+    } else if style.riichi {
+        "#fff3b0"
+    } else {
+        "#fdfdf8"
+    };
+    let opacity = if style.dim { " opacity=\"0.4\"" } else { "" };
+    let _ = write!(out, "<g{}><rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"3\" fill=\"{}\" stroke=\"#666\"/>", opacity, x, y, TILE_W, TILE_H, fill);
+    if !style.hidden {
+        let kind = hai.to_u8() as usize / 4;
+        let (label, color) = match kind / 9 {
+            3 => (HONORS[kind - 27].to_string(), if kind >= 31 { ["#222", "#1d7a2e", "#c0392b"][kind - 31] } else { "#222" }),
+            suit => (format!("{}{}", kind % 9 + 1, SUITS[suit].0), SUITS[suit].1),
+        };
+        let color = if red && matches!(hai.to_u8(), 16 | 52 | 88) { "#c0392b" } else { color };
+        let _ = write!(out, "<text x=\"{}\" y=\"{}\" fill=\"{}\">{}</text>", x + TILE_W / 2, y + TILE_H / 2 + 5, color, label);
+    }
+    out.push_str("</g>");
+}
+
+// The seat drawn at the bottom, before the rotation.
+fn write_seat(out: &mut String, state: &GameState, who: usize, label: &str, drawn: Option<Hai>, hidden: bool) {
+    let player = &state.players[who];
+    let red = !state.settings.no_red;
+
+    let _ = write!(out, "<text x=\"{}\" y=\"{}\" class=\"label\">{}</text>", CENTER, LABEL_Y, escape_html(label));
+
+    for (i, x) in player.discards.iter().enumerate() {
+        let row = (i / RIVER_COLUMNS).min(2);
+        let column = (i - row * RIVER_COLUMNS) as i32;
+        let style = TileStyle {
+            hidden: false,
+            dim: x.called,
+            riichi: x.riichi,
+        };
+        write_tile(out, CENTER - TILE_W * 3 + column * TILE_W, RIVER_Y + row as i32 * TILE_H, x.hai, red, style);
+    }
+
+    // Sorted hand, the drawn tile apart on the right, then the melds.
+    let mut hand = player.hand.clone();
+    let drawn = drawn.and_then(|hai| hand.iter().rposition(|&x| x == hai).map(|pos| hand.remove(pos)));
+    hand.sort_by_key(|x| x.to_u8());
+    let melds: Vec<Vec<Hai>> = player.melds.iter().map(meld_hais).collect();
+    let gap = TILE_W / 3;
+    let width = (hand.len() + drawn.iter().count() + melds.iter().map(|m| m.len()).sum::<usize>()) as i32 * TILE_W + (drawn.iter().count() + melds.len()) as i32 * gap;
+
+    let mut x = CENTER - width / 2;
+    let style = || TileStyle { hidden, ..NORMAL };
+    for &hai in &hand {
+        write_tile(out, x, HAND_Y, hai, red, style());
+        x += TILE_W;
+    }
+    if let Some(hai) = drawn {
+        x += gap;
+        write_tile(out, x, HAND_Y, hai, red, style());
+        x += TILE_W;
+    }
+    for meld in melds {
+        x += gap;
+        for hai in meld {
+            write_tile(out, x, HAND_Y, hai, red, NORMAL);
+            x += TILE_W;
+        }
+    }
+}
+
+/// Renders the state as an SVG image, with the seat `perspective` at the bottom.
+///
+/// `drawn` is put apart from the rest of the hand of the player holding it, like the table of a client.
+pub fn render_board_svg(state: &GameState, names: &[String], drawn: Option<Hai>, perspective: usize, hide_hands: bool) -> String {
+    let num_players = state.players.len().max(1);
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"sans-serif\" text-anchor=\"middle\">",
+        SIZE, SIZE, SIZE, SIZE
+    );
+    out.push_str("<style>text{font-size:13px}.label{font-size:14px}.info{font-size:16px}</style>");
+    let _ = write!(out, "<rect width=\"{}\" height=\"{}\" fill=\"#2f6b4f\"/>", SIZE, SIZE);
+    let _ = write!(out, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#e8e4d8\" stroke=\"#333\"/>", CENTER - BOX, CENTER - BOX, BOX * 2, BOX * 2);
+
+    let round = format!("{} {}, {} honba", WINDS[state.kyoku as usize / 4 % 4], state.kyoku % 4 + 1, state.honba);
+    let sticks = format!("Riichi sticks: {}  Wall: {}", state.kyoutaku, state.wall_remaining);
+    let _ = write!(out, "<text x=\"{}\" y=\"{}\" class=\"info\">{}</text>", CENTER, CENTER - 50, round);
+    let _ = write!(out, "<text x=\"{}\" y=\"{}\" class=\"info\">{}</text>", CENTER, CENTER - 25, sticks);
+    let dora_x = CENTER - state.dora_indicators.len() as i32 * TILE_W / 2;
+    for (i, &hai) in state.dora_indicators.iter().enumerate() {
+        write_tile(&mut out, dora_x + i as i32 * TILE_W, CENTER - 5, hai, !state.settings.no_red, NORMAL);
+    }
+
+    for who in 0..state.players.len() {
+        let position = (who + num_players - perspective % num_players) % num_players;
+        // Counterclockwise from the bottom. Three-player games have no seat at the top.
+        let angle = match (position, num_players) {
+            (0, _) => 0,
+            (1, _) => 270,
+            (2, 4) => 180,
+            _ => 90,
+        };
+        let player = &state.players[who];
+        let wind = (who + num_players - state.oya.to_u8() as usize) % num_players;
+        let name = names.get(who).map_or("", |x| x.as_str());
+        let label = format!("{} {} {}{}", WINDS[wind], name, player.score as i64 * 100, if player.riichi { " riichi" } else { "" });
+        let _ = write!(out, "<g transform=\"rotate({} {} {})\">", angle, CENTER, CENTER);
+        write_seat(&mut out, state, who, &label, drawn, hide_hands && position != 0);
+        out.push_str("</g>");
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Renders the table just after the draw of the mark as an SVG image.
+pub fn board_svg(mjlog: &Mjlog, mark: TurnMark, options: &BoardSvgOptions) -> Result<String, ProblemError> {
+    let index = find_turn(mjlog, mark).ok_or(ProblemError::TurnNotFound(mark))?;
+    let Action::DRAW(draw) = &mjlog.actions[index] else { unreachable!("find_turn returns a draw") };
+    let state = GameState::from_actions(&mjlog.actions[..=index])?;
+    let names = mjlog.actions.iter().find_map(|a| a.as_un1()).map(|x| x.names.clone()).unwrap_or_default();
+    let perspective = options.perspective.unwrap_or(draw.who.to_u8() as usize);
+    Ok(render_board_svg(&state, &names, Some(draw.hai), perspective, options.hide_hands))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mjlog::parser::parse_mjlogs;
+
+    #[test]
+    fn test_board_svg() {
+        let mjlog = parse_mjlogs(include_str!("../fixtures/observer.xml")).unwrap().remove(0);
+        let mark = TurnMark { round: 1, draw: 1 };
+        let svg = board_svg(&mjlog, mark, &BoardSvgOptions::default()).unwrap();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.contains("East 1, 1 honba"));
+        // 4 seats of 13 tiles and the drawn tile, the discard of seat 0 and the dora indicator.
+        assert_eq!(svg.matches("<rect x=").count(), 1 + 4 * 13 + 1 + 1 + 1);
+        assert_eq!(svg.matches("fill=\"#3b7a57\"").count(), 0);
+
+        let hidden = board_svg(&mjlog, mark, &BoardSvgOptions { hide_hands: true, ..Default::default() }).unwrap();
+        assert_eq!(hidden.matches("fill=\"#3b7a57\"").count(), 3 * 13);
+        assert!(board_svg(&mjlog, TurnMark { round: 2, draw: 0 }, &BoardSvgOptions::default()).is_err());
+    }
+}
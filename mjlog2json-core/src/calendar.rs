@@ -0,0 +1,100 @@
+//! # calendar
+//!
+//! Aggregates games of a player by hour of day, weekday and month, using the time in the game ID.
+
+use crate::game_id::*;
+use mjlog::model::*;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// Games played and the sum of placements (1-origin).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CalendarBucket {
+    pub games: u32,
+    pub placement_sum: u32,
+}
+
+impl CalendarBucket {
+    fn add(&mut self, placement: u32) {
+        self.games += 1;
+        self.placement_sum += placement;
+    }
+
+    pub fn average_placement(&self) -> f64 {
+        if self.games == 0 {
+            0.0
+        } else {
+            self.placement_sum as f64 / self.games as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CalendarStats {
+    pub by_hour: [CalendarBucket; 24],
+    /// 0 = Monday, ..., 6 = Sunday.
+    pub by_weekday: [CalendarBucket; 7],
+    /// Keyed by (year, month).
+    pub by_month: BTreeMap<(u16, u8), CalendarBucket>,
+}
+
+/// 1-origin placement of the seat from the final points. Ties go to the seat closer to the first dealer.
+pub fn final_placement<T: PartialOrd>(final_points: &[T], seat: usize) -> u32 {
+    let points = &final_points[seat];
+    final_points.iter().enumerate().filter(|&(i, x)| x > points || (x == points && i < seat)).count() as u32 + 1
+}
+
+fn final_points(mjlog: &Mjlog) -> Option<&Vec<GamePoint>> {
+    mjlog.actions.iter().rev().find_map(|x| match x {
+        Action::AGARI(a) => a.owari.as_ref().map(|(points, _)| points),
+        Action::RYUUKYOKU(r) => r.owari.as_ref().map(|(points, _)| points),
+        _ => None,
+    })
+}
+
+impl CalendarStats {
+    /// Aggregates the games of the player. Each game is given with its reference (game ID or file name).
+    /// Games without the player, a parsable game ID or a final result are skipped.
+    pub fn from_games<'a, I: IntoIterator<Item = (&'a str, &'a Mjlog)>>(games: I, player: &str) -> Self {
+        let mut stats = CalendarStats::default();
+
+        for (reference, mjlog) in games {
+            let Some(id) = parse_game_id(reference) else { continue };
+            let Some(seat) = mjlog.actions.iter().find_map(|x| x.as_un1()).and_then(|x| x.names.iter().position(|name| name == player)) else {
+                continue;
+            };
+            let Some(points) = final_points(mjlog).filter(|x| seat < x.len()) else { continue };
+
+            let placement = final_placement(points, seat);
+            stats.by_hour[id.hour as usize].add(placement);
+            stats.by_weekday[id.weekday() as usize].add(placement);
+            stats.by_month.entry((id.year, id.month)).or_default().add(placement);
+        }
+
+        stats
+    }
+
+    /// Exports as CSV with the columns `unit,key,games,average_placement`.
+    pub fn to_csv(&self) -> String {
+        let mut csv = vec![];
+        self.write_csv(&mut csv).expect("writing to a Vec never fails");
+        String::from_utf8(csv).expect("exporters write UTF-8 only")
+    }
+
+    /// Same as `to_csv`, but writes to the writer.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "unit,key,games,average_placement")?;
+        let mut row = |unit: &str, key: String, x: &CalendarBucket| writeln!(writer, "{},{},{},{:.3}", unit, key, x.games, x.average_placement());
+
+        for (hour, x) in self.by_hour.iter().enumerate() {
+            row("hour", hour.to_string(), x)?;
+        }
+        for (weekday, x) in self.by_weekday.iter().enumerate() {
+            row("weekday", WEEKDAY_NAMES[weekday].to_string(), x)?;
+        }
+        for ((year, month), x) in &self.by_month {
+            row("month", format!("{:04}-{:02}", year, month), x)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+//! # capabilities
+//!
+//! What this build supports, for frontends that populate format pickers at runtime.
+
+use crate::conv::ConvOptions;
+use serde_json::json;
+
+/// A supported format.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FormatInfo {
+    /// Name of the format, same as `--format` of the CLI.
+    pub name: &'static str,
+    /// File extension without the dot.
+    pub extension: &'static str,
+    pub description: &'static str,
+}
+
+/// Supported formats and features of this build.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capabilities {
+    /// Version of mjlog2json-core.
+    pub version: &'static str,
+    pub input_formats: Vec<FormatInfo>,
+    pub output_formats: Vec<FormatInfo>,
+    /// "ver" of the tenhou-JSON output by default.
+    pub tenhou_json_version: f64,
+    /// Enabled cargo features.
+    pub features: Vec<&'static str>,
+}
+
+const INPUT_FORMATS: [FormatInfo; 1] = [FormatInfo {
+    name: "mjlog",
+    extension: "xml",
+    description: "mjlog-XML, one or more games per document",
+}];
+
+const OUTPUT_FORMATS: [FormatInfo; 8] = [
+    FormatInfo {
+        name: "tenhou",
+        extension: "json",
+        description: "tenhou-JSON",
+    },
+    FormatInfo {
+        name: "mjai",
+        extension: "mjson",
+        description: "mjai events, one JSON per line",
+    },
+    FormatInfo {
+        name: "csv",
+        extension: "csv",
+        description: "result of each round",
+    },
+    FormatInfo {
+        name: "xml",
+        extension: "xml",
+        description: "mjlog-XML of each game",
+    },
+    FormatInfo {
+        name: "text",
+        extension: "txt",
+        description: "human-readable transcript",
+    },
+    FormatInfo {
+        name: "narrative",
+        extension: "txt",
+        description: "every draw, discard and call in order",
+    },
+    FormatInfo {
+        name: "markdown",
+        extension: "md",
+        description: "summary tables",
+    },
+    FormatInfo {
+        name: "html",
+        extension: "html",
+        description: "self-contained replayer of the turns",
+    },
+];
+
+// Only with the "arrow" feature.
+const ARROW_FORMAT: FormatInfo = FormatInfo {
+    name: "arrow",
+    extension: "arrows",
+    description: "result of each round as an Arrow IPC stream",
+};
+
+// Only with the "parquet" feature.
+const PARQUET_FORMAT: FormatInfo = FormatInfo {
+    name: "parquet",
+    extension: "parquet",
+    description: "round or action table as a Parquet file",
+};
+
+// Only with the "sqlite" feature.
+const SQLITE_FORMAT: FormatInfo = FormatInfo {
+    name: "sqlite",
+    extension: "db",
+    description: "games, rounds, actions and results as a SQLite database",
+};
+
+/// Returns what this build supports.
+pub fn capabilities() -> Capabilities {
+    let mut features = vec![];
+    let mut output_formats = OUTPUT_FORMATS.to_vec();
+    if cfg!(feature = "parallel") {
+        features.push("parallel");
+    }
+    if cfg!(feature = "arrow") {
+        features.push("arrow");
+        output_formats.push(ARROW_FORMAT);
+    }
+    if cfg!(feature = "parquet") {
+        features.push("parquet");
+        output_formats.push(PARQUET_FORMAT);
+    }
+    if cfg!(feature = "sqlite") {
+        features.push("sqlite");
+        output_formats.push(SQLITE_FORMAT);
+    }
+    if cfg!(feature = "wasm") {
+        features.push("wasm");
+    }
+
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        input_formats: INPUT_FORMATS.to_vec(),
+        output_formats,
+        tenhou_json_version: ConvOptions::default().ver,
+        features,
+    }
+}
+
+impl FormatInfo {
+    fn to_json(self) -> serde_json::Value {
+        json!({"name": self.name, "extension": self.extension, "description": self.description})
+    }
+}
+
+impl Capabilities {
+    /// JSON for services, e.g. `{"version":"0.1.3","input_formats":[...],...}`.
+    pub fn to_json(&self) -> String {
+        json!({
+            "version": self.version,
+            "input_formats": self.input_formats.iter().map(|x| x.to_json()).collect::<Vec<_>>(),
+            "output_formats": self.output_formats.iter().map(|x| x.to_json()).collect::<Vec<_>>(),
+            "tenhou_json_version": self.tenhou_json_version,
+            "features": self.features,
+        })
+        .to_string()
+    }
+}
@@ -0,0 +1,197 @@
+//! # compare
+//!
+//! Side-by-side statistics of two corpora, e.g. Houou 2019 vs 2024 or two private lobbies.
+//!
+//! Differences are marked by a two-sided z-test: "*" for p < 0.05 and "**" for p < 0.01.
+//! Rates use the two-proportion test and averages the test of the difference of means,
+//! which are good approximations for corpora of hundreds of games or more.
+
+use crate::conv::conv_yaku;
+use mjlog::model::*;
+use std::collections::BTreeMap;
+
+/// Distributions of a corpus to compare.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CorpusSummary {
+    pub games: usize,
+    pub rounds: usize,
+    /// Rounds ended by an exhaustive or abortive draw.
+    pub ryuukyoku: usize,
+    /// Wins. Multiple ron counts once for every winner.
+    pub wins: usize,
+    /// Sum and sum of squares of the winning points, excluding honba and riichi sticks.
+    pub win_points: f64,
+    pub win_points_squared: f64,
+    /// Wins with each yaku, by the tenhou-JSON name. Dora count once per win regardless of the number.
+    pub yaku: BTreeMap<String, usize>,
+}
+
+impl CorpusSummary {
+    pub fn from_mjlogs<'a, I: IntoIterator<Item = &'a Mjlog>>(mjlogs: I) -> Self {
+        let mut summary = Self::default();
+        for mjlog in mjlogs {
+            summary.games += 1;
+            for a in &mjlog.actions {
+                match a {
+                    Action::INIT(_) => summary.rounds += 1,
+                    Action::RYUUKYOKU(_) => summary.ryuukyoku += 1,
+                    Action::AGARI(x) => {
+                        let points = x.ten.total as f64;
+                        summary.wins += 1;
+                        summary.win_points += points;
+                        summary.win_points_squared += points * points;
+                        let yaku = x.yaku.iter().filter(|&&(_, han)| han > 0).map(|&(y, _)| y).chain(x.yakuman.iter().copied());
+                        for y in yaku {
+                            *summary.yaku.entry(conv_yaku(y).to_str().to_string()).or_default() += 1;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        summary
+    }
+
+    pub fn ryuukyoku_rate(&self) -> f64 {
+        ratio(self.ryuukyoku, self.rounds)
+    }
+
+    pub fn average_win_points(&self) -> f64 {
+        self.win_points / self.wins.max(1) as f64
+    }
+
+    fn win_points_variance(&self) -> f64 {
+        if self.wins < 2 {
+            return 0.0;
+        }
+        let n = self.wins as f64;
+        ((self.win_points_squared - self.win_points * self.win_points / n) / (n - 1.0)).max(0.0)
+    }
+
+    /// Fraction of the wins with the yaku.
+    pub fn yaku_rate(&self, yaku: &str) -> f64 {
+        ratio(self.yaku.get(yaku).copied().unwrap_or_default(), self.wins)
+    }
+}
+
+fn ratio(x: usize, n: usize) -> f64 {
+    x as f64 / n.max(1) as f64
+}
+
+/// Significance of a difference by a two-sided z-test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Significance {
+    None,
+    /// p < 0.05
+    P05,
+    /// p < 0.01
+    P01,
+}
+
+impl Significance {
+    fn from_z(z: f64) -> Self {
+        match z.abs() {
+            x if x >= 2.576 => Significance::P01,
+            x if x >= 1.960 => Significance::P05,
+            _ => Significance::None,
+        }
+    }
+
+    pub fn to_str(&self) -> &str {
+        match self {
+            Significance::None => "",
+            Significance::P05 => "*",
+            Significance::P01 => "**",
+        }
+    }
+}
+
+/// Two-proportion z-test of x1/n1 and x2/n2.
+pub fn proportion_significance(x1: usize, n1: usize, x2: usize, n2: usize) -> Significance {
+    if n1 == 0 || n2 == 0 {
+        return Significance::None;
+    }
+    let (p1, p2) = (ratio(x1, n1), ratio(x2, n2));
+    let pooled = ratio(x1 + x2, n1 + n2);
+    let se = (pooled * (1.0 - pooled) * (1.0 / n1 as f64 + 1.0 / n2 as f64)).sqrt();
+    if se == 0.0 {
+        return Significance::None;
+    }
+    Significance::from_z((p1 - p2) / se)
+}
+
+/// z-test of the difference of two means from the sample variances.
+pub fn mean_significance(mean1: f64, variance1: f64, n1: usize, mean2: f64, variance2: f64, n2: usize) -> Significance {
+    if n1 < 2 || n2 < 2 {
+        return Significance::None;
+    }
+    let se = (variance1 / n1 as f64 + variance2 / n2 as f64).sqrt();
+    if se == 0.0 {
+        return Significance::None;
+    }
+    Significance::from_z((mean1 - mean2) / se)
+}
+
+/// A statistic of the two corpora.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComparedValue {
+    pub name: String,
+    pub a: f64,
+    pub b: f64,
+    pub significance: Significance,
+}
+
+/// Comparison of two corpora.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub ryuukyoku_rate: ComparedValue,
+    pub average_win_points: ComparedValue,
+    /// Fraction of the wins with each yaku seen in either corpus, most frequent in `a` first.
+    pub yaku_rate: Vec<ComparedValue>,
+}
+
+pub fn compare(a: &CorpusSummary, b: &CorpusSummary) -> Comparison {
+    let ryuukyoku_rate = ComparedValue {
+        name: "ryuukyoku_rate".to_string(),
+        a: a.ryuukyoku_rate(),
+        b: b.ryuukyoku_rate(),
+        significance: proportion_significance(a.ryuukyoku, a.rounds, b.ryuukyoku, b.rounds),
+    };
+    let average_win_points = ComparedValue {
+        name: "average_win_points".to_string(),
+        a: a.average_win_points(),
+        b: b.average_win_points(),
+        significance: mean_significance(a.average_win_points(), a.win_points_variance(), a.wins, b.average_win_points(), b.win_points_variance(), b.wins),
+    };
+
+    let mut names: Vec<&String> = a.yaku.keys().chain(b.yaku.keys().filter(|x| !a.yaku.contains_key(*x))).collect();
+    names.sort_by_key(|x| std::cmp::Reverse((a.yaku.get(*x).copied().unwrap_or_default(), b.yaku.get(*x).copied().unwrap_or_default())));
+    let yaku_rate = names
+        .into_iter()
+        .map(|name| {
+            let (xa, xb) = (a.yaku.get(name).copied().unwrap_or_default(), b.yaku.get(name).copied().unwrap_or_default());
+            ComparedValue {
+                name: name.clone(),
+                a: a.yaku_rate(name),
+                b: b.yaku_rate(name),
+                significance: proportion_significance(xa, a.wins, xb, b.wins),
+            }
+        })
+        .collect();
+
+    Comparison { ryuukyoku_rate, average_win_points, yaku_rate }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_significance() {
+        assert_eq!(proportion_significance(50, 100, 50, 100), Significance::None);
+        assert_eq!(proportion_significance(500, 1000, 550, 1000), Significance::P05);
+        assert_eq!(proportion_significance(500, 1000, 600, 1000), Significance::P01);
+        assert_eq!(proportion_significance(0, 0, 1, 10), Significance::None);
+        assert_eq!(mean_significance(6000.0, 1.0e7, 1000, 6500.0, 1.0e7, 1000), Significance::P01);
+    }
+}
@@ -1,7 +1,9 @@
 use mjlog::model::*;
-use mjlog::parser::MjlogError;
+use mjlog::parser::{parse_mjlogs, MjlogError};
+use mjlog::uma_oka::*;
 use std::iter::once;
 use tenhou_json::calc::*;
+use tenhou_json::exporter::{export_tenhou_json, Locale};
 use tenhou_json::model::*;
 use tenhou_json::parser::*;
 use tenhou_json::score::*;
@@ -27,10 +29,94 @@ pub enum ConvError {
     InvalidRoundFormat,
     #[error("Invalid tile format")]
     InvalidTileFormat,
+    /// Error in a round. `action_index` is the index in `Mjlog::actions`.
+    #[error("kyoku {kyoku}, honba {honba}, action {action_index}: {source}")]
+    InRound { kyoku: u8, honba: u8, action_index: usize, source: Box<ConvError> },
 }
 
 type ConvResult<T> = Result<T, ConvError>;
 
+/// How red fives are recognized.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum RedFiveMode {
+    /// Always treat hai 16, 52 and 88 as red fives, regardless of the rule.
+    #[default]
+    Always,
+    /// Follow the rule in the GO tag.
+    FollowRule,
+    /// Never output red fives.
+    Never,
+}
+
+/// What the conversion does with a round without AGARI or RYUUKYOKU, e.g. the last round of a truncated log.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum IncompleteRound {
+    /// Fail with `NotFoundTerminalAction`.
+    #[default]
+    Fail,
+    /// Drop the round, and convert the complete rounds only.
+    Drop,
+    /// Keep the round with a "中断" (aborted) result without payments.
+    Abort,
+}
+
+impl std::str::FromStr for IncompleteRound {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(IncompleteRound::Fail),
+            "drop" => Ok(IncompleteRound::Drop),
+            "abort" => Ok(IncompleteRound::Abort),
+            _ => Err(format!("unknown incomplete round mode: {} (expected fail, drop or abort)", s)),
+        }
+    }
+}
+
+/// Options of the conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvOptions {
+    /// "ver" of the output.
+    pub ver: f64,
+    /// "ratingc" of the output.
+    pub ratingc: String,
+    /// "ref" of the output. Usually the file name of the log.
+    pub reference: String,
+    pub red_five: RedFiveMode,
+    /// Language of the dan names and the rule name (`rule.disp`), e.g. "四段" and "般南喰赤",
+    /// or "4 dan" and "Ippan South Kuitan Red".
+    pub locale: Locale,
+    /// When the last AGARI or RYUUKYOKU has no owari (e.g. a truncated log), compute the final points from
+    /// the last sc values and the final results by `mjlog::uma_oka`, instead of failing.
+    pub synthesize_final_result: bool,
+    /// For truncated logs, usually with `synthesize_final_result`.
+    pub incomplete_round: IncompleteRound,
+}
+
+impl Default for ConvOptions {
+    fn default() -> Self {
+        ConvOptions {
+            ver: 2.3,                   // Using this conversion system
+            ratingc: "PF4".to_string(), // What does this mean?
+            reference: String::new(),
+            red_five: RedFiveMode::default(),
+            locale: Locale::default(),
+            synthesize_final_result: false,
+            incomplete_round: IncompleteRound::default(),
+        }
+    }
+}
+
+impl RedFiveMode {
+    fn is_enabled(&self, settings: &GameSettings) -> bool {
+        match self {
+            RedFiveMode::Always => true,
+            RedFiveMode::FollowRule => !settings.no_red,
+            RedFiveMode::Never => false,
+        }
+    }
+}
+
 fn extract_round_indices(actions: &[Action]) -> Vec<(usize, usize)> {
     let mut indices: Vec<(usize, usize)> = Vec::new();
     let mut start = None;
@@ -51,24 +137,38 @@ fn extract_round_indices(actions: &[Action]) -> Vec<(usize, usize)> {
     indices
 }
 
+// Final points from the last sc values, with the riichi sticks left on the table going to the top.
+fn synthesize_final_result(before_points: &[i32], delta_points: &[i32], kyoutaku: u8, rule: &UmaOka) -> (Vec<i32>, Vec<f64>) {
+    let mut points: Vec<i32> = before_points.iter().zip(delta_points).map(|(a, b)| a + b).collect();
+    if let Some(top) = placements(&points).iter().position(|&x| x == 0) {
+        points[top] += kyoutaku as i32 * 10;
+    }
+    let results = final_results(&points, rule);
+    (points, results)
+}
+
 // NOT CLEAR: When double ron
-fn find_final_result(actions: &[Action]) -> ConvResult<(Vec<i32>, Vec<f64>)> {
+// `synthesize` is the rule to compute the final result when the last AGARI or RYUUKYOKU has no owari.
+fn find_final_result(actions: &[Action], synthesize: Option<&UmaOka>) -> ConvResult<(Vec<i32>, Vec<f64>)> {
     // find from last
     for a in actions.iter().rev() {
         match a {
-            Action::AGARI(ActionAGARI { owari, .. }) => {
-                if let Some(x) = owari {
-                    return Ok(x.clone());
-                } else {
-                    return Err(ConvError::InvalidRoundFormat);
-                }
+            Action::AGARI(ActionAGARI { owari, before_points, delta_points, .. }) => {
+                return match (owari, synthesize) {
+                    (Some(x), _) => Ok(x.clone()),
+                    // The winner already took the riichi sticks.
+                    (None, Some(rule)) => Ok(synthesize_final_result(before_points, delta_points, 0, rule)),
+                    (None, None) => Err(ConvError::InvalidRoundFormat),
+                };
             }
-            Action::RYUUKYOKU(ActionRYUUKYOKU { owari, .. }) => {
-                if let Some(x) = owari {
-                    return Ok(x.clone());
-                } else {
-                    return Err(ConvError::InvalidRoundFormat);
-                }
+            Action::RYUUKYOKU(ActionRYUUKYOKU {
+                owari, before_points, delta_points, kyoutaku, ..
+            }) => {
+                return match (owari, synthesize) {
+                    (Some(x), _) => Ok(x.clone()),
+                    (None, Some(rule)) => Ok(synthesize_final_result(before_points, delta_points, *kyoutaku, rule)),
+                    (None, None) => Err(ConvError::InvalidRoundFormat),
+                };
             }
             _ => {}
         }
@@ -80,8 +180,23 @@ const DAN_NAME: [&str; 21] = [
     "新人", "９級", "８級", "７級", "６級", "５級", "４級", "３級", "２級", "１級", "初段", "二段", "三段", "四段", "五段", "六段", "七段", "八段", "九段", "十段", "天鳳",
 ];
 
-fn conv_dan(dan: &TenhouRank) -> String {
-    DAN_NAME[*dan as usize].to_string()
+const DAN_NAME_EN: [&str; 21] = [
+    "Newcomer", "9 kyu", "8 kyu", "7 kyu", "6 kyu", "5 kyu", "4 kyu", "3 kyu", "2 kyu", "1 kyu", "1 dan", "2 dan", "3 dan", "4 dan", "5 dan", "6 dan", "7 dan", "8 dan", "9 dan", "10 dan", "Tenhou",
+];
+
+/// Points of mjlog (units of 100) to points, widened first so that the multiplication cannot overflow.
+pub(crate) fn to_points(x: mjlog::model::GamePoint) -> tenhou_json::model::GamePoint {
+    tenhou_json::model::GamePoint::from(x) * 100
+}
+
+// Rate of AI or observer seats without a valid rate. Their dan is an empty string.
+const UNKNOWN_RATE: f64 = 0.0;
+
+fn conv_dan(dan: &TenhouRank, locale: Locale) -> String {
+    match locale {
+        Locale::Japanese => DAN_NAME[*dan as usize].to_string(),
+        Locale::English => DAN_NAME_EN[*dan as usize].to_string(),
+    }
 }
 
 fn conv_tile_from_u8(x: u8) -> ConvResult<Tile> {
@@ -116,14 +231,14 @@ fn conv_hai_to_tile(hai: Hai, red_enable: bool) -> ConvResult<Tile> {
     conv_tile_from_u8(pict_type * 10 + pict_num)
 }
 
-fn get_dora_vec(dora_hyouji: Hai, mid_actions: &[Action]) -> ConvResult<Vec<Tile>> {
+fn get_dora_vec(dora_hyouji: Hai, mid_actions: &[Action], red: bool) -> ConvResult<Vec<Tile>> {
     let dora_hais: Vec<Hai> = once(dora_hyouji).chain(mid_actions.iter().filter_map(|x| x.as_dora()).map(|x| x.hai)).collect();
-    dora_hais.iter().map(|x| conv_hai_to_tile(*x, true)).collect()
+    dora_hais.iter().map(|x| conv_hai_to_tile(*x, red)).collect()
 }
 
-fn get_ura_dora(end_action: &Action) -> ConvResult<Vec<Tile>> {
+fn get_ura_dora(end_action: &Action, red: bool) -> ConvResult<Vec<Tile>> {
     match end_action {
-        Action::AGARI(ActionAGARI { dora_hai_ura, .. }) => dora_hai_ura.iter().map(|x| conv_hai_to_tile(*x, true)).collect(),
+        Action::AGARI(ActionAGARI { dora_hai_ura, .. }) => dora_hai_ura.iter().map(|x| conv_hai_to_tile(*x, red)).collect(),
         Action::RYUUKYOKU(_) => Ok(vec![]),
         _ => panic!("unexpected end action"),
     }
@@ -133,9 +248,9 @@ fn get_ura_dora(end_action: &Action) -> ConvResult<Vec<Tile>> {
 /// The ura-dora is only recorded in the winning information of the riichi declarer.
 /// Therefore, in the case of multiple ron, the ura-dora must be retrieved from each winner.
 /// However, if it is found for one player, it will be the same for all winners.
-fn get_ura_dora_vec(end_actions: &[&Action]) -> ConvResult<Vec<Tile>> {
+fn get_ura_dora_vec(end_actions: &[&Action], red: bool) -> ConvResult<Vec<Tile>> {
     for a in end_actions {
-        let ura_dora = get_ura_dora(a)?;
+        let ura_dora = get_ura_dora(a, red)?;
         if !ura_dora.is_empty() {
             return Ok(ura_dora);
         }
@@ -143,49 +258,69 @@ fn get_ura_dora_vec(end_actions: &[&Action]) -> ConvResult<Vec<Tile>> {
     Ok(Vec::new())
 }
 
-fn conv_rule(settings: &GameSettings) -> ConvResult<Rule> {
-    let room_str = match settings.room {
-        TenhouRoom::Ippan => "般",
-        TenhouRoom::Joukyu => "上",
-        TenhouRoom::Tokujou => "特",
-        TenhouRoom::Houou => "鳳",
-    };
-
-    let hanchan_str = if settings.hanchan { "南" } else { "東" };
-    let aka_str = if settings.no_red { "" } else { "赤" };
-    let kuitan_str = if settings.no_kuitan { "" } else { "喰" };
-    let soku_str = if settings.soku { "速" } else { "" };
+// "般南喰赤" / "Ippan South Kuitan Red"
+pub(crate) fn conv_rule_disp(settings: &GameSettings, locale: Locale) -> String {
+    match locale {
+        Locale::Japanese => {
+            let room_str = match settings.room {
+                TenhouRoom::Ippan => "般",
+                TenhouRoom::Joukyu => "上",
+                TenhouRoom::Tokujou => "特",
+                TenhouRoom::Houou => "鳳",
+            };
+
+            let hanchan_str = if settings.hanchan { "南" } else { "東" };
+            let aka_str = if settings.no_red { "" } else { "赤" };
+            let kuitan_str = if settings.no_kuitan { "" } else { "喰" };
+            let soku_str = if settings.soku { "速" } else { "" };
+            format!("{}{}{}{}{}", room_str, hanchan_str, kuitan_str, aka_str, soku_str)
+        }
+        Locale::English => {
+            let room_str = match settings.room {
+                TenhouRoom::Ippan => "Ippan",
+                TenhouRoom::Joukyu => "Joukyuu",
+                TenhouRoom::Tokujou => "Tokujou",
+                TenhouRoom::Houou => "Houou",
+            };
+            let words = [
+                Some(room_str),
+                Some(if settings.hanchan { "South" } else { "East" }),
+                (!settings.no_kuitan).then_some("Kuitan"),
+                (!settings.no_red).then_some("Red"),
+                settings.soku.then_some("Fast"),
+            ];
+            words.into_iter().flatten().collect::<Vec<_>>().join(" ")
+        }
+    }
+}
 
+fn conv_rule(settings: &GameSettings, locale: Locale) -> ConvResult<Rule> {
     Ok(Rule {
-        disp: format!("{}{}{}{}{}", room_str, hanchan_str, kuitan_str, aka_str, soku_str),
+        disp: conv_rule_disp(settings, locale),
         aka53: !settings.no_red,
         aka52: !settings.no_red,
         aka51: !settings.no_red,
     })
 }
 
-fn conv_round_setting(actions: &[Action]) -> ConvResult<RoundSettings> {
+fn conv_round_setting(actions: &[Action], red: bool) -> ConvResult<RoundSettings> {
     let start_action = &actions[0];
     assert!(start_action.is_init());
 
     let init = start_action.as_init().unwrap();
     let end_actions: Vec<&Action> = actions.iter().filter(|x| x.is_agari() || x.is_ryuukyoku()).collect();
 
-    if end_actions.is_empty() {
-        return Err(ConvError::NotFoundTerminalAction);
-    }
-
     Ok(RoundSettings {
         kyoku: init.seed.kyoku,
         honba: init.seed.honba,
         kyoutaku: init.seed.kyoutaku,
-        points: init.ten.iter().map(|x| x * 100).collect(),
-        dora: get_dora_vec(init.seed.dora_hyouji, actions)?,
-        ura_dora: get_ura_dora_vec(&end_actions)?,
+        points: init.ten.iter().map(|&x| to_points(x)).collect(),
+        dora: get_dora_vec(init.seed.dora_hyouji, actions, red)?,
+        ura_dora: get_ura_dora_vec(&end_actions, red)?,
     })
 }
 
-fn conv_yaku(x: mjlog::model::Yaku) -> tenhou_json::model::Yaku {
+pub(crate) fn conv_yaku(x: mjlog::model::Yaku) -> tenhou_json::model::Yaku {
     match x {
         mjlog::model::Yaku::MenzenTsumo => tenhou_json::model::Yaku::MenzenTsumo,
         mjlog::model::Yaku::Riichi => tenhou_json::model::Yaku::Riichi,
@@ -242,10 +377,11 @@ fn conv_yaku(x: mjlog::model::Yaku) -> tenhou_json::model::Yaku {
         mjlog::model::Yaku::Dora => tenhou_json::model::Yaku::Dora,
         mjlog::model::Yaku::UraDora => tenhou_json::model::Yaku::UraDora,
         mjlog::model::Yaku::AkaDora => tenhou_json::model::Yaku::AkaDora,
+        mjlog::model::Yaku::Unknown(x) => tenhou_json::model::Yaku::Unknown(x),
     }
 }
 
-fn conv_extra_ryuukyoku_reason(x: &Option<mjlog::model::ExtraRyuukyokuReason>) -> tenhou_json::model::ExtraRyuukyokuReason {
+pub(crate) fn conv_extra_ryuukyoku_reason(x: &Option<mjlog::model::ExtraRyuukyokuReason>) -> tenhou_json::model::ExtraRyuukyokuReason {
     match x {
         Some(mjlog::model::ExtraRyuukyokuReason::KyuusyuKyuuhai) => tenhou_json::model::ExtraRyuukyokuReason::KyuusyuKyuuhai,
         Some(mjlog::model::ExtraRyuukyokuReason::SuuchaRiichi) => tenhou_json::model::ExtraRyuukyokuReason::SuuchaRiichi,
@@ -267,19 +403,19 @@ fn is_not_ura_zero(x: &YakuPair) -> bool {
     )
 }
 
-fn conv_ranked_score_normal(v: &ActionAGARI, han: u8, oya: Player) -> RankedScore {
+fn conv_ranked_score_normal(v: &ActionAGARI, han: u8, oya: Player, profile: ScoreProfile) -> RankedScore {
     match (v.is_tsumo(), v.who == oya) {
-        (true, true) => get_oya_tsumo(v.fu, han),
-        (true, false) => get_ko_tsumo(v.fu, han),
-        (false, true) => get_oya_ron(v.fu, han),
-        (false, false) => get_ko_ron(v.fu, han),
+        (true, true) => get_oya_tsumo_with(v.ten.fu, han, profile),
+        (true, false) => get_ko_tsumo_with(v.ten.fu, han, profile),
+        (false, true) => get_oya_ron(v.ten.fu, han),
+        (false, false) => get_ko_ron(v.ten.fu, han),
     }
 }
 
-fn conv_ranked_score_yakuman(v: &ActionAGARI, num: u8, oya: Player) -> RankedScore {
+fn conv_ranked_score_yakuman(v: &ActionAGARI, num: u8, oya: Player, profile: ScoreProfile) -> RankedScore {
     match (v.is_tsumo(), v.who == oya) {
-        (true, true) => get_oya_tsumo_yakuman(num),
-        (true, false) => get_ko_tsumo_yakuman(num),
+        (true, true) => get_oya_tsumo_yakuman_with(num, profile),
+        (true, false) => get_ko_tsumo_yakuman_with(num, profile),
         (false, true) => get_oya_ron_yakuman(num),
         (false, false) => get_ko_ron_yakuman(num),
     }
@@ -304,8 +440,8 @@ fn conv_yakuman_vec(vs: &[mjlog::model::Yaku]) -> Vec<YakuPair> {
         .collect()
 }
 
-fn conv_agari(v: &ActionAGARI, oya: Player) -> ConvResult<Agari> {
-    let delta_points = v.delta_points.iter().map(|&x| x * 100).collect();
+fn conv_agari(v: &ActionAGARI, oya: Player, profile: ScoreProfile) -> ConvResult<Agari> {
+    let delta_points = v.delta_points.iter().map(|&x| to_points(x)).collect();
     let who = v.who.to_u8();
     let from_who = v.from_who.to_u8();
     let pao_who = if let Some(w) = v.pao_who { w.to_u8() } else { v.who.to_u8() };
@@ -313,11 +449,11 @@ fn conv_agari(v: &ActionAGARI, oya: Player) -> ConvResult<Agari> {
     let (yaku, ranked_score) = if !v.yaku.is_empty() {
         let yaku = conv_yaku_vec(&v.yaku);
         let han = yaku.iter().fold(0, |sum, YakuPair { level, .. }| sum + level.get_number());
-        (yaku, conv_ranked_score_normal(v, han, oya))
+        (yaku, conv_ranked_score_normal(v, han, oya, profile))
     } else if !v.yakuman.is_empty() {
         let yaku = conv_yakuman_vec(&v.yakuman);
         let num = yaku.iter().fold(0, |sum, YakuPair { level, .. }| sum + level.get_number());
-        (yaku, conv_ranked_score_yakuman(v, num, oya))
+        (yaku, conv_ranked_score_yakuman(v, num, oya, profile))
     } else {
         panic!("unexpected");
     };
@@ -332,17 +468,17 @@ fn conv_agari(v: &ActionAGARI, oya: Player) -> ConvResult<Agari> {
     })
 }
 
-fn conv_agari_vec(vs: &[&ActionAGARI], oya: Player) -> ConvResult<Vec<Agari>> {
-    vs.iter().map(|x| conv_agari(x, oya)).collect()
+fn conv_agari_vec(vs: &[&ActionAGARI], oya: Player, profile: ScoreProfile) -> ConvResult<Vec<Agari>> {
+    vs.iter().map(|x| conv_agari(x, oya, profile)).collect()
 }
 
-fn conv_round_result_from_agari(vs: &[&ActionAGARI], oya: Player) -> ConvResult<RoundResult> {
-    Ok(RoundResult::Agari { agari_vec: conv_agari_vec(vs, oya)? })
+fn conv_round_result_from_agari(vs: &[&ActionAGARI], oya: Player, profile: ScoreProfile) -> ConvResult<RoundResult> {
+    Ok(RoundResult::Agari { agari_vec: conv_agari_vec(vs, oya, profile)? })
 }
 
-fn conv_delta_points_ryuukyoku(v: &ActionRYUUKYOKU) -> Vec<i32> {
+fn conv_delta_points_ryuukyoku(v: &ActionRYUUKYOKU) -> Vec<tenhou_json::model::GamePoint> {
     if v.delta_points.iter().any(|&x| x != 0) {
-        v.delta_points.iter().map(|&x| x * 100).collect()
+        v.delta_points.iter().map(|&x| to_points(x)).collect()
     } else {
         Vec::new()
     }
@@ -364,7 +500,7 @@ fn conv_round_result_from_ryuukyoku(v: &ActionRYUUKYOKU) -> ConvResult<RoundResu
     })
 }
 
-fn conv_round_result(actions: &[Action]) -> ConvResult<RoundResult> {
+fn conv_round_result(actions: &[Action], profile: ScoreProfile) -> ConvResult<RoundResult> {
     let init_action = actions[0].as_init().unwrap();
 
     let ryuukyoku_actions: Vec<&ActionRYUUKYOKU> = actions.iter().filter_map(|x| x.as_ryuukyoku()).collect();
@@ -375,26 +511,15 @@ fn conv_round_result(actions: &[Action]) -> ConvResult<RoundResult> {
     // Note: Consider double ron
     let agari_actions: Vec<&ActionAGARI> = actions.iter().filter_map(|x| x.as_agari()).collect();
     if !agari_actions.is_empty() {
-        return conv_round_result_from_agari(&agari_actions, init_action.oya);
+        return conv_round_result_from_agari(&agari_actions, init_action.oya, profile);
     }
 
     // not found terminal action, or there are multi ryuukyoku tags
     Err(ConvError::InvalidRoundFormat)
 }
 
-fn conv_tiles(xs: &[Hai]) -> ConvResult<Vec<Tile>> {
-    xs.iter().map(|&x| conv_hai_to_tile(x, true)).collect()
-}
-
-// tenhou json's initial hand order:
-// normal 5 -> red 5 -> normal 6
-fn get_initial_hand_order(t: &Tile) -> u32 {
-    match t.to_u8() {
-        51 => 151,
-        52 => 251,
-        53 => 351,
-        x => x as u32 * 10,
-    }
+fn conv_tiles(xs: &[Hai], red: bool) -> ConvResult<Vec<Tile>> {
+    xs.iter().map(|&x| conv_hai_to_tile(x, red)).collect()
 }
 
 fn is_valid_player_action(action: &Action, target_player: Player) -> bool {
@@ -416,169 +541,170 @@ fn conv_dir(d: mjlog::model::Direction) -> tenhou_json::model::Direction {
     }
 }
 
-fn replay_actions(actions: &[&Action]) -> ConvResult<(Vec<IncomingTile>, Vec<OutgoingTile>)> {
+// On error, also returns the index of the failed action in the round.
+fn replay_actions(actions: &[(usize, &Action)], red: bool) -> Result<(Vec<IncomingTile>, Vec<OutgoingTile>), (usize, ConvError)> {
     let mut incoming = vec![];
     let mut outgoing = vec![];
     let mut reach_declared = false;
     let mut last_draw = None;
 
-    for a in actions {
-        match a {
-            Action::DRAW(x) => {
-                let tile = conv_hai_to_tile(x.hai, true)?;
-                incoming.push(IncomingTile::Tsumo(tile));
-                last_draw = Some(x.hai);
-            }
-            Action::DISCARD(x) => {
-                match last_draw {
-                    Some(h) if h == x.hai => {
-                        if reach_declared {
-                            outgoing.push(OutgoingTile::TsumogiriRiichi)
-                        } else {
-                            outgoing.push(OutgoingTile::Tsumogiri)
+    for &(index, a) in actions {
+        let mut replay = || -> ConvResult<()> {
+            match a {
+                Action::DRAW(x) => {
+                    let tile = conv_hai_to_tile(x.hai, red)?;
+                    incoming.push(IncomingTile::Tsumo(tile));
+                    last_draw = Some(x.hai);
+                }
+                Action::DISCARD(x) => {
+                    match last_draw {
+                        Some(h) if h == x.hai => {
+                            if reach_declared {
+                                outgoing.push(OutgoingTile::TsumogiriRiichi)
+                            } else {
+                                outgoing.push(OutgoingTile::Tsumogiri)
+                            }
                         }
-                    }
-                    _ => {
-                        let tile = conv_hai_to_tile(x.hai, true)?;
-                        if reach_declared {
-                            outgoing.push(OutgoingTile::Riichi(tile))
-                        } else {
-                            outgoing.push(OutgoingTile::Discard(tile))
+                        _ => {
+                            let tile = conv_hai_to_tile(x.hai, red)?;
+                            if reach_declared {
+                                outgoing.push(OutgoingTile::Riichi(tile))
+                            } else {
+                                outgoing.push(OutgoingTile::Discard(tile))
+                            }
                         }
                     }
+                    reach_declared = false;
+                    last_draw = None;
                 }
-                reach_declared = false;
-                last_draw = None;
-            }
-            Action::REACH1(_) => {
-                reach_declared = true;
-            }
-            Action::N(x) => {
-                match x.m {
-                    Meld::Chii { combination, called_position } => {
-                        // mjlog: sorted in ascending order.
-                        // tenhou json: the placement order on the board.
-                        let orders = match called_position {
-                            0 => combination,
-                            1 => (combination.1, combination.0, combination.2),
-                            2 => (combination.2, combination.0, combination.1),
-                            _ => panic!("unexpected called position"),
-                        };
-
-                        let incoming_tile = IncomingTile::Chii {
-                            combination: (conv_hai_to_tile(orders.0, true)?, conv_hai_to_tile(orders.1, true)?, conv_hai_to_tile(orders.2, true)?),
-                        };
-                        incoming.push(incoming_tile);
-                    }
-                    Meld::Pon { dir: src_dir, called, unused, .. } => {
-                        let dir = conv_dir(src_dir);
-                        // mjlog: sorted in ascending order.
-                        // tenhou json: the placement order on the board.
-                        if called.is_number5() {
-                            let called_tile = conv_hai_to_tile(called, true)?;
-                            let unused_tile = conv_hai_to_tile(unused, true)?;
-                            let tile = called_tile.to_black();
-
-                            if unused_tile.is_red() {
-                                incoming.push(IncomingTile::Pon { dir, combination: (tile, tile, tile) })
-                            } else if called_tile.is_red() {
-                                let combination = match dir {
-                                    tenhou_json::model::Direction::Kamicha => (called_tile, tile, tile),
-                                    tenhou_json::model::Direction::Toimen => (tile, called_tile, tile),
-                                    tenhou_json::model::Direction::Shimocha => (tile, tile, called_tile),
-                                    _ => panic!("unexpected"),
-                                };
-                                incoming.push(IncomingTile::Pon { dir, combination });
+                Action::REACH1(_) => {
+                    reach_declared = true;
+                }
+                Action::N(x) => {
+                    match x.m {
+                        Meld::Chii { combination, called_position } => {
+                            // mjlog: sorted in ascending order.
+                            // tenhou json: the placement order on the board.
+                            let orders = called_position.board_order(combination);
+
+                            let incoming_tile = IncomingTile::Chii {
+                                combination: (conv_hai_to_tile(orders.0, red)?, conv_hai_to_tile(orders.1, red)?, conv_hai_to_tile(orders.2, red)?),
+                            };
+                            incoming.push(incoming_tile);
+                        }
+                        Meld::Pon { dir: src_dir, called, unused, .. } => {
+                            let dir = conv_dir(src_dir);
+                            // mjlog: sorted in ascending order.
+                            // tenhou json: the placement order on the board.
+                            if red && called.is_number5() {
+                                let called_tile = conv_hai_to_tile(called, true)?;
+                                let unused_tile = conv_hai_to_tile(unused, true)?;
+                                let tile = called_tile.to_black();
+
+                                if unused_tile.is_red() {
+                                    incoming.push(IncomingTile::Pon { dir, combination: (tile, tile, tile) })
+                                } else if called_tile.is_red() {
+                                    let combination = match dir {
+                                        tenhou_json::model::Direction::Kamicha => (called_tile, tile, tile),
+                                        tenhou_json::model::Direction::Toimen => (tile, called_tile, tile),
+                                        tenhou_json::model::Direction::Shimocha => (tile, tile, called_tile),
+                                        _ => panic!("unexpected"),
+                                    };
+                                    incoming.push(IncomingTile::Pon { dir, combination });
+                                } else {
+                                    let combination = match dir {
+                                        tenhou_json::model::Direction::Shimocha => (tile, tile.to_red(), tile),
+                                        _ => (tile, tile, tile.to_red()),
+                                    };
+                                    incoming.push(IncomingTile::Pon { dir, combination });
+                                }
                             } else {
-                                let combination = match dir {
-                                    tenhou_json::model::Direction::Shimocha => (tile, tile.to_red(), tile),
-                                    _ => (tile, tile, tile.to_red()),
-                                };
-                                incoming.push(IncomingTile::Pon { dir, combination });
+                                // combination, called, unused, all the same
+                                let tile = conv_hai_to_tile(called, red)?;
+                                incoming.push(IncomingTile::Pon { dir, combination: (tile, tile, tile) })
                             }
-                        } else {
-                            // combination, called, unused, all the same
-                            let tile = conv_hai_to_tile(called, true)?;
-                            incoming.push(IncomingTile::Pon { dir, combination: (tile, tile, tile) })
                         }
-                    }
-                    Meld::Kakan { dir: src_dir, called, added, .. } => {
-                        let dir = conv_dir(src_dir);
-
-                        // mjlog: sorted in ascending order.
-                        // tenhou json: the placement order on the board.
-                        if called.is_number5() {
-                            let called_tile = conv_hai_to_tile(called, true)?;
-                            let added_tile = conv_hai_to_tile(added, true)?;
-                            let tile = called_tile.to_black();
-
-                            if added_tile.is_red() {
+                        Meld::Kakan { dir: src_dir, called, added, .. } => {
+                            let dir = conv_dir(src_dir);
+
+                            // mjlog: sorted in ascending order.
+                            // tenhou json: the placement order on the board.
+                            if red && called.is_number5() {
+                                let called_tile = conv_hai_to_tile(called, true)?;
+                                let added_tile = conv_hai_to_tile(added, true)?;
+                                let tile = called_tile.to_black();
+
+                                if added_tile.is_red() {
+                                    outgoing.push(OutgoingTile::Kakan {
+                                        dir,
+                                        combination: (tile, tile, tile),
+                                        added: added_tile,
+                                    })
+                                } else if called_tile.is_red() {
+                                    let combination = match dir {
+                                        tenhou_json::model::Direction::Kamicha => (called_tile, tile, tile),
+                                        tenhou_json::model::Direction::Toimen => (tile, called_tile, tile),
+                                        tenhou_json::model::Direction::Shimocha => (tile, tile, called_tile),
+                                        _ => panic!("unexpected"),
+                                    };
+                                    outgoing.push(OutgoingTile::Kakan { dir, combination, added: added_tile });
+                                } else {
+                                    let combination = match dir {
+                                        tenhou_json::model::Direction::Shimocha => (tile, tile.to_red(), tile),
+                                        _ => (tile, tile, tile.to_red()),
+                                    };
+                                    outgoing.push(OutgoingTile::Kakan { dir, combination, added: added_tile });
+                                }
+                            } else {
+                                // combination, called, added, all the same
+                                let tile = conv_hai_to_tile(called, red)?;
                                 outgoing.push(OutgoingTile::Kakan {
                                     dir,
                                     combination: (tile, tile, tile),
-                                    added: added_tile,
+                                    added: tile,
                                 })
-                            } else if called_tile.is_red() {
-                                let combination = match dir {
-                                    tenhou_json::model::Direction::Kamicha => (called_tile, tile, tile),
-                                    tenhou_json::model::Direction::Toimen => (tile, called_tile, tile),
-                                    tenhou_json::model::Direction::Shimocha => (tile, tile, called_tile),
-                                    _ => panic!("unexpected"),
-                                };
-                                outgoing.push(OutgoingTile::Kakan { dir, combination, added: added_tile });
-                            } else {
-                                let combination = match dir {
-                                    tenhou_json::model::Direction::Shimocha => (tile, tile.to_red(), tile),
-                                    _ => (tile, tile, tile.to_red()),
-                                };
-                                outgoing.push(OutgoingTile::Kakan { dir, combination, added: added_tile });
                             }
-                        } else {
-                            // combination, called, added, all the same
-                            let tile = conv_hai_to_tile(called, true)?;
-                            outgoing.push(OutgoingTile::Kakan {
-                                dir,
-                                combination: (tile, tile, tile),
-                                added: tile,
-                            })
                         }
-                    }
-                    Meld::Daiminkan { dir: src_dir, hai } => {
-                        let dir = conv_dir(src_dir);
-                        if hai.is_number5() {
-                            let called_tile = conv_hai_to_tile(hai, true)?;
-                            let tile = called_tile.to_black();
-
-                            if called_tile.is_red() {
-                                let combination = match dir {
-                                    tenhou_json::model::Direction::Kamicha => (called_tile, tile, tile, tile),
-                                    tenhou_json::model::Direction::Toimen => (tile, called_tile, tile, tile),
-                                    tenhou_json::model::Direction::Shimocha => (tile, tile, tile, called_tile),
-                                    _ => panic!("unexpected"),
-                                };
-                                incoming.push(IncomingTile::Daiminkan { combination, dir });
+                        Meld::Daiminkan { dir: src_dir, hai } => {
+                            let dir = conv_dir(src_dir);
+                            if red && hai.is_number5() {
+                                let called_tile = conv_hai_to_tile(hai, true)?;
+                                let tile = called_tile.to_black();
+
+                                if called_tile.is_red() {
+                                    let combination = match dir {
+                                        tenhou_json::model::Direction::Kamicha => (called_tile, tile, tile, tile),
+                                        tenhou_json::model::Direction::Toimen => (tile, called_tile, tile, tile),
+                                        tenhou_json::model::Direction::Shimocha => (tile, tile, tile, called_tile),
+                                        _ => panic!("unexpected"),
+                                    };
+                                    incoming.push(IncomingTile::Daiminkan { combination, dir });
+                                } else {
+                                    let combination = match dir {
+                                        tenhou_json::model::Direction::Shimocha => (tile, tile, tile.to_red(), tile),
+                                        _ => (tile, tile, tile, tile.to_red()),
+                                    };
+                                    incoming.push(IncomingTile::Daiminkan { combination, dir });
+                                }
                             } else {
-                                let combination = match dir {
-                                    tenhou_json::model::Direction::Shimocha => (tile, tile, tile.to_red(), tile),
-                                    _ => (tile, tile, tile, tile.to_red()),
-                                };
-                                incoming.push(IncomingTile::Daiminkan { combination, dir });
+                                let tile = conv_hai_to_tile(hai, red)?;
+                                incoming.push(IncomingTile::Daiminkan { combination: (tile, tile, tile, tile), dir });
                             }
-                        } else {
-                            let tile = conv_hai_to_tile(hai, true)?;
-                            incoming.push(IncomingTile::Daiminkan { combination: (tile, tile, tile, tile), dir });
+                            outgoing.push(OutgoingTile::Dummy)
+                        }
+                        Meld::Ankan { hai } => {
+                            // NOT CLEAR
+                            // I think the red 5 is always recorded when ankan of 5.
+                            let tile = conv_hai_to_tile(hai, red)?;
+                            outgoing.push(OutgoingTile::Ankan(if red { tile.to_red() } else { tile }))
                         }
-                        outgoing.push(OutgoingTile::Dummy)
-                    }
-                    Meld::Ankan { hai } => {
-                        // NOT CLEAR
-                        // I think the red 5 is always recorded when ankan of 5.
-                        outgoing.push(OutgoingTile::Ankan(conv_hai_to_tile(hai, true)?.to_red()))
                     }
                 }
+                _ => panic!("unexpected"),
             }
-            _ => panic!("unexpected"),
-        }
+            Ok(())
+        };
+        replay().map_err(|e| (index, e))?;
     }
 
     // The last dummy is invalid and should be removed.
@@ -589,40 +715,80 @@ fn replay_actions(actions: &[&Action]) -> ConvResult<(Vec<IncomingTile>, Vec<Out
     Ok((incoming, outgoing))
 }
 
-fn conv_round_players(actions: &[Action]) -> ConvResult<Vec<RoundPlayer>> {
+fn conv_round_players(actions: &[Action], red: bool) -> Result<Vec<RoundPlayer>, (usize, ConvError)> {
     let init_action = actions[0].as_init().unwrap();
 
     let mut players = vec![];
     for (i, h) in init_action.hai.iter().enumerate() {
-        let mut hand = conv_tiles(h)?;
-        hand.sort_by_key(get_initial_hand_order);
+        let mut hand = conv_tiles(h, red).map_err(|e| (0, e))?;
+        // normal 5 -> red 5 -> normal 6
+        sort_hand_display(&mut hand);
 
-        let player_actions: Vec<&Action> = actions.iter().filter(|x| is_valid_player_action(x, Player::new(i as u8))).collect();
-        let (incoming, outgoing) = replay_actions(&player_actions)?;
+        let player_actions: Vec<(usize, &Action)> = actions.iter().enumerate().filter(|(_, x)| is_valid_player_action(x, Player::new(i as u8))).collect();
+        let (incoming, outgoing) = replay_actions(&player_actions, red)?;
 
         players.push(RoundPlayer { hand, incoming, outgoing });
     }
     Ok(players)
 }
 
-fn conv_round(actions: &[Action]) -> ConvResult<Round> {
+// `start` is the index of the INIT action in the whole game, used for error reporting.
+// `abort` gives a round without a terminal action the result "中断" instead of failing.
+fn conv_round(actions: &[Action], start: usize, red: bool, profile: ScoreProfile, abort: bool) -> ConvResult<Round> {
+    let seed = &actions[0].as_init().unwrap().seed;
+    let in_round = |i: usize, e: ConvError| ConvError::InRound {
+        kyoku: seed.kyoku,
+        honba: seed.honba,
+        action_index: start + i,
+        source: Box::new(e),
+    };
+    let terminal_index = actions.iter().position(|x| x.is_agari() || x.is_ryuukyoku());
+    if terminal_index.is_none() && !abort {
+        return Err(in_round(0, ConvError::NotFoundTerminalAction));
+    }
+
     Ok(Round {
-        settings: conv_round_setting(actions)?,
-        players: conv_round_players(actions)?,
-        result: conv_round_result(actions)?,
+        settings: conv_round_setting(actions, red).map_err(|e| in_round(0, e))?,
+        players: conv_round_players(actions, red).map_err(|(i, e)| in_round(i, e))?,
+        result: match terminal_index {
+            Some(i) => conv_round_result(actions, profile).map_err(|e| in_round(i, e))?,
+            None => RoundResult::Ryuukyoku {
+                reason: tenhou_json::model::ExtraRyuukyokuReason::Chuudan,
+                delta_points: vec![],
+            },
+        },
     })
 }
 
-fn conv_rounds(actions: &[Action], indices: &[(usize, usize)]) -> ConvResult<Vec<Round>> {
+#[cfg(not(feature = "parallel"))]
+fn conv_rounds(actions: &[Action], indices: &[(usize, usize)], red: bool, profile: ScoreProfile, abort: bool) -> ConvResult<Vec<Round>> {
     let mut rounds = vec![];
 
     for &(start, end) in indices {
-        rounds.push(conv_round(&actions[start..end])?);
+        rounds.push(conv_round(&actions[start..end], start, red, profile, abort)?);
     }
 
     Ok(rounds)
 }
 
+/// Games with more rounds than this are converted in parallel. A normal hanchan has about 10 rounds.
+#[cfg(feature = "parallel")]
+const PARALLEL_ROUNDS_THRESHOLD: usize = 32;
+
+#[cfg(feature = "parallel")]
+fn conv_rounds(actions: &[Action], indices: &[(usize, usize)], red: bool, profile: ScoreProfile, abort: bool) -> ConvResult<Vec<Round>> {
+    use rayon::prelude::*;
+
+    let conv = |&(start, end): &(usize, usize)| conv_round(&actions[start..end], start, red, profile, abort);
+    if indices.len() <= PARALLEL_ROUNDS_THRESHOLD {
+        return indices.iter().map(conv).collect();
+    }
+
+    // Collect every result first, so that the error of the earliest round is returned as in the sequential version.
+    let results: Vec<ConvResult<Round>> = indices.par_iter().map(conv).collect();
+    results.into_iter().collect()
+}
+
 fn conv_connections(actions: &[Action], indices: &[(usize, usize)]) -> ConvResult<Vec<Connection>> {
     let mut connections = vec![];
 
@@ -673,6 +839,7 @@ fn conv_connections(actions: &[Action], indices: &[(usize, usize)]) -> ConvResul
                 Action::DORA(_) => {}
                 Action::REACH1(_) => {}
                 Action::REACH2(_) => {}
+                Action::Unknown(_) => {}
                 Action::N(_) => step += 1,
                 Action::DRAW(_) => step += 1,
                 Action::DISCARD(_) => step += 1,
@@ -684,30 +851,167 @@ fn conv_connections(actions: &[Action], indices: &[(usize, usize)]) -> ConvResul
 }
 
 pub fn conv_to_tenhou_json(mjlog: &Mjlog) -> ConvResult<TenhouJson> {
+    conv_to_tenhou_json_with(mjlog, &ConvOptions::default())
+}
+
+pub fn conv_to_tenhou_json_with(mjlog: &Mjlog, options: &ConvOptions) -> ConvResult<TenhouJson> {
     let action_go = if let Some(Action::GO(x)) = mjlog.actions.iter().find(|x| x.is_go()) { Ok(x) } else { Err(ConvError::NotFoundActionGO) }?;
     let action_un1 = if let Some(Action::UN1(x)) = mjlog.actions.iter().find(|x| x.is_un1()) { Ok(x) } else { Err(ConvError::NotFoundActionUN1) }?;
-    let round_indices = extract_round_indices(&mjlog.actions);
+    let mut round_indices = extract_round_indices(&mjlog.actions);
+    if options.incomplete_round == IncompleteRound::Drop {
+        round_indices.retain(|&(start, end)| mjlog.actions[start..end].iter().any(|x| x.is_agari() || x.is_ryuukyoku()));
+    }
     if round_indices.is_empty() {
         return Err(ConvError::NotFoundRound);
     }
 
-    let (final_points_raw, final_results_raw): (Vec<i32>, Vec<f64>) = find_final_result(&mjlog.actions)?;
-    let final_points = final_points_raw.iter().map(|x| x * 100).collect();
+    let (final_points_raw, final_results_raw): (Vec<i32>, Vec<f64>) = find_final_result(&mjlog.actions, options.synthesize_final_result.then(|| UmaOka::for_players(action_go.settings.sanma)).as_ref())?;
+    let final_points = final_points_raw.iter().map(|&x| to_points(x)).collect();
     let final_results = final_results_raw.clone();
+    let red = options.red_five.is_enabled(&action_go.settings);
 
     Ok(TenhouJson {
-        ver: 2.3, // Using this conversion system
-        reference: String::new(),
-        rounds: conv_rounds(&mjlog.actions, &round_indices)?,
+        ver: options.ver,
+        reference: options.reference.clone(),
+        rounds: conv_rounds(&mjlog.actions, &round_indices, red, ScoreProfile::for_players(action_go.settings.sanma), options.incomplete_round == IncompleteRound::Abort)?,
         connections: conv_connections(&mjlog.actions, &round_indices)?,
-        ratingc: "PF4".to_string(), // What does this mean?
-        rule: conv_rule(&action_go.settings)?,
+        ratingc: options.ratingc.clone(),
+        rule: conv_rule(&action_go.settings, options.locale)?,
         lobby: action_go.lobby,
-        dan: action_un1.dan.iter().map(conv_dan).collect(),
-        rate: action_un1.rate.clone(),
+        dan: action_un1.dan.iter().map(|x| x.map_or(String::new(), |x| conv_dan(&x, options.locale))).collect(),
+        rate: action_un1.rate.iter().map(|x| x.unwrap_or(UNKNOWN_RATE)).collect(),
         sx: action_un1.sx.clone(),
         final_points,
         final_results,
         names: action_un1.names.clone(),
     })
 }
+
+/// IDs of the yaku unknown to this version in the wins, kept by the lenient parser. The conversion writes them as
+/// "不明役" and the ID, so callers should warn that the results may be incomplete.
+pub fn unknown_yaku(mjlog: &Mjlog) -> Vec<u8> {
+    let agaris = mjlog.actions.iter().filter_map(|x| x.as_agari());
+    agaris
+        .flat_map(|x| x.yaku.iter().map(|&(y, _)| y).chain(x.yakuman.iter().copied()))
+        .filter_map(|y| if let mjlog::model::Yaku::Unknown(id) = y { Some(id) } else { None })
+        .collect()
+}
+
+/// Converts every game, e.g. all mjloggm sections in one file.
+pub fn conv_all(mjlogs: &[Mjlog]) -> ConvResult<Vec<TenhouJson>> {
+    conv_all_with(mjlogs, &ConvOptions::default())
+}
+
+pub fn conv_all_with(mjlogs: &[Mjlog], options: &ConvOptions) -> ConvResult<Vec<TenhouJson>> {
+    mjlogs.iter().map(|x| conv_to_tenhou_json_with(x, options)).collect()
+}
+
+/// "foo" -> "foo-1", "foo-2", ... when the file has multiple games.
+pub fn numbered_name(name: &str, index: usize, count: usize) -> String {
+    if count == 1 || name.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}-{}", name, index + 1)
+    }
+}
+
+/// Converts mjlog-XML to tenhou-JSON strings in one call, one for each game in the XML.
+///
+/// References of multiple games are numbered in the same way as the CLI (see `numbered_name`).
+pub fn convert_xml_str_to_json_str(xml: &str, options: &ConvOptions) -> ConvResult<Vec<String>> {
+    let mjlogs = parse_mjlogs(xml)?;
+    let count = mjlogs.len();
+    let mut ret = Vec::with_capacity(count);
+    for (i, mjlog) in mjlogs.iter().enumerate() {
+        let mut tenhou_json = conv_to_tenhou_json_with(mjlog, options)?;
+        tenhou_json.reference = numbered_name(&options.reference, i, count);
+        ret.push(export_tenhou_json(&tenhou_json)?);
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observer_seats() {
+        // AI or observer entries of event logs: empty and out-of-range dan, empty and invalid rate, empty sx.
+        let xml = include_str!("../fixtures/observer.xml");
+        let tenhou_json = conv_all(&parse_mjlogs(xml).unwrap()).unwrap().remove(0);
+        assert_eq!(tenhou_json.dan, ["初段", "", "三段", ""]);
+        assert_eq!(tenhou_json.rate, [1500.0, 0.0, 1700.5, 0.0]);
+        assert_eq!(tenhou_json.sx, ["M", "", "M", "C"]);
+    }
+
+    #[test]
+    fn test_english_locale() {
+        let xml = include_str!("../fixtures/observer.xml");
+        let options = ConvOptions {
+            locale: Locale::English,
+            ..ConvOptions::default()
+        };
+        let japanese = conv_all(&parse_mjlogs(xml).unwrap()).unwrap().remove(0);
+        let english = conv_all_with(&parse_mjlogs(xml).unwrap(), &options).unwrap().remove(0);
+        assert_eq!(english.dan, ["1 dan", "", "3 dan", ""]);
+
+        let settings = GameSettings {
+            room: TenhouRoom::Houou,
+            hanchan: true,
+            ..GameSettings::default()
+        };
+        assert_eq!(conv_rule_disp(&settings, Locale::Japanese), "鳳南喰赤");
+        assert_eq!(conv_rule_disp(&settings, Locale::English), "Houou South Kuitan Red");
+        assert_ne!(english.rule.disp, japanese.rule.disp);
+        assert!(english.rule.disp.is_ascii());
+    }
+
+    #[test]
+    fn test_synthesize_final_result() {
+        let xml = include_str!("../fixtures/observer.xml").replace(r#" owari="280,38.0,240,-16.0,263,6.0,217,-28.0""#, "");
+        let mjlogs = parse_mjlogs(&xml).unwrap();
+        assert!(conv_all(&mjlogs).is_err());
+
+        let options = ConvOptions {
+            synthesize_final_result: true,
+            ..ConvOptions::default()
+        };
+        let tenhou_json = conv_all_with(&mjlogs, &options).unwrap().remove(0);
+        assert_eq!(tenhou_json.final_points, [28000, 24000, 26300, 21700]);
+        assert_eq!(tenhou_json.final_results, [38.0, -16.0, 6.0, -28.0]);
+    }
+
+    #[test]
+    fn test_truncated_log() {
+        use mjlog::parser::{parse_mjlogs_with, ParseOptions};
+
+        let xml = include_str!("../fixtures/observer.xml");
+        let rounds = conv_all(&parse_mjlogs(xml).unwrap()).unwrap()[0].rounds.len();
+        // Cut in the middle of the tag after the last INIT.
+        let last_init = xml.rfind("<INIT").unwrap();
+        let truncated = &xml[..xml[last_init..].find("/>").unwrap() + last_init + 5];
+        assert!(parse_mjlogs(truncated).is_err());
+
+        let parse_options = ParseOptions { allow_truncated: true, ..Default::default() };
+        let mjlogs = parse_mjlogs_with(truncated, &parse_options).unwrap();
+        assert!(mjlogs[0].incomplete);
+        assert!(conv_all(&mjlogs).is_err());
+
+        let options = ConvOptions {
+            synthesize_final_result: true,
+            incomplete_round: IncompleteRound::Drop,
+            ..ConvOptions::default()
+        };
+        let tenhou_json = conv_all_with(&mjlogs, &options).unwrap().remove(0);
+        assert_eq!(tenhou_json.rounds.len(), rounds - 1);
+
+        let options = ConvOptions {
+            incomplete_round: IncompleteRound::Abort,
+            ..options
+        };
+        let tenhou_json = conv_all_with(&mjlogs, &options).unwrap().remove(0);
+        assert_eq!(tenhou_json.rounds.len(), rounds);
+        assert!(matches!(&tenhou_json.rounds[rounds - 1].result, RoundResult::Ryuukyoku { reason: tenhou_json::model::ExtraRyuukyokuReason::Chuudan, delta_points } if delta_points.is_empty()));
+        assert!(export_tenhou_json(&tenhou_json).unwrap().contains("[\"中断\"]"));
+    }
+}
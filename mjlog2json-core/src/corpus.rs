@@ -0,0 +1,344 @@
+//! # corpus
+//!
+//! Columnar (struct-of-arrays) representation of many mjlogs.
+//!
+//! Rows are stored in three levels, game -> round -> event, and each level keeps the
+//! index of the first row of the next level so ranges can be sliced without searching.
+
+use crate::conv::to_points;
+use mjlog::model::*;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Value of `event_hai` when the event has no tile.
+pub const NO_HAI: u8 = u8::MAX;
+
+/// Kind of each event row.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Draw,
+    Discard,
+    Meld,
+    Riichi,
+    Dora,
+    Agari,
+    Ryuukyoku,
+}
+
+/// Interned strings. Each distinct string is stored once and referred to by its index,
+/// because the same player names repeat across tens of thousands of games. See `Corpus::with_interned_names`.
+#[derive(Debug, Clone, Default)]
+pub struct StringTable {
+    strings: Vec<Arc<str>>,
+    indices: HashMap<Arc<str>, u32>,
+}
+
+impl StringTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the index of the string, adding it if new.
+    pub fn intern(&mut self, s: &str) -> u32 {
+        if let Some(&i) = self.indices.get(s) {
+            return i;
+        }
+        let i = self.strings.len() as u32;
+        let s: Arc<str> = Arc::from(s);
+        self.strings.push(s.clone());
+        self.indices.insert(s, i);
+        i
+    }
+
+    pub fn get(&self, index: u32) -> &str {
+        &self.strings[index as usize]
+    }
+
+    /// Index of the string, if interned.
+    pub fn find(&self, s: &str) -> Option<u32> {
+        self.indices.get(s).copied()
+    }
+
+    /// Number of distinct strings.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+/// Events of many games stored column by column.
+#[derive(Debug, Clone, Default)]
+pub struct Corpus {
+    /// Game settings, one per game.
+    pub game_settings: Vec<GameSettings>,
+    /// Lobby number, one per game.
+    pub game_lobby: Vec<u32>,
+    /// Player names, one per game. Empty if the names are interned.
+    pub game_names: Vec<Vec<String>>,
+    /// Player names per seat in `strings`, one per game if the names are interned. Missing names are empty strings.
+    pub game_name_ids: Vec<[u32; 4]>,
+    /// Final points (x100) per seat, one per game. Empty if the game has no owari.
+    pub game_final_points: Vec<Vec<tenhou_json::model::GamePoint>>,
+    /// Index of the first round of each game.
+    pub game_round_start: Vec<u32>,
+
+    /// Owning game of each round.
+    pub round_game: Vec<u32>,
+    pub round_kyoku: Vec<u8>,
+    pub round_honba: Vec<u8>,
+    pub round_oya: Vec<u8>,
+    /// Index of the first event of each round.
+    pub round_event_start: Vec<u32>,
+
+    pub event_kind: Vec<EventKind>,
+    /// Seat of the acting player.
+    pub event_who: Vec<u8>,
+    /// Tile of the event (drawn, discarded, new dora indicator, winning tile), or [`NO_HAI`].
+    pub event_hai: Vec<u8>,
+    /// Seat the win was claimed from (AGARI only, same as `event_who` otherwise).
+    pub event_from: Vec<u8>,
+    /// Number of discards the acting player made in the round before this event.
+    pub event_turn: Vec<u8>,
+    /// Net score of AGARI events, 0 otherwise.
+    pub event_value: Vec<i32>,
+    /// Han of AGARI events without aka dora, 13 for each yakuman. 0 otherwise.
+    pub event_han: Vec<u8>,
+    /// Aka dora of AGARI events, 0 otherwise.
+    pub event_aka_dora: Vec<u8>,
+    /// AGARI events of an open hand with tanyao, which is not a win without kuitan.
+    pub event_kuitan: Vec<bool>,
+    /// Meld events other than closed kans, which open the hand.
+    pub event_open_meld: Vec<bool>,
+
+    /// Strings referred to by `game_name_ids`.
+    pub strings: StringTable,
+    intern_names: bool,
+}
+
+// (han without aka dora, aka dora, kuitan) of the win.
+fn agari_han(x: &ActionAGARI) -> (u8, u8, bool) {
+    let han_of = |aka: bool| x.yaku.iter().filter(|&&(y, _)| (y == Yaku::AkaDora) == aka).map(|&(_, han)| han as u32).sum::<u32>();
+    let han = han_of(false) + 13 * x.yakuman.len() as u32;
+    let open = x.m.iter().any(|m| !matches!(m, Meld::Ankan { .. }));
+    let kuitan = open && x.yaku.iter().any(|&(y, _)| y == Yaku::Tanyao);
+    (han.min(u8::MAX as u32) as u8, han_of(true).min(u8::MAX as u32) as u8, kuitan)
+}
+
+impl Corpus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Empty corpus that keeps each distinct player name once in `strings`, for corpora where the same players repeat.
+    pub fn with_interned_names() -> Self {
+        Self { intern_names: true, ..Self::default() }
+    }
+
+    pub fn from_mjlogs<'a, I: IntoIterator<Item = &'a Mjlog>>(mjlogs: I) -> Self {
+        let mut corpus = Self::new();
+        for mjlog in mjlogs {
+            corpus.push_mjlog(mjlog);
+        }
+        corpus
+    }
+
+    pub fn num_games(&self) -> usize {
+        self.game_settings.len()
+    }
+
+    pub fn num_rounds(&self) -> usize {
+        self.round_game.len()
+    }
+
+    pub fn num_events(&self) -> usize {
+        self.event_kind.len()
+    }
+
+    /// Name of the player of the seat in the game.
+    pub fn game_name(&self, game: usize, seat: usize) -> &str {
+        if self.intern_names {
+            self.strings.get(self.game_name_ids[game][seat])
+        } else {
+            self.game_names[game].get(seat).map_or("", |x| x.as_str())
+        }
+    }
+
+    /// (game, seat) of every game the player played. Interned names are compared as indices.
+    pub fn player_games(&self, name: &str) -> Vec<(usize, usize)> {
+        if !self.intern_names {
+            let games = self.game_names.iter().enumerate();
+            return games.flat_map(|(game, names)| names.iter().enumerate().filter(move |&(_, x)| x == name).map(move |(seat, _)| (game, seat))).collect();
+        }
+        let Some(id) = self.strings.find(name) else { return vec![] };
+        let games = self.game_name_ids.iter().enumerate();
+        games.flat_map(|(game, names)| names.iter().enumerate().filter(move |&(_, &x)| x == id).map(move |(seat, _)| (game, seat))).collect()
+    }
+
+    /// Settings of the game the round belongs to.
+    pub fn round_settings(&self, round: usize) -> &GameSettings {
+        &self.game_settings[self.round_game[round] as usize]
+    }
+
+    /// Range of round rows belonging to the game.
+    pub fn game_rounds(&self, game: usize) -> Range<usize> {
+        let end = self.game_round_start.get(game + 1).map_or(self.num_rounds(), |&x| x as usize);
+        self.game_round_start[game] as usize..end
+    }
+
+    /// Range of event rows belonging to the round.
+    pub fn round_events(&self, round: usize) -> Range<usize> {
+        let end = self.round_event_start.get(round + 1).map_or(self.num_events(), |&x| x as usize);
+        self.round_event_start[round] as usize..end
+    }
+
+    pub fn push_mjlog(&mut self, mjlog: &Mjlog) {
+        let game = self.num_games() as u32;
+        let go = mjlog.actions.iter().find_map(|x| x.as_go());
+        let un1 = mjlog.actions.iter().find_map(|x| x.as_un1());
+        let final_points = mjlog.actions.iter().rev().find_map(|x| match x {
+            Action::AGARI(a) => a.owari.as_ref(),
+            Action::RYUUKYOKU(r) => r.owari.as_ref(),
+            _ => None,
+        });
+
+        self.game_settings.push(go.map(|x| x.settings.clone()).unwrap_or_default());
+        self.game_lobby.push(go.map_or(0, |x| x.lobby));
+        if self.intern_names {
+            let names = un1.map_or(&[][..], |x| x.names.as_slice());
+            let names = [0, 1, 2, 3].map(|i| self.strings.intern(names.get(i).map_or("", |x| x.as_str())));
+            self.game_name_ids.push(names);
+        } else {
+            self.game_names.push(un1.map(|x| x.names.clone()).unwrap_or_default());
+        }
+        self.game_final_points.push(final_points.map(|(points, _)| points.iter().map(|&x| to_points(x)).collect()).unwrap_or_default());
+        self.game_round_start.push(self.num_rounds() as u32);
+
+        let mut in_round = false;
+        let mut turns = [0u8; 4];
+
+        for a in &mjlog.actions {
+            let (han, aka_dora, kuitan) = match a {
+                Action::AGARI(x) => agari_han(x),
+                _ => (0, 0, false),
+            };
+            let (kind, who, hai, from, value) = match a {
+                Action::INIT(x) => {
+                    self.round_game.push(game);
+                    self.round_kyoku.push(x.seed.kyoku);
+                    self.round_honba.push(x.seed.honba);
+                    self.round_oya.push(x.oya.to_u8());
+                    self.round_event_start.push(self.num_events() as u32);
+                    in_round = true;
+                    turns = [0; 4];
+                    continue;
+                }
+                _ if !in_round => continue,
+                Action::DRAW(x) => (EventKind::Draw, x.who.to_u8(), x.hai.to_u8(), x.who.to_u8(), 0),
+                Action::DISCARD(x) => (EventKind::Discard, x.who.to_u8(), x.hai.to_u8(), x.who.to_u8(), 0),
+                Action::N(x) => (EventKind::Meld, x.who.to_u8(), NO_HAI, x.who.to_u8(), 0),
+                Action::REACH1(x) => (EventKind::Riichi, x.who.to_u8(), NO_HAI, x.who.to_u8(), 0),
+                Action::DORA(x) => (EventKind::Dora, 0, x.hai.to_u8(), 0, 0),
+                Action::AGARI(x) => (EventKind::Agari, x.who.to_u8(), x.machi.to_u8(), x.from_who.to_u8(), i32::try_from(x.ten.total).unwrap_or(i32::MAX)),
+                Action::RYUUKYOKU(_) => (EventKind::Ryuukyoku, 0, NO_HAI, 0, 0),
+                _ => continue,
+            };
+
+            let turn = &mut turns[who as usize % 4];
+            self.event_kind.push(kind);
+            self.event_who.push(who);
+            self.event_hai.push(hai);
+            self.event_from.push(from);
+            self.event_turn.push(*turn);
+            self.event_value.push(value);
+            self.event_han.push(han);
+            self.event_aka_dora.push(aka_dora);
+            self.event_kuitan.push(kuitan);
+            self.event_open_meld.push(matches!(a, Action::N(x) if !matches!(x.m, Meld::Ankan { .. })));
+
+            if kind == EventKind::Discard {
+                *turn = turn.saturating_add(1);
+            }
+        }
+    }
+
+    /// Histogram of tile kinds (34 kinds, red fives folded into fives) over events of the given kind.
+    pub fn tile_frequencies(&self, kind: EventKind) -> [u32; 34] {
+        let mut freq = [0; 34];
+        for (&k, &hai) in self.event_kind.iter().zip(&self.event_hai) {
+            if k == kind && hai != NO_HAI {
+                freq[hai as usize / 4] += 1;
+            }
+        }
+        freq
+    }
+
+    pub fn count_events(&self, kind: EventKind) -> usize {
+        self.event_kind.iter().filter(|&&k| k == kind).count()
+    }
+
+    /// Fraction of rounds won by each seat. Multiple ron counts once for every winner.
+    pub fn win_rate_by_seat(&self) -> [f64; 4] {
+        let mut wins = [0u32; 4];
+        for (&k, &who) in self.event_kind.iter().zip(&self.event_who) {
+            if k == EventKind::Agari {
+                wins[who as usize % 4] += 1;
+            }
+        }
+
+        let rounds = self.num_rounds().max(1) as f64;
+        wins.map(|x| x as f64 / rounds)
+    }
+
+    /// Fraction of rounds where the seat dealt in (lost by ron).
+    pub fn deal_in_rate_by_seat(&self) -> [f64; 4] {
+        let mut deal_ins = [0u32; 4];
+        for i in 0..self.num_events() {
+            if self.event_kind[i] == EventKind::Agari && self.event_who[i] != self.event_from[i] {
+                deal_ins[self.event_from[i] as usize % 4] += 1;
+            }
+        }
+
+        let rounds = self.num_rounds().max(1) as f64;
+        deal_ins.map(|x| x as f64 / rounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_table() {
+        let mut table = StringTable::new();
+        let a = table.intern("あい");
+        let b = table.intern("Bob");
+        assert_eq!(table.intern("あい"), a);
+        assert_eq!((table.get(a), table.get(b)), ("あい", "Bob"));
+        assert_eq!(table.find("Bob"), Some(b));
+        assert_eq!(table.find("C"), None);
+        assert_eq!(table.len(), 2);
+    }
+
+    #[test]
+    fn test_interned_names() {
+        let mjlogs = mjlog::parser::parse_mjlogs(include_str!("../fixtures/observer.xml")).unwrap();
+        let corpus = Corpus::from_mjlogs(&mjlogs);
+        let mut interned = Corpus::with_interned_names();
+        for mjlog in mjlogs.iter().chain(&mjlogs) {
+            interned.push_mjlog(mjlog);
+        }
+        assert_eq!((corpus.game_names.len(), corpus.game_name_ids.len()), (1, 0));
+        assert_eq!((interned.game_names.len(), interned.game_name_ids.len(), interned.strings.len()), (0, 2, 4));
+        for seat in 0..4 {
+            assert_eq!(interned.game_name(1, seat), corpus.game_name(0, seat));
+        }
+        assert_eq!(corpus.player_games("Bob"), [(0, 1)]);
+        assert_eq!(interned.player_games("Bob"), [(0, 1), (1, 1)]);
+        assert_eq!(interned.player_games("nobody"), []);
+    }
+}
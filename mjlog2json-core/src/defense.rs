@@ -0,0 +1,299 @@
+//! # defense
+//!
+//! Extracts discards made against an opponent's riichi, for defense studies.
+
+use crate::escape::*;
+use crate::stats::*;
+use mjlog::model::*;
+use mjlog::replay::meld_hand_hais;
+use std::io::{self, Write};
+
+/// Safety category of a tile against a riichi player, judged from the tiles visible to the discarder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DangerCategory {
+    /// 現物: discarded by the riichi player, or passed by them after the riichi.
+    Genbutsu,
+    /// 筋: both sides (or the only side for 1-3/7-9) of the suji are genbutsu.
+    Suji,
+    /// ノーチャンス: every ryanmen shape waiting on the tile needs a tile that is fully visible.
+    NoChance,
+    /// 片筋: one side of a 4-6 suji is genbutsu.
+    HalfSuji,
+    /// 字牌
+    Honor,
+    /// 無筋
+    Musuji,
+}
+
+/// A discard made while an opponent was in riichi.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiichiDiscard {
+    pub kyoku: u8,
+    pub honba: u8,
+    pub who: Player,
+    pub riichi_who: Player,
+    /// 1-origin discard count of the discarder in the round.
+    pub turn: u8,
+    pub hai: Hai,
+    pub category: DangerCategory,
+    /// Number of copies of the tile visible to the discarder, including the discarded one.
+    pub visible: u8,
+    pub dealt_in: bool,
+    /// The discarder held a genbutsu against the riichi player, so that they could have discarded safely.
+    pub genbutsu_in_hand: bool,
+}
+
+impl DangerCategory {
+    pub fn to_str(&self) -> &str {
+        match self {
+            DangerCategory::Genbutsu => "genbutsu",
+            DangerCategory::Suji => "suji",
+            DangerCategory::NoChance => "no_chance",
+            DangerCategory::HalfSuji => "half_suji",
+            DangerCategory::Honor => "honor",
+            DangerCategory::Musuji => "musuji",
+        }
+    }
+}
+
+fn kind_of(hai: Hai) -> usize {
+    hai.kind() as usize
+}
+
+struct RoundTracker {
+    /// Tiles visible to everyone: rivers, exposed meld tiles and dora indicators.
+    public: [[u8; 9]; 4],
+    hands: [Vec<Hai>; 4],
+    /// Tiles each riichi player can no longer ron (own discards and tiles passed after riichi).
+    genbutsu: [[bool; TILE_KINDS]; 4],
+    /// Own discards only, used for suji.
+    river: [[bool; TILE_KINDS]; 4],
+    riichi_declared: [bool; 4],
+    in_riichi: [bool; 4],
+    turns: [u8; 4],
+}
+
+impl RoundTracker {
+    fn new() -> Self {
+        RoundTracker {
+            public: [[0; 9]; 4],
+            hands: Default::default(),
+            genbutsu: [[false; TILE_KINDS]; 4],
+            river: [[false; TILE_KINDS]; 4],
+            riichi_declared: [false; 4],
+            in_riichi: [false; 4],
+            turns: [0; 4],
+        }
+    }
+
+    fn public_count(&self, kind: usize) -> u8 {
+        self.public[kind / 9][kind % 9]
+    }
+
+    fn add_public(&mut self, kind: usize) {
+        self.public[kind / 9][kind % 9] += 1;
+    }
+
+    fn visible_count(&self, who: usize, kind: usize) -> u8 {
+        self.public_count(kind) + self.hands[who].iter().filter(|&&h| kind_of(h) == kind).count() as u8
+    }
+
+    fn categorize(&self, who: usize, riichi_who: usize, kind: usize) -> DangerCategory {
+        if self.genbutsu[riichi_who][kind] {
+            return DangerCategory::Genbutsu;
+        }
+        if kind >= 27 {
+            return DangerCategory::Honor;
+        }
+
+        let river = &self.river[riichi_who];
+        let n = kind % 9;
+        let lower = n >= 3 && river[kind - 3];
+        let upper = n <= 5 && river[kind + 3];
+        let is_suji = match n {
+            0..=2 => upper,
+            6..=8 => lower,
+            _ => lower && upper,
+        };
+        if is_suji {
+            return DangerCategory::Suji;
+        }
+
+        // ryanmen shapes waiting on this tile: (n+1, n+2) and (n-2, n-1)
+        let mut shapes = vec![];
+        if n <= 6 {
+            shapes.push((kind + 1, kind + 2));
+        }
+        if n >= 2 {
+            shapes.push((kind - 2, kind - 1));
+        }
+        let is_wall = |k: usize| self.visible_count(who, k) >= 4;
+        if shapes.iter().all(|&(a, b)| is_wall(a) || is_wall(b)) {
+            return DangerCategory::NoChance;
+        }
+
+        if lower || upper {
+            DangerCategory::HalfSuji
+        } else {
+            DangerCategory::Musuji
+        }
+    }
+}
+
+/// Extracts every discard made while an opponent was in riichi, one row per riichi opponent.
+pub fn extract_riichi_discards(mjlog: &Mjlog) -> Vec<RiichiDiscard> {
+    let mut rows: Vec<RiichiDiscard> = Vec::new();
+    let mut tracker = RoundTracker::new();
+    let mut seed = InitSeed::default();
+    // rows of the last discard, waiting for a possible ron
+    let mut pending = 0..0;
+
+    for a in &mjlog.actions {
+        match a {
+            Action::INIT(x) => {
+                tracker = RoundTracker::new();
+                seed = x.seed.clone();
+                tracker.add_public(kind_of(x.seed.dora_hyouji));
+                for (i, h) in x.hai.iter().enumerate().take(4) {
+                    tracker.hands[i] = h.clone();
+                }
+                pending = 0..0;
+            }
+            Action::DRAW(x) => {
+                tracker.hands[x.who.to_u8() as usize % 4].push(x.hai);
+                pending = 0..0;
+            }
+            Action::REACH1(x) => tracker.riichi_declared[x.who.to_u8() as usize % 4] = true,
+            Action::DISCARD(x) => {
+                let who = x.who.to_u8() as usize % 4;
+                let kind = kind_of(x.hai);
+                tracker.turns[who] = tracker.turns[who].saturating_add(1);
+
+                let start = rows.len();
+                for r in (0..4).filter(|&r| r != who && tracker.in_riichi[r]) {
+                    rows.push(RiichiDiscard {
+                        kyoku: seed.kyoku,
+                        honba: seed.honba,
+                        who: x.who,
+                        riichi_who: Player::new(r as u8),
+                        turn: tracker.turns[who],
+                        hai: x.hai,
+                        category: tracker.categorize(who, r, kind),
+                        visible: tracker.visible_count(who, kind),
+                        dealt_in: false,
+                        genbutsu_in_hand: tracker.hands[who].iter().any(|&h| tracker.genbutsu[r][kind_of(h)]),
+                    });
+                }
+                pending = start..rows.len();
+
+                tracker.hands[who].retain(|&h| h != x.hai);
+                tracker.add_public(kind);
+                tracker.river[who][kind] = true;
+                tracker.genbutsu[who][kind] = true;
+                for r in 0..4 {
+                    if tracker.in_riichi[r] {
+                        tracker.genbutsu[r][kind] = true;
+                    }
+                }
+                if tracker.riichi_declared[who] {
+                    tracker.riichi_declared[who] = false;
+                    tracker.in_riichi[who] = true;
+                }
+            }
+            Action::N(x) => {
+                let who = x.who.to_u8() as usize % 4;
+                for h in meld_hand_hais(&x.m) {
+                    tracker.hands[who].retain(|&y| y != h);
+                    tracker.add_public(kind_of(h));
+                }
+                pending = 0..0;
+            }
+            Action::DORA(x) => tracker.add_public(kind_of(x.hai)),
+            Action::AGARI(x) if !x.is_tsumo() => {
+                for row in &mut rows[pending.clone()] {
+                    if row.riichi_who == x.who && row.who == x.from_who {
+                        row.dealt_in = true;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    rows
+}
+
+/// Exports riichi discards of many games as CSV. Each game is given with a reference (e.g. the file name).
+pub fn export_riichi_discards_csv<'a, I: IntoIterator<Item = (&'a str, &'a [RiichiDiscard])>>(games: I) -> String {
+    let mut csv = vec![];
+    export_riichi_discards_csv_to(games, &mut csv).expect("writing to a Vec never fails");
+    String::from_utf8(csv).expect("exporters write UTF-8 only")
+}
+
+/// Same as `export_riichi_discards_csv`, but writes to the writer.
+pub fn export_riichi_discards_csv_to<'a, I: IntoIterator<Item = (&'a str, &'a [RiichiDiscard])>, W: Write>(games: I, mut writer: W) -> io::Result<()> {
+    writeln!(writer, "ref,kyoku,honba,who,riichi_who,turn,tile,category,visible,dealt_in,genbutsu_in_hand")?;
+    for (reference, rows) in games {
+        for row in rows {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{},{},{}",
+                escape_csv_field(reference),
+                row.kyoku,
+                row.honba,
+                row.who.to_u8(),
+                row.riichi_who.to_u8(),
+                row.turn,
+                tile_kind_name(kind_of(row.hai)),
+                row.category.to_str(),
+                row.visible,
+                row.dealt_in as u8,
+                row.genbutsu_in_hand as u8,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Kinds: 0..9 m, 9..18 p, 18..27 s, 27..34 z. The discarder is 0 and the riichi player is 1.
+    fn tracker(river: &[usize], public: &[usize], hand: &[usize]) -> RoundTracker {
+        let mut t = RoundTracker::new();
+        for &k in river {
+            t.river[1][k] = true;
+            t.genbutsu[1][k] = true;
+        }
+        for &k in public {
+            t.add_public(k);
+        }
+        t.hands[0] = hand.iter().map(|&k| Hai::new(k as u8 * 4)).collect();
+        t
+    }
+
+    #[test]
+    fn test_categorize() {
+        use DangerCategory::*;
+        #[rustfmt::skip]
+        let cases: [(&str, RoundTracker, usize, DangerCategory); 13] = [
+            ("own discard", tracker(&[4], &[], &[]), 4, Genbutsu),
+            ("honor", tracker(&[], &[], &[]), 27, Honor),
+            ("honor passed", tracker(&[27], &[], &[]), 27, Genbutsu),
+            ("1m by 4m", tracker(&[3], &[], &[]), 0, Suji),
+            ("9p by 6p", tracker(&[14], &[], &[]), 17, Suji),
+            ("3s by 6s", tracker(&[23], &[], &[]), 20, Suji),
+            ("5m by 2m and 8m", tracker(&[1, 7], &[], &[]), 4, Suji),
+            ("5m by 2m only", tracker(&[1], &[], &[]), 4, HalfSuji),
+            ("5m by 8m only", tracker(&[7], &[], &[]), 4, HalfSuji),
+            ("1m with four 2m visible", tracker(&[], &[1, 1, 1], &[1]), 0, NoChance),
+            ("1m with three 2m visible", tracker(&[], &[1, 1], &[1]), 0, Musuji),
+            ("3m with four 2m and four 4m visible", tracker(&[], &[1, 1, 1, 1, 3, 3], &[3, 3]), 2, NoChance),
+            ("3m with four 4m visible only", tracker(&[], &[3, 3, 3, 3], &[]), 2, Musuji),
+        ];
+        for (name, t, kind, expected) in cases {
+            assert_eq!(t.categorize(0, 1, kind), expected, "{}", name);
+        }
+    }
+}
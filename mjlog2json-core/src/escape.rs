@@ -0,0 +1,84 @@
+//! # escape
+//!
+//! Escaping of free text, mainly player names, for each output format.
+//!
+//! Names come from the percent-encoded XML and may contain any character,
+//! so every exporter that writes them must go through this module.
+
+/// Quotes a CSV field (RFC 4180) if it contains a comma, a double quote or a line break.
+pub fn escape_csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Escapes text for HTML element content and attribute values.
+pub fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escapes text in a Markdown table cell.
+///
+/// Emphasis, links and inline code are escaped with a backslash, HTML with entities,
+/// and line breaks (which would end the row) are replaced with spaces.
+pub fn escape_markdown_cell(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' | '|' | '*' | '_' | '`' | '[' | ']' | '~' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\r' | '\n' => out.push(' '),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_csv_field() {
+        assert_eq!(escape_csv_field("Bob"), "Bob");
+        assert_eq!(escape_csv_field("あい"), "あい");
+        assert_eq!(escape_csv_field("C,D"), "\"C,D\"");
+        assert_eq!(escape_csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_csv_field("a\nb"), "\"a\nb\"");
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(escape_html("<eve>"), "&lt;eve&gt;");
+        assert_eq!(escape_html("A&B \"q\" 'x'"), "A&amp;B &quot;q&quot; &#39;x&#39;");
+        assert_eq!(escape_html("ｱｲ★"), "ｱｲ★");
+    }
+
+    #[test]
+    fn test_escape_markdown_cell() {
+        assert_eq!(escape_markdown_cell("a|b"), "a\\|b");
+        assert_eq!(escape_markdown_cell("<eve>"), "&lt;eve&gt;");
+        assert_eq!(escape_markdown_cell("*_bold_*"), "\\*\\_bold\\_\\*");
+        assert_eq!(escape_markdown_cell("[link](x)"), "\\[link\\](x)");
+        assert_eq!(escape_markdown_cell("a\r\nb"), "a  b");
+        assert_eq!(escape_markdown_cell("C,D"), "C,D");
+    }
+}
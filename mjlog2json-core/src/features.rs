@@ -0,0 +1,231 @@
+//! # features
+//!
+//! Fixed-shape numeric features of the observations of `observation`, for training pipelines.
+//!
+//! Each observation is encoded as `NUM_PLANES` planes of 34 tile kinds (f32), and each action as a class label (i64).
+//! Seats are relative to the observing seat: 0 is itself, 1 the next player (shimocha), and so on.
+//!
+//! | Planes | Content |
+//! | --- | --- |
+//! | 0-3 | Own concealed tiles, plane `i` is 1 for the kinds with more than `i` copies |
+//! | 4 | The tile just drawn or discarded by another player |
+//! | 5-20 | Rivers of the relative seats 0-3, 4 planes each, counted like the hand |
+//! | 21-36 | Melds of the relative seats 0-3, 4 planes each, counted like the hand |
+//! | 37-40 | Dora indicators, counted like the hand |
+//! | 41-44 | Riichi of the relative seats 0-3 (all 1 or all 0) |
+//! | 45-48 | Scores of the relative seats 0-3 divided by 100,000 |
+//! | 49 | Round wind (0 East, 1/3 South, 2/3 West, 1 North) |
+//! | 50 | Seat wind, same as the round wind |
+//! | 51 | Honba divided by 10 |
+//! | 52 | Riichi sticks divided by 10 |
+//! | 53 | Tiles left in the wall divided by 70 |
+//! | 54-56 | Decision kind: draw, after call or claim |
+//!
+//! Labels: 0-33 discard of the kind, 34-67 riichi discard of the kind, then `LABEL_KAN` and the others below.
+//! Red fives are the same kind as normal fives. Arrays are written as npy (NumPy format 1.0), or as flat little-endian binary.
+
+use crate::observation::*;
+use mjlog::model::*;
+use mjlog::replay::meld_hais;
+use std::io::{self, Write};
+
+/// Number of tile kinds, the width of a plane.
+pub const NUM_KINDS: usize = 34;
+/// Number of planes of an observation.
+pub const NUM_PLANES: usize = 57;
+
+pub const LABEL_RIICHI_DISCARD: i64 = 34;
+pub const LABEL_KAN: i64 = 68;
+pub const LABEL_TSUMO: i64 = 69;
+pub const LABEL_KYUUSYU_KYUUHAI: i64 = 70;
+pub const LABEL_CHII: i64 = 71;
+pub const LABEL_PON: i64 = 72;
+pub const LABEL_DAIMINKAN: i64 = 73;
+pub const LABEL_RON: i64 = 74;
+pub const LABEL_PASS: i64 = 75;
+/// Number of labels.
+pub const NUM_LABELS: usize = 76;
+
+// Sets planes [first, first + 4) from the counts of the tiles by kind.
+fn set_counts(planes: &mut [f32], first: usize, hais: impl IntoIterator<Item = Hai>) {
+    let mut counts = [0usize; NUM_KINDS];
+    for hai in hais {
+        counts[hai.kind() as usize] += 1;
+    }
+    for (kind, &count) in counts.iter().enumerate() {
+        for i in 0..count.min(4) {
+            planes[(first + i) * NUM_KINDS + kind] = 1.0;
+        }
+    }
+}
+
+fn fill_plane(planes: &mut [f32], plane: usize, value: f32) {
+    planes[plane * NUM_KINDS..(plane + 1) * NUM_KINDS].fill(value);
+}
+
+/// Encodes the observation as `NUM_PLANES * NUM_KINDS` values, plane by plane. See the module documentation.
+pub fn encode_observation(obs: &Observation) -> Vec<f32> {
+    let mut planes = vec![0.0; NUM_PLANES * NUM_KINDS];
+    let num_players = obs.players.len().max(1);
+    let seat = obs.seat.to_u8() as usize;
+
+    set_counts(&mut planes, 0, obs.hand.iter().copied());
+    if let Some(tile) = obs.tile {
+        planes[4 * NUM_KINDS + tile.kind() as usize] = 1.0;
+    }
+    for relative in 0..num_players.min(4) {
+        let player = &obs.players[(seat + relative) % num_players];
+        set_counts(&mut planes, 5 + relative * 4, player.discards.iter().map(|x| x.hai));
+        set_counts(&mut planes, 21 + relative * 4, player.melds.iter().flat_map(meld_hais));
+        fill_plane(&mut planes, 41 + relative, player.riichi as u8 as f32);
+        fill_plane(&mut planes, 45 + relative, player.score as f32 * 100.0 / 100_000.0);
+    }
+    set_counts(&mut planes, 37, obs.dora_indicators.iter().copied());
+
+    let seat_wind = (seat + num_players - obs.oya.to_u8() as usize) % num_players;
+    fill_plane(&mut planes, 49, (obs.kyoku / 4 % 4) as f32 / 3.0);
+    fill_plane(&mut planes, 50, seat_wind as f32 / 3.0);
+    fill_plane(&mut planes, 51, obs.honba as f32 / 10.0);
+    fill_plane(&mut planes, 52, obs.kyoutaku as f32 / 10.0);
+    fill_plane(&mut planes, 53, obs.wall_remaining as f32 / 70.0);
+    let kind = match obs.kind {
+        DecisionKind::Draw => 0,
+        DecisionKind::AfterCall => 1,
+        DecisionKind::Claim => 2,
+    };
+    fill_plane(&mut planes, 54 + kind, 1.0);
+    planes
+}
+
+/// Class label of the action. See the module documentation.
+pub fn action_label(action: &SeatAction) -> i64 {
+    match action {
+        SeatAction::Discard { hai, riichi: false } => hai.kind() as i64,
+        SeatAction::Discard { hai, riichi: true } => LABEL_RIICHI_DISCARD + hai.kind() as i64,
+        SeatAction::Kan(_) => LABEL_KAN,
+        SeatAction::Tsumo => LABEL_TSUMO,
+        SeatAction::KyuusyuKyuuhai => LABEL_KYUUSYU_KYUUHAI,
+        SeatAction::Call(Meld::Chii { .. }) => LABEL_CHII,
+        SeatAction::Call(Meld::Daiminkan { .. }) => LABEL_DAIMINKAN,
+        SeatAction::Call(_) => LABEL_PON,
+        SeatAction::Ron => LABEL_RON,
+        SeatAction::Pass => LABEL_PASS,
+    }
+}
+
+/// Encoded observations and labels of any number of samples.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Features {
+    /// `len() * NUM_PLANES * NUM_KINDS` values.
+    pub planes: Vec<f32>,
+    pub labels: Vec<i64>,
+}
+
+impl Features {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of samples.
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Encodes and appends the pairs of `extract_observations`.
+    pub fn extend(&mut self, pairs: &[(Observation, SeatAction)]) {
+        for (obs, action) in pairs {
+            self.planes.extend(encode_observation(obs));
+            self.labels.push(action_label(action));
+        }
+    }
+
+    /// Writes the planes as npy of shape `(len, NUM_PLANES, NUM_KINDS)`.
+    pub fn write_planes_npy<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_npy_to(writer, &[self.len(), NUM_PLANES, NUM_KINDS], &self.planes)
+    }
+
+    /// Writes the labels as npy of shape `(len,)`.
+    pub fn write_labels_npy<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_npy_to(writer, &[self.len()], &self.labels)
+    }
+}
+
+/// Element type of npy arrays.
+pub trait NpyElement: Copy {
+    /// dtype of NumPy, e.g. `<f4`.
+    const DESCR: &'static str;
+    fn write_le<W: Write>(self, writer: &mut W) -> io::Result<()>;
+}
+
+impl NpyElement for f32 {
+    const DESCR: &'static str = "<f4";
+    fn write_le<W: Write>(self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_le_bytes())
+    }
+}
+
+impl NpyElement for i64 {
+    const DESCR: &'static str = "<i8";
+    fn write_le<W: Write>(self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_le_bytes())
+    }
+}
+
+/// Writes the values as flat little-endian binary, without a header.
+pub fn write_flat_to<W: Write, T: NpyElement>(mut writer: W, data: &[T]) -> io::Result<()> {
+    for &x in data {
+        x.write_le(&mut writer)?;
+    }
+    Ok(())
+}
+
+/// Writes the values as npy (format 1.0) of the shape in C order.
+pub fn write_npy_to<W: Write, T: NpyElement>(mut writer: W, shape: &[usize], data: &[T]) -> io::Result<()> {
+    debug_assert_eq!(shape.iter().product::<usize>(), data.len());
+    let dims: Vec<String> = shape.iter().map(|x| x.to_string()).collect();
+    let shape = if dims.len() == 1 { format!("({},)", dims[0]) } else { format!("({})", dims.join(", ")) };
+    let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", T::DESCR, shape);
+    // The magic (6), version (2), header length (2) and header end with a newline at a multiple of 64 bytes.
+    let total = (10 + header.len() + 1).div_ceil(64) * 64;
+    header.push_str(&" ".repeat(total - 10 - header.len() - 1));
+    header.push('\n');
+
+    writer.write_all(b"\x93NUMPY\x01\x00")?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    write_flat_to(writer, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mjlog::parser::parse_mjlogs;
+
+    #[test]
+    fn test_features() {
+        let mjlog = parse_mjlogs(include_str!("../fixtures/observer.xml")).unwrap().remove(0);
+        let pairs = extract_observations(&mjlog, 0).unwrap();
+        let mut features = Features::new();
+        features.extend(&pairs);
+        assert_eq!(features.len(), pairs.len());
+        assert_eq!(features.planes.len(), pairs.len() * NUM_PLANES * NUM_KINDS);
+        assert!(features.labels.iter().all(|&x| (0..NUM_LABELS as i64).contains(&x)));
+
+        // The first decision is after a draw: 14 tiles in the hand planes.
+        let first = &features.planes[..NUM_PLANES * NUM_KINDS];
+        assert_eq!(first[..4 * NUM_KINDS].iter().sum::<f32>(), 14.0);
+        assert_eq!(first[54 * NUM_KINDS], 1.0);
+
+        let mut npy = vec![];
+        features.write_labels_npy(&mut npy).unwrap();
+        assert!(npy.starts_with(b"\x93NUMPY\x01\x00"));
+        let header_len = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        assert_eq!(npy.len(), 10 + header_len + 8 * features.len());
+        assert!(String::from_utf8_lossy(&npy[10..10 + header_len]).contains(&format!("'shape': ({},)", features.len())));
+    }
+}
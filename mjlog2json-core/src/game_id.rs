@@ -0,0 +1,131 @@
+//! # game_id
+//!
+//! Parses tenhou game IDs such as `2025010203gm-00a9-0000-0123abcd`,
+//! and the viewer URLs such as `https://tenhou.net/0/?log=2025010203gm-00a9-0000-0123abcd&tw=2`.
+
+use std::fmt;
+
+/// Parsed game ID. The time is in JST, as issued by tenhou.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameId {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    /// GO type as a number (e.g. 0x00a9 for 鳳南喰赤).
+    pub game_type: u16,
+    pub lobby: u16,
+    pub hash: String,
+}
+
+pub const WEEKDAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Parses a game ID. A file name with extensions (e.g. `2025010203gm-00a9-0000-0123abcd.mjlog`) is also accepted.
+pub fn parse_game_id(s: &str) -> Option<GameId> {
+    let id = s.split('.').next()?;
+    let mut parts = id.split('-');
+    let date = parts.next()?.strip_suffix("gm")?;
+    let game_type = u16::from_str_radix(parts.next()?, 16).ok()?;
+    let lobby = parts.next()?.parse().ok()?;
+    let hash = parts.next()?.to_string();
+
+    if date.len() != 10 || !date.bytes().all(|x| x.is_ascii_digit()) {
+        return None;
+    }
+    let game_id = GameId {
+        year: date[0..4].parse().ok()?,
+        month: date[4..6].parse().ok()?,
+        day: date[6..8].parse().ok()?,
+        hour: date[8..10].parse().ok()?,
+        game_type,
+        lobby,
+        hash,
+    };
+
+    if !(1..=12).contains(&game_id.month) || !(1..=31).contains(&game_id.day) || game_id.hour >= 24 {
+        return None;
+    }
+    Some(game_id)
+}
+
+impl GameId {
+    /// Days since 1970-01-01.
+    fn days_from_epoch(&self) -> i64 {
+        // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let y = self.year as i64 - if self.month <= 2 { 1 } else { 0 };
+        let m = self.month as i64;
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146097 + doe - 719468
+    }
+
+    /// 0 = Monday, ..., 6 = Sunday.
+    pub fn weekday(&self) -> u8 {
+        // 1970-01-01 is Thursday.
+        (self.days_from_epoch() + 3).rem_euclid(7) as u8
+    }
+}
+
+impl fmt::Display for GameId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}{:02}{:02}{:02}gm-{:04x}-{:04}-{}", self.year, self.month, self.day, self.hour, self.game_type, self.lobby, self.hash)
+    }
+}
+
+/// Game referred by a `log=` URL of the tenhou viewer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogUrl {
+    pub game_id: GameId,
+    /// Seat shown at the bottom (`tw=`).
+    pub seat: Option<u8>,
+    /// 0-origin index of the round shown first (`ts=`).
+    pub round: Option<u8>,
+}
+
+/// Parses the query of a viewer URL, e.g. `https://tenhou.net/0/?log=2025010203gm-00a9-0000-0123abcd&tw=2&ts=3`.
+/// The query alone (`log=...&tw=2`) and the `#` form are also accepted. Unknown parameters are ignored.
+pub fn parse_log_url(s: &str) -> Option<LogUrl> {
+    let query = s.rsplit(['?', '#']).next()?;
+    let mut game_id = None;
+    let mut seat = None;
+    let mut round = None;
+    for param in query.split('&') {
+        match param.split_once('=') {
+            Some(("log", v)) => game_id = parse_game_id(v),
+            Some(("tw", v)) => seat = Some(v.parse().ok().filter(|&x| x < 4)?),
+            Some(("ts", v)) => round = Some(v.parse().ok()?),
+            _ => {}
+        }
+    }
+    Some(LogUrl { game_id: game_id?, seat, round })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_game_id() {
+        let id = parse_game_id("2019010215gm-00a9-0000-93e74c9f.mjlog").unwrap();
+        assert_eq!((id.year, id.month, id.day, id.hour), (2019, 1, 2, 15));
+        assert_eq!(id.game_type, 0xa9);
+        assert_eq!(id.hash, "93e74c9f");
+        assert_eq!(id.weekday(), 2); // Wednesday
+        assert_eq!(parse_game_id("2024022900gm-0001-0000-00000000").unwrap().weekday(), 3); // Thursday
+        assert_eq!(parse_game_id("foo"), None);
+        assert_eq!(parse_game_id("2019013115gm-zzzz-0000-93e74c9f"), None);
+    }
+
+    #[test]
+    fn test_parse_log_url() {
+        let url = parse_log_url("https://tenhou.net/0/?log=2019010215gm-00a9-0000-93e74c9f&tw=2&ts=3").unwrap();
+        assert_eq!(url.game_id.to_string(), "2019010215gm-00a9-0000-93e74c9f");
+        assert_eq!((url.seat, url.round), (Some(2), Some(3)));
+        let url = parse_log_url("log=2019010215gm-00a9-0000-93e74c9f").unwrap();
+        assert_eq!((url.seat, url.round), (None, None));
+        assert_eq!(parse_log_url("https://tenhou.net/0/?tw=2"), None);
+        assert_eq!(parse_log_url("log=2019010215gm-00a9-0000-93e74c9f&tw=4"), None);
+    }
+}
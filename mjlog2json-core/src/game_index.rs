@@ -0,0 +1,159 @@
+//! # game_index
+//!
+//! Compact index of a corpus, one JSON line per game, so that queries do not parse every log again.
+//!
+//! ```text
+//! {"ref":"2025010203gm-00a9-0000-0123abcd","date":"2025-01-02T03","names":["A","B","C","D"],"rule":"鳳南喰赤","lobby":0,"hanchan":true,"sanma":false,"final_points":[40000,30000,20000,10000],"rounds":9,"yakuman":false,"double_riichi":false}
+//! ```
+//!
+//! Not to be confused with `log_index`, which reads the index files published by tenhou.
+
+use crate::conv::{conv_rule_disp, to_points};
+use crate::game_id::parse_game_id;
+use crate::query::*;
+use mjlog::model::*;
+use serde_json::{json, Value};
+use std::io::{self, Write};
+use tenhou_json::exporter::Locale;
+
+/// A game of the index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameIndexEntry {
+    /// Log name of the game, numbered for files with multiple games.
+    pub reference: String,
+    /// "YYYY-MM-DDTHH" (JST) from the game ID of the log name. Empty if the name is not a game ID.
+    pub date: String,
+    pub names: Vec<String>,
+    /// Rule name such as "鳳南喰赤".
+    pub rule: String,
+    pub lobby: u32,
+    pub hanchan: bool,
+    pub sanma: bool,
+    /// Final points per seat. Empty if the game has no owari.
+    pub final_points: Vec<i64>,
+    pub rounds: usize,
+    /// A player won with a yakuman.
+    pub yakuman: bool,
+    /// A player declared riichi on the first discard, before any call.
+    pub double_riichi: bool,
+}
+
+/// Index entry of the game. The query terms are evaluated here, once per game.
+pub fn index_entry(reference: &str, mjlog: &Mjlog) -> GameIndexEntry {
+    let go = mjlog.actions.iter().find_map(|x| x.as_go());
+    let settings = go.map(|x| x.settings.clone()).unwrap_or_default();
+    let owari = mjlog.actions.iter().rev().find_map(|a| match a {
+        Action::AGARI(x) => x.owari.as_ref(),
+        Action::RYUUKYOKU(x) => x.owari.as_ref(),
+        _ => None,
+    });
+    GameIndexEntry {
+        reference: reference.to_string(),
+        date: parse_game_id(reference).map_or(String::new(), |x| format!("{:04}-{:02}-{:02}T{:02}", x.year, x.month, x.day, x.hour)),
+        names: mjlog.actions.iter().find_map(|x| x.as_un1()).map(|x| x.names.clone()).unwrap_or_default(),
+        rule: conv_rule_disp(&settings, Locale::Japanese),
+        lobby: go.map_or(0, |x| x.lobby),
+        hanchan: settings.hanchan,
+        sanma: settings.sanma,
+        final_points: owari.map(|(points, _)| points.iter().map(|&x| to_points(x)).collect()).unwrap_or_default(),
+        rounds: round_ranges(mjlog).len(),
+        yakuman: Query::Yakuman.matches_game(mjlog),
+        double_riichi: Query::DoubleRiichi.matches_game(mjlog),
+    }
+}
+
+fn entry_json(entry: &GameIndexEntry) -> Value {
+    json!({
+        "ref": entry.reference,
+        "date": entry.date,
+        "names": entry.names,
+        "rule": entry.rule,
+        "lobby": entry.lobby,
+        "hanchan": entry.hanchan,
+        "sanma": entry.sanma,
+        "final_points": entry.final_points,
+        "rounds": entry.rounds,
+        "yakuman": entry.yakuman,
+        "double_riichi": entry.double_riichi,
+    })
+}
+
+/// Same as `export_game_index`, but writes to the writer.
+pub fn export_game_index_to<W: Write>(entries: &[GameIndexEntry], mut writer: W) -> io::Result<()> {
+    for entry in entries {
+        writeln!(writer, "{}", entry_json(entry))?;
+    }
+    Ok(())
+}
+
+/// Exports the entries as JSON lines.
+pub fn export_game_index(entries: &[GameIndexEntry]) -> String {
+    let mut out = vec![];
+    export_game_index_to(entries, &mut out).expect("writing to a Vec never fails");
+    String::from_utf8(out).expect("exporters write UTF-8 only")
+}
+
+fn parse_entry(line: &str) -> Option<GameIndexEntry> {
+    let v: Value = serde_json::from_str(line).ok()?;
+    let strings = |x: &Value| -> Option<Vec<String>> { x.as_array()?.iter().map(|x| x.as_str().map(String::from)).collect() };
+    Some(GameIndexEntry {
+        reference: v["ref"].as_str()?.to_string(),
+        date: v["date"].as_str()?.to_string(),
+        names: strings(&v["names"])?,
+        rule: v["rule"].as_str()?.to_string(),
+        lobby: v["lobby"].as_u64()? as u32,
+        hanchan: v["hanchan"].as_bool()?,
+        sanma: v["sanma"].as_bool()?,
+        final_points: v["final_points"].as_array()?.iter().map(|x| x.as_i64()).collect::<Option<_>>()?,
+        rounds: v["rounds"].as_u64()? as usize,
+        yakuman: v["yakuman"].as_bool()?,
+        double_riichi: v["double_riichi"].as_bool()?,
+    })
+}
+
+/// Parses the JSON lines of `export_game_index`. Returns the 1-origin number of the first invalid line on failure.
+pub fn parse_game_index(s: &str) -> Result<Vec<GameIndexEntry>, usize> {
+    s.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()).map(|(i, line)| parse_entry(line).ok_or(i + 1)).collect()
+}
+
+impl Query {
+    /// Same as `matches_game`, but on the index entry of the game.
+    pub fn matches_entry(&self, entry: &GameIndexEntry) -> bool {
+        match self {
+            Query::Player(name) => entry.names.iter().any(|x| x == name),
+            Query::Lobby(lobby) => entry.lobby == *lobby,
+            Query::Hanchan => entry.hanchan,
+            Query::Tonpuu => !entry.hanchan,
+            Query::Sanma => entry.sanma,
+            Query::Yonma => !entry.sanma,
+            Query::DoubleRiichi => entry.double_riichi,
+            Query::Yakuman => entry.yakuman,
+            Query::Not(x) => !x.matches_entry(entry),
+            Query::And(xs) => xs.iter().all(|x| x.matches_entry(entry)),
+            Query::Or(xs) => xs.iter().any(|x| x.matches_entry(entry)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mjlog::parser::parse_mjlogs;
+
+    #[test]
+    fn test_game_index() {
+        let mjlog = parse_mjlogs(include_str!("../fixtures/observer.xml")).unwrap().remove(0);
+        let entry = index_entry("2025010203gm-00a9-0000-0123abcd", &mjlog);
+        assert_eq!(entry.date, "2025-01-02T03");
+        assert_eq!(entry.names[1], "Bob");
+
+        let index = export_game_index(&[entry.clone(), entry.clone()]);
+        assert_eq!(parse_game_index(&index), Ok(vec![entry.clone(), entry.clone()]));
+        assert_eq!(parse_game_index("{}\n"), Err(1));
+
+        for s in ["player:Bob and yonma", "yakuman or double_riichi", "not hanchan", "lobby:0 and not sanma"] {
+            let query: Query = s.parse().unwrap();
+            assert_eq!(query.matches_entry(&entry), query.matches_game(&mjlog), "{}", s);
+        }
+    }
+}
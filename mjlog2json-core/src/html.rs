@@ -0,0 +1,225 @@
+//! # html
+//!
+//! Self-contained HTML replayer of a game, to review private logs in a browser without uploading them anywhere.
+//!
+//! The turn order of each round is embedded as a list of steps, and the page only applies them,
+//! with no external script or style.
+
+use crate::escape::*;
+use crate::number_format::*;
+use crate::transcript::*;
+use crate::turn_order::*;
+use serde_json::{json, Value};
+use std::io::{self, Write};
+use tenhou_json::exporter::Locale;
+use tenhou_json::model::*;
+
+fn tiles_json(tiles: &[Tile]) -> Value {
+    json!(tiles.iter().map(|x| x.to_u8()).collect::<Vec<_>>())
+}
+
+// Compact form read by the script, e.g. ["d",0,11] for a draw.
+fn step_json(step: &Step) -> Value {
+    match step {
+        Step::Draw { who, tile } => json!(["d", who, tile.to_u8()]),
+        Step::Discard { who, tile, riichi } => json!(["x", who, tile.to_u8(), riichi]),
+        Step::Call { who, from, tiles, called } => json!(["c", who, from, tiles_json(tiles), called.to_u8()]),
+        Step::Ankan { who, tiles } => json!(["a", who, tiles_json(tiles)]),
+        Step::Kakan { who, added } => json!(["k", who, added.to_u8()]),
+    }
+}
+
+fn result_text(round: &Round, names: &[String], locale: Locale, grouping: DigitGrouping) -> String {
+    let kyoku = round.settings.kyoku;
+    match &round.result {
+        RoundResult::Agari { agari_vec } => agari_vec
+            .iter()
+            .map(|agari| {
+                let yaku: Vec<String> = agari.yaku.iter().map(|x| yaku_text(x, locale)).collect();
+                format!("{} {} [{}]", player_name(names, kyoku, agari.who as usize, locale), score_text(&agari.ranked_score, locale, grouping), yaku.join(", "))
+            })
+            .collect::<Vec<_>>()
+            .join(" / "),
+        RoundResult::Ryuukyoku { reason, .. } => ryuukyoku_text(reason, locale).to_string(),
+    }
+}
+
+fn game_json(tenhou_json: &TenhouJson, locale: Locale, grouping: DigitGrouping) -> Value {
+    let rounds: Vec<Value> = tenhou_json
+        .rounds
+        .iter()
+        .map(|round| {
+            let kyoku = round.settings.kyoku;
+            let players: Vec<Value> = round
+                .players
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| !p.hand.is_empty())
+                .map(|(who, p)| json!({"name": player_name(&tenhou_json.names, kyoku, who, locale), "hand": tiles_json(&p.hand)}))
+                .collect();
+            json!({
+                "label": round_label(&round.settings, locale),
+                "dora": tiles_json(&round.settings.dora),
+                "players": players,
+                "steps": turn_order(round).iter().map(step_json).collect::<Vec<_>>(),
+                "result": result_text(round, &tenhou_json.names, locale, grouping),
+            })
+        })
+        .collect();
+    json!({ "rounds": rounds })
+}
+
+const STYLE: &str = "body{font-family:sans-serif;margin:1em}
+.seat{margin:.5em 0;padding:.3em;border:1px solid #ccc}
+.tiles span{display:inline-block;min-width:1.6em;margin:1px;padding:2px;border:1px solid #999;border-radius:3px;text-align:center}
+.m{color:#a00}.p{color:#00a}.s{color:#070}.z{color:#000}.red{background:#fdd}.riichi{transform:rotate(90deg)}.called{opacity:.35}.drawn{margin-left:.6em!important}";
+
+const SCRIPT: &str = r#"const game = JSON.parse(document.getElementById("game").textContent);
+const honors = ["東", "南", "西", "北", "白", "發", "中"];
+const kind = (t) => (t > 50 ? (t - 50) * 10 + 5 : t);
+function tile(t, cls) {
+  const e = document.createElement("span");
+  const k = kind(t), suit = Math.floor(k / 10);
+  e.textContent = suit === 4 ? honors[(k % 10) - 1] : (t > 50 ? 0 : k % 10) + "mps"[suit - 1];
+  e.className = (suit === 4 ? "z" : "mps"[suit - 1]) + (t > 50 ? " red" : "") + (cls ? " " + cls : "");
+  return e;
+}
+function take(hand, t) {
+  const i = hand.indexOf(t);
+  if (i >= 0) hand.splice(i, 1);
+}
+function state(round, n) {
+  const seats = round.players.map((p) => ({ hand: p.hand.slice(), river: [], melds: [], drawn: null }));
+  for (const [type, who, ...args] of round.steps.slice(0, n)) {
+    const s = seats[who];
+    if (s.drawn !== null) s.hand.push(s.drawn);
+    s.drawn = null;
+    if (type === "d") s.drawn = args[0];
+    if (type === "x") { take(s.hand, args[0]); s.river.push({ t: args[0], riichi: args[1] }); }
+    if (type === "c") {
+      const [from, tiles, called] = args;
+      seats[from].river[seats[from].river.length - 1].called = true;
+      let skipped = false;
+      for (const t of tiles) { if (t === called && !skipped) skipped = true; else take(s.hand, t); }
+      s.melds.push(tiles);
+    }
+    if (type === "a") { const tiles = args[0]; tiles.forEach((t) => take(s.hand, t)); s.melds.push(tiles); }
+    if (type === "k") {
+      take(s.hand, args[0]);
+      const m = s.melds.find((m) => m.length === 3 && kind(m[0]) === kind(args[0]) && kind(m[1]) === kind(args[0]));
+      if (m) m.push(args[0]);
+    }
+  }
+  return seats;
+}
+let r = 0, n = 0;
+function render() {
+  const round = game.rounds[r];
+  document.getElementById("label").textContent = round.label + " (" + n + "/" + round.steps.length + ")";
+  const dora = document.getElementById("dora");
+  dora.replaceChildren(...round.dora.map((t) => tile(t)));
+  const board = document.getElementById("board");
+  board.replaceChildren();
+  state(round, n).forEach((s, i) => {
+    const div = document.createElement("div");
+    div.className = "seat";
+    const name = document.createElement("div");
+    name.textContent = round.players[i].name;
+    const hand = document.createElement("div");
+    hand.className = "tiles";
+    hand.append(...s.hand.sort((a, b) => kind(a) - kind(b) || a - b).map((t) => tile(t)));
+    if (s.drawn !== null) hand.append(tile(s.drawn, "drawn"));
+    s.melds.forEach((m) => { hand.append(" "); hand.append(...m.map((t) => tile(t))); });
+    const river = document.createElement("div");
+    river.className = "tiles";
+    river.append(...s.river.map((x) => tile(x.t, (x.riichi ? "riichi" : "") + (x.called ? " called" : ""))));
+    div.append(name, hand, river);
+    board.append(div);
+  });
+  document.getElementById("result").textContent = n === round.steps.length ? round.result : "";
+}
+function go(dr, dn) {
+  if (dr !== 0) { r = Math.min(Math.max(r + dr, 0), game.rounds.length - 1); n = 0; }
+  n = Math.min(Math.max(n + dn, 0), game.rounds[r].steps.length);
+  render();
+}
+document.getElementById("prev-round").onclick = () => go(-1, 0);
+document.getElementById("next-round").onclick = () => go(1, 0);
+document.getElementById("prev").onclick = () => go(0, -1);
+document.getElementById("next").onclick = () => go(0, 1);
+document.getElementById("end").onclick = () => go(0, Infinity);
+document.addEventListener("keydown", (e) => {
+  if (e.key === "ArrowLeft") go(0, -1);
+  if (e.key === "ArrowRight") go(0, 1);
+  if (e.key === "ArrowUp") go(-1, 0);
+  if (e.key === "ArrowDown") go(1, 0);
+});
+if (game.rounds.length > 0) render();
+"#;
+
+fn write_html(out: &mut impl Write, tenhou_json: &TenhouJson, locale: Locale, grouping: DigitGrouping) -> io::Result<()> {
+    let (lang, labels) = match locale {
+        Locale::Japanese => ("ja", ["前局", "戻る", "進む", "局終了", "次局"]),
+        Locale::English => ("en", ["Prev round", "Back", "Next", "End", "Next round"]),
+    };
+    let title = escape_html(&format!("{} {}", tenhou_json.rule.disp, tenhou_json.reference));
+    // "</" would end the script element.
+    let data = game_json(tenhou_json, locale, grouping).to_string().replace("</", "<\\/");
+
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html lang=\"{}\">", lang)?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", title)?;
+    writeln!(out, "<style>{}</style>", STYLE)?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<h1>{}</h1>", title)?;
+    writeln!(
+        out,
+        "<p><button id=\"prev-round\">{}</button> <button id=\"prev\">{}</button> <button id=\"next\">{}</button> <button id=\"end\">{}</button> <button id=\"next-round\">{}</button></p>",
+        labels[0], labels[1], labels[2], labels[3], labels[4]
+    )?;
+    writeln!(out, "<h2 id=\"label\"></h2>")?;
+    writeln!(out, "<div id=\"dora\" class=\"tiles\"></div>")?;
+    writeln!(out, "<div id=\"board\"></div>")?;
+    writeln!(out, "<p id=\"result\"></p>")?;
+    writeln!(out, "<script type=\"application/json\" id=\"game\">{}</script>", data)?;
+    writeln!(out, "<script>{}</script>", SCRIPT)?;
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")?;
+    Ok(())
+}
+
+/// Exports a single HTML page that replays the game turn by turn: hands, discards and melds of every player.
+///
+/// The page works offline. Use the buttons or the arrow keys to step through the turns (left/right) and the rounds (up/down).
+pub fn export_html(tenhou_json: &TenhouJson, locale: Locale, grouping: DigitGrouping) -> String {
+    let mut out = vec![];
+    export_html_to(tenhou_json, locale, grouping, &mut out).expect("writing to a Vec never fails");
+    String::from_utf8(out).expect("exporters write UTF-8 only")
+}
+
+/// Same as `export_html`, but writes to the writer.
+pub fn export_html_to<W: Write>(tenhou_json: &TenhouJson, locale: Locale, grouping: DigitGrouping, mut writer: W) -> io::Result<()> {
+    write_html(&mut writer, tenhou_json, locale, grouping)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tenhou_json::parser::parse_tenhou_json;
+
+    #[test]
+    fn test_export_html() {
+        let src = r#"{"ver":2.3,"ref":"","log":[[[0,0,0],[25000,25000,25000,25000],[11],[],
+            [11,12,13,14,15,16,17,18,21,22,23,24,25],[19,26],[19],
+            [17,18,21,22,23,24,25,26,27,28,29,47,47],["c191718"],[47],
+            [31,32,33,34,35,36,37,38,47,47,41,42,43],[],[],
+            [31,32,33,34,35,36,37,38,39,47,47,41,42],["47p4747"],[39],
+            ["流局",[0,0,0,0]]]],"ratingc":"PF4","rule":{"disp":"","aka53":1,"aka52":1,"aka51":1},"lobby":0,"dan":["","","",""],"rate":[1500,1500,1500,1500],"sx":["M","M","M","M"],"sc":[25000,0,25000,0,25000,0,25000,0],"name":["a","</script>","c","d"]}"#;
+        let html = export_html(&parse_tenhou_json(src).unwrap(), Locale::English, DigitGrouping::default());
+        assert!(html.contains("[\"c\",1,0,[19,17,18],19]"));
+        assert_eq!(html.matches("</script>").count(), 2);
+    }
+}
@@ -1 +1,55 @@
+//! # mjlog2json-core
+//!
+//! Converts mjlog-XML to tenhou-JSON.
+//!
+//! # Usage
+//!
+//! ```ignore
+//! use mjlog2json_core::conv::ConvOptions;
+//!
+//! let jsons: Vec<String> = mjlog2json_core::convert_xml_str_to_json_str(&xml, &ConvOptions::default())?;
+//! let formats = mjlog2json_core::capabilities().output_formats;
+//! ```
+
+#[cfg(feature = "arrow")]
+pub mod action_arrow;
+pub mod anonymize;
+pub mod behaviors;
+pub mod board_svg;
+pub mod calendar;
+pub mod capabilities;
+pub mod compare;
 pub mod conv;
+pub mod corpus;
+pub mod defense;
+pub mod escape;
+pub mod features;
+pub mod game_id;
+pub mod game_index;
+pub mod html;
+pub mod log_index;
+pub mod markdown;
+pub mod mjai;
+pub mod mortal;
+pub mod number_format;
+pub mod observation;
+pub mod problem;
+pub mod problem_bank;
+pub mod query;
+#[cfg(feature = "arrow")]
+pub mod round_arrow;
+pub mod round_csv;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stats;
+pub mod synth;
+#[cfg(feature = "parquet")]
+pub mod table_parquet;
+pub mod transcript;
+pub mod turn_order;
+pub mod validate;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use capabilities::capabilities;
+pub use conv::{convert_xml_str_to_json_str, ConvError, ConvOptions};
@@ -0,0 +1,123 @@
+//! # log_index
+//!
+//! Parses the daily index files published by tenhou, to enumerate games for batch downloads.
+//!
+//! * `sccYYYYMMDD.html.gz`: games of the phoenix table, with the game ID.
+//!   `00:03 | 13 | 四鳳南喰赤－ | <a href="http://tenhou.net/0/?log=2019010100gm-00a9-0000-6c3f7e5f">牌譜</a> | A(+57.0) B(+8.0) C(-20.0) D(-45.0)<br>`
+//! * `scaYYYYMMDD.log.gz`: all ranked games, without the game ID.
+//!   `L1000 | 00:00 | 四般東喰赤－ | A(+44.0) B(+3.0) C(-16.0) D(-31.0)`
+//!
+//! The files are gzip-compressed; decompress them before parsing.
+
+use crate::game_id::*;
+
+/// A player of an index entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexPlayer {
+    pub name: String,
+    /// 1-origin placement. The players are listed in this order.
+    pub rank: u8,
+    /// Final result, e.g. +57.0.
+    pub result: f64,
+}
+
+/// A game listed in an index file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexEntry {
+    /// Only in scc files.
+    pub game_id: Option<GameId>,
+    /// Lobby such as "L1000". Only in sca files.
+    pub lobby: Option<String>,
+    /// Start time in JST. The date is in the file name.
+    pub hour: u8,
+    pub minute: u8,
+    /// Length of the game in minutes. Only in scc files.
+    pub duration: Option<u16>,
+    /// Rule name such as "四鳳南喰赤－".
+    pub rule: String,
+    pub players: Vec<IndexPlayer>,
+}
+
+// "12:34" -> (12, 34)
+fn parse_time(s: &str) -> Option<(u8, u8)> {
+    let (hour, minute) = s.split_once(':')?;
+    Some((hour.parse().ok()?, minute.parse().ok()?))
+}
+
+// "A(+57.0) B(+8.0) ..." -> players in order. Chips may follow the result, e.g. "A(+57.0,+3枚)".
+fn parse_players(s: &str) -> Option<Vec<IndexPlayer>> {
+    s.split_whitespace()
+        .enumerate()
+        .map(|(i, x)| {
+            let (name, rest) = x.rsplit_once('(')?;
+            let result = rest.strip_suffix(')')?.split(',').next()?.parse().ok()?;
+            Some(IndexPlayer {
+                name: name.to_string(),
+                rank: i as u8 + 1,
+                result,
+            })
+        })
+        .collect()
+}
+
+/// Parses a line of an scc or sca file. Returns `None` for other lines.
+pub fn parse_index_line(line: &str) -> Option<IndexEntry> {
+    let line = line.trim().trim_end_matches("<br>");
+    let fields: Vec<&str> = line.split('|').map(|x| x.trim()).collect();
+    match fields[..] {
+        [time, duration, rule, link, players] => {
+            let (hour, minute) = parse_time(time)?;
+            let href = link.split('"').find(|x| x.contains("log="))?;
+            Some(IndexEntry {
+                game_id: Some(parse_log_url(href)?.game_id),
+                lobby: None,
+                hour,
+                minute,
+                duration: Some(duration.parse().ok()?),
+                rule: rule.to_string(),
+                players: parse_players(players)?,
+            })
+        }
+        [lobby, time, rule, players] => {
+            let (hour, minute) = parse_time(time)?;
+            Some(IndexEntry {
+                game_id: None,
+                lobby: Some(lobby.to_string()),
+                hour,
+                minute,
+                duration: None,
+                rule: rule.to_string(),
+                players: parse_players(players)?,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Parses a decompressed scc or sca file. Lines that are not games are skipped.
+pub fn parse_index(s: &str) -> Vec<IndexEntry> {
+    s.lines().filter_map(parse_index_line).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_index() {
+        let scc = "00:03 | 13 | 四鳳南喰赤－ | <a href=\"http://tenhou.net/0/?log=2019010100gm-00a9-0000-6c3f7e5f\">牌譜</a> | A(+57.0) B(+8.0) C(-20.0) D(-45.0)<br>\n";
+        let sca = "L1000 | 23:59 | 三般東喰赤祝５ | A(+44.0,+3枚) B(-3.0,-1枚) C(-41.0,-2枚)\n\n";
+        let entries = parse_index(&format!("{}{}", scc, sca));
+        assert_eq!(entries.len(), 2);
+
+        assert_eq!(entries[0].game_id.as_ref().unwrap().to_string(), "2019010100gm-00a9-0000-6c3f7e5f");
+        assert_eq!((entries[0].hour, entries[0].minute, entries[0].duration), (0, 3, Some(13)));
+        assert_eq!(entries[0].rule, "四鳳南喰赤－");
+        assert_eq!(entries[0].players[3], IndexPlayer { name: "D".to_string(), rank: 4, result: -45.0 });
+
+        assert_eq!(entries[1].game_id, None);
+        assert_eq!(entries[1].lobby.as_deref(), Some("L1000"));
+        assert_eq!(entries[1].players.len(), 3);
+        assert_eq!(entries[1].players[0].result, 44.0);
+    }
+}
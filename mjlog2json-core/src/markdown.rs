@@ -0,0 +1,128 @@
+//! # markdown
+//!
+//! Per-game summary in Markdown, for pasting into writeups.
+
+use crate::escape::*;
+use crate::number_format::*;
+use crate::transcript::*;
+use std::io::{self, Write};
+use tenhou_json::exporter::Locale;
+use tenhou_json::model::*;
+use tenhou_json::score::*;
+
+struct Labels {
+    players_header: &'static str,
+    rounds_header: &'static str,
+    highlights: &'static str,
+    tsumo: &'static str,
+    ron: &'static str,
+}
+
+const LABELS_JA: Labels = Labels {
+    players_header: "| 席 | 名前 | 段位 | レート | 点数 | 結果 |",
+    rounds_header: "| 局 | 結果 | 和了者 | 放銃者 | 点数 | 役 |",
+    highlights: "見どころ",
+    tsumo: "ツモ",
+    ron: "ロン",
+};
+
+const LABELS_EN: Labels = Labels {
+    players_header: "| Seat | Name | Dan | Rate | Points | Result |",
+    rounds_header: "| Round | Result | Winner | Dealt in | Score | Yaku |",
+    highlights: "Highlights",
+    tsumo: "Tsumo",
+    ron: "Ron",
+};
+
+fn write_summary(out: &mut impl Write, tenhou_json: &TenhouJson, locale: Locale, grouping: DigitGrouping) -> io::Result<()> {
+    let labels = match locale {
+        Locale::Japanese => &LABELS_JA,
+        Locale::English => &LABELS_EN,
+    };
+
+    writeln!(out, "## {} {}", escape_markdown_cell(&tenhou_json.rule.disp), escape_markdown_cell(&tenhou_json.reference))?;
+    writeln!(out)?;
+    writeln!(out, "{}", labels.players_header)?;
+    writeln!(out, "|---:|---|---|---:|---:|---:|")?;
+    for (i, name) in tenhou_json.names.iter().enumerate() {
+        writeln!(
+            out,
+            "| {} | {} | {} | {:.2} | {} | {:+.1} |",
+            i + 1,
+            escape_markdown_cell(name),
+            escape_markdown_cell(tenhou_json.dan.get(i).map_or("", |x| x.as_str())),
+            tenhou_json.rate.get(i).copied().unwrap_or_default(),
+            format_number(tenhou_json.final_points.get(i).copied().unwrap_or_default(), grouping),
+            tenhou_json.final_results.get(i).copied().unwrap_or_default(),
+        )?;
+    }
+
+    let mut highlights = vec![];
+    writeln!(out)?;
+    writeln!(out, "{}", labels.rounds_header)?;
+    writeln!(out, "|---|---|---|---|---|---|")?;
+    for round in &tenhou_json.rounds {
+        let label = round_label(&round.settings, locale);
+        let kyoku = round.settings.kyoku;
+        match &round.result {
+            RoundResult::Agari { agari_vec } => {
+                for agari in agari_vec {
+                    let winner = escape_markdown_cell(&player_name(&tenhou_json.names, kyoku, agari.who as usize, locale));
+                    let (how, from) = if agari.who == agari.from_who {
+                        (labels.tsumo, String::new())
+                    } else {
+                        (labels.ron, escape_markdown_cell(&player_name(&tenhou_json.names, kyoku, agari.from_who as usize, locale)))
+                    };
+                    let score = score_text(&agari.ranked_score, locale, grouping);
+                    let yaku: Vec<String> = agari.yaku.iter().map(|x| yaku_text(x, locale)).collect();
+                    writeln!(out, "| {} | {} | {} | {} | {} | {} |", label, how, winner, from, score, escape_markdown_cell(&yaku.join(", ")))?;
+
+                    // mangan or more
+                    if !matches!(agari.ranked_score.rank, ScoreRank::Normal { .. }) {
+                        highlights.push(format!("{}: {} {} {}", label, winner, score, escape_markdown_cell(&yaku.join(", "))));
+                    }
+                }
+            }
+            RoundResult::Ryuukyoku { reason, .. } => {
+                writeln!(out, "| {} | {} | | | | |", label, ryuukyoku_text(reason, locale))?;
+            }
+        }
+    }
+
+    if !highlights.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "**{}**", labels.highlights)?;
+        writeln!(out)?;
+        for x in highlights {
+            writeln!(out, "- {}", x)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Exports a summary of the game (players, results of each round and big hands) as Markdown.
+pub fn export_markdown(tenhou_json: &TenhouJson, locale: Locale, grouping: DigitGrouping) -> String {
+    let mut out = vec![];
+    export_markdown_to(tenhou_json, locale, grouping, &mut out).expect("writing to a Vec never fails");
+    String::from_utf8(out).expect("exporters write UTF-8 only")
+}
+
+/// Same as `export_markdown`, but writes to the writer.
+pub fn export_markdown_to<W: Write>(tenhou_json: &TenhouJson, locale: Locale, grouping: DigitGrouping, mut writer: W) -> io::Result<()> {
+    write_summary(&mut writer, tenhou_json, locale, grouping)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conv::conv_to_tenhou_json;
+    use mjlog::parser::parse_mjlogs;
+
+    #[test]
+    fn test_export_markdown() {
+        let mjlog = parse_mjlogs(include_str!("../fixtures/observer.xml")).unwrap().remove(0);
+        let tenhou_json = conv_to_tenhou_json(&mjlog).unwrap();
+        assert_eq!(export_markdown(&tenhou_json, Locale::Japanese, DigitGrouping::Comma), include_str!("../fixtures/observer.md"));
+    }
+}
@@ -0,0 +1,186 @@
+//! # mjai
+//!
+//! Exports mjlog as mjai events, one JSON object per line.
+//!
+//! Points are in points (not units of 100), and tiles are in mjai notation ("1m", "5mr", "E", "P", ...).
+
+use crate::conv::to_points;
+use mjlog::model::*;
+use mjlog::replay::meld_hand_hais;
+use serde_json::{json, Value};
+use std::io::{self, Write};
+
+const HONOR_NAMES: [&str; 7] = ["E", "S", "W", "N", "P", "F", "C"];
+const WIND_NAMES: [&str; 4] = ["E", "S", "W", "N"];
+
+/// mjai notation of the tile, e.g. "1m", "5mr", "E".
+pub(crate) fn pai(hai: Hai, red: bool) -> String {
+    let kind = hai.kind() as usize;
+    if kind >= 27 {
+        HONOR_NAMES[kind - 27].to_string()
+    } else {
+        let suit = ['m', 'p', 's'][kind / 9];
+        let is_red = red && matches!(hai.to_u8(), 16 | 52 | 88);
+        format!("{}{}{}", kind % 9 + 1, suit, if is_red { "r" } else { "" })
+    }
+}
+
+fn pais(hais: &[Hai], red: bool) -> Vec<String> {
+    hais.iter().map(|&x| pai(x, red)).collect()
+}
+
+fn scores(points: &[GamePoint]) -> Vec<tenhou_json::model::GamePoint> {
+    points.iter().map(|&x| to_points(x)).collect()
+}
+
+// Seat of the player the tile was called from.
+fn target(who: Player, dir: Direction) -> u8 {
+    let offset = match dir {
+        Direction::Shimocha => 1,
+        Direction::Toimen => 2,
+        _ => 3,
+    };
+    who.offset(offset, 4).to_u8()
+}
+
+fn meld_event(who: Player, m: &Meld, red: bool) -> Value {
+    let actor = who.to_u8();
+    let consumed = pais(&meld_hand_hais(m), red);
+    match *m {
+        Meld::Chii { combination: (a, b, c), called_position } => {
+            json!({"type": "chi", "actor": actor, "target": who.offset(3, 4).to_u8(), "pai": pai([a, b, c][called_position as usize], red), "consumed": consumed})
+        }
+        Meld::Pon { dir, called, .. } => json!({"type": "pon", "actor": actor, "target": target(who, dir), "pai": pai(called, red), "consumed": consumed}),
+        Meld::Daiminkan { dir, hai } => json!({"type": "daiminkan", "actor": actor, "target": target(who, dir), "pai": pai(hai, red), "consumed": consumed}),
+        Meld::Kakan { combination: (a, b, c), added, .. } => json!({"type": "kakan", "actor": actor, "pai": pai(added, red), "consumed": pais(&[a, b, c], red)}),
+        Meld::Ankan { .. } => json!({"type": "ankan", "actor": actor, "consumed": consumed}),
+    }
+}
+
+fn emit<W: Write>(writer: &mut W, x: Value) -> io::Result<()> {
+    writeln!(writer, "{}", x)
+}
+
+/// Options of the mjai export.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MjaiOptions {
+    /// Seat written as "id" of start_game, which tells a bot or a reviewer whose view the events are for.
+    pub id: Option<u8>,
+}
+
+/// Writes mjai events of the game to the writer, one per line.
+pub fn export_mjai_to<W: Write>(mjlog: &Mjlog, writer: W) -> io::Result<()> {
+    export_mjai_with_to(mjlog, &MjaiOptions::default(), writer)
+}
+
+/// Same as `export_mjai_to`, with options.
+pub fn export_mjai_with_to<W: Write>(mjlog: &Mjlog, options: &MjaiOptions, mut writer: W) -> io::Result<()> {
+    let mut red = true;
+    let mut last_draw = None;
+
+    for (i, a) in mjlog.actions.iter().enumerate() {
+        match a {
+            Action::GO(x) => red = !x.settings.no_red,
+            Action::UN1(x) => {
+                let mut event = json!({"type": "start_game", "names": x.names});
+                if let Some(id) = options.id {
+                    event["id"] = id.into();
+                }
+                emit(&mut writer, event)?
+            }
+            Action::INIT(x) => {
+                let tehais: Vec<Vec<String>> = x.hai.iter().map(|h| pais(h, red)).collect();
+                emit(
+                    &mut writer,
+                    json!({
+                        "type": "start_kyoku",
+                        "bakaze": WIND_NAMES[x.seed.kyoku as usize / 4 % 4],
+                        "kyoku": x.seed.kyoku % 4 + 1,
+                        "honba": x.seed.honba,
+                        "kyotaku": x.seed.kyoutaku,
+                        "oya": x.oya.to_u8(),
+                        "dora_marker": pai(x.seed.dora_hyouji, red),
+                        "scores": scores(&x.ten),
+                        "tehais": tehais,
+                    }),
+                )?;
+            }
+            Action::DRAW(x) => {
+                last_draw = Some(x.hai);
+                emit(&mut writer, json!({"type": "tsumo", "actor": x.who.to_u8(), "pai": pai(x.hai, red)}))?;
+            }
+            Action::DISCARD(x) => {
+                let tsumogiri = last_draw == Some(x.hai);
+                last_draw = None;
+                emit(&mut writer, json!({"type": "dahai", "actor": x.who.to_u8(), "pai": pai(x.hai, red), "tsumogiri": tsumogiri}))?;
+            }
+            Action::REACH1(x) => emit(&mut writer, json!({"type": "reach", "actor": x.who.to_u8()}))?,
+            Action::REACH2(x) => {
+                let mut deltas = vec![0; x.ten.len()];
+                if let Some(d) = deltas.get_mut(x.who.to_u8() as usize) {
+                    *d = -1000;
+                }
+                emit(&mut writer, json!({"type": "reach_accepted", "actor": x.who.to_u8(), "deltas": deltas, "scores": scores(&x.ten)}))?;
+            }
+            Action::N(x) => {
+                last_draw = None;
+                emit(&mut writer, meld_event(x.who, &x.m, red))?;
+            }
+            Action::DORA(x) => emit(&mut writer, json!({"type": "dora", "dora_marker": pai(x.hai, red)}))?,
+            Action::AGARI(x) => {
+                let after: Vec<GamePoint> = x.before_points.iter().zip(&x.delta_points).map(|(a, b)| a + b).collect();
+                emit(
+                    &mut writer,
+                    json!({
+                        "type": "hora",
+                        "actor": x.who.to_u8(),
+                        "target": x.from_who.to_u8(),
+                        "pai": pai(x.machi, red),
+                        "uradora_markers": pais(&x.dora_hai_ura, red),
+                        "deltas": scores(&x.delta_points),
+                        "scores": scores(&after),
+                    }),
+                )?;
+            }
+            Action::RYUUKYOKU(x) => {
+                let after: Vec<GamePoint> = x.before_points.iter().zip(&x.delta_points).map(|(a, b)| a + b).collect();
+                let tenpais: Vec<bool> = [&x.hai0, &x.hai1, &x.hai2, &x.hai3].iter().map(|h| h.is_some()).collect();
+                emit(&mut writer, json!({"type": "ryukyoku", "tenpais": tenpais, "deltas": scores(&x.delta_points), "scores": scores(&after)}))?;
+            }
+            Action::SHUFFLE(_) | Action::UN2(_) | Action::BYE(_) | Action::TAIKYOKU(_) | Action::Unknown(_) => {}
+        }
+
+        // end_kyoku after the last AGARI of a multiple ron.
+        if matches!(a, Action::AGARI(_) | Action::RYUUKYOKU(_)) && !matches!(mjlog.actions.get(i + 1), Some(Action::AGARI(_))) {
+            emit(&mut writer, json!({"type": "end_kyoku"}))?;
+        }
+    }
+
+    emit(&mut writer, json!({"type": "end_game"}))
+}
+
+/// Exports mjai events of the game, one per line.
+pub fn export_mjai(mjlog: &Mjlog) -> String {
+    let mut out = vec![];
+    export_mjai_to(mjlog, &mut out).expect("writing to a Vec never fails");
+    String::from_utf8(out).expect("exporters write UTF-8 only")
+}
+
+/// Same as `export_mjai`, with options.
+pub fn export_mjai_with(mjlog: &Mjlog, options: &MjaiOptions) -> String {
+    let mut out = vec![];
+    export_mjai_with_to(mjlog, options, &mut out).expect("writing to a Vec never fails");
+    String::from_utf8(out).expect("exporters write UTF-8 only")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mjlog::parser::parse_mjlogs;
+
+    #[test]
+    fn test_export_mjai() {
+        let mjlog = parse_mjlogs(include_str!("../fixtures/observer.xml")).unwrap().remove(0);
+        assert_eq!(export_mjai(&mjlog), include_str!("../fixtures/observer.mjai.jsonl"));
+    }
+}
@@ -0,0 +1,45 @@
+//! # mortal
+//!
+//! Review input for Mortal (<https://github.com/Equim-chan/Mortal>) and mjai-reviewer.
+//!
+//! The input is the mjai events of the game, where "id" of start_game is the hero seat to be reviewed.
+//! Mortal plays 4-player games only, so 3-player games are rejected.
+
+use crate::mjai::*;
+use mjlog::model::*;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ReviewError {
+    #[error("Seat {0} is not in the game (expected 0 to 3)")]
+    InvalidSeat(u8),
+    #[error("Mortal reviews 4-player games only")]
+    Sanma,
+}
+
+/// mjai events of the game for the review of the hero seat (0-origin), one per line.
+pub fn export_mortal_review(mjlog: &Mjlog, hero: u8) -> Result<String, ReviewError> {
+    if mjlog.actions.iter().any(|a| matches!(a, Action::GO(x) if x.settings.sanma)) {
+        return Err(ReviewError::Sanma);
+    }
+    if hero >= 4 {
+        return Err(ReviewError::InvalidSeat(hero));
+    }
+    Ok(export_mjai_with(mjlog, &MjaiOptions { id: Some(hero) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mjlog::parser::parse_mjlogs;
+
+    #[test]
+    fn test_export_mortal_review() {
+        let mjlog = parse_mjlogs(include_str!("../fixtures/observer.xml")).unwrap().remove(0);
+        let events = export_mortal_review(&mjlog, 2).unwrap();
+        let start: serde_json::Value = serde_json::from_str(events.lines().next().unwrap()).unwrap();
+        assert_eq!(start["type"], "start_game");
+        assert_eq!(start["id"], 2);
+        assert!(matches!(export_mortal_review(&mjlog, 4), Err(ReviewError::InvalidSeat(4))));
+    }
+}
@@ -0,0 +1,85 @@
+//! # number_format
+//!
+//! Formatting of points in the human-readable outputs (text and markdown).
+//! Machine-readable outputs (JSON, CSV, ...) always write plain numbers.
+
+/// Thousands separator of points, e.g. 25,000 or 25000.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DigitGrouping {
+    /// 25000
+    None,
+    /// 25,000 (Japanese and English)
+    #[default]
+    Comma,
+    /// 25.000 (German and others)
+    Period,
+    /// 25 000 (French and others), with a narrow no-break space.
+    Space,
+}
+
+impl DigitGrouping {
+    fn separator(self) -> Option<char> {
+        match self {
+            DigitGrouping::None => None,
+            DigitGrouping::Comma => Some(','),
+            DigitGrouping::Period => Some('.'),
+            DigitGrouping::Space => Some('\u{202f}'),
+        }
+    }
+}
+
+impl std::str::FromStr for DigitGrouping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(DigitGrouping::None),
+            "comma" => Ok(DigitGrouping::Comma),
+            "period" => Ok(DigitGrouping::Period),
+            "space" => Ok(DigitGrouping::Space),
+            _ => Err(format!("unknown digit grouping: {} (expected none, comma, period or space)", s)),
+        }
+    }
+}
+
+/// Formats the number with the separator, e.g. -12000 -> "-12,000".
+pub fn format_number(x: i64, grouping: DigitGrouping) -> String {
+    let digits = x.unsigned_abs().to_string();
+    let mut out = String::with_capacity(digits.len() * 2);
+    if x < 0 {
+        out.push('-');
+    }
+    for (i, c) in digits.chars().enumerate() {
+        if let Some(separator) = grouping.separator() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push(separator);
+            }
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Same as `format_number`, but with a sign for non-negative numbers too, e.g. 8000 -> "+8,000".
+pub fn format_signed(x: i64, grouping: DigitGrouping) -> String {
+    if x < 0 {
+        format_number(x, grouping)
+    } else {
+        format!("+{}", format_number(x, grouping))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(25000, DigitGrouping::Comma), "25,000");
+        assert_eq!(format_number(25000, DigitGrouping::None), "25000");
+        assert_eq!(format_number(-123456789, DigitGrouping::Period), "-123.456.789");
+        assert_eq!(format_number(999, DigitGrouping::Comma), "999");
+        assert_eq!(format_signed(0, DigitGrouping::Comma), "+0");
+        assert_eq!(format_signed(-1000, DigitGrouping::Space), "-1\u{202f}000");
+    }
+}
@@ -0,0 +1,237 @@
+//! # observation
+//!
+//! Observation and action pairs of one seat, for imitation learning.
+//!
+//! The game is replayed, and at each decision point of the seat, only the information visible to the seat is kept:
+//! its own hand, the discards and melds of every player, the dora indicators, the scores and the riichi status.
+//!
+//! Decision points:
+//!
+//! * `Draw`: after a draw of the seat. It discards, declares riichi, calls a kan, wins by tsumo or aborts by 九種九牌.
+//! * `AfterCall`: after a chii or pon of the seat. It discards.
+//! * `Claim`: after a discard of another player that the seat can call or win on. It calls, wins by ron or passes.
+//!
+//! Claims are judged by the shape of the hand only, ignoring furiten, yaku and kuikae. Chankan is not a decision point.
+
+use mjlog::model::*;
+use mjlog::replay::*;
+use tenhou_json::model::Tile;
+use tenhou_json::shanten::*;
+
+/// When the seat decides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecisionKind {
+    Draw,
+    AfterCall,
+    Claim,
+}
+
+/// What the other seats see of a player.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicPlayer {
+    pub melds: Vec<Meld>,
+    pub discards: Vec<DiscardedTile>,
+    pub riichi: bool,
+    /// Points in units of 100, same as the XML.
+    pub score: GamePoint,
+    /// Number of concealed tiles.
+    pub hand_size: usize,
+}
+
+/// The state of the game visible to the seat at a decision point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observation {
+    pub kind: DecisionKind,
+    pub seat: Player,
+    pub kyoku: u8,
+    pub honba: u8,
+    pub kyoutaku: u8,
+    pub oya: Player,
+    pub wall_remaining: u8,
+    pub dora_indicators: Vec<Hai>,
+    /// Concealed tiles of the seat, including the drawn tile.
+    pub hand: Vec<Hai>,
+    /// The tile just drawn for `Draw`, or the tile just discarded by another player for `Claim`.
+    pub tile: Option<Hai>,
+    /// Every player by seat, including the seat itself.
+    pub players: Vec<PublicPlayer>,
+}
+
+/// What the seat did at the decision point.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SeatAction {
+    Discard {
+        hai: Hai,
+        riichi: bool,
+    },
+    /// Ankan or kakan.
+    Kan(Meld),
+    Tsumo,
+    /// 九種九牌
+    KyuusyuKyuuhai,
+    /// Chii, pon or daiminkan.
+    Call(Meld),
+    Ron,
+    Pass,
+}
+
+fn observe(state: &GameState, kind: DecisionKind, seat: usize, tile: Option<Hai>) -> Observation {
+    Observation {
+        kind,
+        seat: Player::new(seat as u8),
+        kyoku: state.kyoku,
+        honba: state.honba,
+        kyoutaku: state.kyoutaku,
+        oya: state.oya,
+        wall_remaining: state.wall_remaining,
+        dora_indicators: state.dora_indicators.clone(),
+        hand: state.players[seat].hand.clone(),
+        tile,
+        players: state
+            .players
+            .iter()
+            .map(|p| PublicPlayer {
+                melds: p.melds.clone(),
+                discards: p.discards.clone(),
+                riichi: p.riichi,
+                score: p.score,
+                hand_size: p.hand.len(),
+            })
+            .collect(),
+    }
+}
+
+fn to_tiles(hand: &[Hai]) -> Vec<Tile> {
+    hand.iter().map(|&x| kind_to_tile(x.kind() as usize)).collect()
+}
+
+// Whether the seat can call or win on the discard, by the shape of the hand.
+fn can_claim(state: &GameState, seat: usize, from: usize, hai: Hai) -> bool {
+    let player = &state.players[seat];
+    let mut with_tile = player.hand.clone();
+    with_tile.push(hai);
+    if shanten(&to_tiles(&with_tile)) == -1 {
+        return true;
+    }
+    if player.riichi || state.wall_remaining == 0 {
+        return false;
+    }
+
+    let kind = hai.kind();
+    let has = |k: u8| player.hand.iter().any(|x| x.kind() == k);
+    let same = player.hand.iter().filter(|x| x.kind() == kind).count();
+    // Only the next seat chiis, and sanma has no chii.
+    let chii = state.players.len() == 4 && seat == (from + 1) % 4 && kind < 27 && {
+        let n = kind % 9;
+        (n >= 2 && has(kind - 2) && has(kind - 1)) || ((1..=7).contains(&n) && has(kind - 1) && has(kind + 1)) || (n <= 6 && has(kind + 1) && has(kind + 2))
+    };
+    same >= 2 || chii
+}
+
+// The action of the seat on its own turn, from the actions after the decision.
+fn own_action(rest: &[Action], seat: u8) -> Option<SeatAction> {
+    let mut riichi = false;
+    for a in rest {
+        match a {
+            Action::REACH1(x) if x.who.to_u8() == seat => riichi = true,
+            Action::DISCARD(x) if x.who.to_u8() == seat => return Some(SeatAction::Discard { hai: x.hai, riichi }),
+            Action::N(x) if x.who.to_u8() == seat => return Some(SeatAction::Kan(x.m.clone())),
+            Action::AGARI(x) if x.who.to_u8() == seat => return Some(SeatAction::Tsumo),
+            Action::RYUUKYOKU(x) if x.reason == Some(ExtraRyuukyokuReason::KyuusyuKyuuhai) => return Some(SeatAction::KyuusyuKyuuhai),
+            Action::UN2(_) | Action::BYE(_) | Action::DORA(_) => {}
+            _ => return None,
+        }
+    }
+    None
+}
+
+// The reaction of the seat to a discard, from the actions after the discard. Double ron gives several AGARIs in a row.
+fn claim_action(rest: &[Action], seat: u8) -> SeatAction {
+    for a in rest {
+        match a {
+            Action::AGARI(x) if x.who.to_u8() == seat => return SeatAction::Ron,
+            Action::N(x) if x.who.to_u8() == seat => return SeatAction::Call(x.m.clone()),
+            Action::AGARI(_) | Action::REACH2(_) | Action::DORA(_) | Action::UN2(_) | Action::BYE(_) => {}
+            _ => return SeatAction::Pass,
+        }
+    }
+    SeatAction::Pass
+}
+
+/// Observation and action pairs of the seat (0-origin) in the order of the game. See the module documentation.
+///
+/// Empty if the seat is not in the game.
+pub fn extract_observations(mjlog: &Mjlog, seat: u8) -> ReplayResult<Vec<(Observation, SeatAction)>> {
+    let mut ret = vec![];
+    let mut state = GameState::new();
+    let s = seat as usize;
+
+    for (i, a) in mjlog.actions.iter().enumerate() {
+        state.apply(a)?;
+        if !state.in_round || s >= state.players.len() {
+            continue;
+        }
+        let rest = &mjlog.actions[i + 1..];
+        match a {
+            Action::DRAW(x) if x.who.to_u8() == seat => {
+                if let Some(action) = own_action(rest, seat) {
+                    ret.push((observe(&state, DecisionKind::Draw, s, Some(x.hai)), action));
+                }
+            }
+            Action::N(x) if x.who.to_u8() == seat && matches!(x.m, Meld::Chii { .. } | Meld::Pon { .. }) => {
+                if let Some(action) = own_action(rest, seat) {
+                    ret.push((observe(&state, DecisionKind::AfterCall, s, None), action));
+                }
+            }
+            Action::DISCARD(x) if x.who.to_u8() != seat => {
+                let action = claim_action(rest, seat);
+                if action != SeatAction::Pass || can_claim(&state, s, x.who.to_u8() as usize, x.hai) {
+                    ret.push((observe(&state, DecisionKind::Claim, s, Some(x.hai)), action));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mjlog::parser::parse_mjlogs;
+
+    #[test]
+    fn test_extract_observations() {
+        let mjlog = parse_mjlogs(include_str!("../fixtures/observer.xml")).unwrap().remove(0);
+        let pairs = extract_observations(&mjlog, 0).unwrap();
+        let (first, action) = &pairs[0];
+        assert_eq!(first.kind, DecisionKind::Draw);
+        assert_eq!(first.hand.len(), 14);
+        assert_eq!(first.players.len(), 4);
+        assert!(first.players.iter().all(|p| p.hand_size >= 13));
+        assert!(matches!(action, SeatAction::Discard { riichi: false, .. }));
+        // The discarded tile is in the observed hand.
+        for (obs, action) in &pairs {
+            if let SeatAction::Discard { hai, .. } = action {
+                assert!(obs.hand.contains(hai));
+            }
+        }
+        assert!(extract_observations(&mjlog, 4).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_can_claim() {
+        let mut state = GameState::new();
+        state.in_round = true;
+        state.wall_remaining = 10;
+        state.players = vec![PlayerState::default(); 4];
+        // 1m2m 5p5p and 9 unrelated honors and terminals.
+        state.players[1].hand = [0, 4, 52, 53, 108, 112, 116, 120, 124, 128, 132, 32, 68].into_iter().map(Hai::new).collect();
+        assert!(can_claim(&state, 1, 0, Hai::new(8)), "chii 3m");
+        assert!(!can_claim(&state, 1, 2, Hai::new(8)), "chii from the seat before only");
+        assert!(can_claim(&state, 1, 2, Hai::new(54)), "pon 5p");
+        assert!(!can_claim(&state, 1, 0, Hai::new(76)), "2s");
+        state.players[1].riichi = true;
+        assert!(!can_claim(&state, 1, 0, Hai::new(8)), "no calls in riichi");
+    }
+}
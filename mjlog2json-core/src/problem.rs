@@ -0,0 +1,161 @@
+//! # problem
+//!
+//! "What would you discard" problems cut out of real games.
+//!
+//! A problem is the situation just after a player draws a tile: the hand, the melds and rivers of all players,
+//! the dora indicators and the scores, with the tile actually discarded as the answer.
+//! Tiles are in mjai notation and points are in points, same as `mjai`.
+//!
+//! ```json
+//! {"ref":"...","round":1,"draw":1,"bakaze":"E","kyoku":1,"honba":1,"kyotaku":0,"oya":0,"actor":1,"wall":68,
+//!  "dora_markers":["8m"],"scores":[28000,24000,24000,24000],"hand":["1p",...],"tsumo":"3s",
+//!  "melds":[[],[],[],[]],"discards":[["C"],[],[],[]],"riichi":[false,false,false,false],
+//!  "answer":"S","answer_riichi":false}
+//! ```
+
+use crate::conv::to_points;
+use crate::mjai::pai;
+use mjlog::model::*;
+use mjlog::replay::*;
+use serde_json::{json, Value};
+use thiserror::Error;
+
+const WIND_NAMES: [&str; 4] = ["E", "S", "W", "N"];
+
+/// A decision point: the `draw`-th draw of the `round`-th round, both 0-origin. Written as "round:draw", e.g. "3:12".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TurnMark {
+    pub round: usize,
+    pub draw: usize,
+}
+
+impl std::str::FromStr for TurnMark {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parsed = s.split_once(':').and_then(|(round, draw)| {
+            Some(TurnMark {
+                round: round.parse().ok()?,
+                draw: draw.parse().ok()?,
+            })
+        });
+        parsed.ok_or_else(|| format!("invalid turn: {} (expected ROUND:DRAW, e.g. 3:12)", s))
+    }
+}
+
+impl std::fmt::Display for TurnMark {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}", self.round, self.draw)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ProblemError {
+    #[error("Turn {0} is not in the game")]
+    TurnNotFound(TurnMark),
+    #[error(transparent)]
+    Replay(#[from] ReplayError),
+}
+
+/// Index in `Mjlog::actions` of the draw of the mark.
+pub fn find_turn(mjlog: &Mjlog, mark: TurnMark) -> Option<usize> {
+    let init = mjlog.actions.iter().enumerate().filter(|(_, a)| matches!(a, Action::INIT(_))).nth(mark.round)?.0;
+    mjlog.actions[init..]
+        .iter()
+        .enumerate()
+        .take_while(|&(i, a)| i == 0 || !matches!(a, Action::INIT(_)))
+        .filter(|(_, a)| matches!(a, Action::DRAW(_)))
+        .nth(mark.draw)
+        .map(|(i, _)| init + i)
+}
+
+/// The mark of the draw at the index in `Mjlog::actions`. `None` if the action is not a draw.
+pub fn turn_of_action(mjlog: &Mjlog, index: usize) -> Option<TurnMark> {
+    if !matches!(mjlog.actions.get(index), Some(Action::DRAW(_))) {
+        return None;
+    }
+    let before = &mjlog.actions[..index];
+    let init = before.iter().rposition(|a| matches!(a, Action::INIT(_)))?;
+    Some(TurnMark {
+        round: before.iter().filter(|a| matches!(a, Action::INIT(_))).count() - 1,
+        draw: before[init..].iter().filter(|a| matches!(a, Action::DRAW(_))).count(),
+    })
+}
+
+/// Cuts out the situation at the mark as a problem. `reference` is written as "ref", usually the log name.
+pub fn export_problem(mjlog: &Mjlog, mark: TurnMark, reference: &str) -> Result<Value, ProblemError> {
+    let index = find_turn(mjlog, mark).ok_or(ProblemError::TurnNotFound(mark))?;
+    let Action::DRAW(draw) = &mjlog.actions[index] else { unreachable!("find_turn returns a draw") };
+    let state = GameState::from_actions(&mjlog.actions[..=index])?;
+    let red = !state.settings.no_red;
+    let actor = draw.who;
+
+    let mut hand = state.players[actor.to_u8() as usize].hand.clone();
+    if let Some(pos) = hand.iter().rposition(|&x| x == draw.hai) {
+        hand.remove(pos);
+    }
+    hand.sort_by_key(|x| x.to_u8());
+
+    // The answer is the next discard of the player, unless they win or call a kan instead.
+    let mut answer = None;
+    let mut answer_riichi = false;
+    for a in &mjlog.actions[index + 1..] {
+        match a {
+            Action::REACH1(x) if x.who == actor => answer_riichi = true,
+            Action::DISCARD(x) if x.who == actor => {
+                answer = Some(pai(x.hai, red));
+                break;
+            }
+            Action::N(x) if x.who == actor => break,
+            Action::AGARI(_) | Action::RYUUKYOKU(_) | Action::INIT(_) => break,
+            _ => {}
+        }
+    }
+
+    let melds: Vec<Vec<String>> = state.players.iter().map(|p| p.melds.iter().flat_map(meld_hais).map(|x| pai(x, red)).collect()).collect();
+    let discards: Vec<Vec<String>> = state.players.iter().map(|p| p.discards.iter().map(|x| pai(x.hai, red)).collect()).collect();
+    Ok(json!({
+        "ref": reference,
+        "round": mark.round,
+        "draw": mark.draw,
+        "bakaze": WIND_NAMES[state.kyoku as usize / 4 % 4],
+        "kyoku": state.kyoku % 4 + 1,
+        "honba": state.honba,
+        "kyotaku": state.kyoutaku,
+        "oya": state.oya.to_u8(),
+        "actor": actor.to_u8(),
+        "wall": state.wall_remaining,
+        "dora_markers": state.dora_indicators.iter().map(|&x| pai(x, red)).collect::<Vec<_>>(),
+        "scores": state.players.iter().map(|p| to_points(p.score)).collect::<Vec<_>>(),
+        "hand": hand.iter().map(|&x| pai(x, red)).collect::<Vec<_>>(),
+        "tsumo": pai(draw.hai, red),
+        "melds": melds,
+        "discards": discards,
+        "riichi": state.players.iter().map(|p| p.riichi).collect::<Vec<_>>(),
+        "answer": answer,
+        "answer_riichi": answer_riichi,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mjlog::parser::parse_mjlogs;
+
+    #[test]
+    fn test_export_problem() {
+        let mjlog = parse_mjlogs(include_str!("../fixtures/observer.xml")).unwrap().remove(0);
+        let mark: TurnMark = "1:1".parse().unwrap();
+        let index = find_turn(&mjlog, mark).unwrap();
+        assert_eq!(turn_of_action(&mjlog, index), Some(mark));
+
+        let problem = export_problem(&mjlog, mark, "observer").unwrap();
+        assert_eq!(problem["actor"], 1);
+        assert_eq!(problem["honba"], 1);
+        assert_eq!(problem["tsumo"], "3s");
+        assert_eq!(problem["hand"].as_array().unwrap().len(), 13);
+        assert_eq!(problem["discards"][0], json!(["C"]));
+        assert_eq!(problem["answer"], "S");
+        assert!(export_problem(&mjlog, TurnMark { round: 2, draw: 0 }, "").is_err());
+    }
+}
@@ -0,0 +1,149 @@
+//! # problem_bank
+//!
+//! Finds decision points worth studying in games, to cut them out with `problem::export_problem`.
+//!
+//! * `deal_in_with_safe_tile`: the player dealt into a riichi while holding a genbutsu against the riichi player.
+//! * `tenpai_choice`: the player could reach tenpai by discarding tiles of different kinds, with different waits.
+
+use crate::defense::*;
+use crate::problem::TurnMark;
+use mjlog::model::*;
+use mjlog::replay::*;
+use std::collections::HashSet;
+use tenhou_json::model::Tile;
+use tenhou_json::shanten::*;
+
+/// Heuristic that found a problem.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProblemKind {
+    DealInWithSafeTile,
+    TenpaiChoice,
+}
+
+impl ProblemKind {
+    pub const ALL: [ProblemKind; 2] = [ProblemKind::DealInWithSafeTile, ProblemKind::TenpaiChoice];
+
+    pub fn to_str(&self) -> &str {
+        match self {
+            ProblemKind::DealInWithSafeTile => "deal_in_with_safe_tile",
+            ProblemKind::TenpaiChoice => "tenpai_choice",
+        }
+    }
+}
+
+impl std::str::FromStr for ProblemKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ProblemKind::ALL
+            .into_iter()
+            .find(|x| x.to_str() == s)
+            .ok_or_else(|| format!("unknown problem kind: {} (expected deal_in_with_safe_tile or tenpai_choice)", s))
+    }
+}
+
+/// A decision point found by a heuristic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FoundProblem {
+    pub mark: TurnMark,
+    pub kind: ProblemKind,
+}
+
+fn to_tiles(hand: &[Hai]) -> Vec<Tile> {
+    hand.iter().map(|&x| kind_to_tile(x.kind() as usize)).collect()
+}
+
+// Waits of each discard that keeps the hand tenpai. Red fives are the same kind as normal fives.
+fn tenpai_discards(hand: &[Hai]) -> Vec<Vec<usize>> {
+    let tiles = to_tiles(hand);
+    let mut kinds: Vec<usize> = tiles.iter().map(|&t| tile_to_kind(t)).collect();
+    kinds.sort();
+    kinds.dedup();
+
+    let mut ret = vec![];
+    for kind in kinds {
+        let mut rest = tiles.clone();
+        let pos = rest.iter().position(|&t| tile_to_kind(t) == kind).expect("the kind is in the hand");
+        rest.remove(pos);
+        if shanten(&rest) == 0 {
+            ret.push(ukeire(&rest).into_iter().map(|(t, _)| tile_to_kind(t)).collect());
+        }
+    }
+    ret
+}
+
+/// Decision points of the game found by the heuristics, in the order of the game.
+pub fn find_problems(mjlog: &Mjlog, kinds: &[ProblemKind]) -> ReplayResult<Vec<FoundProblem>> {
+    // (kyoku, honba, who, turn) of the deal-ins. Kyoku and honba identify a round in a game.
+    let deal_ins: HashSet<(u8, u8, u8, u8)> = if kinds.contains(&ProblemKind::DealInWithSafeTile) {
+        extract_riichi_discards(mjlog).iter().filter(|x| x.dealt_in && x.genbutsu_in_hand).map(|x| (x.kyoku, x.honba, x.who.to_u8(), x.turn)).collect()
+    } else {
+        HashSet::new()
+    };
+
+    let mut found = vec![];
+    let mut state = GameState::new();
+    let mut round = None;
+    let mut draws = 0;
+    let mut turns = [0u8; 4];
+    // Mark of the last draw, until the player acts.
+    let mut last_draw: Option<(u8, TurnMark)> = None;
+
+    for a in &mjlog.actions {
+        state.apply(a)?;
+        match a {
+            Action::INIT(_) => {
+                round = Some(round.map_or(0, |x| x + 1));
+                draws = 0;
+                turns = [0; 4];
+                last_draw = None;
+            }
+            Action::DRAW(x) => {
+                let mark = TurnMark { round: round.unwrap_or_default(), draw: draws };
+                draws += 1;
+                last_draw = Some((x.who.to_u8(), mark));
+
+                let player = &state.players[x.who.to_u8() as usize];
+                if kinds.contains(&ProblemKind::TenpaiChoice) && !player.riichi {
+                    let mut waits = tenpai_discards(&player.hand);
+                    waits.sort();
+                    waits.dedup();
+                    if waits.len() >= 2 {
+                        found.push(FoundProblem { mark, kind: ProblemKind::TenpaiChoice });
+                    }
+                }
+            }
+            Action::DISCARD(x) => {
+                let who = x.who.to_u8();
+                turns[who as usize % 4] = turns[who as usize % 4].saturating_add(1);
+                if let Some((drawer, mark)) = last_draw {
+                    if drawer == who && deal_ins.contains(&(state.kyoku, state.honba, who, turns[who as usize % 4])) {
+                        found.push(FoundProblem { mark, kind: ProblemKind::DealInWithSafeTile });
+                    }
+                }
+                last_draw = None;
+            }
+            // The discard after a call is not a choice after a draw.
+            Action::N(_) => last_draw = None,
+            _ => {}
+        }
+    }
+
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenpai_discards() {
+        // 123m 456p 789s 11z 23s + 9p: discarding 9p waits on 1s/4s, and discarding 2s or 3s is not tenpai.
+        let hais = [0, 4, 8, 48, 52, 56, 96, 100, 104, 108, 109, 76, 80, 68].map(Hai::new);
+        let waits = tenpai_discards(&hais);
+        assert_eq!(waits, [vec![18, 21]]);
+        // 123m 456p 789s 11z 22s + 3s: discarding 2s waits on 1s/4s, and 3s on 2s/1z.
+        let hais = [0, 4, 8, 48, 52, 56, 96, 100, 104, 108, 109, 76, 77, 80].map(Hai::new);
+        assert_eq!(tenpai_discards(&hais), [vec![18, 21], vec![19, 27]]);
+    }
+}
@@ -0,0 +1,187 @@
+//! # query
+//!
+//! Composable predicates over games and rounds, to pick logs out of a corpus.
+//!
+//! Queries are built from the enum or parsed from an expression:
+//!
+//! ```text
+//! player:Bob and yakuman
+//! lobby:0 and hanchan and not sanma
+//! double_riichi or yakuman
+//! ```
+//!
+//! Terms:
+//!
+//! * `player:NAME`: a player of the game is NAME. Names with spaces cannot be written in expressions.
+//! * `lobby:N`: the lobby of the game is N.
+//! * `hanchan`, `tonpuu`, `sanma`, `yonma`: the rule of the game.
+//! * `double_riichi`: a player declared riichi on the first discard, before any call.
+//! * `yakuman`: a player won with a yakuman.
+//!
+//! `not` binds tighter than `and`, and `and` tighter than `or`. There are no parentheses.
+//!
+//! A game matches when the whole game satisfies the query, e.g. `not yakuman` is a game without any yakuman.
+//! A round matches when the round satisfies the query, where the terms of the game hold for every round of the game.
+
+use mjlog::model::*;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// A predicate over games and rounds. See the module documentation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    Player(String),
+    Lobby(u32),
+    Hanchan,
+    Tonpuu,
+    Sanma,
+    Yonma,
+    DoubleRiichi,
+    Yakuman,
+    Not(Box<Query>),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+}
+
+// What the terms look at in a game.
+struct GameInfo<'a> {
+    settings: GameSettings,
+    lobby: u32,
+    names: &'a [String],
+}
+
+fn game_info(mjlog: &Mjlog) -> GameInfo<'_> {
+    let go = mjlog.actions.iter().find_map(|x| x.as_go());
+    GameInfo {
+        settings: go.map(|x| x.settings.clone()).unwrap_or_default(),
+        lobby: go.map_or(0, |x| x.lobby),
+        names: mjlog.actions.iter().find_map(|x| x.as_un1()).map_or(&[], |x| x.names.as_slice()),
+    }
+}
+
+/// Ranges of the actions of each round, from its INIT to the next INIT or the end of the game.
+pub fn round_ranges(mjlog: &Mjlog) -> Vec<Range<usize>> {
+    let starts: Vec<usize> = mjlog.actions.iter().enumerate().filter(|(_, x)| x.is_init()).map(|(i, _)| i).collect();
+    starts.iter().enumerate().map(|(i, &start)| start..starts.get(i + 1).copied().unwrap_or(mjlog.actions.len())).collect()
+}
+
+fn has_double_riichi(actions: &[Action]) -> bool {
+    let mut discarded = [false; 4];
+    for a in actions {
+        match a {
+            Action::INIT(_) => discarded = [false; 4],
+            Action::REACH1(x) if !discarded[x.who.to_u8() as usize % 4] => return true,
+            Action::DISCARD(x) => discarded[x.who.to_u8() as usize % 4] = true,
+            // A call interrupts the first go-around.
+            Action::N(_) => discarded = [true; 4],
+            _ => {}
+        }
+    }
+    false
+}
+
+fn has_yakuman(actions: &[Action]) -> bool {
+    actions.iter().any(|x| matches!(x, Action::AGARI(agari) if !agari.yakuman.is_empty()))
+}
+
+impl Query {
+    // The terms of the rounds look at `actions`, the whole game or a round.
+    fn eval(&self, game: &GameInfo, actions: &[Action]) -> bool {
+        match self {
+            Query::Player(name) => game.names.iter().any(|x| x == name),
+            Query::Lobby(lobby) => game.lobby == *lobby,
+            Query::Hanchan => game.settings.hanchan,
+            Query::Tonpuu => !game.settings.hanchan,
+            Query::Sanma => game.settings.sanma,
+            Query::Yonma => !game.settings.sanma,
+            Query::DoubleRiichi => has_double_riichi(actions),
+            Query::Yakuman => has_yakuman(actions),
+            Query::Not(x) => !x.eval(game, actions),
+            Query::And(xs) => xs.iter().all(|x| x.eval(game, actions)),
+            Query::Or(xs) => xs.iter().any(|x| x.eval(game, actions)),
+        }
+    }
+
+    /// Whether the game satisfies the query.
+    pub fn matches_game(&self, mjlog: &Mjlog) -> bool {
+        self.eval(&game_info(mjlog), &mjlog.actions)
+    }
+
+    /// Indices (0-origin) of the rounds of the game that satisfy the query.
+    pub fn matching_rounds(&self, mjlog: &Mjlog) -> Vec<usize> {
+        let game = game_info(mjlog);
+        round_ranges(mjlog).into_iter().enumerate().filter(|(_, range)| self.eval(&game, &mjlog.actions[range.clone()])).map(|(i, _)| i).collect()
+    }
+}
+
+fn parse_term(token: &str) -> Result<Query, String> {
+    if let Some(name) = token.strip_prefix("player:") {
+        return Ok(Query::Player(name.to_string()));
+    }
+    if let Some(lobby) = token.strip_prefix("lobby:") {
+        return lobby.parse().map(Query::Lobby).map_err(|_| format!("invalid lobby: {}", lobby));
+    }
+    match token {
+        "hanchan" => Ok(Query::Hanchan),
+        "tonpuu" => Ok(Query::Tonpuu),
+        "sanma" => Ok(Query::Sanma),
+        "yonma" => Ok(Query::Yonma),
+        "double_riichi" => Ok(Query::DoubleRiichi),
+        "yakuman" => Ok(Query::Yakuman),
+        _ => Err(format!("unknown query term: {} (expected player:NAME, lobby:N, hanchan, tonpuu, sanma, yonma, double_riichi or yakuman)", token)),
+    }
+}
+
+fn parse_unary(tokens: &[&str]) -> Result<Query, String> {
+    match tokens {
+        ["not", rest @ ..] => Ok(Query::Not(Box::new(parse_unary(rest)?))),
+        [token] => parse_term(token),
+        [] => Err("missing query term".to_string()),
+        _ => Err(format!("expected and or or between the terms: {}", tokens.join(" "))),
+    }
+}
+
+// Splits the tokens by the operator, and combines the parts with `combine` if there are several.
+fn parse_list(tokens: &[&str], op: &str, combine: fn(Vec<Query>) -> Query, parse: fn(&[&str]) -> Result<Query, String>) -> Result<Query, String> {
+    let mut parts = tokens.split(|x| *x == op).map(parse).collect::<Result<Vec<_>, _>>()?;
+    Ok(if parts.len() == 1 { parts.remove(0) } else { combine(parts) })
+}
+
+impl FromStr for Query {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens: Vec<&str> = s.split_whitespace().collect();
+        parse_list(&tokens, "or", Query::Or, |tokens| parse_list(tokens, "and", Query::And, parse_unary))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mjlog::parser::parse_mjlogs;
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            "player:Bob and not sanma or yakuman".parse(),
+            Ok(Query::Or(vec![Query::And(vec![Query::Player("Bob".to_string()), Query::Not(Box::new(Query::Sanma))]), Query::Yakuman]))
+        );
+        assert_eq!("lobby:12".parse(), Ok(Query::Lobby(12)));
+        assert!("hanchan yakuman".parse::<Query>().is_err());
+        assert!("hanchan and".parse::<Query>().is_err());
+        assert!("foo".parse::<Query>().is_err());
+    }
+
+    #[test]
+    fn test_query() {
+        let mjlog = parse_mjlogs(include_str!("../fixtures/observer.xml")).unwrap().remove(0);
+        let rounds = round_ranges(&mjlog).len();
+        let query = |s: &str| s.parse::<Query>().unwrap();
+        assert!(query("player:Bob and yonma").matches_game(&mjlog));
+        assert!(!query("player:Alice or lobby:1").matches_game(&mjlog));
+        assert_eq!(query("player:Bob").matching_rounds(&mjlog), (0..rounds).collect::<Vec<_>>());
+        assert_eq!(query("yakuman").matches_game(&mjlog), !query("yakuman").matching_rounds(&mjlog).is_empty());
+        assert_eq!(query("not double_riichi").matching_rounds(&mjlog).len() + query("double_riichi").matching_rounds(&mjlog).len(), rounds);
+    }
+}
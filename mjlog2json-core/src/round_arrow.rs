@@ -0,0 +1,168 @@
+//! # round_arrow
+//!
+//! Streams the result of each round as Arrow IPC, with the result columns of `round_csv`.
+//! Each game is a record batch, so consumers (Polars, Spark, ...) can read the games while they are converted.
+//!
+//! # Usage
+//!
+//! ```ignore
+//! let mut writer = RoundsArrowWriter::new(std::io::stdout().lock())?;
+//! for tenhou_json in &games {
+//!     writer.write(tenhou_json)?;
+//! }
+//! writer.finish()?;
+//! ```
+
+use arrow_array::builder::{Int64Builder, StringBuilder, UInt32Builder, UInt8Builder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use std::io::Write;
+use std::sync::Arc;
+use tenhou_json::model::*;
+
+/// Schema of the round table. `who`, `from_who` and `score` are null for drawn games,
+/// and `delta3` is null for sanma.
+pub fn rounds_schema() -> Schema {
+    let mut fields = vec![
+        Field::new("ref", DataType::Utf8, false),
+        Field::new("round", DataType::UInt32, false),
+        Field::new("kyoku", DataType::UInt8, false),
+        Field::new("honba", DataType::UInt8, false),
+        Field::new("kyoutaku", DataType::UInt8, false),
+        Field::new("result", DataType::Utf8, false),
+        Field::new("who", DataType::UInt8, true),
+        Field::new("from_who", DataType::UInt8, true),
+        Field::new("score", DataType::Utf8, true),
+    ];
+    fields.extend((0..4).map(|i| Field::new(format!("delta{}", i), DataType::Int64, true)));
+    Schema::new(fields)
+}
+
+/// Round table of the game, one row per winner.
+pub fn rounds_record_batch(tenhou_json: &TenhouJson) -> Result<RecordBatch, ArrowError> {
+    let mut reference = StringBuilder::new();
+    let mut round = UInt32Builder::new();
+    let mut kyoku = UInt8Builder::new();
+    let mut honba = UInt8Builder::new();
+    let mut kyoutaku = UInt8Builder::new();
+    let mut result = StringBuilder::new();
+    let mut who = UInt8Builder::new();
+    let mut from_who = UInt8Builder::new();
+    let mut score = StringBuilder::new();
+    let mut deltas: Vec<Int64Builder> = (0..4).map(|_| Int64Builder::new()).collect();
+
+    for (i, r) in tenhou_json.rounds.iter().enumerate() {
+        let mut push_row = |result_text: &str, agari: Option<&Agari>, delta_points: &[GamePoint]| {
+            reference.append_value(&tenhou_json.reference);
+            round.append_value(i as u32);
+            kyoku.append_value(r.settings.kyoku);
+            honba.append_value(r.settings.honba);
+            kyoutaku.append_value(r.settings.kyoutaku);
+            result.append_value(result_text);
+            who.append_option(agari.map(|x| x.who));
+            from_who.append_option(agari.map(|x| x.from_who));
+            score.append_option(agari.map(|x| x.ranked_score.to_string()));
+            for (j, builder) in deltas.iter_mut().enumerate() {
+                builder.append_option(delta_points.get(j).copied());
+            }
+        };
+        match &r.result {
+            RoundResult::Agari { agari_vec } => {
+                for agari in agari_vec {
+                    let result_text = if agari.who == agari.from_who { "tsumo" } else { "ron" };
+                    push_row(result_text, Some(agari), &agari.delta_points);
+                }
+            }
+            RoundResult::Ryuukyoku { reason, delta_points } => push_row(reason.to_str(), None, delta_points),
+        }
+    }
+
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(reference.finish()),
+        Arc::new(round.finish()),
+        Arc::new(kyoku.finish()),
+        Arc::new(honba.finish()),
+        Arc::new(kyoutaku.finish()),
+        Arc::new(result.finish()),
+        Arc::new(who.finish()),
+        Arc::new(from_who.finish()),
+        Arc::new(score.finish()),
+    ];
+    columns.extend(deltas.iter_mut().map(|x| Arc::new(x.finish()) as ArrayRef));
+    RecordBatch::try_new(Arc::new(rounds_schema()), columns)
+}
+
+/// Writes the round tables of games as an Arrow IPC stream.
+pub struct RoundsArrowWriter<W: Write> {
+    writer: StreamWriter<W>,
+}
+
+impl<W: Write> RoundsArrowWriter<W> {
+    /// Writes the schema.
+    pub fn new(writer: W) -> Result<Self, ArrowError> {
+        Ok(Self {
+            writer: StreamWriter::try_new(writer, &rounds_schema())?,
+        })
+    }
+
+    /// Writes the round table of the game as a record batch, and flushes it for live consumers.
+    pub fn write(&mut self, tenhou_json: &TenhouJson) -> Result<(), ArrowError> {
+        self.writer.write(&rounds_record_batch(tenhou_json)?)?;
+        self.writer.flush()
+    }
+
+    /// Writes the end of the stream.
+    pub fn finish(mut self) -> Result<(), ArrowError> {
+        self.writer.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::Int64Type;
+    use arrow_array::Array;
+    use arrow_ipc::reader::StreamReader;
+
+    #[test]
+    fn test_rounds_arrow_stream() {
+        let agari = Agari {
+            delta_points: vec![0, 0, 2300, -2300],
+            who: 2,
+            from_who: 3,
+            ..Default::default()
+        };
+        let tenhou_json = TenhouJson {
+            reference: "2025010203gm-00a9-0000-0123abcd".to_string(),
+            rounds: vec![
+                Round {
+                    result: RoundResult::Ryuukyoku {
+                        reason: ExtraRyuukyokuReason::Ryuukyoku,
+                        delta_points: vec![3000, -1000, -1000, -1000],
+                    },
+                    ..Default::default()
+                },
+                Round {
+                    result: RoundResult::Agari { agari_vec: vec![agari] },
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let mut bytes = vec![];
+        let mut writer = RoundsArrowWriter::new(&mut bytes).unwrap();
+        writer.write(&tenhou_json).unwrap();
+        writer.write(&tenhou_json).unwrap();
+        writer.finish().unwrap();
+
+        let batches: Vec<RecordBatch> = StreamReader::try_new(bytes.as_slice(), None).unwrap().map(|x| x.unwrap()).collect();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].num_rows(), 2);
+        assert_eq!(batches[0].column_by_name("result").unwrap().as_string::<i32>().value(1), "ron");
+        assert!(batches[0].column_by_name("who").unwrap().is_null(0));
+        assert_eq!(batches[0].column_by_name("delta2").unwrap().as_primitive::<Int64Type>().value(1), 2300);
+    }
+}
@@ -0,0 +1,101 @@
+//! # round_csv
+//!
+//! Exports the result of each round as CSV, one row per winner, as a lightweight alternative to tenhou-JSON for spreadsheets.
+
+use crate::calendar::final_placement;
+use crate::escape::*;
+use std::io::{self, Write};
+use tenhou_json::model::*;
+
+const HEADER: &str = "ref,round,kyoku,honba,kyoutaku,result,who,from_who,score,delta0,delta1,delta2,delta3,yaku,riichi0,riichi1,riichi2,riichi3,placement0,placement1,placement2,placement3";
+
+// Four columns, empty for the seats of sanma and the missing values.
+fn seat_columns<T: ToString>(values: &[T]) -> String {
+    (0..4).map(|i| values.get(i).map_or(String::new(), |x| x.to_string())).collect::<Vec<_>>().join(",")
+}
+
+fn riichi_flags(round: &Round) -> String {
+    let flags: Vec<u8> = round.players.iter().map(|p| p.outgoing.iter().any(|x| matches!(x, OutgoingTile::Riichi(_))) as u8).collect();
+    seat_columns(&flags)
+}
+
+/// Same as `export_rounds_csv`, but writes to the writer.
+pub fn export_rounds_csv_to<W: Write>(tenhou_json: &TenhouJson, mut writer: W) -> io::Result<()> {
+    writeln!(writer, "{}", HEADER)?;
+    let reference = escape_csv_field(&tenhou_json.reference);
+    let placements: Vec<u32> = (0..tenhou_json.final_points.len()).map(|seat| final_placement(&tenhou_json.final_points, seat)).collect();
+    let placements = seat_columns(&placements);
+    for (i, round) in tenhou_json.rounds.iter().enumerate() {
+        let s = &round.settings;
+        let riichi = riichi_flags(round);
+        match &round.result {
+            RoundResult::Agari { agari_vec } => {
+                for agari in agari_vec {
+                    let result = if agari.who == agari.from_who { "tsumo" } else { "ron" };
+                    let score = escape_csv_field(&agari.ranked_score.to_string());
+                    let yaku = escape_csv_field(&agari.yaku.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(" "));
+                    writeln!(
+                        writer,
+                        "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                        reference,
+                        i,
+                        s.kyoku,
+                        s.honba,
+                        s.kyoutaku,
+                        result,
+                        agari.who,
+                        agari.from_who,
+                        score,
+                        seat_columns(&agari.delta_points),
+                        yaku,
+                        riichi,
+                        placements
+                    )?;
+                }
+            }
+            RoundResult::Ryuukyoku { reason, delta_points } => {
+                writeln!(
+                    writer,
+                    "{},{},{},{},{},{},,,,{},,{},{}",
+                    reference,
+                    i,
+                    s.kyoku,
+                    s.honba,
+                    s.kyoutaku,
+                    escape_csv_field(reason.to_str()),
+                    seat_columns(delta_points),
+                    riichi,
+                    placements
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Exports the result of each round as CSV with the columns
+/// `ref,round,kyoku,honba,kyoutaku,result,who,from_who,score,delta0..3,yaku,riichi0..3,placement0..3`.
+///
+/// * `result`: `tsumo`, `ron` or the reason of the drawn game (e.g. `流局`).
+/// * `yaku`: the yaku of the win separated by spaces, e.g. `立直(1飜) 平和(1飜)`.
+/// * `riichi0..3`: 1 if the seat declared riichi in the round.
+/// * `placement0..3`: final placement of the seat in the game, the same in every row. Empty without the final result.
+pub fn export_rounds_csv(tenhou_json: &TenhouJson) -> String {
+    let mut out = vec![];
+    export_rounds_csv_to(tenhou_json, &mut out).expect("writing to a Vec never fails");
+    String::from_utf8(out).expect("exporters write UTF-8 only")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conv::conv_to_tenhou_json;
+    use mjlog::parser::parse_mjlogs;
+
+    #[test]
+    fn test_export_rounds_csv() {
+        let mjlog = parse_mjlogs(include_str!("../fixtures/observer.xml")).unwrap().remove(0);
+        let tenhou_json = conv_to_tenhou_json(&mjlog).unwrap();
+        assert_eq!(export_rounds_csv(&tenhou_json), include_str!("../fixtures/observer.rounds.csv"));
+    }
+}
@@ -0,0 +1,292 @@
+//! # sqlite
+//!
+//! Writes games into a normalized SQLite database, so that many logs can be analyzed by SQL.
+//!
+//! | table         | one row per                                                      |
+//! |---------------|------------------------------------------------------------------|
+//! | `games`       | game, with the reference, the lobby and the rules                |
+//! | `players`     | seat of a game, with the name, the rank and the final result     |
+//! | `rounds`      | round (INIT) of a game                                           |
+//! | `actions`     | draw, discard, call, riichi and dora of a round, in the log order |
+//! | `results`     | win or draw that ended a round, with the point changes           |
+//! | `result_yaku` | yaku of a win                                                    |
+//!
+//! Points are in points, not in units of 100 as in mjlog. Tiles are the tile IDs of mjlog (0..136)
+//! and calls are the "m" attribute of N.
+//!
+//! # Usage
+//!
+//! ```ignore
+//! let mut writer = SqliteWriter::create("games.db")?;
+//! for (reference, mjlog) in &games {
+//!     writer.write(reference, mjlog)?;
+//! }
+//! writer.finish()?;
+//! ```
+
+use crate::conv::{conv_extra_ryuukyoku_reason, conv_yaku};
+use mjlog::exporter::export_meld;
+use mjlog::model::*;
+use rusqlite::{params, Connection, Result};
+use std::path::Path;
+
+/// Schema of the database. Tables are created only if they do not exist, so that games can be appended.
+pub const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS games (
+    id INTEGER PRIMARY KEY,
+    ref TEXT NOT NULL,
+    lobby INTEGER NOT NULL,
+    sanma INTEGER NOT NULL,
+    hanchan INTEGER NOT NULL,
+    no_red INTEGER NOT NULL,
+    no_kuitan INTEGER NOT NULL,
+    soku INTEGER NOT NULL,
+    room INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS players (
+    game_id INTEGER NOT NULL REFERENCES games(id),
+    seat INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    dan INTEGER,
+    rate REAL,
+    final_points INTEGER,
+    final_result REAL,
+    PRIMARY KEY (game_id, seat)
+);
+CREATE TABLE IF NOT EXISTS rounds (
+    id INTEGER PRIMARY KEY,
+    game_id INTEGER NOT NULL REFERENCES games(id),
+    number INTEGER NOT NULL,
+    kyoku INTEGER NOT NULL,
+    honba INTEGER NOT NULL,
+    kyoutaku INTEGER NOT NULL,
+    oya INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS actions (
+    round_id INTEGER NOT NULL REFERENCES rounds(id),
+    seq INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    who INTEGER NOT NULL,
+    hai INTEGER,
+    meld INTEGER,
+    PRIMARY KEY (round_id, seq)
+);
+CREATE TABLE IF NOT EXISTS results (
+    id INTEGER PRIMARY KEY,
+    round_id INTEGER NOT NULL REFERENCES rounds(id),
+    result TEXT NOT NULL,
+    who INTEGER,
+    from_who INTEGER,
+    pao_who INTEGER,
+    fu INTEGER,
+    han INTEGER,
+    yakuman INTEGER,
+    points INTEGER,
+    delta0 INTEGER,
+    delta1 INTEGER,
+    delta2 INTEGER,
+    delta3 INTEGER
+);
+CREATE TABLE IF NOT EXISTS result_yaku (
+    result_id INTEGER NOT NULL REFERENCES results(id),
+    yaku INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    han INTEGER NOT NULL
+);
+";
+
+fn to_points(x: GamePoint) -> i64 {
+    x as i64 * 100
+}
+
+/// Writes games to a SQLite database in a single transaction.
+pub struct SqliteWriter {
+    conn: Connection,
+}
+
+impl SqliteWriter {
+    /// Opens or creates the database file and the tables.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new(Connection::open(path)?)
+    }
+
+    /// Creates the tables on the connection, e.g. an in-memory database.
+    pub fn new(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        conn.execute_batch("BEGIN")?;
+        Ok(Self { conn })
+    }
+
+    /// Writes the game and returns its ID in `games`.
+    pub fn write(&mut self, reference: &str, mjlog: &Mjlog) -> Result<i64> {
+        let go = mjlog.actions.iter().find_map(|x| x.as_go());
+        let settings = go.map(|x| x.settings.clone()).unwrap_or_default();
+        self.conn
+            .prepare_cached("INSERT INTO games (ref, lobby, sanma, hanchan, no_red, no_kuitan, soku, room) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)")?
+            .execute(params![
+                reference,
+                go.map_or(0, |x| x.lobby),
+                settings.sanma,
+                settings.hanchan,
+                settings.no_red,
+                settings.no_kuitan,
+                settings.soku,
+                settings.room as u8
+            ])?;
+        let game_id = self.conn.last_insert_rowid();
+
+        self.write_players(game_id, mjlog, &settings)?;
+        self.write_rounds(game_id, mjlog)?;
+        Ok(game_id)
+    }
+
+    fn write_players(&mut self, game_id: i64, mjlog: &Mjlog, settings: &GameSettings) -> Result<()> {
+        let un1 = mjlog.actions.iter().find_map(|x| x.as_un1());
+        let owari = mjlog.actions.iter().rev().find_map(|x| match x {
+            Action::AGARI(a) => a.owari.as_ref(),
+            Action::RYUUKYOKU(r) => r.owari.as_ref(),
+            _ => None,
+        });
+
+        let mut stmt = self.conn.prepare_cached("INSERT INTO players (game_id, seat, name, dan, rate, final_points, final_result) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)")?;
+        for seat in 0..if settings.sanma { 3 } else { 4 } {
+            let name = un1.and_then(|x| x.names.get(seat)).map_or("", |x| x.as_str());
+            let dan = un1.and_then(|x| x.dan.get(seat).copied().flatten()).map(|x| x as u8);
+            let rate = un1.and_then(|x| x.rate.get(seat).copied().flatten());
+            let final_points = owari.and_then(|(points, _)| points.get(seat)).map(|&x| to_points(x));
+            let final_result = owari.and_then(|(_, results)| results.get(seat));
+            stmt.execute(params![game_id, seat as u8, name, dan, rate, final_points, final_result])?;
+        }
+        Ok(())
+    }
+
+    fn write_rounds(&mut self, game_id: i64, mjlog: &Mjlog) -> Result<()> {
+        let mut round_id = None;
+        let mut in_round = false;
+        let mut number = 0;
+        let mut seq = 0;
+
+        for a in &mjlog.actions {
+            let (kind, who, hai, meld) = match a {
+                Action::INIT(x) => {
+                    self.conn
+                        .prepare_cached("INSERT INTO rounds (game_id, number, kyoku, honba, kyoutaku, oya) VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?
+                        .execute(params![game_id, number, x.seed.kyoku, x.seed.honba, x.seed.kyoutaku, x.oya.to_u8()])?;
+                    round_id = Some(self.conn.last_insert_rowid());
+                    in_round = true;
+                    number += 1;
+                    seq = 0;
+                    continue;
+                }
+                // Multiple ron has an AGARI after the end of the round.
+                Action::AGARI(x) => {
+                    if let Some(round_id) = round_id {
+                        self.write_agari(round_id, x)?;
+                    }
+                    in_round = false;
+                    continue;
+                }
+                Action::RYUUKYOKU(x) => {
+                    if let Some(round_id) = round_id {
+                        self.write_ryuukyoku(round_id, x)?;
+                    }
+                    in_round = false;
+                    continue;
+                }
+                _ if !in_round => continue,
+                Action::DRAW(x) => ("draw", x.who, Some(x.hai.to_u8()), None),
+                Action::DISCARD(x) => ("discard", x.who, Some(x.hai.to_u8()), None),
+                Action::N(x) => ("meld", x.who, None, Some(export_meld(&x.m))),
+                Action::REACH1(x) => ("riichi", x.who, None, None),
+                Action::REACH2(x) => ("riichi_accepted", x.who, None, None),
+                Action::DORA(x) => ("dora", Player::new(0), Some(x.hai.to_u8()), None),
+                Action::BYE(x) => ("bye", x.who, None, None),
+                Action::UN2(x) => ("reconnect", x.who, None, None),
+                _ => continue,
+            };
+
+            let mut stmt = self.conn.prepare_cached("INSERT INTO actions (round_id, seq, kind, who, hai, meld) VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?;
+            stmt.execute(params![round_id, seq, kind, who.to_u8(), hai, meld])?;
+            seq += 1;
+        }
+        Ok(())
+    }
+
+    // Inserts the result with the point changes of each seat, and returns its ID.
+    fn insert_result(&mut self, round_id: i64, result: &str, agari: Option<&ActionAGARI>, delta_points: &[GamePoint]) -> Result<i64> {
+        let delta = |i: usize| delta_points.get(i).map(|&x| to_points(x));
+        self.conn
+            .prepare_cached("INSERT INTO results (round_id, result, who, from_who, pao_who, fu, han, yakuman, points, delta0, delta1, delta2, delta3) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)")?
+            .execute(params![
+                round_id,
+                result,
+                agari.map(|x| x.who.to_u8()),
+                agari.map(|x| x.from_who.to_u8()),
+                agari.and_then(|x| x.pao_who).map(|x| x.to_u8()),
+                agari.map(|x| x.ten.fu),
+                agari.map(|x| x.yaku.iter().map(|&(_, han)| han as u32).sum::<u32>()),
+                agari.map(|x| x.yakuman.len() as u32),
+                agari.map(|x| x.ten.total),
+                delta(0),
+                delta(1),
+                delta(2),
+                delta(3)
+            ])?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    fn write_agari(&mut self, round_id: i64, x: &ActionAGARI) -> Result<()> {
+        let result = if x.is_tsumo() { "tsumo" } else { "ron" };
+        let result_id = self.insert_result(round_id, result, Some(x), &x.delta_points)?;
+
+        // Yakuman count 13 han each, same as the corpus.
+        let yaku = x.yaku.iter().copied().chain(x.yakuman.iter().map(|&y| (y, 13)));
+        let mut stmt = self.conn.prepare_cached("INSERT INTO result_yaku (result_id, yaku, name, han) VALUES (?1, ?2, ?3, ?4)")?;
+        for (y, han) in yaku {
+            stmt.execute(params![result_id, y.to_u8(), conv_yaku(y).to_string(), han])?;
+        }
+        Ok(())
+    }
+
+    fn write_ryuukyoku(&mut self, round_id: i64, x: &ActionRYUUKYOKU) -> Result<()> {
+        self.insert_result(round_id, conv_extra_ryuukyoku_reason(&x.reason).to_str(), None, &x.delta_points)?;
+        Ok(())
+    }
+
+    /// Commits the games written so far.
+    pub fn finish(self) -> Result<()> {
+        self.conn.execute_batch("COMMIT")
+    }
+
+    /// The connection, e.g. to query the games before `finish`.
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synth::synthesize_mjlog;
+
+    #[test]
+    fn test_sqlite_writer() {
+        let mjlogs: Vec<_> = (0..4).map(synthesize_mjlog).collect();
+        let mut writer = SqliteWriter::new(Connection::open_in_memory().unwrap()).unwrap();
+        for (i, mjlog) in mjlogs.iter().enumerate() {
+            assert_eq!(writer.write(&format!("game{}", i), mjlog).unwrap(), i as i64 + 1);
+        }
+
+        let count = |sql: &str| writer.connection().query_row(sql, [], |row| row.get::<_, i64>(0)).unwrap();
+        let actions = |f: fn(&Action) -> bool| mjlogs.iter().flat_map(|x| &x.actions).filter(|x| f(x)).count() as i64;
+        assert_eq!(count("SELECT COUNT(*) FROM games"), 4);
+        assert_eq!(count("SELECT COUNT(*) FROM rounds"), actions(Action::is_init));
+        assert_eq!(count("SELECT COUNT(*) FROM results"), actions(|x| x.is_agari() || x.is_ryuukyoku()));
+        assert_eq!(count("SELECT COUNT(*) FROM actions WHERE kind = 'discard'"), actions(Action::is_discard));
+        assert_eq!(count("SELECT COUNT(DISTINCT result_id) FROM result_yaku"), actions(Action::is_agari));
+
+        // Draws move points only between the players.
+        assert_eq!(count("SELECT SUM(delta0 + delta1 + delta2 + COALESCE(delta3, 0)) FROM results WHERE result NOT IN ('tsumo', 'ron')"), 0);
+        writer.finish().unwrap();
+    }
+}
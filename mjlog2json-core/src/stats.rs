@@ -0,0 +1,575 @@
+//! # stats
+//!
+//! Corpus statistics.
+//!
+//! Rooms and lobbies differ in rules such as red fives and kuitan. `Normalization` removes their effect
+//! from the win statistics, so that corpora of different rules can be compared apples to apples.
+//!
+//! `DealInStats` attributes every ron to the discard that dealt in, with the riichi state of the table at the time.
+//!
+//! `YakuStats` is made from the AGARI actions rather than the corpus, which keeps only the han of the wins.
+
+use crate::calendar::final_placement;
+use crate::conv::conv_yaku;
+use crate::corpus::*;
+use crate::escape::escape_csv_field;
+use mjlog::model::{Action, GamePoint, GameSettings, Mjlog, Yaku};
+use std::collections::BTreeMap;
+
+/// Number of tile kinds (1m..9m, 1p..9p, 1s..9s, 1z..7z).
+pub const TILE_KINDS: usize = 34;
+
+/// Returns a tile kind name such as "1m" or "7z".
+pub fn tile_kind_name(kind: usize) -> String {
+    let suit = ['m', 'p', 's', 'z'][kind / 9];
+    format!("{}{}", kind % 9 + 1, suit)
+}
+
+/// Terminals and honors.
+pub fn is_yaochuu_kind(kind: usize) -> bool {
+    kind >= 27 || matches!(kind % 9, 0 | 8)
+}
+
+/// Baselines of discard choices.
+#[derive(Debug, Clone)]
+pub struct DiscardStats {
+    /// Distribution of the first discard of each player in each round.
+    pub first_discard: [u32; TILE_KINDS],
+
+    /// Number of discards by turn (0-origin discard index of the player).
+    pub discards_by_turn: Vec<u32>,
+
+    /// Number of terminal/honor discards by turn.
+    pub yaochuu_discards_by_turn: Vec<u32>,
+
+    /// Distribution of the tile discarded when declaring riichi.
+    pub riichi_tile: [u32; TILE_KINDS],
+}
+
+impl DiscardStats {
+    pub fn from_corpus(corpus: &Corpus) -> Self {
+        let mut stats = DiscardStats {
+            first_discard: [0; TILE_KINDS],
+            discards_by_turn: Vec::new(),
+            yaochuu_discards_by_turn: Vec::new(),
+            riichi_tile: [0; TILE_KINDS],
+        };
+
+        for round in 0..corpus.num_rounds() {
+            let mut riichi_declared = [false; 4];
+
+            for i in corpus.round_events(round) {
+                let who = corpus.event_who[i] as usize % 4;
+                match corpus.event_kind[i] {
+                    EventKind::Riichi => riichi_declared[who] = true,
+                    EventKind::Discard => {
+                        let kind = corpus.event_hai[i] as usize / 4;
+                        let turn = corpus.event_turn[i] as usize;
+
+                        if turn == 0 {
+                            stats.first_discard[kind] += 1;
+                        }
+
+                        if stats.discards_by_turn.len() <= turn {
+                            stats.discards_by_turn.resize(turn + 1, 0);
+                            stats.yaochuu_discards_by_turn.resize(turn + 1, 0);
+                        }
+                        stats.discards_by_turn[turn] += 1;
+                        if is_yaochuu_kind(kind) {
+                            stats.yaochuu_discards_by_turn[turn] += 1;
+                        }
+
+                        if riichi_declared[who] {
+                            stats.riichi_tile[kind] += 1;
+                            riichi_declared[who] = false;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        stats
+    }
+
+    /// Terminal/honor discard rate by turn.
+    pub fn yaochuu_rate_by_turn(&self) -> Vec<f64> {
+        self.yaochuu_discards_by_turn.iter().zip(&self.discards_by_turn).map(|(&y, &n)| if n == 0 { 0.0 } else { y as f64 / n as f64 }).collect()
+    }
+}
+
+/// Rule of the game for grouping, e.g. "yonma,hanchan,aka,kuitan".
+pub fn rule_name(settings: &GameSettings) -> String {
+    [
+        if settings.sanma { "sanma" } else { "yonma" },
+        if settings.hanchan { "hanchan" } else { "tonpuu" },
+        if settings.no_red { "no_aka" } else { "aka" },
+        if settings.no_kuitan { "no_kuitan" } else { "kuitan" },
+    ]
+    .join(",")
+}
+
+/// Normalization of the win statistics across rules.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Normalization {
+    /// Excludes the han of aka dora, as if no game had red fives.
+    pub exclude_aka_dora: bool,
+    /// Excludes the wins by open tanyao, as if no game had kuitan.
+    pub exclude_kuitan: bool,
+}
+
+/// Han distribution of the wins.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HanStats {
+    pub normalization: Normalization,
+    /// Number of wins by han, indexed by the han. Yakuman count 13 han each.
+    pub wins_by_han: Vec<u32>,
+    /// Wins left out by the normalization.
+    pub excluded_wins: u32,
+    /// Number of games of each rule, by `rule_name`.
+    pub games_by_rule: BTreeMap<String, u32>,
+}
+
+impl HanStats {
+    pub fn from_corpus(corpus: &Corpus, normalization: &Normalization) -> Self {
+        let mut stats = HanStats {
+            normalization: *normalization,
+            ..HanStats::default()
+        };
+        for settings in &corpus.game_settings {
+            *stats.games_by_rule.entry(rule_name(settings)).or_default() += 1;
+        }
+
+        for i in 0..corpus.num_events() {
+            if corpus.event_kind[i] != EventKind::Agari {
+                continue;
+            }
+            if normalization.exclude_kuitan && corpus.event_kuitan[i] {
+                stats.excluded_wins += 1;
+                continue;
+            }
+            let aka_dora = if normalization.exclude_aka_dora { 0 } else { corpus.event_aka_dora[i] };
+            let han = (corpus.event_han[i] + aka_dora) as usize;
+            if stats.wins_by_han.len() <= han {
+                stats.wins_by_han.resize(han + 1, 0);
+            }
+            stats.wins_by_han[han] += 1;
+        }
+        stats
+    }
+
+    pub fn wins(&self) -> u32 {
+        self.wins_by_han.iter().sum()
+    }
+
+    pub fn average_han(&self) -> f64 {
+        let total: u64 = self.wins_by_han.iter().enumerate().map(|(han, &n)| han as u64 * n as u64).sum();
+        total as f64 / self.wins().max(1) as f64
+    }
+}
+
+/// Wins with a yaku and the han it gave.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct YakuCount {
+    pub wins: u32,
+    /// Sum of the han, 13 for each yakuman.
+    pub han: u32,
+}
+
+/// Yaku frequency and value of the wins.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct YakuStats {
+    /// Wins. Multiple ron counts once for every winner.
+    pub wins: u32,
+    /// Wins with one or more yakuman.
+    pub yakuman_wins: u32,
+    /// Sum of the han of the wins other than yakuman, including dora.
+    pub han: u64,
+    /// Wins by the yaku, with the yaku ID of mjlog as the key. Dora count once per win regardless of the number.
+    pub yaku: BTreeMap<u8, YakuCount>,
+    /// Dora, aka dora and ura dora over all wins.
+    pub dora: u32,
+    pub aka_dora: u32,
+    pub ura_dora: u32,
+}
+
+impl YakuStats {
+    pub fn from_mjlogs<'a, I: IntoIterator<Item = &'a Mjlog>>(mjlogs: I) -> Self {
+        let mut stats = Self::default();
+        for x in mjlogs.into_iter().flat_map(|x| &x.actions).filter_map(Action::as_agari) {
+            stats.wins += 1;
+            if !x.yakuman.is_empty() {
+                stats.yakuman_wins += 1;
+            } else {
+                stats.han += x.yaku.iter().map(|&(_, han)| han as u64).sum::<u64>();
+            }
+
+            let yaku = x.yaku.iter().filter(|&&(_, han)| han > 0).copied().chain(x.yakuman.iter().map(|&y| (y, 13)));
+            for (y, han) in yaku {
+                let count = stats.yaku.entry(y.to_u8()).or_default();
+                count.wins += 1;
+                count.han += han as u32;
+                match y {
+                    Yaku::Dora => stats.dora += han as u32,
+                    Yaku::AkaDora => stats.aka_dora += han as u32,
+                    Yaku::UraDora => stats.ura_dora += han as u32,
+                    _ => {}
+                }
+            }
+        }
+        stats
+    }
+
+    /// Average han of the wins other than yakuman.
+    pub fn average_han(&self) -> f64 {
+        self.han as f64 / (self.wins - self.yakuman_wins).max(1) as f64
+    }
+
+    /// Average dora of all kinds per win.
+    pub fn average_dora(&self) -> f64 {
+        (self.dora + self.aka_dora + self.ura_dora) as f64 / self.wins.max(1) as f64
+    }
+
+    /// Fraction of the wins with the yaku.
+    pub fn yaku_rate(&self, yaku: Yaku) -> f64 {
+        let wins = self.yaku.get(&yaku.to_u8()).map_or(0, |x| x.wins);
+        wins as f64 / self.wins.max(1) as f64
+    }
+}
+
+/// Name of the yaku ID of mjlog in tenhou JSON, with the ID for unknown yaku.
+pub fn yaku_name(id: u8) -> String {
+    conv_yaku(Yaku::from_u8(id).unwrap_or(Yaku::Unknown(id))).to_string()
+}
+
+/// The yaku table of `YakuStats` as CSV, most frequent first.
+pub fn export_yaku_stats_csv(stats: &YakuStats) -> String {
+    let mut rows: Vec<(&u8, &YakuCount)> = stats.yaku.iter().collect();
+    rows.sort_by_key(|&(&id, x)| (std::cmp::Reverse(x.wins), id));
+
+    let mut csv = String::from("id,name,wins,rate,average_han\n");
+    for (&id, x) in rows {
+        csv += &format!("{},{},{},{:.4},{:.2}\n", id, escape_csv_field(&yaku_name(id)), x.wins, x.wins as f64 / stats.wins.max(1) as f64, x.han as f64 / x.wins as f64);
+    }
+    csv
+}
+
+/// Counts of a player over the games of the corpus, for the rates and averages.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerStats {
+    pub name: String,
+    pub games: u32,
+    pub rounds: u32,
+    /// Wins. Multiple ron counts once for every winner.
+    pub wins: u32,
+    /// Rounds lost by dealing in.
+    pub deal_ins: u32,
+    /// Riichi declarations.
+    pub riichi: u32,
+    /// Rounds with at least one call (chii, pon or open kan).
+    pub called_rounds: u32,
+    /// Sum of the points of the wins.
+    pub win_points: u64,
+    /// Sum of the points of the wins dealt in.
+    pub deal_in_points: u64,
+    /// Games with owari, which have a placement.
+    pub placed_games: u32,
+    /// Sum of the placements (1-origin).
+    pub placement_sum: u32,
+}
+
+fn ratio(a: u64, b: u32) -> f64 {
+    if b == 0 {
+        0.0
+    } else {
+        a as f64 / b as f64
+    }
+}
+
+impl PlayerStats {
+    pub fn win_rate(&self) -> f64 {
+        ratio(self.wins as u64, self.rounds)
+    }
+
+    pub fn deal_in_rate(&self) -> f64 {
+        ratio(self.deal_ins as u64, self.rounds)
+    }
+
+    pub fn riichi_rate(&self) -> f64 {
+        ratio(self.riichi as u64, self.rounds)
+    }
+
+    pub fn call_rate(&self) -> f64 {
+        ratio(self.called_rounds as u64, self.rounds)
+    }
+
+    pub fn average_win_points(&self) -> f64 {
+        ratio(self.win_points, self.wins)
+    }
+
+    pub fn average_deal_in_points(&self) -> f64 {
+        ratio(self.deal_in_points, self.deal_ins)
+    }
+
+    pub fn average_placement(&self) -> f64 {
+        ratio(self.placement_sum as u64, self.placed_games)
+    }
+}
+
+/// Statistics of every player in the corpus by name, in the order of names. Seats without a name are left out.
+pub fn player_stats(corpus: &Corpus) -> Vec<PlayerStats> {
+    let mut players: BTreeMap<&str, PlayerStats> = BTreeMap::new();
+    for game in 0..corpus.num_games() {
+        let num_players = if corpus.game_settings[game].sanma { 3 } else { 4 };
+        // The corpus keeps the points in units of 1, the placement only needs their order.
+        let final_points = &corpus.game_final_points[game];
+        let final_points: Option<Vec<GamePoint>> = (final_points.len() >= num_players).then(|| final_points[..num_players].iter().map(|&x| (x / 100) as GamePoint).collect());
+
+        for seat in 0..num_players {
+            let name = corpus.game_name(game, seat);
+            if name.is_empty() {
+                continue;
+            }
+            let x = players.entry(name).or_insert_with(|| PlayerStats {
+                name: name.to_string(),
+                ..PlayerStats::default()
+            });
+            x.games += 1;
+            if let Some(final_points) = &final_points {
+                x.placed_games += 1;
+                x.placement_sum += final_placement(final_points, seat);
+            }
+
+            for round in corpus.game_rounds(game) {
+                x.rounds += 1;
+                let mut called = false;
+                for i in corpus.round_events(round) {
+                    let (who, from) = (corpus.event_who[i] as usize, corpus.event_from[i] as usize);
+                    match corpus.event_kind[i] {
+                        EventKind::Agari if who == seat => {
+                            x.wins += 1;
+                            x.win_points += corpus.event_value[i] as u64;
+                        }
+                        EventKind::Agari if from == seat => {
+                            x.deal_ins += 1;
+                            x.deal_in_points += corpus.event_value[i] as u64;
+                        }
+                        EventKind::Riichi if who == seat => x.riichi += 1,
+                        EventKind::Meld if who == seat && corpus.event_open_meld[i] => called = true,
+                        _ => {}
+                    }
+                }
+                x.called_rounds += called as u32;
+            }
+        }
+    }
+    players.into_values().collect()
+}
+
+/// CSV of the player statistics, one row per player.
+pub fn export_player_stats_csv(players: &[PlayerStats]) -> String {
+    let mut csv = String::from("name,games,rounds,win_rate,deal_in_rate,riichi_rate,call_rate,average_win_points,average_deal_in_points,average_placement\n");
+    for x in players {
+        csv += &format!(
+            "{},{},{},{:.4},{:.4},{:.4},{:.4},{:.1},{:.1},{:.3}\n",
+            escape_csv_field(&x.name),
+            x.games,
+            x.rounds,
+            x.win_rate(),
+            x.deal_in_rate(),
+            x.riichi_rate(),
+            x.call_rate(),
+            x.average_win_points(),
+            x.average_deal_in_points(),
+            x.average_placement()
+        );
+    }
+    csv
+}
+
+/// A ron and the discard that dealt in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DealIn {
+    pub game: usize,
+    pub round: usize,
+    /// Event row of the AGARI.
+    pub event: usize,
+    pub winner: u8,
+    pub discarder: u8,
+    /// The winning tile.
+    pub hai: u8,
+    /// Discards of the discarder in the round before the one that dealt in.
+    pub turn: u8,
+    /// Robbed from an added kan instead of a discard.
+    pub chankan: bool,
+    /// The discarder was in riichi. A ron on the riichi declaration tile is not in riichi.
+    pub discarder_riichi: bool,
+    /// Opponents of the discarder in riichi at the time of the discard.
+    pub opponents_riichi: u8,
+    /// Points of the win.
+    pub points: i32,
+}
+
+impl DealIn {
+    /// Dealt in while not in riichi against an opponent's riichi.
+    pub fn pushed_against_riichi(&self) -> bool {
+        !self.discarder_riichi && self.opponents_riichi > 0
+    }
+}
+
+/// Deal-ins of the corpus with the discards they could have been, for defense metrics.
+#[derive(Debug, Clone, Default)]
+pub struct DealInStats {
+    pub deal_ins: Vec<DealIn>,
+    /// Discards by players not in riichi while an opponent was in riichi.
+    pub discards_against_riichi: u32,
+}
+
+impl DealInStats {
+    pub fn from_corpus(corpus: &Corpus) -> Self {
+        let mut stats = DealInStats::default();
+        for round in 0..corpus.num_rounds() {
+            // Riichi is in effect after the declaration discard passes.
+            let mut declared = [false; 4];
+            let mut riichi = [false; 4];
+            // (discarder, turn, discarder in riichi, opponents in riichi, chankan) of the last discard or kan.
+            let mut last = None;
+
+            for i in corpus.round_events(round) {
+                let who = corpus.event_who[i] as usize % 4;
+                let opponents = (0..4).filter(|&x| x != who && riichi[x]).count() as u8;
+                match corpus.event_kind[i] {
+                    EventKind::Riichi => declared[who] = true,
+                    EventKind::Discard => {
+                        last = Some((who, corpus.event_turn[i], riichi[who], opponents, false));
+                        if !riichi[who] && opponents > 0 {
+                            stats.discards_against_riichi += 1;
+                        }
+                        riichi[who] |= declared[who];
+                    }
+                    EventKind::Meld => last = Some((who, corpus.event_turn[i], riichi[who], opponents, true)),
+                    EventKind::Agari if corpus.event_from[i] as usize != who => {
+                        let Some((discarder, turn, discarder_riichi, opponents_riichi, chankan)) = last else {
+                            continue;
+                        };
+                        stats.deal_ins.push(DealIn {
+                            game: corpus.round_game[round] as usize,
+                            round,
+                            event: i,
+                            winner: who as u8,
+                            discarder: discarder as u8,
+                            hai: corpus.event_hai[i],
+                            turn,
+                            chankan,
+                            discarder_riichi,
+                            opponents_riichi,
+                            points: corpus.event_value[i],
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+        stats
+    }
+
+    /// Fraction of the discards against riichi that dealt in.
+    pub fn deal_in_rate_against_riichi(&self) -> f64 {
+        let pushed = self.deal_ins.iter().filter(|x| x.pushed_against_riichi() && !x.chankan).count();
+        ratio(pushed as u64, self.discards_against_riichi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tile_kind() {
+        assert_eq!(tile_kind_name(0), "1m");
+        assert_eq!(tile_kind_name(13), "5p");
+        assert_eq!(tile_kind_name(26), "9s");
+        assert_eq!(tile_kind_name(33), "7z");
+        assert!(is_yaochuu_kind(0));
+        assert!(is_yaochuu_kind(17));
+        assert!(is_yaochuu_kind(27));
+        assert!(!is_yaochuu_kind(1));
+        assert!(!is_yaochuu_kind(25));
+    }
+
+    #[test]
+    fn test_han_stats() {
+        let mjlogs: Vec<_> = (0..8).map(crate::synth::synthesize_mjlog).collect();
+        let corpus = Corpus::from_mjlogs(&mjlogs);
+        let han = |normalization| HanStats::from_corpus(&corpus, &normalization);
+        let total = |x: &HanStats| x.wins_by_han.iter().enumerate().map(|(han, &n)| han as u32 * n).sum::<u32>();
+
+        let all = han(Normalization::default());
+        assert_eq!(all.wins() as usize, corpus.count_events(EventKind::Agari));
+        assert_eq!(all.games_by_rule.values().sum::<u32>(), 8);
+
+        let no_aka = han(Normalization {
+            exclude_aka_dora: true,
+            ..Normalization::default()
+        });
+        assert_eq!(no_aka.wins(), all.wins());
+        assert_eq!(total(&all) - total(&no_aka), corpus.event_aka_dora.iter().map(|&x| x as u32).sum::<u32>());
+
+        let no_kuitan = han(Normalization {
+            exclude_kuitan: true,
+            ..Normalization::default()
+        });
+        let kuitan = corpus.event_kuitan.iter().filter(|&&x| x).count() as u32;
+        assert!(kuitan > 0 && total(&all) > total(&no_aka));
+        assert_eq!((no_kuitan.wins(), no_kuitan.excluded_wins), (all.wins() - kuitan, kuitan));
+    }
+
+    #[test]
+    fn test_yaku_stats() {
+        let mjlogs: Vec<_> = (0..8).map(crate::synth::synthesize_mjlog).collect();
+        let corpus = Corpus::from_mjlogs(&mjlogs);
+        let stats = YakuStats::from_mjlogs(&mjlogs);
+        assert_eq!(stats.wins as usize, corpus.count_events(EventKind::Agari));
+        assert_eq!(stats.aka_dora, corpus.event_aka_dora.iter().map(|&x| x as u32).sum::<u32>());
+
+        // Generated closed wins have riichi.
+        let riichi = stats.yaku.get(&Yaku::Riichi.to_u8()).map_or(0, |x| x.wins);
+        assert!(riichi > 0 && stats.yaku_rate(Yaku::Riichi) <= 1.0);
+        assert!(stats.average_han() >= 1.0 && stats.average_dora() >= 0.0);
+        assert_eq!(export_yaku_stats_csv(&stats).lines().count(), stats.yaku.len() + 1);
+    }
+
+    #[test]
+    fn test_player_stats() {
+        let mjlogs: Vec<_> = (0..8).map(crate::synth::synthesize_mjlog).collect();
+        let corpus = Corpus::from_mjlogs(&mjlogs);
+        let players = player_stats(&corpus);
+        let sum = |f: fn(&PlayerStats) -> u32| players.iter().map(f).sum::<u32>();
+        let seats: u32 = corpus.game_settings.iter().map(|x| if x.sanma { 3 } else { 4 }).sum();
+        let rons = (0..corpus.num_events()).filter(|&i| corpus.event_kind[i] == EventKind::Agari && corpus.event_who[i] != corpus.event_from[i]).count();
+
+        assert_eq!(sum(|x| x.games), seats);
+        assert_eq!(sum(|x| x.wins) as usize, corpus.count_events(EventKind::Agari));
+        assert_eq!(sum(|x| x.deal_ins) as usize, rons);
+        assert_eq!(sum(|x| x.riichi) as usize, corpus.count_events(EventKind::Riichi));
+        assert!(players.iter().all(|x| (1.0..=4.0).contains(&x.average_placement()) && x.call_rate() <= 1.0));
+        assert!(export_player_stats_csv(&players).lines().count() == players.len() + 1);
+    }
+
+    #[test]
+    fn test_deal_in_stats() {
+        let mjlogs: Vec<_> = (0..8).map(crate::synth::synthesize_mjlog).collect();
+        let corpus = Corpus::from_mjlogs(&mjlogs);
+        let stats = DealInStats::from_corpus(&corpus);
+        let rons = (0..corpus.num_events()).filter(|&i| corpus.event_kind[i] == EventKind::Agari && corpus.event_who[i] != corpus.event_from[i]).count();
+        assert_eq!(stats.deal_ins.len(), rons);
+
+        for x in &stats.deal_ins {
+            assert_eq!(x.discarder, corpus.event_from[x.event]);
+            let riichi_before = |seat: u8| corpus.round_events(x.round).take_while(|&i| i < x.event).any(|i| corpus.event_kind[i] == EventKind::Riichi && corpus.event_who[i] == seat);
+            assert!(!x.discarder_riichi || riichi_before(x.discarder));
+        }
+        assert!(stats.deal_ins.iter().any(|x| x.opponents_riichi > 0) && stats.discards_against_riichi > 0);
+        assert!((0.0..=1.0).contains(&stats.deal_in_rate_against_riichi()));
+    }
+}
@@ -0,0 +1,92 @@
+//! # synth
+//!
+//! Synthetic games for property tests of the parser, the exporters and the conversion.
+//!
+//! The games are made by `mjlog::generator`, with the rules and the number of rounds also picked from the seed,
+//! so that the tests cover three-player games, no red fives and no kuitan as well.
+//!
+//! ```ignore
+//! let mjlog = mjlog2json_core::synth::synthesize_mjlog(seed);
+//! ```
+
+use mjlog::generator::{generate_mjlog, GeneratorOptions};
+use mjlog::model::*;
+
+// splitmix64, only to pick the options. The generator has its own generator from the same seed.
+fn mix(seed: u64, i: u64) -> u64 {
+    let mut z = seed.wrapping_add(i.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A random game from the seed, with random rules. The same seed always gives the same game.
+pub fn synthesize_mjlog(seed: u64) -> Mjlog {
+    let mut i = 0;
+    let mut below = |n: u64| {
+        i += 1;
+        (mix(seed, i) % n) as usize
+    };
+    let settings = GameSettings {
+        vs_human: true,
+        no_red: below(4) == 0,
+        no_kuitan: below(4) == 0,
+        hanchan: below(3) != 0,
+        sanma: below(4) == 0,
+        soku: below(4) == 0,
+        room: [TenhouRoom::Ippan, TenhouRoom::Joukyu, TenhouRoom::Tokujou, TenhouRoom::Houou][below(4)],
+    };
+    let options = GeneratorOptions { settings, rounds: 1 + below(8) };
+    generate_mjlog(seed, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conv::conv_to_tenhou_json;
+    use mjlog::exporter::export_mjlog;
+    use mjlog::parser::parse_mjlogs;
+    use mjlog::replay::GameState;
+    use proptest::prelude::*;
+    use tenhou_json::exporter::export_tenhou_json;
+    use tenhou_json::parser::parse_tenhou_json;
+
+    // XML -> model -> XML, model -> JSON -> model, and the replay of the actions.
+    fn check_round_trips(mjlog: &Mjlog) {
+        let xml = export_mjlog(mjlog);
+        let parsed = parse_mjlogs(&xml).unwrap().remove(0);
+        assert_eq!(&parsed, mjlog);
+        assert_eq!(export_mjlog(&parsed), xml);
+
+        let json = conv_to_tenhou_json(mjlog).unwrap();
+        assert_eq!(parse_tenhou_json(&export_tenhou_json(&json).unwrap()).unwrap(), json);
+
+        assert!(!GameState::from_actions(&mjlog.actions).unwrap().in_round);
+    }
+
+    #[test]
+    fn test_multiple_ron_owari() {
+        // The game ends by a double ron, where the owari is in the second AGARI only.
+        let xml = r#"<mjloggm ver="2.3"><GO type="169" lobby="0"/><UN n0="a" n1="b" n2="c" n3="d" dan="0,0,0,0" rate="1500.00,1500.00,1500.00,1500.00" sx="M,M,M,M"/><TAIKYOKU oya="0"/><INIT seed="0,1,0,4,1,30" ten="280,240,240,240" oya="0" hai0="0,1,2,28,29,32,108,112,116,120,124,128,132" hai1="36,56,60,64,68,72,76,80,84,88,104,109,113" hai2="4,8,12,17,20,24,40,41,44,48,53,92,96" hai3="5,9,13,21,25,33,37,45,49,57,61,65,73"/><T77/><D132/><U81/><E113/><V117/><F117/><W100/><G100/><AGARI ba="1,0" hai="4,8,12,17,20,24,40,41,44,48,53,92,96,100" machi="100" ten="30,2000,0" yaku="7,1,8,1" doraHai="30" who="2" fromWho="3" sc="280,0,240,0,240,23,240,-23"/><AGARI ba="0,0" hai="0,1,2,28,29,32,77,100,108,112,116,120,124,128" machi="100" ten="30,2000,0" yaku="7,1,8,1" doraHai="30" who="0" fromWho="3" sc="280,20,240,0,263,0,217,-20" owari="300,40.0,240,-16.0,263,6.0,197,-30.0"/></mjloggm>"#;
+        let mjlog = parse_mjlogs(xml).unwrap().remove(0);
+        let agari: Vec<_> = mjlog.actions.iter().filter_map(|x| x.as_agari()).collect();
+        assert_eq!(agari.len(), 2);
+        assert_eq!(agari[0].from_who, agari[1].from_who);
+        assert!(agari[0].owari.is_none() && agari[1].owari.is_some());
+        check_round_trips(&mjlog);
+
+        let json = conv_to_tenhou_json(&mjlog).unwrap();
+        assert_eq!(json.final_points, [30000, 24000, 26300, 19700]);
+        assert_eq!(json.final_results, [40.0, -16.0, 6.0, -30.0]);
+    }
+
+    proptest! {
+        // Games are slow to generate in debug builds, so the cases are fewer than the default.
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn round_trips(seed: u64) {
+            check_round_trips(&synthesize_mjlog(seed));
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! # table_parquet
+//!
+//! Writes the round table of `round_arrow` or the action table of `action_arrow` as a Parquet file,
+//! which Polars, pandas and Spark load directly. Rows are buffered into row groups of the default size,
+//! so that small games share a row group.
+//!
+//! # Usage
+//!
+//! ```ignore
+//! let mut writer = TableParquetWriter::new(File::create("actions.parquet")?, ParquetTable::Actions)?;
+//! for (reference, mjlog) in &games {
+//!     writer.write(&actions_record_batch(reference, mjlog)?)?;
+//! }
+//! writer.finish()?;
+//! ```
+
+use crate::action_arrow::actions_schema;
+use crate::round_arrow::rounds_schema;
+use arrow_array::RecordBatch;
+use arrow_schema::Schema;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Table of the Parquet file.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ParquetTable {
+    /// One row per winner or draw, same as `round_arrow`.
+    #[default]
+    Rounds,
+    /// One row per action, see `action_arrow`.
+    Actions,
+}
+
+impl ParquetTable {
+    pub fn schema(&self) -> Schema {
+        match self {
+            ParquetTable::Rounds => rounds_schema(),
+            ParquetTable::Actions => actions_schema(),
+        }
+    }
+}
+
+impl std::str::FromStr for ParquetTable {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rounds" => Ok(ParquetTable::Rounds),
+            "actions" => Ok(ParquetTable::Actions),
+            _ => Err(format!("unknown table: {} (expected rounds or actions)", s)),
+        }
+    }
+}
+
+/// Writes record batches of a table to a Parquet file, compressed by Snappy.
+pub struct TableParquetWriter<W: Write + Send> {
+    writer: ArrowWriter<W>,
+}
+
+impl<W: Write + Send> TableParquetWriter<W> {
+    pub fn new(writer: W, table: ParquetTable) -> Result<Self, ParquetError> {
+        let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+        Ok(Self {
+            writer: ArrowWriter::try_new(writer, Arc::new(table.schema()), Some(properties))?,
+        })
+    }
+
+    /// Writes the record batch, which must have the schema of the table.
+    pub fn write(&mut self, batch: &RecordBatch) -> Result<(), ParquetError> {
+        self.writer.write(batch)
+    }
+
+    /// Writes the buffered rows and the footer. The file is not readable without it.
+    pub fn finish(self) -> Result<(), ParquetError> {
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action_arrow::actions_record_batch;
+    use crate::conv::conv_to_tenhou_json;
+    use crate::round_arrow::rounds_record_batch;
+    use crate::synth::synthesize_mjlog;
+    use arrow_array::cast::AsArray;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::fs::File;
+
+    // Writes the batches to a file and reads them back.
+    fn round_trip(table: ParquetTable, batches: &[RecordBatch]) -> Vec<RecordBatch> {
+        let path = std::env::temp_dir().join(format!("mjlog2json-test-{}-{:?}.parquet", std::process::id(), table));
+        let mut writer = TableParquetWriter::new(File::create(&path).unwrap(), table).unwrap();
+        for batch in batches {
+            writer.write(batch).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap().build().unwrap();
+        let read = reader.map(|x| x.unwrap()).collect();
+        std::fs::remove_file(&path).unwrap();
+        read
+    }
+
+    #[test]
+    fn test_parquet_tables() {
+        let mjlogs: Vec<_> = (0..3).map(synthesize_mjlog).collect();
+
+        let actions: Vec<RecordBatch> = mjlogs.iter().enumerate().map(|(i, x)| actions_record_batch(&format!("game{}", i), x).unwrap()).collect();
+        let read = round_trip(ParquetTable::Actions, &actions);
+        let rows = |x: &[RecordBatch]| x.iter().map(|x| x.num_rows()).sum::<usize>();
+        assert_eq!(rows(&read), rows(&actions));
+        let discards = mjlogs.iter().flat_map(|x| &x.actions).filter(|x| x.is_discard()).count();
+        assert_eq!(read.iter().flat_map(|x| x.column_by_name("kind").unwrap().as_string::<i32>().iter()).filter(|x| *x == Some("discard")).count(), discards);
+
+        let rounds: Vec<RecordBatch> = mjlogs.iter().map(|x| rounds_record_batch(&conv_to_tenhou_json(x).unwrap()).unwrap()).collect();
+        let read = round_trip(ParquetTable::Rounds, &rounds);
+        assert_eq!(rows(&read), mjlogs.iter().flat_map(|x| &x.actions).filter(|x| x.is_agari() || x.is_ryuukyoku()).count());
+    }
+}
@@ -0,0 +1,456 @@
+//! # transcript
+//!
+//! Human-readable game record generated from tenhou-JSON, for sharing reviews in plain messages:
+//! a summary of each round, or a narrative of every turn.
+
+use crate::number_format::*;
+use crate::turn_order::*;
+use std::io::{self, Write};
+use tenhou_json::exporter::{ExportOptions, Locale};
+use tenhou_json::model::*;
+use tenhou_json::score::*;
+
+const WIND_JA: [&str; 4] = ["東", "南", "西", "北"];
+const WIND_EN: [&str; 4] = ["East", "South", "West", "North"];
+const HONOR_JA: [&str; 7] = ["東", "南", "西", "北", "白", "發", "中"];
+const HONOR_EN: [&str; 7] = ["East", "South", "West", "North", "White", "Green", "Red"];
+const SUIT_JA: [&str; 3] = ["萬", "筒", "索"];
+const SUIT_EN: [&str; 3] = ["m", "p", "s"];
+
+const YAKU_NAME_EN: [&str; 55] = [
+    "Menzen Tsumo",
+    "Riichi",
+    "Ippatsu",
+    "Chankan",
+    "Rinshan Kaihou",
+    "Haitei Raoyue",
+    "Houtei Raoyui",
+    "Pinfu",
+    "Tanyao",
+    "Iipeikou",
+    "Seat Wind East",
+    "Seat Wind South",
+    "Seat Wind West",
+    "Seat Wind North",
+    "Round Wind East",
+    "Round Wind South",
+    "Round Wind West",
+    "Round Wind North",
+    "Haku",
+    "Hatsu",
+    "Chun",
+    "Double Riichi",
+    "Chiitoitsu",
+    "Chanta",
+    "Ittsu",
+    "Sanshoku Doujun",
+    "Sanshoku Doukou",
+    "Sankantsu",
+    "Toitoi",
+    "Sanankou",
+    "Shousangen",
+    "Honroutou",
+    "Ryanpeikou",
+    "Junchan",
+    "Honitsu",
+    "Chinitsu",
+    "Renhou",
+    "Tenhou",
+    "Chiihou",
+    "Daisangen",
+    "Suuankou",
+    "Suuankou Tanki",
+    "Tsuuiisou",
+    "Ryuuiisou",
+    "Chinroutou",
+    "Chuuren Poutou",
+    "Junsei Chuuren Poutou",
+    "Kokushi Musou",
+    "Kokushi Musou 13-sided",
+    "Daisuushii",
+    "Shousuushii",
+    "Suukantsu",
+    "Dora",
+    "Ura Dora",
+    "Aka Dora",
+];
+
+/// "東1局 0本場" / "East 1, 0 honba"
+pub(crate) fn round_label(settings: &RoundSettings, locale: Locale) -> String {
+    let wind = settings.kyoku as usize / 4 % 4;
+    let number = settings.kyoku % 4 + 1;
+    match locale {
+        Locale::Japanese => format!("{}{}局 {}本場", WIND_JA[wind], number, settings.honba),
+        Locale::English => format!("{} {}, {} honba", WIND_EN[wind], number, settings.honba),
+    }
+}
+
+fn round_name(settings: &RoundSettings, locale: Locale) -> String {
+    match locale {
+        Locale::Japanese => format!("{} 供託{}", round_label(settings, locale), settings.kyoutaku),
+        Locale::English => format!("{}, {} riichi stick(s)", round_label(settings, locale), settings.kyoutaku),
+    }
+}
+
+// Seat wind of the player in the round, followed by the name.
+pub(crate) fn player_name(names: &[String], kyoku: u8, who: usize, locale: Locale) -> String {
+    let wind = (who + 4 - kyoku as usize % 4) % 4;
+    let name = names.get(who).map_or("", |x| x.as_str());
+    match locale {
+        Locale::Japanese => format!("{}家 {}", WIND_JA[wind], name),
+        Locale::English => format!("{} {}", WIND_EN[wind], name),
+    }
+}
+
+fn yaku_name_en(x: Yaku) -> String {
+    match x {
+        Yaku::Unknown(id) => format!("Unknown yaku {}", id),
+        _ => YAKU_NAME_EN[x.index().unwrap()].to_string(),
+    }
+}
+
+pub(crate) fn yaku_text(x: &YakuPair, locale: Locale) -> String {
+    match locale {
+        Locale::Japanese => x.to_string(),
+        Locale::English => match x.level {
+            YakuLevel::Normal(han) => format!("{} {} han", yaku_name_en(x.yaku), han),
+            YakuLevel::Yakuman(_) => format!("{} yakuman", yaku_name_en(x.yaku)),
+        },
+    }
+}
+
+pub(crate) fn score_text(x: &RankedScore, locale: Locale, grouping: DigitGrouping) -> String {
+    match locale {
+        // Same as the string of tenhou-JSON, e.g. "30符1飜1000点".
+        Locale::Japanese => x.to_string(),
+        Locale::English => {
+            let rank = match x.rank {
+                ScoreRank::Normal { fu, han } => format!("{} fu {} han", fu, han),
+                ScoreRank::Mangan => "Mangan".to_string(),
+                ScoreRank::Haneman => "Haneman".to_string(),
+                ScoreRank::Baiman => "Baiman".to_string(),
+                ScoreRank::Sanbaiman => "Sanbaiman".to_string(),
+                ScoreRank::Yakuman => "Yakuman".to_string(),
+            };
+            let score = match x.score {
+                Score::OyaTsumo(all) => format!("{} all", format_number(all.into(), grouping)),
+                Score::KoTsumo(ko, oya) => format!("{}-{}", format_number(ko.into(), grouping), format_number(oya.into(), grouping)),
+                Score::Ron(x) => format_number(x.into(), grouping),
+            };
+            format!("{} {}", rank, score)
+        }
+    }
+}
+
+pub(crate) fn ryuukyoku_text(x: &ExtraRyuukyokuReason, locale: Locale) -> &str {
+    match locale {
+        Locale::Japanese => x.to_str(),
+        Locale::English => match x {
+            ExtraRyuukyokuReason::Ryuukyoku => "Exhaustive draw",
+            ExtraRyuukyokuReason::KyuusyuKyuuhai => "Nine terminals",
+            ExtraRyuukyokuReason::SuuchaRiichi => "Four riichi",
+            ExtraRyuukyokuReason::SanchaHoura => "Triple ron",
+            ExtraRyuukyokuReason::SuukanSanra => "Four kans",
+            ExtraRyuukyokuReason::SuufuuRenda => "Four winds",
+            ExtraRyuukyokuReason::NagashiMangan => "Nagashi mangan",
+            ExtraRyuukyokuReason::TenpaiEverybody => "All tenpai",
+            ExtraRyuukyokuReason::TenpaiNobody => "All noten",
+            ExtraRyuukyokuReason::Chuudan => "Aborted",
+        },
+    }
+}
+
+// Riichi and calls of the player, with the 1-origin turn.
+fn player_events(player: &RoundPlayer, locale: Locale) -> Vec<String> {
+    let (riichi, chii, pon, kan) = match locale {
+        Locale::Japanese => ("リーチ", "チー", "ポン", "カン"),
+        Locale::English => ("riichi", "chii", "pon", "kan"),
+    };
+    let turn = |i: usize| match locale {
+        Locale::Japanese => format!("{}巡目", i + 1),
+        Locale::English => format!("turn {}", i + 1),
+    };
+
+    let mut events = vec![];
+    for i in 0..player.incoming.len().max(player.outgoing.len()) {
+        match player.incoming.get(i) {
+            Some(IncomingTile::Chii { .. }) => events.push(format!("{} {}", turn(i), chii)),
+            Some(IncomingTile::Pon { .. }) => events.push(format!("{} {}", turn(i), pon)),
+            Some(IncomingTile::Daiminkan { .. }) => events.push(format!("{} {}", turn(i), kan)),
+            _ => {}
+        }
+        match player.outgoing.get(i) {
+            Some(OutgoingTile::Riichi(_) | OutgoingTile::TsumogiriRiichi) => events.push(format!("{} {}", turn(i), riichi)),
+            Some(OutgoingTile::Ankan(_) | OutgoingTile::Kakan { .. }) => events.push(format!("{} {}", turn(i), kan)),
+            _ => {}
+        }
+    }
+    events
+}
+
+fn format_deltas(deltas: &[GamePoint], grouping: DigitGrouping) -> String {
+    deltas.iter().map(|&x| format_signed(x, grouping)).collect::<Vec<_>>().join(" / ")
+}
+
+fn write_result(out: &mut impl Write, round: &Round, names: &[String], locale: Locale, grouping: DigitGrouping) -> io::Result<()> {
+    let kyoku = round.settings.kyoku;
+    let (result_label, delta_label) = match locale {
+        Locale::Japanese => ("結果", "収支"),
+        Locale::English => ("Result", "Delta"),
+    };
+    match &round.result {
+        RoundResult::Agari { agari_vec } => {
+            for agari in agari_vec {
+                let winner = player_name(names, kyoku, agari.who as usize, locale);
+                let how = match (agari.who == agari.from_who, locale) {
+                    (true, Locale::Japanese) => "ツモ".to_string(),
+                    (true, Locale::English) => "tsumo".to_string(),
+                    (false, Locale::Japanese) => format!("ロン ({}から)", player_name(names, kyoku, agari.from_who as usize, locale)),
+                    (false, Locale::English) => format!("ron from {}", player_name(names, kyoku, agari.from_who as usize, locale)),
+                };
+                let yaku: Vec<String> = agari.yaku.iter().map(|x| yaku_text(x, locale)).collect();
+                writeln!(out, "  {}: {} {} {} [{}]", result_label, winner, how, score_text(&agari.ranked_score, locale, grouping), yaku.join(", "))?;
+                writeln!(out, "  {}: {}", delta_label, format_deltas(&agari.delta_points, grouping))?;
+            }
+        }
+        RoundResult::Ryuukyoku { reason, delta_points } => {
+            writeln!(out, "  {}: {}", result_label, ryuukyoku_text(reason, locale))?;
+            if !delta_points.is_empty() {
+                writeln!(out, "  {}: {}", delta_label, format_deltas(delta_points, grouping))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_round(out: &mut impl Write, round: &Round, names: &[String], locale: Locale, grouping: DigitGrouping) -> io::Result<()> {
+    let kyoku = round.settings.kyoku;
+    let points_label = match locale {
+        Locale::Japanese => "持ち点",
+        Locale::English => "Points",
+    };
+
+    writeln!(out, "{}", round_name(&round.settings, locale))?;
+    let points: Vec<String> = round.settings.points.iter().map(|&x| format_number(x, grouping)).collect();
+    writeln!(out, "  {}: {}", points_label, points.join(" / "))?;
+
+    for (who, player) in round.players.iter().enumerate() {
+        let events = player_events(player, locale);
+        if !events.is_empty() {
+            writeln!(out, "  {}: {}", player_name(names, kyoku, who, locale), events.join(", "))?;
+        }
+    }
+
+    write_result(out, round, names, locale, grouping)
+}
+
+// "5m" / "5萬", with red fives as "red 5m" / "赤5萬".
+fn tile_text(tile: Tile, locale: Locale) -> String {
+    let x = tile.to_black().to_u8();
+    let (suit, number) = ((x / 10) as usize, (x % 10) as usize);
+    let text = match (suit, locale) {
+        (4, Locale::Japanese) => HONOR_JA[number - 1].to_string(),
+        (4, Locale::English) => HONOR_EN[number - 1].to_string(),
+        (_, Locale::Japanese) => format!("{}{}", number, SUIT_JA[suit - 1]),
+        (_, Locale::English) => format!("{}{}", number, SUIT_EN[suit - 1]),
+    };
+    match (tile.is_red(), locale) {
+        (false, _) => text,
+        (true, Locale::Japanese) => format!("赤{}", text),
+        (true, Locale::English) => format!("red {}", text),
+    }
+}
+
+fn tiles_text(tiles: &[Tile], locale: Locale) -> String {
+    let separator = match locale {
+        Locale::Japanese => "",
+        Locale::English => " ",
+    };
+    tiles.iter().map(|&x| tile_text(x, locale)).collect::<Vec<_>>().join(separator)
+}
+
+fn step_text(step: &Step, locale: Locale) -> String {
+    match (step, locale) {
+        (Step::Draw { tile, .. }, Locale::Japanese) => format!("{}ツモ", tile_text(*tile, locale)),
+        (Step::Draw { tile, .. }, Locale::English) => format!("draws {}", tile_text(*tile, locale)),
+        (Step::Discard { tile, riichi: false, .. }, Locale::Japanese) => format!("{}切り", tile_text(*tile, locale)),
+        (Step::Discard { tile, riichi: true, .. }, Locale::Japanese) => format!("リーチ {}切り", tile_text(*tile, locale)),
+        (Step::Discard { tile, riichi: false, .. }, Locale::English) => format!("discards {}", tile_text(*tile, locale)),
+        (Step::Discard { tile, riichi: true, .. }, Locale::English) => format!("declares riichi, discards {}", tile_text(*tile, locale)),
+        (Step::Call { tiles, .. }, _) => {
+            let call = match (tiles.len(), tiles.iter().all(|x| x.to_black() == tiles[0].to_black()), locale) {
+                (4, _, Locale::Japanese) => "カン",
+                (4, _, Locale::English) => "kan",
+                (_, true, Locale::Japanese) => "ポン",
+                (_, true, Locale::English) => "pon",
+                (_, false, Locale::Japanese) => "チー",
+                (_, false, Locale::English) => "chii",
+            };
+            match locale {
+                Locale::Japanese => format!("{} {}", call, tiles_text(tiles, locale)),
+                Locale::English => format!("calls {} {}", call, tiles_text(tiles, locale)),
+            }
+        }
+        (Step::Ankan { tiles, .. }, Locale::Japanese) => format!("暗槓 {}", tiles_text(tiles, locale)),
+        (Step::Ankan { tiles, .. }, Locale::English) => format!("declares closed kan {}", tiles_text(tiles, locale)),
+        (Step::Kakan { added, .. }, Locale::Japanese) => format!("加槓 {}", tile_text(*added, locale)),
+        (Step::Kakan { added, .. }, Locale::English) => format!("declares added kan {}", tile_text(*added, locale)),
+    }
+}
+
+// One line per turn, e.g. "East A: draws 5m, discards 9p". The draw after a kan stays in the turn of the kan.
+fn turn_lines(round: &Round, names: &[String], locale: Locale) -> Vec<String> {
+    let mut turns: Vec<(usize, Vec<String>)> = vec![];
+    let mut after_kan = false;
+    for step in turn_order(round) {
+        let (who, new_turn) = match step {
+            Step::Draw { who, .. } => (who, !after_kan),
+            Step::Call { who, .. } => (who, true),
+            Step::Discard { who, .. } | Step::Ankan { who, .. } | Step::Kakan { who, .. } => (who, false),
+        };
+        after_kan = matches!(&step, Step::Ankan { .. } | Step::Kakan { .. }) || matches!(&step, Step::Call { tiles, .. } if tiles.len() == 4);
+        match turns.last_mut() {
+            Some((last, texts)) if *last == who && !new_turn => texts.push(step_text(&step, locale)),
+            _ => turns.push((who, vec![step_text(&step, locale)])),
+        }
+    }
+    let separator = match locale {
+        Locale::Japanese => "、",
+        Locale::English => ", ",
+    };
+    turns.into_iter().map(|(who, texts)| format!("{}: {}", player_name(names, round.settings.kyoku, who, locale), texts.join(separator))).collect()
+}
+
+fn write_narrative_round(out: &mut impl Write, round: &Round, names: &[String], locale: Locale, grouping: DigitGrouping) -> io::Result<()> {
+    let kyoku = round.settings.kyoku;
+    let (points_label, hands_label) = match locale {
+        Locale::Japanese => ("持ち点", "配牌"),
+        Locale::English => ("Points", "Starting hands"),
+    };
+
+    writeln!(out, "{}", round_name(&round.settings, locale))?;
+    let points: Vec<String> = round.settings.points.iter().map(|&x| format_number(x, grouping)).collect();
+    writeln!(out, "  {}: {}", points_label, points.join(" / "))?;
+    writeln!(out, "  {}:", hands_label)?;
+    for (who, player) in round.players.iter().enumerate().filter(|(_, p)| !p.hand.is_empty()) {
+        let mut hand = player.hand.clone();
+        sort_hand_display(&mut hand);
+        writeln!(out, "    {}: {}", player_name(names, kyoku, who, locale), tiles_text(&hand, locale))?;
+    }
+    for line in turn_lines(round, names, locale) {
+        writeln!(out, "  {}", line)?;
+    }
+
+    write_result(out, round, names, locale, grouping)
+}
+
+fn write_final_result(out: &mut impl Write, tenhou_json: &TenhouJson, locale: Locale, grouping: DigitGrouping) -> io::Result<()> {
+    let final_label = match locale {
+        Locale::Japanese => "最終結果",
+        Locale::English => "Final result",
+    };
+    writeln!(out)?;
+    writeln!(out, "{}", final_label)?;
+    for (i, name) in tenhou_json.names.iter().enumerate() {
+        let points = tenhou_json.final_points.get(i).copied().unwrap_or_default();
+        let result = tenhou_json.final_results.get(i).copied().unwrap_or_default();
+        writeln!(out, "  {} {} ({:+.1})", name, format_number(points, grouping), result)?;
+    }
+    Ok(())
+}
+
+fn write_transcript(out: &mut impl Write, tenhou_json: &TenhouJson, locale: Locale, grouping: DigitGrouping) -> io::Result<()> {
+    writeln!(out, "{} {}", tenhou_json.rule.disp, tenhou_json.reference)?;
+    for round in &tenhou_json.rounds {
+        writeln!(out)?;
+        write_round(out, round, &tenhou_json.names, locale, grouping)?;
+    }
+    write_final_result(out, tenhou_json, locale, grouping)
+}
+
+fn write_narrative(out: &mut impl Write, tenhou_json: &TenhouJson, locale: Locale, grouping: DigitGrouping) -> io::Result<()> {
+    writeln!(out, "{} {}", tenhou_json.rule.disp, tenhou_json.reference)?;
+    for round in &tenhou_json.rounds {
+        writeln!(out)?;
+        write_narrative_round(out, round, &tenhou_json.names, locale, grouping)?;
+    }
+    write_final_result(out, tenhou_json, locale, grouping)
+}
+
+/// Exports a readable transcript of the game. Points are grouped by `grouping`.
+pub fn export_transcript(tenhou_json: &TenhouJson, locale: Locale, grouping: DigitGrouping) -> String {
+    let mut out = vec![];
+    export_transcript_to(tenhou_json, locale, grouping, &mut out).expect("writing to a Vec never fails");
+    String::from_utf8(out).expect("exporters write UTF-8 only")
+}
+
+/// Same as `export_transcript`, but writes to the writer.
+pub fn export_transcript_to<W: Write>(tenhou_json: &TenhouJson, locale: Locale, grouping: DigitGrouping, mut writer: W) -> io::Result<()> {
+    write_transcript(&mut writer, tenhou_json, locale, grouping)
+}
+
+/// Exports the game turn by turn: the starting hands, then every draw, discard and call in order,
+/// e.g. "East A: draws 5m, discards 9p", followed by the result of each round.
+/// The language is `options.locale`, and points are grouped by `grouping`.
+pub fn export_narrative(tenhou_json: &TenhouJson, options: &ExportOptions, grouping: DigitGrouping) -> String {
+    let mut out = vec![];
+    export_narrative_to(tenhou_json, options, grouping, &mut out).expect("writing to a Vec never fails");
+    String::from_utf8(out).expect("exporters write UTF-8 only")
+}
+
+/// Same as `export_narrative`, but writes to the writer.
+pub fn export_narrative_to<W: Write>(tenhou_json: &TenhouJson, options: &ExportOptions, grouping: DigitGrouping, mut writer: W) -> io::Result<()> {
+    write_narrative(&mut writer, tenhou_json, options.locale, grouping)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conv::{conv_to_tenhou_json, conv_to_tenhou_json_with, ConvOptions};
+    use mjlog::parser::parse_mjlogs;
+
+    #[test]
+    fn test_export_transcript() {
+        let mjlog = parse_mjlogs(include_str!("../fixtures/observer.xml")).unwrap().remove(0);
+        let tenhou_json = conv_to_tenhou_json(&mjlog).unwrap();
+        assert_eq!(export_transcript(&tenhou_json, Locale::Japanese, DigitGrouping::Comma), include_str!("../fixtures/observer.transcript.txt"));
+    }
+
+    #[test]
+    fn test_export_narrative() {
+        let mjlog = parse_mjlogs(include_str!("../fixtures/observer.xml")).unwrap().remove(0);
+        let ja = ExportOptions::default();
+        assert_eq!(export_narrative(&conv_to_tenhou_json(&mjlog).unwrap(), &ja, DigitGrouping::Comma), include_str!("../fixtures/observer.narrative.txt"));
+
+        // The rule name is also in English when converted so.
+        let tenhou_json = conv_to_tenhou_json_with(&mjlog, &ConvOptions { locale: Locale::English, ..Default::default() }).unwrap();
+        let en = ExportOptions { locale: Locale::English, ..Default::default() };
+        assert_eq!(export_narrative(&tenhou_json, &en, DigitGrouping::Comma), include_str!("../fixtures/observer.narrative.en.txt"));
+    }
+
+    #[test]
+    fn test_turn_lines_kan() {
+        // Dealer 0 makes ankan of 11 and discards the replacement 12, seat 1 calls daiminkan on it and discards the replacement 13.
+        let src = r#"{"ver":2.3,"ref":"","log":[[[0,0,0],[25000,25000,25000,25000],[11],[],
+            [11,11,11,13,14,15,16,17,18,21,22,23,24],[11,12],["111111a11",12],
+            [12,12,12,22,23,24,25,26,27,28,29,47,47],["m12121212",13],[0,13],
+            [31,32,33,34,35,36,37,38,47,47,41,42,43],[14],[],
+            [31,32,33,34,35,36,37,38,39,47,47,41,42],[],[],
+            ["流局",[0,0,0,0]]]],"ratingc":"PF4","rule":{"disp":"","aka53":1,"aka52":1,"aka51":1},"lobby":0,"dan":["","","",""],"rate":[1500,1500,1500,1500],"sx":["M","M","M","M"],"sc":[25000,0,25000,0,25000,0,25000,0],"name":["a","b","c","d"]}"#;
+        let tenhou_json = tenhou_json::parser::parse_tenhou_json(src).unwrap();
+        let round = &tenhou_json.rounds[0];
+        assert_eq!(
+            turn_lines(round, &tenhou_json.names, Locale::Japanese),
+            ["東家 a: 1萬ツモ、暗槓 1萬1萬1萬1萬、2萬ツモ、2萬切り", "南家 b: カン 2萬2萬2萬2萬、3萬ツモ、3萬切り", "西家 c: 4萬ツモ"]
+        );
+        assert_eq!(
+            turn_lines(round, &tenhou_json.names, Locale::English),
+            [
+                "East a: draws 1m, declares closed kan 1m 1m 1m 1m, draws 2m, discards 2m",
+                "South b: calls kan 2m 2m 2m 2m, draws 3m, discards 3m",
+                "West c: draws 4m"
+            ]
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! # turn_order
+//!
+//! Order of the draws, discards and calls of a round in tenhou-JSON.
+//!
+//! tenhou-JSON keeps the draws and discards of each player separately, so the turn order is rebuilt
+//! from the calls: a discard is followed by the call of the player whose next draw is a meld of it.
+
+use tenhou_json::model::*;
+
+/// One step of a round in the turn order. `who` and `from` are seats.
+#[derive(Debug, PartialEq)]
+pub enum Step {
+    Draw {
+        who: usize,
+        tile: Tile,
+    },
+    Discard {
+        who: usize,
+        tile: Tile,
+        riichi: bool,
+    },
+    /// Chii, pon or daiminkan of the last discard of `from`. `tiles` includes the called tile.
+    Call {
+        who: usize,
+        from: usize,
+        tiles: Vec<Tile>,
+        called: Tile,
+    },
+    Ankan {
+        who: usize,
+        tiles: Vec<Tile>,
+    },
+    Kakan {
+        who: usize,
+        added: Tile,
+    },
+}
+
+// Called tile of the meld, by the position of the letter in tenhou-JSON.
+fn called_tile(incoming: &IncomingTile) -> Option<Tile> {
+    match *incoming {
+        IncomingTile::Tsumo(_) => None,
+        IncomingTile::Chii { combination: (t, _, _) } => Some(t),
+        IncomingTile::Pon { combination: (t1, t2, t3), dir } => Some(match dir {
+            Direction::Toimen => t2,
+            Direction::Shimocha => t3,
+            _ => t1,
+        }),
+        IncomingTile::Daiminkan { combination: (t1, t2, _, t4), dir } => Some(match dir {
+            Direction::Toimen => t2,
+            Direction::Shimocha => t4,
+            _ => t1,
+        }),
+    }
+}
+
+// Seat of the player the meld was called from.
+fn called_from(incoming: &IncomingTile, who: usize, num_players: usize) -> Option<usize> {
+    let dir = match *incoming {
+        IncomingTile::Tsumo(_) => return None,
+        IncomingTile::Chii { .. } => Direction::Kamicha,
+        IncomingTile::Pon { dir, .. } | IncomingTile::Daiminkan { dir, .. } => dir,
+    };
+    let offset = match dir {
+        Direction::Kamicha => num_players - 1,
+        Direction::Toimen => 2,
+        Direction::Shimocha => 1,
+        Direction::SelfSeat => return None,
+    };
+    Some((who + offset) % num_players)
+}
+
+fn meld_tiles(incoming: &IncomingTile) -> Vec<Tile> {
+    match *incoming {
+        IncomingTile::Tsumo(t) => vec![t],
+        IncomingTile::Chii { combination: (t1, t2, t3) } | IncomingTile::Pon { combination: (t1, t2, t3), .. } => vec![t1, t2, t3],
+        IncomingTile::Daiminkan { combination: (t1, t2, t3, t4), .. } => vec![t1, t2, t3, t4],
+    }
+}
+
+// The player who calls the discard, with pon and kan before chii.
+fn find_caller(round: &Round, num_players: usize, next: &[usize], who: usize, tile: Tile) -> Option<usize> {
+    let calls = |caller: usize| round.players[caller].incoming.get(next[caller]).filter(|x| called_tile(x) == Some(tile) && called_from(x, caller, num_players) == Some(who));
+    let others = (1..num_players).map(|i| (who + i) % num_players);
+    others.clone().find(|&c| calls(c).is_some_and(|x| !matches!(x, IncomingTile::Chii { .. }))).or_else(|| others.take(1).find(|&c| calls(c).is_some()))
+}
+
+/// Rebuilds the turn order of the round: the dealer draws first, and after each discard
+/// either a player calls it or the next seat draws. Stops at the end of the draws of the round.
+pub fn turn_order(round: &Round) -> Vec<Step> {
+    let num_players = round.players.iter().filter(|p| !p.hand.is_empty()).count();
+    if num_players == 0 {
+        return vec![];
+    }
+
+    let mut next_incoming = vec![0; num_players];
+    let mut next_outgoing = vec![0; num_players];
+    let mut steps = vec![];
+    let mut who = round.settings.kyoku as usize % 4 % num_players;
+    let mut drawn = None;
+    let mut draw = true;
+    loop {
+        let player = &round.players[who];
+        if draw {
+            match player.incoming.get(next_incoming[who]) {
+                Some(&IncomingTile::Tsumo(tile)) => {
+                    next_incoming[who] += 1;
+                    drawn = Some(tile);
+                    steps.push(Step::Draw { who, tile });
+                }
+                _ => break,
+            }
+        }
+
+        // No discard after tsumo, kyuushu kyuuhai or the end of the draws.
+        let Some(outgoing) = player.outgoing.get(next_outgoing[who]) else {
+            break;
+        };
+        next_outgoing[who] += 1;
+        let (tile, riichi) = match *outgoing {
+            OutgoingTile::Discard(t) => (t, false),
+            OutgoingTile::Riichi(t) => (t, true),
+            OutgoingTile::Tsumogiri | OutgoingTile::TsumogiriRiichi => match drawn {
+                Some(t) => (t, *outgoing == OutgoingTile::TsumogiriRiichi),
+                None => break,
+            },
+            OutgoingTile::Ankan(t) => {
+                let black = t.to_black();
+                steps.push(Step::Ankan { who, tiles: vec![black, black, black, t] });
+                draw = true;
+                continue;
+            }
+            OutgoingTile::Kakan { added, .. } => {
+                steps.push(Step::Kakan { who, added });
+                draw = true;
+                continue;
+            }
+            // Placeholder after a daiminkan, followed by the draw from the dead wall.
+            OutgoingTile::Dummy => {
+                draw = true;
+                continue;
+            }
+        };
+        steps.push(Step::Discard { who, tile, riichi });
+
+        match find_caller(round, num_players, &next_incoming, who, tile) {
+            Some(caller) => {
+                let incoming = &round.players[caller].incoming[next_incoming[caller]];
+                next_incoming[caller] += 1;
+                steps.push(Step::Call {
+                    who: caller,
+                    from: who,
+                    tiles: meld_tiles(incoming),
+                    called: tile,
+                });
+                who = caller;
+                drawn = None;
+                draw = false;
+            }
+            None => {
+                who = (who + 1) % num_players;
+                draw = true;
+            }
+        }
+    }
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tenhou_json::parser::parse_tenhou_json;
+
+    fn t(x: u8) -> Tile {
+        Tile::from_u8(x).ok().unwrap()
+    }
+
+    #[test]
+    fn test_turn_order() {
+        // Dealer 0 discards 19, seat 1 chiis it and discards 47, seat 3 pons 47 from toimen, then seat 0 draws and wins.
+        let src = r#"{"ver":2.3,"ref":"","log":[[[0,0,0],[25000,25000,25000,25000],[11],[],
+            [11,12,13,14,15,16,17,18,21,22,23,24,25],[19,26],[19],
+            [17,18,21,22,23,24,25,26,27,28,29,47,47],["c191718"],[47],
+            [31,32,33,34,35,36,37,38,47,47,41,42,43],[],[],
+            [31,32,33,34,35,36,37,38,39,47,47,41,42],["47p4747"],[39],
+            ["流局",[0,0,0,0]]]],"ratingc":"PF4","rule":{"disp":"","aka53":1,"aka52":1,"aka51":1},"lobby":0,"dan":["","","",""],"rate":[1500,1500,1500,1500],"sx":["M","M","M","M"],"sc":[25000,0,25000,0,25000,0,25000,0],"name":["a","b","c","d"]}"#;
+        let tenhou_json = parse_tenhou_json(src).unwrap();
+        let steps = turn_order(&tenhou_json.rounds[0]);
+        assert_eq!(
+            steps,
+            vec![
+                Step::Draw { who: 0, tile: t(19) },
+                Step::Discard { who: 0, tile: t(19), riichi: false },
+                Step::Call {
+                    who: 1,
+                    from: 0,
+                    tiles: vec![t(19), t(17), t(18)],
+                    called: t(19)
+                },
+                Step::Discard { who: 1, tile: t(47), riichi: false },
+                Step::Call {
+                    who: 3,
+                    from: 1,
+                    tiles: vec![t(47), t(47), t(47)],
+                    called: t(47)
+                },
+                Step::Discard { who: 3, tile: t(39), riichi: false },
+                Step::Draw { who: 0, tile: t(26) },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_turn_order_kan() {
+        // Dealer 0 makes ankan of 11 and discards the replacement 12, seat 1 calls daiminkan on it and discards the replacement 13.
+        let src = r#"{"ver":2.3,"ref":"","log":[[[0,0,0],[25000,25000,25000,25000],[11],[],
+            [11,11,11,13,14,15,16,17,18,21,22,23,24],[11,12],["111111a11",12],
+            [12,12,12,22,23,24,25,26,27,28,29,47,47],["m12121212",13],[0,13],
+            [31,32,33,34,35,36,37,38,47,47,41,42,43],[14],[],
+            [31,32,33,34,35,36,37,38,39,47,47,41,42],[],[],
+            ["流局",[0,0,0,0]]]],"ratingc":"PF4","rule":{"disp":"","aka53":1,"aka52":1,"aka51":1},"lobby":0,"dan":["","","",""],"rate":[1500,1500,1500,1500],"sx":["M","M","M","M"],"sc":[25000,0,25000,0,25000,0,25000,0],"name":["a","b","c","d"]}"#;
+        let tenhou_json = parse_tenhou_json(src).unwrap();
+        assert_eq!(
+            turn_order(&tenhou_json.rounds[0]),
+            vec![
+                Step::Draw { who: 0, tile: t(11) },
+                Step::Ankan { who: 0, tiles: vec![t(11); 4] },
+                Step::Draw { who: 0, tile: t(12) },
+                Step::Discard { who: 0, tile: t(12), riichi: false },
+                Step::Call {
+                    who: 1,
+                    from: 0,
+                    tiles: vec![t(12); 4],
+                    called: t(12)
+                },
+                Step::Draw { who: 1, tile: t(13) },
+                Step::Discard { who: 1, tile: t(13), riichi: false },
+                Step::Draw { who: 2, tile: t(14) },
+            ]
+        );
+    }
+}
@@ -0,0 +1,328 @@
+//! # validate
+//!
+//! Consistency checks of mjlog, for catching parser and scoring bugs across a corpus.
+//!
+//! Only four-player games are supported.
+
+use mjlog::model::*;
+use mjlog::replay::*;
+use std::fmt;
+use tenhou_json::calc::*;
+use tenhou_json::score::{RankedScore, Score};
+
+/// What was compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreCheck {
+    /// Points before AGARI/RYUUKYOKU against the replayed points.
+    BeforePoints,
+    /// Payment of AGARI.
+    Agari,
+    /// Payment of AGARI with pao (sekinin barai), split between the responsible player and the others.
+    Pao,
+    /// Tenpai payment of RYUUKYOKU.
+    Ryuukyoku,
+}
+
+/// A payment that does not match the recomputed one. Points are in units of 100, same as the XML.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreMismatch {
+    /// Index in `Mjlog::actions`.
+    pub action_index: usize,
+    pub kyoku: u8,
+    pub honba: u8,
+    pub check: ScoreCheck,
+    pub expected: Vec<GamePoint>,
+    pub actual: Vec<GamePoint>,
+}
+
+impl fmt::Display for ScoreMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "action {} (kyoku {}, honba {}) {:?}: expected {:?}, actual {:?}",
+            self.action_index, self.kyoku, self.honba, self.check, self.expected, self.actual
+        )
+    }
+}
+
+fn yakuman_score(tsumo: bool, is_oya: bool, num: u8, profile: ScoreProfile) -> RankedScore {
+    match (tsumo, is_oya) {
+        (true, true) => get_oya_tsumo_yakuman_with(num, profile),
+        (true, false) => get_ko_tsumo_yakuman_with(num, profile),
+        (false, true) => get_oya_ron_yakuman(num),
+        (false, false) => get_ko_ron_yakuman(num),
+    }
+}
+
+fn ranked_score(x: &ActionAGARI, oya: Player, profile: ScoreProfile) -> RankedScore {
+    let tsumo = x.is_tsumo();
+    let is_oya = x.who == oya;
+    if !x.yakuman.is_empty() {
+        yakuman_score(tsumo, is_oya, x.yakuman.len() as u8, profile)
+    } else {
+        let han = x.yaku.iter().map(|&(_, han)| han).sum();
+        match (tsumo, is_oya) {
+            (true, true) => get_oya_tsumo_with(x.ten.fu, han, profile),
+            (true, false) => get_ko_tsumo_with(x.ten.fu, han, profile),
+            (false, true) => get_oya_ron(x.ten.fu, han),
+            (false, false) => get_ko_ron(x.ten.fu, han),
+        }
+    }
+}
+
+/// Yakuman with pao (sekinin barai) on Tenhou.
+pub fn is_pao_yakuman(x: Yaku) -> bool {
+    matches!(x, Yaku::Daisangen | Yaku::Daisuushii)
+}
+
+// Normal payment of the score, without pao. Honba is 300 for ron, 100 from each player for tsumo.
+fn pay(deltas: &mut [GamePoint; 4], score: Score, who: usize, from: usize, oya: usize, honba: GamePoint, num_players: usize) {
+    let payers = deltas.iter_mut().enumerate().take(num_players).filter(|&(i, _)| i != who);
+    match score {
+        Score::Ron(v) => deltas[from] -= v + honba * 300,
+        Score::OyaTsumo(v) => {
+            for (_, d) in payers {
+                *d -= v + honba * 100;
+            }
+        }
+        Score::KoTsumo(ko, oya_part) => {
+            for (i, d) in payers {
+                *d -= if i == oya { oya_part } else { ko } + honba * 100;
+            }
+        }
+    }
+}
+
+/// Recomputes the point changes of an AGARI, in points (not units of 100).
+///
+/// * Tsumo: paid by the players of the profile, so three-player games have one payer less.
+/// * Honba: 300 for ron, 100 from each player for tsumo.
+/// * Kyoutaku: 1000 each to the winner.
+/// * Pao: the responsible player pays everything for tsumo including honba, and half of the ron (the discarder pays the rest and honba).
+///   With compound yakuman, only the pao yakuman are paid that way and the others are paid normally.
+pub fn expected_agari_deltas(x: &ActionAGARI, oya: Player, profile: ScoreProfile) -> [GamePoint; 4] {
+    let num_players = profile.num_players() as usize;
+    let who = x.who.to_u8() as usize % 4;
+    let from = x.from_who.to_u8() as usize % 4;
+    let oya_seat = oya.to_u8() as usize % 4;
+    let pao = x.pao_who.map(|p| p.to_u8() as usize % 4).filter(|&p| p != who);
+    let honba = x.honba as GamePoint;
+    let mut deltas = [0; 4];
+
+    match pao {
+        None => pay(&mut deltas, ranked_score(x, oya, profile).score, who, from, oya_seat, honba, num_players),
+        Some(p) => {
+            let tsumo = x.is_tsumo();
+            let is_oya = x.who == oya;
+            let num = x.yakuman.len() as u8;
+            let pao_num = x.yakuman.iter().filter(|&&y| is_pao_yakuman(y)).count() as u8;
+            let pao_score = if num > pao_num && pao_num > 0 {
+                pay(&mut deltas, yakuman_score(tsumo, is_oya, num - pao_num, profile).score, who, from, oya_seat, 0, num_players);
+                yakuman_score(tsumo, is_oya, pao_num, profile).score
+            } else {
+                ranked_score(x, oya, profile).score
+            };
+            match pao_score {
+                Score::Ron(v) => {
+                    deltas[p] -= v / 2;
+                    deltas[from] -= v - v / 2 + honba * 300;
+                }
+                score => {
+                    let mut tsumo_deltas = [0; 4];
+                    pay(&mut tsumo_deltas, score, who, from, oya_seat, honba, num_players);
+                    deltas[p] += tsumo_deltas.iter().sum::<GamePoint>();
+                }
+            }
+        }
+    }
+
+    deltas[who] = -deltas.iter().sum::<GamePoint>() + x.kyoutaku as GamePoint * 1000;
+    deltas
+}
+
+/// Index of the winner who receives honba and kyoutaku in multiple ron: the first one in turn order from the discarder.
+pub fn honba_winner(agaris: &[&ActionAGARI], profile: ScoreProfile) -> Option<usize> {
+    let winners: Vec<Player> = agaris.iter().map(|x| x.who).collect();
+    atamahane(agaris.first()?.from_who, &winners, profile.num_players())
+}
+
+/// Recomputes the point changes of the AGARIs of a round, which are more than one for multiple ron.
+///
+/// Only the winner of `honba_winner` receives honba and kyoutaku; the others are paid the score only.
+pub fn expected_multiple_agari_deltas(agaris: &[&ActionAGARI], oya: Player, profile: ScoreProfile) -> Vec<[GamePoint; 4]> {
+    let first = honba_winner(agaris, profile);
+    agaris
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| {
+            if Some(i) == first {
+                expected_agari_deltas(x, oya, profile)
+            } else {
+                expected_agari_deltas(&ActionAGARI { honba: 0, kyoutaku: 0, ..x.clone() }, oya, profile)
+            }
+        })
+        .collect()
+}
+
+// Consecutive AGARIs containing the action at `index`, and the position of it.
+fn agari_group(actions: &[Action], index: usize) -> (Vec<&ActionAGARI>, usize) {
+    let is_agari = |a: &Action| matches!(a, Action::AGARI(_));
+    let start = actions[..index].iter().rposition(|a| !is_agari(a)).map_or(0, |p| p + 1);
+    let group = actions[start..].iter().map_while(|a| if let Action::AGARI(x) = a { Some(x) } else { None }).collect();
+    (group, index - start)
+}
+
+/// Recomputes the tenpai payment of a RYUUKYOKU, in points (not units of 100).
+///
+/// Only the normal draw is computed. Abortive draws have no payment, and nagashi mangan returns `None`.
+/// The payment is 3000 in total between the players of the profile.
+pub fn expected_ryuukyoku_deltas(x: &ActionRYUUKYOKU, profile: ScoreProfile) -> Option<[GamePoint; 4]> {
+    match x.reason {
+        None => {}
+        Some(ExtraRyuukyokuReason::NagashiMangan) => return None,
+        Some(_) => return Some([0; 4]),
+    }
+
+    let num_players = profile.num_players() as GamePoint;
+    let tenpai = [&x.hai0, &x.hai1, &x.hai2, &x.hai3].map(|h| h.is_some());
+    let num_tenpai = tenpai.iter().filter(|&&t| t).count() as GamePoint;
+    if num_tenpai == 0 || num_tenpai == num_players {
+        return Some([0; 4]);
+    }
+
+    let mut deltas = tenpai.map(|t| if t { 3000 / num_tenpai } else { -3000 / (num_players - num_tenpai) });
+    deltas[num_players as usize..].fill(0);
+    Some(deltas)
+}
+
+/// Recomputes every AGARI payment and RYUUKYOKU tenpai payment with the replay engine,
+/// and returns the ones that differ from the logged `sc`.
+pub fn validate_scores(mjlog: &Mjlog) -> ReplayResult<Vec<ScoreMismatch>> {
+    let mut state = GameState::new();
+    let mut mismatches = vec![];
+    let mut profile = ScoreProfile::Yonma;
+
+    for (i, a) in mjlog.actions.iter().enumerate() {
+        let (before, actual_deltas, expected) = match a {
+            Action::AGARI(x) => {
+                let check = if x.pao_who.is_some_and(|p| p != x.who) { ScoreCheck::Pao } else { ScoreCheck::Agari };
+                let (group, pos) = agari_group(&mjlog.actions, i);
+                (&x.before_points, &x.delta_points, Some((check, expected_multiple_agari_deltas(&group, state.oya, profile)[pos])))
+            }
+            Action::RYUUKYOKU(x) => (&x.before_points, &x.delta_points, expected_ryuukyoku_deltas(x, profile).map(|d| (ScoreCheck::Ryuukyoku, d))),
+            Action::GO(x) => {
+                profile = ScoreProfile::for_players(x.settings.sanma);
+                state.apply(a)?;
+                continue;
+            }
+            _ => {
+                state.apply(a)?;
+                continue;
+            }
+        };
+
+        let mut push = |check, expected: Vec<GamePoint>, actual: &Vec<GamePoint>| {
+            if &expected != actual {
+                mismatches.push(ScoreMismatch {
+                    action_index: i,
+                    kyoku: state.kyoku,
+                    honba: state.honba,
+                    check,
+                    expected,
+                    actual: actual.clone(),
+                });
+            }
+        };
+
+        // Multiple ron: the second AGARI starts from the points after the first one.
+        let replayed: Vec<GamePoint> = state.players.iter().map(|p| p.score).collect();
+        push(ScoreCheck::BeforePoints, replayed, before);
+        if let Some((check, deltas)) = expected {
+            push(check, deltas.iter().map(|x| x / 100).collect(), actual_deltas);
+        }
+
+        state.apply(a)?;
+    }
+
+    Ok(mismatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agari(who: u8, from_who: u8, fu: u8, han: u8, honba: u8, kyoutaku: u8) -> ActionAGARI {
+        ActionAGARI {
+            honba,
+            kyoutaku,
+            hai: vec![],
+            m: vec![],
+            machi: Hai::new(0),
+            ten: AgariTen { fu, ..Default::default() },
+            yaku: vec![(Yaku::Pinfu, han)],
+            yakuman: vec![],
+            dora_hai: vec![],
+            dora_hai_ura: vec![],
+            who: Player::new(who),
+            from_who: Player::new(from_who),
+            pao_who: None,
+            before_points: vec![],
+            delta_points: vec![],
+            owari: None,
+        }
+    }
+
+    #[test]
+    fn test_agari_deltas() {
+        // ko ron 30fu 2han, 1 honba
+        assert_eq!(expected_agari_deltas(&agari(2, 3, 30, 2, 1, 0), Player::new(0), ScoreProfile::Yonma), [0, 0, 2300, -2300]);
+        // ko tsumo 30fu 3han (1000-2000), 1 honba, 1 kyoutaku
+        assert_eq!(expected_agari_deltas(&agari(1, 1, 30, 3, 1, 1), Player::new(0), ScoreProfile::Yonma), [-2100, 5300, -1100, -1100]);
+        // oya tsumo 40fu 2han (1300 all)
+        assert_eq!(expected_agari_deltas(&agari(0, 0, 40, 2, 0, 0), Player::new(0), ScoreProfile::Yonma), [3900, -1300, -1300, -1300]);
+    }
+
+    #[test]
+    fn test_sanma_agari_deltas() {
+        // ko tsumo 30fu 3han (1000-2000) with the tsumo loss, no payment from the absent seat
+        assert_eq!(expected_agari_deltas(&agari(1, 1, 30, 3, 1, 0), Player::new(0), ScoreProfile::Sanma), [-2100, 3200, -1100, 0]);
+        // the same without the tsumo loss (1500-2400), the total is the same as ron
+        assert_eq!(expected_agari_deltas(&agari(1, 1, 30, 3, 0, 0), Player::new(0), ScoreProfile::SanmaNoTsumoLoss), [-2400, 3900, -1500, 0]);
+    }
+
+    fn pao_agari(who: u8, from_who: u8, pao_who: u8, yakuman: Vec<Yaku>, honba: u8) -> ActionAGARI {
+        ActionAGARI {
+            yaku: vec![],
+            yakuman,
+            pao_who: Some(Player::new(pao_who)),
+            ..agari(who, from_who, 0, 0, honba, 0)
+        }
+    }
+
+    #[test]
+    fn test_pao_deltas() {
+        // ko ron daisangen, pao and discarder pay half each, honba by the discarder
+        assert_eq!(expected_agari_deltas(&pao_agari(1, 2, 3, vec![Yaku::Daisangen], 1), Player::new(0), ScoreProfile::Yonma), [0, 32300, -16300, -16000]);
+        // ko tsumo daisangen, pao pays all including honba
+        assert_eq!(expected_agari_deltas(&pao_agari(1, 1, 3, vec![Yaku::Daisangen], 1), Player::new(0), ScoreProfile::Yonma), [0, 32300, 0, -32300]);
+        // oya tsumo daisangen + tsuuiisou, pao pays only the daisangen part
+        assert_eq!(
+            expected_agari_deltas(&pao_agari(0, 0, 2, vec![Yaku::Daisangen, Yaku::Tsuuiisou], 0), Player::new(0), ScoreProfile::Yonma),
+            [96000, -16000, -64000, -16000]
+        );
+    }
+
+    #[test]
+    fn test_multiple_ron_honba() {
+        // Player 3 discards. Player 1 is earlier than player 2 in turn order, even if logged later.
+        let second = agari(2, 3, 30, 2, 2, 1);
+        let first = agari(1, 3, 30, 2, 2, 1);
+        assert_eq!(honba_winner(&[&second, &first], ScoreProfile::Yonma), Some(1));
+        assert_eq!(expected_multiple_agari_deltas(&[&second, &first], Player::new(0), ScoreProfile::Yonma), vec![[0, 0, 2000, -2000], [0, 3600, 0, -2600]]);
+
+        // Player 0 discards, player 3 is the last in turn order.
+        let a = agari(3, 0, 30, 2, 1, 0);
+        let b = agari(1, 0, 30, 2, 1, 0);
+        assert_eq!(honba_winner(&[&a, &b], ScoreProfile::Yonma), Some(1));
+    }
+}
@@ -0,0 +1,32 @@
+//! # wasm
+//!
+//! JavaScript bindings, for converting logs in the browser without uploading them.
+//!
+//! ```js
+//! import init, { convert, parseTenhouJson } from "./pkg/mjlog2json_core.js";
+//!
+//! await init();
+//! const json = convert(xml);
+//! ```
+
+use crate::conv::*;
+use tenhou_json::exporter::export_tenhou_json;
+use tenhou_json::parser::parse_tenhou_json;
+use wasm_bindgen::prelude::*;
+
+/// Converts mjlog-XML to tenhou-JSON. A file with multiple games gives an array.
+#[wasm_bindgen]
+pub fn convert(xml: &str) -> Result<String, JsError> {
+    let mut jsons = convert_xml_str_to_json_str(xml, &ConvOptions::default())?;
+    if jsons.len() == 1 {
+        Ok(jsons.remove(0))
+    } else {
+        Ok(format!("[{}]", jsons.join(",")))
+    }
+}
+
+/// Validates tenhou-JSON and returns it as exported by this crate, e.g. to normalize files from other tools.
+#[wasm_bindgen(js_name = parseTenhouJson)]
+pub fn parse_tenhou_json_js(json: &str) -> Result<String, JsError> {
+    Ok(export_tenhou_json(&parse_tenhou_json(json)?)?)
+}
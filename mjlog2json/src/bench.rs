@@ -0,0 +1,147 @@
+use crate::converter::*;
+use futures::StreamExt;
+use mjlog::parser::parse_mjlogs;
+use mjlog2json_core::conv::conv_all;
+use serde_json::{json, Value};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::time::Instant;
+use tenhou_json::exporter::export_tenhou_json;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+// The system allocator with counters, for the allocations of each stage. Relaxed counters cost little elsewhere.
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count_allocation(layout.size());
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn count_allocation(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+}
+
+// Peak resident set size from /proc, only on Linux.
+fn peak_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kb = status.lines().find_map(|x| x.strip_prefix("VmHWM:"))?.trim().strip_suffix("kB")?.trim();
+    kb.parse::<u64>().ok().map(|x| x * 1024)
+}
+
+// Runs the stage and reports its time and allocations, with the throughput over the input.
+fn run_stage<T, F>(name: &str, input_bytes: usize, games: usize, f: F) -> Result<(T, Value), Box<dyn Error + Send + Sync>>
+where
+    F: FnOnce() -> Result<T, Box<dyn Error + Send + Sync>>,
+{
+    let (allocations, allocated_bytes) = (ALLOCATIONS.load(Ordering::Relaxed), ALLOCATED_BYTES.load(Ordering::Relaxed));
+    let start = Instant::now();
+    let ret = f()?;
+    let seconds = start.elapsed().as_secs_f64();
+
+    let report = json!({
+        "name": name,
+        "seconds": seconds,
+        "megabytes_per_second": input_bytes as f64 / 1e6 / seconds.max(1e-9),
+        "games_per_second": games as f64 / seconds.max(1e-9),
+        "allocations": ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        "allocated_bytes": ALLOCATED_BYTES.load(Ordering::Relaxed) - allocated_bytes,
+        "peak_rss_bytes": peak_rss_bytes(),
+    });
+    Ok((ret, report))
+}
+
+// Converts the files to the output directory on all CPUs, with async tasks and with the thread pool of `--sync-parallel`.
+fn conv_files_stages(paths: &[PathBuf], output_dir: &Path, file_bytes: usize, games: usize) -> Result<Vec<Value>, Box<dyn Error + Send + Sync>> {
+    let output = OutputOptions::default();
+    let check = |results: Vec<FileResult>| -> Result<(), Box<dyn Error + Send + Sync>> {
+        for (path, ret) in results {
+            ret.map_err(|e| format!("{}: {}", path.to_string_lossy(), e))?;
+        }
+        Ok(())
+    };
+
+    let (_, async_stage) = run_stage("convert_files_async", file_bytes, games, || check(async_std::task::block_on(async_conv_files(paths.to_vec(), output_dir, output, 0).collect())))?;
+    let pool = new_thread_pool(0)?;
+    let (_, sync_stage) = run_stage("convert_files_sync_parallel", file_bytes, games, || {
+        let (sender, receiver) = mpsc::channel();
+        sync_conv_files(&pool, paths.to_vec(), output_dir, output, sender);
+        check(receiver.into_iter().collect())
+    })?;
+    Ok(vec![async_stage, sync_stage])
+}
+
+// Same as `conv_files_stages`, to a temporary directory removed afterwards.
+fn parallel_stages(paths: &[PathBuf], file_bytes: usize, games: usize) -> Result<Vec<Value>, Box<dyn Error + Send + Sync>> {
+    let output_dir = std::env::temp_dir().join(format!("mjlog2json-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&output_dir)?;
+    let stages = conv_files_stages(paths, &output_dir, file_bytes, games);
+    std::fs::remove_dir_all(&output_dir)?;
+    stages
+}
+
+/// Runs read, parse, convert and export over the input, one stage at a time, and prints the time,
+/// throughput, allocations and peak RSS of each stage as JSON.
+///
+/// Stages run on one thread, so the numbers compare machines and versions rather than measure the parallel conversion.
+/// With `parallel`, two more stages convert the files end to end, from reading to writing, on all CPUs:
+/// with the async tasks of the directory conversion, and with the thread pool of `--sync-parallel`.
+pub fn run_bench(input_path: &Path, parallel: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let paths: Vec<PathBuf> = if input_path.is_dir() { list_input_files(input_path) } else { vec![input_path.to_path_buf()] };
+    let file_bytes: usize = paths.iter().map(|x| std::fs::metadata(x).map_or(0, |m| m.len() as usize)).sum();
+
+    // The number of games is known after parsing, so the first two stages report it afterwards.
+    let (xmls, mut read) = run_stage("read", file_bytes, 0, || paths.iter().map(|x| decode_contents(std::fs::read(x)?)).collect::<Result<Vec<_>, _>>())?;
+    let xml_bytes: usize = xmls.iter().map(|x| x.len()).sum();
+    let (mjlogs, mut parse) = run_stage("parse", xml_bytes, 0, || Ok(xmls.iter().map(|x| parse_mjlogs(x)).collect::<Result<Vec<_>, _>>()?.concat()))?;
+    let games = mjlogs.len();
+    for stage in [&mut read, &mut parse] {
+        stage["games_per_second"] = json!(games as f64 / stage["seconds"].as_f64().unwrap_or_default().max(1e-9));
+    }
+    let (jsons, convert) = run_stage("convert", xml_bytes, games, || Ok(conv_all(&mjlogs)?))?;
+    let (_, export) = run_stage("export", xml_bytes, games, || Ok(jsons.iter().map(export_tenhou_json).collect::<Result<Vec<_>, _>>()?))?;
+    let mut stages = vec![read, parse, convert, export];
+    if parallel {
+        stages.extend(parallel_stages(&paths, file_bytes, games)?);
+    }
+
+    let root = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "cpus": std::thread::available_parallelism().map_or(1, |x| x.get()),
+        "input": input_path.to_string_lossy(),
+        "files": paths.len(),
+        "file_bytes": file_bytes,
+        "xml_bytes": xml_bytes,
+        "games": games,
+        "stages": stages,
+        "peak_rss_bytes": peak_rss_bytes(),
+    });
+    println!("{}", serde_json::to_string_pretty(&root)?);
+    Ok(())
+}
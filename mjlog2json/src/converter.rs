@@ -1,54 +1,755 @@
-use futures::stream::{FuturesUnordered, StreamExt};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use futures::stream::{self, Stream, StreamExt};
 use glob::glob;
+use indicatif::{ProgressBar, ProgressStyle};
+use mjlog::encoding::decode_mjlog;
+use mjlog::exporter::export_mjlog;
+use mjlog::model::*;
 use mjlog::parser::*;
+#[cfg(feature = "parquet")]
+use mjlog2json_core::action_arrow::*;
+use mjlog2json_core::anonymize::*;
 use mjlog2json_core::conv::*;
+use mjlog2json_core::game_id::parse_game_id;
+use mjlog2json_core::html::*;
+use mjlog2json_core::markdown::*;
+use mjlog2json_core::mjai::*;
+use mjlog2json_core::number_format::DigitGrouping;
+#[cfg(feature = "arrow")]
+use mjlog2json_core::round_arrow::*;
+use mjlog2json_core::round_csv::*;
+#[cfg(feature = "sqlite")]
+use mjlog2json_core::sqlite::SqliteWriter;
+#[cfg(feature = "parquet")]
+use mjlog2json_core::table_parquet::*;
+use mjlog2json_core::transcript::*;
+use rayon::iter::{IntoParallelIterator, ParallelBridge, ParallelIterator};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::collections::HashMap;
 use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
 use tenhou_json::exporter::*;
 use tenhou_json::model::*;
+use zip::ZipArchive;
 
-fn read_contents(input_path: &Path, content_xml: String) -> Result<String, Box<dyn Error + Send + Sync>> {
-    let mjlog = &parse_mjlogs(&content_xml)?[0];
-    let reference = input_path.file_stem().unwrap().to_string_lossy().to_string();
-    let converted_tenhou_json = TenhouJson { reference, ..conv_to_tenhou_json(mjlog)? };
+// Tenhou serves mjlog as gzip, so downloaded logs may be .mjlog or .gz as well as plain .xml.
+const INPUT_PATTERNS: [&str; 3] = ["*.xml", "*.mjlog", "*.gz"];
 
-    Ok(export_tenhou_json(&converted_tenhou_json)?)
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Text of the file, gunzipped and decoded to UTF-8 in the detected encoding. See `mjlog::encoding`.
+pub fn decode_contents(bytes: Vec<u8>) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let bytes = if bytes.starts_with(&GZIP_MAGIC) {
+        let mut content = vec![];
+        GzDecoder::new(bytes.as_slice()).read_to_end(&mut content)?;
+        content
+    } else {
+        bytes
+    };
+    Ok(decode_mjlog(&bytes)?.into_owned())
 }
 
-pub fn read_mjlog(input_path: &PathBuf) -> Result<String, Box<dyn Error + Send + Sync>> {
-    let content_xml = std::fs::read_to_string(input_path)?;
-    read_contents(input_path, content_xml)
+// "foo.xml" / "foo.mjlog" / "foo.mjlog.gz" -> "foo"
+fn get_log_name(input_path: &Path) -> String {
+    let mut name = PathBuf::from(input_path.file_name().unwrap());
+    if name.extension().is_some_and(|x| x == "gz") {
+        name.set_extension("");
+    }
+    name.file_stem().unwrap().to_string_lossy().to_string()
 }
 
-async fn async_conv_file(input_path: PathBuf, output_dir: PathBuf) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
-    let file_stem: &str = input_path.file_stem().unwrap().to_str().unwrap();
-    let output_path = output_dir.join(format!("{}.json", file_stem));
+// Fails if two inputs have the same log name, e.g. "foo.xml" and "foo.mjlog", whose outputs would overwrite each other.
+fn check_log_names<'a>(input_paths: impl IntoIterator<Item = &'a Path>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut names: HashMap<String, &Path> = HashMap::new();
+    for input_path in input_paths {
+        if let Some(other) = names.insert(get_log_name(input_path), input_path) {
+            return Err(format!(
+                "{} and {} have the same log name {}, so their outputs would overwrite each other. Rename or move one of them.",
+                other.to_string_lossy(),
+                input_path.to_string_lossy(),
+                get_log_name(input_path)
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
 
-    let content_xml = async_std::fs::read_to_string(&input_path).await?;
-    let content_json = read_contents(&input_path, content_xml)?;
+/// Output format of the conversion.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OutputFormat {
+    /// tenhou-JSON
+    #[default]
+    Json,
+    /// Human-readable transcript
+    Text,
+    /// Every draw, discard and call in order
+    Narrative,
+    /// Summary tables in Markdown
+    Markdown,
+    /// Replayer of the turns in a self-contained HTML page
+    Html,
+    /// mjai events, one JSON per line
+    Mjai,
+    /// Result of each round in CSV
+    Csv,
+    /// mjlog-XML of each game, decompressed
+    Xml,
+    /// Result of each round as an Arrow IPC stream, one record batch per game
+    #[cfg(feature = "arrow")]
+    Arrow,
+    /// Round or action table of all inputs as one Parquet file
+    #[cfg(feature = "parquet")]
+    Parquet,
+    /// Games of all inputs as one SQLite database
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
 
-    async_std::fs::write(output_path, &content_json).await?;
-    Ok(input_path)
+impl OutputFormat {
+    pub fn extension(&self) -> &str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Text | OutputFormat::Narrative => "txt",
+            OutputFormat::Markdown => "md",
+            OutputFormat::Html => "html",
+            OutputFormat::Mjai => "mjson",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Xml => "xml",
+            #[cfg(feature = "arrow")]
+            OutputFormat::Arrow => "arrows",
+            #[cfg(feature = "parquet")]
+            OutputFormat::Parquet => "parquet",
+            #[cfg(feature = "sqlite")]
+            OutputFormat::Sqlite => "db",
+        }
+    }
+
+    /// All inputs go to one output, instead of a file per game.
+    pub fn is_combined(&self) -> bool {
+        match self {
+            #[cfg(feature = "arrow")]
+            OutputFormat::Arrow => true,
+            #[cfg(feature = "parquet")]
+            OutputFormat::Parquet => true,
+            #[cfg(feature = "sqlite")]
+            OutputFormat::Sqlite => true,
+            _ => false,
+        }
+    }
 }
 
-pub async fn async_conv_dir(input_dir: &Path, output_dir: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
-    std::fs::create_dir_all(output_dir)?;
+// The formats of the optional features are still known, to tell how to get them.
+#[cfg(not(all(feature = "arrow", feature = "parquet", feature = "sqlite")))]
+fn not_compiled_in(format: &str) -> String {
+    format!("{0} is not compiled in. Build mjlog2json with the {0} feature.", format)
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" | "tenhou" => Ok(OutputFormat::Json),
+            "text" => Ok(OutputFormat::Text),
+            "narrative" => Ok(OutputFormat::Narrative),
+            "markdown" => Ok(OutputFormat::Markdown),
+            "html" => Ok(OutputFormat::Html),
+            "mjai" => Ok(OutputFormat::Mjai),
+            "csv" => Ok(OutputFormat::Csv),
+            "xml" => Ok(OutputFormat::Xml),
+            #[cfg(feature = "arrow")]
+            "arrow" => Ok(OutputFormat::Arrow),
+            #[cfg(not(feature = "arrow"))]
+            "arrow" => Err(not_compiled_in(s)),
+            #[cfg(feature = "parquet")]
+            "parquet" => Ok(OutputFormat::Parquet),
+            #[cfg(not(feature = "parquet"))]
+            "parquet" => Err(not_compiled_in(s)),
+            #[cfg(feature = "sqlite")]
+            "sqlite" => Ok(OutputFormat::Sqlite),
+            #[cfg(not(feature = "sqlite"))]
+            "sqlite" => Err(not_compiled_in(s)),
+            _ => Err(format!("unknown format: {} (expected tenhou, mjai, csv, xml, text, narrative, markdown, html, arrow, parquet or sqlite)", s)),
+        }
+    }
+}
+
+/// Compression of the output files.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
 
-    let pattern_binding = input_dir.join("*.xml");
-    let pattern = pattern_binding.to_string_lossy();
+impl Compression {
+    /// Suffix of the compressed file after the extension of the format, e.g. "gz" of foo.json.gz.
+    pub fn extension(&self) -> Option<&str> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip => Some("gz"),
+            Compression::Zstd => Some("zst"),
+        }
+    }
+
+    /// Compresses the content, or returns it as is.
+    pub fn compress(&self, content: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(content.to_vec()),
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(content)?;
+                encoder.finish()
+            }
+            Compression::Zstd => zstd::encode_all(content, zstd::DEFAULT_COMPRESSION_LEVEL),
+        }
+    }
+}
 
-    let mut tasks = FuturesUnordered::new();
+impl std::str::FromStr for Compression {
+    type Err = String;
 
-    println!("Registering tasks...");
-    for entry in glob(&pattern).expect("Failed to read glob pattern") {
-        tasks.push(async_std::task::spawn(async_conv_file(entry.unwrap().to_path_buf(), output_dir.to_path_buf())));
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Compression::None),
+            "gzip" | "gz" => Ok(Compression::Gzip),
+            "zstd" | "zst" => Ok(Compression::Zstd),
+            _ => Err(format!("unknown compression: {} (expected gzip, zstd or none)", s)),
+        }
     }
+}
+
+/// Options for the output of each game.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutputOptions {
+    pub format: OutputFormat,
+    /// Language of the dan and rule names, and of the text, narrative, markdown and html output.
+    pub locale: Locale,
+    /// Thousands separator of points in the text, narrative, markdown and html output.
+    pub digit_grouping: DigitGrouping,
+    /// Indent the tenhou-JSON output.
+    pub pretty: bool,
+    /// Compute the final result of logs without owari. See `ConvOptions::synthesize_final_result`.
+    pub synthesize_final_result: bool,
+    /// Accept what is unknown to this version with warnings instead of failing. See `parse_mjlogs_lenient`.
+    pub lenient: bool,
+    /// Accept logs without the closing mjloggm tag. See `ParseOptions::allow_truncated`.
+    pub allow_truncated: bool,
+    /// See `ConvOptions::incomplete_round`.
+    pub incomplete_round: IncompleteRound,
+    /// Compression of the files of each game.
+    pub compression: Compression,
+    /// Replace the names, dan, rate and SHUFFLE seed of every game. See `anonymize`.
+    pub anonymize: Option<Anonymize>,
+}
+
+impl OutputOptions {
+    /// File name of the game, e.g. "foo.json" or "foo.json.gz".
+    pub fn file_name(&self, name: &str) -> String {
+        match self.compression.extension() {
+            Some(x) => format!("{}.{}.{}", name, self.format.extension(), x),
+            None => format!("{}.{}", name, self.format.extension()),
+        }
+    }
+}
+
+/// Options of the conversion to tenhou-JSON for the output.
+pub fn conv_options(reference: &str, output: &OutputOptions) -> ConvOptions {
+    ConvOptions {
+        reference: reference.to_string(),
+        locale: output.locale,
+        synthesize_final_result: output.synthesize_final_result,
+        incomplete_round: output.incomplete_round,
+        ..ConvOptions::default()
+    }
+}
+
+/// Parses every game in the XML, with the warnings of the parser in lenient mode.
+/// The games are anonymized here, so every output format gets the same.
+fn parse_contents(reference: &str, content_xml: &str, output: &OutputOptions) -> Result<Vec<Mjlog>, Box<dyn Error + Send + Sync>> {
+    let mut mjlogs = if output.lenient {
+        let (mjlogs, warnings) = parse_mjlogs_lenient(content_xml);
+        for warning in warnings {
+            eprintln!("warning: {}: {}", reference, warning);
+        }
+        mjlogs
+    } else {
+        parse_mjlogs_with(
+            content_xml,
+            &ParseOptions {
+                allow_truncated: output.allow_truncated,
+                ..Default::default()
+            },
+        )?
+    };
+    for (i, mjlog) in mjlogs.iter_mut().enumerate() {
+        if mjlog.incomplete && !output.lenient {
+            eprintln!("warning: {}: game {}: truncated, kept {} actions", reference, i, mjlog.actions.len());
+        }
+        if let Some(mode) = output.anonymize {
+            anonymize(mjlog, mode);
+        }
+    }
+    Ok(mjlogs)
+}
+
+/// Converts every game in the XML to tenhou-JSON, numbering the references of multi-game files.
+fn conv_contents(reference: &str, content_xml: &str, output: &OutputOptions) -> Result<Vec<TenhouJson>, Box<dyn Error + Send + Sync>> {
+    let mjlogs = parse_contents(reference, content_xml, output)?;
+    let options = conv_options(reference, output);
+    let converted = conv_all_with(&mjlogs, &options)?;
+
+    let count = converted.len();
+    Ok(converted
+        .into_iter()
+        .enumerate()
+        .map(|(i, x)| TenhouJson {
+            reference: numbered_name(reference, i, count),
+            ..x
+        })
+        .collect())
+}
+
+/// Converts every game in the XML. Returns the reference and the output of each game.
+fn read_contents(reference: String, content_xml: String, output: &OutputOptions) -> Result<Vec<(String, String)>, Box<dyn Error + Send + Sync>> {
+    let numbered = |contents: Vec<String>| {
+        let count = contents.len();
+        contents.into_iter().enumerate().map(|(i, x)| (numbered_name(&reference, i, count), x)).collect()
+    };
+
+    // These formats do not go through tenhou-JSON.
+    match output.format {
+        // Written back from the model only when anonymized, otherwise the original text of each game.
+        OutputFormat::Xml if output.anonymize.is_some() => return Ok(numbered(parse_contents(&reference, &content_xml, output)?.iter().map(export_mjlog).collect())),
+        OutputFormat::Xml => return Ok(numbered(split_mjlogs(&content_xml)?.into_iter().map(String::from).collect())),
+        OutputFormat::Mjai => return Ok(numbered(parse_contents(&reference, &content_xml, output)?.iter().map(export_mjai).collect())),
+        _ => {}
+    }
+
+    let export_options = ExportOptions {
+        pretty: output.pretty,
+        locale: output.locale,
+        ..Default::default()
+    };
+    let mut contents = Vec::new();
+    for tenhou_json in conv_contents(&reference, &content_xml, output)? {
+        let content = match output.format {
+            OutputFormat::Json => export_tenhou_json_with(&tenhou_json, &export_options)?,
+            OutputFormat::Text => export_transcript(&tenhou_json, output.locale, output.digit_grouping),
+            OutputFormat::Narrative => export_narrative(&tenhou_json, &export_options, output.digit_grouping),
+            OutputFormat::Markdown => export_markdown(&tenhou_json, output.locale, output.digit_grouping),
+            OutputFormat::Html => export_html(&tenhou_json, output.locale, output.digit_grouping),
+            OutputFormat::Csv => export_rounds_csv(&tenhou_json),
+            // Returned above, or written to one output by the callers.
+            _ => unreachable!(),
+        };
+        contents.push((tenhou_json.reference, content));
+    }
+    Ok(contents)
+}
+
+/// Same as `read_contents`, but the XML may be gzip-compressed.
+pub fn read_mjlog_bytes(reference: String, bytes: Vec<u8>, output: &OutputOptions) -> Result<Vec<(String, String)>, Box<dyn Error + Send + Sync>> {
+    read_contents(reference, decode_contents(bytes)?, output)
+}
+
+pub fn read_mjlog(input_path: &PathBuf, output: &OutputOptions) -> Result<Vec<(String, String)>, Box<dyn Error + Send + Sync>> {
+    read_mjlog_bytes(get_log_name(input_path), std::fs::read(input_path)?, output)
+}
+
+pub fn read_mjlog_stdin(output: &OutputOptions) -> Result<Vec<(String, String)>, Box<dyn Error + Send + Sync>> {
+    let mut bytes = Vec::new();
+    std::io::stdin().read_to_end(&mut bytes)?;
+
+    // There is no file name to take the reference from.
+    read_mjlog_bytes(String::new(), bytes, output)
+}
+
+/// Lists mjlog files in the directory.
+pub fn list_input_files(input_dir: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for pattern in INPUT_PATTERNS {
+        let pattern_binding = input_dir.join(pattern);
+        paths.extend(glob(&pattern_binding.to_string_lossy()).expect("Failed to read glob pattern").map(|x| x.unwrap()));
+    }
+    paths
+}
+
+/// Reads every game from a file or from all files in a directory, together with its log name.
+pub fn load_mjlogs(input_path: &Path) -> Result<Vec<(String, Mjlog)>, Box<dyn Error + Send + Sync>> {
+    let paths = if input_path.is_dir() { list_input_files(input_path) } else { vec![input_path.to_path_buf()] };
+
+    let mut mjlogs = Vec::new();
+    for path in paths {
+        let content_xml = decode_contents(std::fs::read(&path)?)?;
+        let name = get_log_name(&path);
+        mjlogs.extend(parse_mjlogs(&content_xml)?.into_iter().map(|x| (name.clone(), x)));
+    }
+    Ok(mjlogs)
+}
+
+/// Options for directory conversion.
+#[derive(Debug, Clone, Default)]
+pub struct ConvDirOptions {
+    /// Stop at the first file that fails to convert.
+    pub fail_fast: bool,
+    /// Number of files converted at a time. 0 means the number of CPUs.
+    pub jobs: usize,
+    /// Print neither the progress nor the converted file names; only failures and the summary.
+    pub quiet: bool,
+    pub output: OutputOptions,
+}
+
+// Writes the output of each game in the file to the output directory.
+async fn write_outputs(log_name: String, bytes: Vec<u8>, output_dir: &Path, output: &OutputOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    for (name, content) in read_mjlog_bytes(log_name, bytes, output)? {
+        let output_path = output_dir.join(output.file_name(&name));
+        async_std::fs::write(output_path, output.compression.compress(content.as_bytes())?).await?;
+    }
+    Ok(())
+}
+
+async fn async_conv_file(input_path: PathBuf, output_dir: PathBuf, output: OutputOptions) -> (PathBuf, Result<(), Box<dyn Error + Send + Sync>>) {
+    let ret = async {
+        let bytes = async_std::fs::read(&input_path).await?;
+        write_outputs(get_log_name(&input_path), bytes, &output_dir, &output).await
+    }
+    .await;
+
+    (input_path, ret)
+}
+
+// Count, throughput and ETA on stderr. Hidden if stderr is not a terminal.
+fn new_progress(total: usize, quiet: bool) -> ProgressBar {
+    if quiet {
+        return ProgressBar::hidden();
+    }
+    let style = ProgressStyle::with_template("{bar:40} {pos}/{len} {per_sec} ETA {eta}").expect("the template is valid");
+    ProgressBar::new(total as u64).with_style(style)
+}
+
+fn jobs_or_cpus(jobs: usize) -> usize {
+    if jobs == 0 {
+        std::thread::available_parallelism().map_or(1, |x| x.get())
+    } else {
+        jobs
+    }
+}
 
-    while let Some(ret) = tasks.next().await {
+/// Result of the conversion of a file, with its path.
+pub type FileResult = (PathBuf, Result<(), Box<dyn Error + Send + Sync>>);
+
+// Progress and summary of the conversion of the files, fed with the result of each file.
+struct ConvReport<'a> {
+    options: &'a ConvDirOptions,
+    progress: ProgressBar,
+    succeeded: usize,
+    failures: Vec<(PathBuf, Box<dyn Error + Send + Sync>)>,
+}
+
+impl<'a> ConvReport<'a> {
+    fn new(total: usize, options: &'a ConvDirOptions) -> Self {
+        ConvReport {
+            options,
+            progress: new_progress(total, options.quiet),
+            succeeded: 0,
+            failures: Vec::new(),
+        }
+    }
+
+    // Fails with the error of the file if `fail_fast`.
+    fn add(&mut self, (input_path, ret): FileResult) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.progress.inc(1);
         match ret {
-            Ok(x) => println!("{}", x.to_string_lossy().into_owned()),
-            Err(x) => return Err(x),
+            Ok(()) => {
+                self.succeeded += 1;
+                // The file names are the progress when stderr is not a terminal, e.g. in CI logs.
+                if self.progress.is_hidden() && !self.options.quiet {
+                    println!("{}", input_path.to_string_lossy().into_owned());
+                }
+            }
+            Err(x) if self.options.fail_fast => {
+                self.progress.abandon();
+                return Err(x);
+            }
+            Err(x) => {
+                self.progress.suspend(|| eprintln!("failed: {}: {}", input_path.to_string_lossy(), x));
+                self.failures.push((input_path, x));
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.progress.finish();
+
+        println!("Succeeded: {}, Failed: {}", self.succeeded, self.failures.len());
+        for (input_path, x) in &self.failures {
+            println!("  {}: {}", input_path.to_string_lossy(), x);
+        }
+
+        if self.failures.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("{} file(s) failed to convert.", self.failures.len()).into())
+        }
+    }
+}
+
+// Reports the progress and the summary of the conversion tasks.
+async fn collect_results<S>(mut tasks: S, total: usize, options: &ConvDirOptions) -> Result<(), Box<dyn Error + Send + Sync>>
+where
+    S: Stream<Item = FileResult> + Unpin,
+{
+    let mut report = ConvReport::new(total, options);
+    while let Some(x) = tasks.next().await {
+        report.add(x)?;
+    }
+    report.finish()
+}
+
+/// Converts the files to the output directory with async tasks, `jobs` at a time, and yields the result of each file as it completes.
+pub fn async_conv_files(input_paths: Vec<PathBuf>, output_dir: &Path, output: OutputOptions, jobs: usize) -> impl Stream<Item = FileResult> + Unpin + '_ {
+    stream::iter(input_paths)
+        .map(move |input_path| async_std::task::spawn(async_conv_file(input_path, output_dir.to_path_buf(), output)))
+        .buffer_unordered(jobs_or_cpus(jobs))
+}
+
+pub async fn async_conv_dir(input_dir: &Path, output_dir: &Path, options: &ConvDirOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    std::fs::create_dir_all(output_dir)?;
+
+    // Only `jobs` files are open at a time, even for directories with hundreds of thousands of logs.
+    let input_paths = list_input_files(input_dir);
+    check_log_names(input_paths.iter().map(|x| x.as_path()))?;
+    let total = input_paths.len();
+    collect_results(async_conv_files(input_paths, output_dir, options.output, options.jobs), total, options).await
+}
+
+// Same as `write_outputs`, but blocks on the writes.
+fn sync_write_outputs(log_name: String, bytes: Vec<u8>, output_dir: &Path, output: &OutputOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    for (name, content) in read_mjlog_bytes(log_name, bytes, output)? {
+        std::fs::write(output_dir.join(output.file_name(&name)), output.compression.compress(content.as_bytes())?)?;
+    }
+    Ok(())
+}
+
+/// Thread pool for `sync_conv_files`, of `jobs` threads or the number of CPUs.
+pub fn new_thread_pool(jobs: usize) -> Result<ThreadPool, Box<dyn Error + Send + Sync>> {
+    Ok(ThreadPoolBuilder::new().num_threads(jobs_or_cpus(jobs)).build()?)
+}
+
+/// Converts the files to the output directory on the thread pool, and sends the result of each file as it completes.
+/// Idle threads steal the files of busy ones, so a few huge logs do not hold up the rest.
+/// The conversion stops early once the receiver is dropped.
+pub fn sync_conv_files(pool: &ThreadPool, input_paths: Vec<PathBuf>, output_dir: &Path, output: OutputOptions, sender: Sender<FileResult>) {
+    pool.install(|| {
+        // An error is the receiver gone.
+        let _ = input_paths.into_par_iter().try_for_each_with(sender, |sender, input_path| {
+            let ret = std::fs::read(&input_path).map_err(|e| e.into()).and_then(|bytes| sync_write_outputs(get_log_name(&input_path), bytes, output_dir, &output));
+            sender.send((input_path, ret))
+        });
+    });
+}
+
+// Runs the conversion on another thread, and reports the results on this thread as they come.
+// Returning early on `fail_fast` drops the receiver, which stops the conversion.
+fn sync_collect_results<F>(convert: F, total: usize, options: &ConvDirOptions) -> Result<(), Box<dyn Error + Send + Sync>>
+where
+    F: FnOnce(Sender<FileResult>) + Send,
+{
+    let (sender, receiver) = mpsc::channel();
+    std::thread::scope(|s| {
+        s.spawn(move || convert(sender));
+        let mut report = ConvReport::new(total, options);
+        for x in receiver {
+            report.add(x)?;
+        }
+        report.finish()
+    })
+}
+
+/// Same as `async_conv_dir`, but on a rayon thread pool instead of async tasks.
+pub fn sync_conv_dir(input_dir: &Path, output_dir: &Path, options: &ConvDirOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    std::fs::create_dir_all(output_dir)?;
+
+    let pool = new_thread_pool(options.jobs)?;
+    let input_paths = list_input_files(input_dir);
+    check_log_names(input_paths.iter().map(|x| x.as_path()))?;
+    let total = input_paths.len();
+    sync_collect_results(|sender| sync_conv_files(&pool, input_paths, output_dir, options.output, sender), total, options)
+}
+
+/// True for the zip members to convert: mjlog files, optionally gzip-compressed.
+/// Other members such as the daily index files (`scc20190101.html.gz`) are skipped.
+fn is_mjlog_member(name: &str) -> bool {
+    let path = Path::new(name);
+    let path = if path.extension().is_some_and(|x| x == "gz") { path.with_extension("") } else { path.to_path_buf() };
+    match path.extension() {
+        Some(x) => x == "xml" || x == "mjlog",
+        None => path.file_name().is_some_and(|x| parse_game_id(&x.to_string_lossy()).is_some()),
+    }
+}
+
+// Bytes of the zip member, reported as "foo.zip/2019/bar.mjlog".
+fn read_member(archive: &mut ZipArchive<BufReader<File>>, input_zip: &Path, name: &str) -> (PathBuf, Result<Vec<u8>, Box<dyn Error + Send + Sync>>) {
+    let bytes = archive.by_name(name).map_err(|e| e.into()).and_then(|mut x| {
+        let mut bytes = Vec::new();
+        x.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    });
+    (input_zip.join(name), bytes)
+}
+
+/// Converts the mjlog files in a zip archive, such as the yearly scraw bundles of tenhou, without extracting them to disk.
+/// The members are read one by one, and at most `jobs` of them are in memory at a time.
+pub async fn async_conv_zip(input_zip: &Path, output_dir: &Path, options: &ConvDirOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    std::fs::create_dir_all(output_dir)?;
+
+    let mut archive = ZipArchive::new(BufReader::new(File::open(input_zip)?))?;
+    let members: Vec<String> = archive.file_names().filter(|x| is_mjlog_member(x)).map(String::from).collect();
+    check_log_names(members.iter().map(Path::new))?;
+    let total = members.len();
+
+    let tasks = stream::iter(members)
+        .map(|name| {
+            let (path, bytes) = read_member(&mut archive, input_zip, &name);
+            let output_dir = output_dir.to_path_buf();
+            let output = options.output;
+            async_std::task::spawn(async move {
+                let ret = match bytes {
+                    Ok(bytes) => write_outputs(get_log_name(&path), bytes, &output_dir, &output).await,
+                    Err(x) => Err(x),
+                };
+                (path, ret)
+            })
+        })
+        .buffer_unordered(jobs_or_cpus(options.jobs));
+    collect_results(tasks, total, options).await
+}
+
+/// Same as `async_conv_zip`, but on a rayon thread pool instead of async tasks.
+/// The members are read one by one as the threads take them.
+pub fn sync_conv_zip(input_zip: &Path, output_dir: &Path, options: &ConvDirOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    std::fs::create_dir_all(output_dir)?;
+
+    let pool = new_thread_pool(options.jobs)?;
+    let mut archive = ZipArchive::new(BufReader::new(File::open(input_zip)?))?;
+    let members: Vec<String> = archive.file_names().filter(|x| is_mjlog_member(x)).map(String::from).collect();
+    check_log_names(members.iter().map(Path::new))?;
+    let total = members.len();
+
+    let output = options.output;
+    let convert = |sender: Sender<FileResult>| {
+        pool.install(|| {
+            let members = members.iter().map(|name| read_member(&mut archive, input_zip, name));
+            // An error is the receiver gone.
+            let _ = members.par_bridge().try_for_each_with(sender, |sender, (path, bytes)| {
+                let ret = bytes.and_then(|bytes| sync_write_outputs(get_log_name(&path), bytes, output_dir, &output));
+                sender.send((path, ret))
+            });
+        })
+    };
+    sync_collect_results(convert, total, options)
+}
+
+/// Converts every game in the XML to tenhou-JSON and writes one line per game. `output.pretty` must be off.
+pub fn write_jsonl_bytes<W: Write>(writer: &mut W, reference: String, bytes: Vec<u8>, output: &OutputOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    for (_, json) in read_mjlog_bytes(reference, bytes, output)? {
+        writeln!(writer, "{}", json)?;
+    }
+    Ok(())
+}
+
+/// Writes the games of the files as JSON Lines in the order of the files. Failures are reported on stderr, and the other files are still written.
+pub fn write_jsonl_files<W: Write>(input_paths: &[PathBuf], writer: &mut W, output: &OutputOptions, fail_fast: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
+    write_files(input_paths, fail_fast, |reference, bytes| write_jsonl_bytes(writer, reference, bytes, output))
+}
+
+/// Converts every game in the XML and writes the round tables to the Arrow stream.
+#[cfg(feature = "arrow")]
+pub fn write_arrow_bytes<W: Write>(writer: &mut RoundsArrowWriter<W>, reference: String, bytes: Vec<u8>, output: &OutputOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    for tenhou_json in conv_contents(&reference, &decode_contents(bytes)?, output)? {
+        writer.write(&tenhou_json)?;
+    }
+    Ok(())
+}
+
+/// Writes the round tables of the files to the Arrow stream, one record batch per game as soon as it is converted.
+/// Failures are reported on stderr, since stdout may be the stream.
+#[cfg(feature = "arrow")]
+pub fn stream_arrow_files<W: Write>(input_paths: &[PathBuf], writer: &mut RoundsArrowWriter<W>, output: &OutputOptions, fail_fast: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
+    write_files(input_paths, fail_fast, |reference, bytes| write_arrow_bytes(writer, reference, bytes, output))
+}
+
+/// Converts every game in the XML and writes the rows of the table to the Parquet file.
+#[cfg(feature = "parquet")]
+pub fn write_parquet_bytes<W: Write + Send>(writer: &mut TableParquetWriter<W>, table: ParquetTable, reference: String, bytes: Vec<u8>, output: &OutputOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let content_xml = decode_contents(bytes)?;
+    match table {
+        ParquetTable::Rounds => {
+            for tenhou_json in conv_contents(&reference, &content_xml, output)? {
+                writer.write(&rounds_record_batch(&tenhou_json)?)?;
+            }
         }
+        ParquetTable::Actions => {
+            let mjlogs = parse_contents(&reference, &content_xml, output)?;
+            for (i, mjlog) in mjlogs.iter().enumerate() {
+                writer.write(&actions_record_batch(&numbered_name(&reference, i, mjlogs.len()), mjlog)?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes the rows of the files to the Parquet file. Failures are reported on stderr, and the other files are still written.
+#[cfg(feature = "parquet")]
+pub fn write_parquet_files<W: Write + Send>(input_paths: &[PathBuf], writer: &mut TableParquetWriter<W>, table: ParquetTable, output: &OutputOptions, fail_fast: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
+    write_files(input_paths, fail_fast, |reference, bytes| write_parquet_bytes(writer, table, reference, bytes, output))
+}
+
+/// Parses every game in the XML and writes it to the database, numbering the references of multi-game files.
+#[cfg(feature = "sqlite")]
+pub fn write_sqlite_bytes(writer: &mut SqliteWriter, reference: String, bytes: Vec<u8>, output: &OutputOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mjlogs = parse_contents(&reference, &decode_contents(bytes)?, output)?;
+    for (i, mjlog) in mjlogs.iter().enumerate() {
+        writer.write(&numbered_name(&reference, i, mjlogs.len()), mjlog)?;
     }
     Ok(())
 }
+
+/// Writes the games of the files to the database. Failures are reported on stderr, and the other files are still written.
+#[cfg(feature = "sqlite")]
+pub fn write_sqlite_files(input_paths: &[PathBuf], writer: &mut SqliteWriter, output: &OutputOptions, fail_fast: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
+    write_files(input_paths, fail_fast, |reference, bytes| write_sqlite_bytes(writer, reference, bytes, output))
+}
+
+// Writes the contents of each file with the log name as the reference, and counts the failures.
+fn write_files<F>(input_paths: &[PathBuf], fail_fast: bool, mut write: F) -> Result<(), Box<dyn Error + Send + Sync>>
+where
+    F: FnMut(String, Vec<u8>) -> Result<(), Box<dyn Error + Send + Sync>>,
+{
+    let mut failed = 0;
+    for input_path in input_paths {
+        let ret = std::fs::read(input_path).map_err(|e| e.into()).and_then(|bytes| write(get_log_name(input_path), bytes));
+        if let Err(x) = ret {
+            let x = format!("{}: {}", input_path.to_string_lossy(), x);
+            if fail_fast {
+                return Err(x.into());
+            }
+            eprintln!("failed: {}", x);
+            failed += 1;
+        }
+    }
+
+    if failed == 0 {
+        Ok(())
+    } else {
+        Err(format!("{} file(s) failed to convert.", failed).into())
+    }
+}
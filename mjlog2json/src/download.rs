@@ -0,0 +1,33 @@
+use crate::converter::*;
+use mjlog2json_core::game_id::*;
+use std::error::Error;
+use std::io::Read;
+use std::time::Duration;
+
+// Tenhou serves the mjlog of the game ID at this URL, e.g. https://tenhou.net/0/log/?2025010203gm-00a9-0000-0123abcd
+const LOG_URL: &str = "https://tenhou.net/0/log/?";
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Parses a game ID (`2025010203gm-00a9-0000-0123abcd`) or a viewer URL (`https://tenhou.net/0/?log=...`).
+pub fn parse_download_target(s: &str) -> Option<GameId> {
+    parse_game_id(s).or_else(|| parse_log_url(s).map(|x| x.game_id))
+}
+
+/// URL of the mjlog of the game.
+pub fn log_url(game_id: &GameId) -> String {
+    format!("{}{}", LOG_URL, game_id)
+}
+
+/// Fetches the mjlog of the game. The response may be gzip-compressed.
+pub fn download_mjlog(game_id: &GameId) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    // The error of ureq contains the URL.
+    let response = ureq::get(&log_url(game_id)).timeout(TIMEOUT).call()?;
+    let mut bytes = Vec::new();
+    response.into_reader().read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Fetches the mjlog of the game and converts it. Returns the reference and the output of each game.
+pub fn read_mjlog_download(game_id: &GameId, output: &OutputOptions) -> Result<Vec<(String, String)>, Box<dyn Error + Send + Sync>> {
+    read_mjlog_bytes(game_id.to_string(), download_mjlog(game_id)?, output)
+}
@@ -0,0 +1,76 @@
+use crate::converter::*;
+use mjlog2json_core::features::*;
+use mjlog2json_core::observation::*;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// File format of the features.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum FeatureFormat {
+    /// NumPy archive with "x.npy" (planes) and "y.npy" (labels).
+    #[default]
+    Npz,
+    /// Flat little-endian planes (f32) in the file, and labels (i64) in the file with ".labels" appended.
+    Bin,
+}
+
+impl FromStr for FeatureFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "npz" => Ok(FeatureFormat::Npz),
+            "bin" => Ok(FeatureFormat::Bin),
+            _ => Err(format!("unknown feature format: {} (expected npz or bin)", s)),
+        }
+    }
+}
+
+/// Encodes the decisions of the seat (or of every seat) in every game in the file or directory, and writes them to the output.
+/// Games that cannot be replayed are reported and skipped.
+pub fn write_features(input_path: &Path, seat: Option<u8>, format: FeatureFormat, output_path: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut features = Features::new();
+    for (reference, mjlog) in load_mjlogs(input_path)? {
+        let seats = match seat {
+            Some(x) => vec![x],
+            None => (0..4).collect(),
+        };
+        for seat in seats {
+            match extract_observations(&mjlog, seat) {
+                Ok(pairs) => features.extend(&pairs),
+                Err(e) => {
+                    eprintln!("{}: {}", reference, e);
+                    break;
+                }
+            }
+        }
+    }
+
+    match format {
+        FeatureFormat::Npz => {
+            let mut zip = ZipWriter::new(File::create(output_path)?);
+            zip.start_file("x.npy", SimpleFileOptions::default())?;
+            features.write_planes_npy(&mut zip)?;
+            zip.start_file("y.npy", SimpleFileOptions::default())?;
+            features.write_labels_npy(&mut zip)?;
+            zip.finish()?;
+        }
+        FeatureFormat::Bin => {
+            let mut planes = BufWriter::new(File::create(output_path)?);
+            write_flat_to(&mut planes, &features.planes)?;
+            planes.flush()?;
+            let mut labels_path = output_path.as_os_str().to_owned();
+            labels_path.push(".labels");
+            let mut labels = BufWriter::new(File::create(labels_path)?);
+            write_flat_to(&mut labels, &features.labels)?;
+            labels.flush()?;
+        }
+    }
+    eprintln!("{} samples of shape ({}, {})", features.len(), NUM_PLANES, NUM_KINDS);
+    Ok(())
+}
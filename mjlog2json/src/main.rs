@@ -7,8 +7,61 @@
 //! ```
 //! mjlog2json 2025010203gm-0000-0000-01234567.xml
 //! mjlog2json 2025010203gm-0000-0000-01234567.xml -o 2025010203gm-0000-0000-01234567.json
+//! mjlog2json 2025010203gm-0000-0000-01234567.mjlog
 //! mjlog2json input_dir
 //! mjlog2json input_dir -o output_dir
+//! mjlog2json input_dir -o output_dir -j 4
+//! mjlog2json input_dir -o output_dir --sync-parallel
+//! mjlog2json scraw2019.zip -o output_dir
+//! curl ... | mjlog2json - | jq
+//! mjlog2json 2025010203gm-0000-0000-01234567.xml --format text --locale en
+//! mjlog2json 2025010203gm-0000-0000-01234567.xml --locale en
+//! mjlog2json convert input_dir -o output_dir
+//! mjlog2json input_dir -o output_dir --format markdown
+//! mjlog2json 2025010203gm-0000-0000-01234567.xml --format narrative --locale en
+//! mjlog2json 2025010203gm-0000-0000-01234567.xml --format html > replay.html
+//! mjlog2json 2025010203gm-0000-0000-01234567.mjlog --format mjai
+//! mjlog2json 2025010203gm-0000-0000-01234567.xml --pretty
+//! mjlog2json truncated_dir -o output_dir --synthesize-owari
+//! mjlog2json new_logs_dir -o output_dir --lenient
+//! mjlog2json truncated_dir -o output_dir --allow-truncated --synthesize-owari
+//! mjlog2json aborted_dir -o output_dir --incomplete-round abort --synthesize-owari
+//! mjlog2json input_dir -o output_dir --anonymize seat
+//! mjlog2json input_dir -o output_dir --behaviors output_dir/behaviors.json
+//! mjlog2json input_dir -o output_dir --compress zstd
+//! mjlog2json input_dir --format arrow | python -c "import polars, sys; print(polars.read_ipc_stream(sys.stdin.buffer))"
+//! mjlog2json input_dir --format arrow -o tcp://localhost:9000
+//! mjlog2json input_dir --single-file games.jsonl
+//! mjlog2json input_dir --format parquet --table actions -o actions.parquet
+//! mjlog2json input_dir --format sqlite -o games.db
+//! mjlog2json stats input_dir
+//! mjlog2json stats input_dir --riichi-discards riichi_discards.csv
+//! mjlog2json stats input_dir --player NAME --calendar calendar.csv
+//! mjlog2json stats input_dir --exclude-aka-dora --exclude-kuitan
+//! mjlog2json stats input_dir --yaku yaku.csv
+//! mjlog2json stats compare houou2019_dir houou2024_dir
+//! mjlog2json stats players input_dir --csv
+//! mjlog2json query input_dir --filter "player:Bob and yakuman"
+//! mjlog2json query input_dir --filter "double_riichi or yakuman" --rounds
+//! mjlog2json index input_dir -o index.jsonl
+//! mjlog2json query --index index.jsonl --filter "player:Bob and hanchan"
+//! mjlog2json check official_dir
+//! mjlog2json check official_dir --all diff_dir --ignore rate --ignore dan
+//! mjlog2json selftest
+//! mjlog2json bench input_dir
+//! mjlog2json bench input_dir --parallel
+//! mjlog2json tui 2025010203gm-0000-0000-01234567.mjlog --export problems.jsonl
+//! mjlog2json replay 2025010203gm-0000-0000-01234567.xml --round 4
+//! mjlog2json problem 2025010203gm-0000-0000-01234567.mjlog --turn 3:12 --turn 5:4
+//! mjlog2json snapshot 2025010203gm-0000-0000-01234567.mjlog --turn 3:12 --hide-hands -o board.svg
+//! mjlog2json problem input_dir --find deal_in_with_safe_tile --find tenpai_choice -o problems.jsonl
+//! mjlog2json review 2025010203gm-0000-0000-01234567.mjlog --hero 2 -o mortal.jsonl
+//! mjlog2json review 2025010203gm-0000-0000-01234567.mjlog --hero 2 --reviewer mjai-reviewer --reviewer-arg -e --reviewer-arg mortal --reviewer-arg -i --reviewer-arg {input} --reviewer-arg -a --reviewer-arg {hero}
+//! mjlog2json features input_dir --seat 0 -o features.npz
+//! mjlog2json download 2025010203gm-0000-0000-01234567
+//! mjlog2json download "https://tenhou.net/0/?log=2025010203gm-0000-0000-01234567&tw=0" -o output_dir --format mjai
+//! mjlog2json serve --port 8080
+//! curl --data-binary @2025010203gm-0000-0000-01234567.xml http://localhost:8080/tenhou
 //! ```
 //!
 //! # Install
@@ -17,44 +70,919 @@
 //! cargo install mjlog2json
 //! ```
 
+mod bench;
 mod converter;
+mod download;
+mod features;
+mod problem;
+mod query;
+mod review;
+mod selftest;
+mod serve;
+mod stats;
+mod tui;
 
+use crate::bench::*;
 use crate::converter::*;
-use argh::FromArgs;
+use crate::download::*;
+use crate::features::*;
+use crate::problem::*;
+use crate::query::*;
+use crate::review::*;
+use crate::selftest::*;
+use crate::serve::*;
+use crate::stats::*;
+use crate::tui::*;
+use argh::{EarlyExit, FromArgs, SubCommands};
+use mjlog2json_checker::checker::{async_check_glob, sync_check_glob, CheckOptions, DiffOutput};
+use mjlog2json_checker::json_diff::IgnoredField;
+use mjlog2json_core::anonymize::Anonymize;
+use mjlog2json_core::behaviors::*;
+use mjlog2json_core::board_svg::BoardSvgOptions;
+use mjlog2json_core::conv::{numbered_name, IncompleteRound};
+use mjlog2json_core::number_format::DigitGrouping;
+use mjlog2json_core::problem::TurnMark;
+use mjlog2json_core::problem_bank::ProblemKind;
+use mjlog2json_core::query::Query;
+#[cfg(feature = "arrow")]
+use mjlog2json_core::round_arrow::RoundsArrowWriter;
+#[cfg(feature = "sqlite")]
+use mjlog2json_core::sqlite::SqliteWriter;
+use mjlog2json_core::stats::Normalization;
+#[cfg(feature = "parquet")]
+use mjlog2json_core::table_parquet::{ParquetTable, TableParquetWriter};
 use std::error::Error;
-use std::path::PathBuf;
+#[cfg(feature = "arrow")]
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Read, Write};
+#[cfg(feature = "arrow")]
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use tenhou_json::exporter::Locale;
 
-/// Convert mjlog-XML to tenhou-JSON.
+// Reads from stdin or writes to stdout instead of a file.
+const STDIO_PATH: &str = "-";
+// Output of the Arrow stream to a socket, e.g. "tcp://localhost:9000".
+#[cfg(feature = "arrow")]
+const TCP_SCHEME: &str = "tcp://";
+
+/// Convert mjlog-XML to tenhou-JSON, and more with the subcommands. Without a subcommand, `mjlog2json <input>` is same as `mjlog2json convert <input>`.
+#[derive(FromArgs, Debug)]
+struct TopLevel {
+    #[argh(subcommand)]
+    command: Command,
+}
+
+/// Convert mjlog-XML to tenhou-JSON or the other formats.
+#[derive(FromArgs, Debug)]
+#[argh(
+    subcommand,
+    name = "convert",
+    note = "Options can also be given by environment variables, e.g. in containers: MJLOG2JSON_INPUT, MJLOG2JSON_OUTPUT, MJLOG2JSON_JOBS, MJLOG2JSON_FORMAT, MJLOG2JSON_LOCALE, and MJLOG2JSON_FAIL_FAST, MJLOG2JSON_QUIET, MJLOG2JSON_PRETTY, MJLOG2JSON_SYNC_PARALLEL (1 or true). The command line takes precedence."
+)]
+struct ConvertArgs {
+    /// input XML file, directory or zip archive such as scraw2019.zip (gzip-compressed .mjlog is also accepted). "-" reads from stdin.
+    #[argh(positional)]
+    input: String,
+
+    /// output JSON file or directory (default: same as the input directory, or the zip archive name without .zip). "-" writes to stdout. Files with multiple games are written as foo-1.json, foo-2.json, ... For --format arrow, a file, "-" (default) or tcp://HOST:PORT.
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+
+    /// stop directory conversion at the first error instead of converting the remaining files.
+    #[argh(switch)]
+    fail_fast: bool,
+
+    /// number of files converted at a time in directory conversion. 0 (default) uses the number of CPUs.
+    #[argh(option, short = 'j', default = "0")]
+    jobs: usize,
+
+    /// convert the files of a directory or zip archive on a thread pool with work stealing instead of async tasks, which suits the CPU-bound conversion of many files.
+    #[argh(switch)]
+    sync_parallel: bool,
+
+    /// do not show the progress of directory conversion. Failures and the summary are still printed.
+    #[argh(switch, short = 'q')]
+    quiet: bool,
+
+    /// output format: tenhou (default, also json), mjai (events per line), csv (one row per round result with yaku, riichi and placements), xml (decompressed mjlog), text (human-readable transcript), narrative (every draw, discard and call in order), markdown (summary tables), html (self-contained replayer of the turns), arrow (round results of all inputs as one Arrow IPC stream), parquet (round or action table of all inputs as one Parquet file, with -o) or sqlite (games of all inputs as one SQLite database, with -o). arrow, parquet and sqlite need the features of the same name, which are enabled by default.
+    #[argh(option, default = "OutputFormat::Json")]
+    format: OutputFormat,
+
+    /// append the tenhou-JSON of every game to this file, one compact object per line (JSON Lines), instead of a file per game. "-" writes to stdout.
+    #[argh(option)]
+    single_file: Option<String>,
+
+    /// table of --format parquet: rounds (default, one row per winner or draw) or actions (one row per action).
+    #[cfg(feature = "parquet")]
+    #[argh(option, default = "ParquetTable::Rounds")]
+    table: ParquetTable,
+
+    /// language of the dan and rule names, and of the text, narrative, markdown and html output: ja (default) or en.
+    #[argh(option, default = "Locale::Japanese")]
+    locale: Locale,
+
+    /// thousands separator of points in the text, markdown and html output: comma (default, 25,000), none (25000), period (25.000) or space.
+    #[argh(option, default = "DigitGrouping::Comma")]
+    digit_grouping: DigitGrouping,
+
+    /// indent the tenhou-JSON output, for reading by eye or committing to git.
+    #[argh(switch)]
+    pretty: bool,
+
+    /// for logs whose last round has no final result (owari), compute it from the last scores with tenhou's uma and oka instead of failing.
+    #[argh(switch)]
+    synthesize_owari: bool,
+    /// accept yaku IDs, tags and attributes unknown to this version, e.g. of logs newer than this tool, with warnings instead of failing.
+    #[argh(switch)]
+    lenient: bool,
+    /// accept logs cut off before the end, e.g. of disconnected downloads, and convert their complete rounds. Use with --synthesize-owari for the final result.
+    #[argh(switch)]
+    allow_truncated: bool,
+    /// for a round without a win or draw, e.g. of a game aborted by a disconnection: fail (default), drop the round, or abort to keep it with a 中断 result. --allow-truncated defaults to drop.
+    #[argh(option)]
+    incomplete_round: Option<IncompleteRound>,
+    /// compress the file of each game: gzip (foo.json.gz), zstd (foo.json.zst) or none (default). Stdout gets one gzip member or zstd frame per game.
+    #[argh(option, default = "Compression::None")]
+    compress: Compression,
+    /// replace the player names by seat (Player0..3) or by a hash of the name (hash), clear the dan and rate, and remove the SHUFFLE seed, to share logs without the account names.
+    #[argh(option)]
+    anonymize: Option<Anonymize>,
+    /// write the conversion behaviors and heuristics with their status under these options to this JSON file, to keep with the converted data.
+    #[argh(option)]
+    behaviors: Option<String>,
+}
+
+/// Print statistics of mjlog files as JSON.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "stats")]
+struct StatsArgs {
+    #[argh(subcommand)]
+    command: Option<StatsCommand>,
+
+    /// input XML file or directory.
+    #[argh(positional)]
+    input: Option<String>,
+
+    /// write every discard made against an opponent's riichi (safety category, deal-in) to this CSV file.
+    #[argh(option)]
+    riichi_discards: Option<String>,
+
+    /// aggregate games of this player by hour of day, weekday and month (games played, average placement).
+    #[argh(option)]
+    player: Option<String>,
+
+    /// write the aggregation of --player to this CSV file.
+    #[argh(option)]
+    calendar: Option<String>,
+
+    /// leave the han of aka dora out of the han distribution, to compare with rules without red fives.
+    #[argh(switch)]
+    exclude_aka_dora: bool,
+
+    /// leave wins by open tanyao out of the han distribution, to compare with rules without kuitan.
+    #[argh(switch)]
+    exclude_kuitan: bool,
+
+    /// write the yaku frequency table (wins, rate and average han of each yaku) to this CSV file.
+    #[argh(option)]
+    yaku: Option<String>,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand)]
+enum StatsCommand {
+    Compare(CompareArgs),
+    Players(PlayersArgs),
+}
+
+/// Compare two corpora side by side: ryuukyoku rate, average winning points and yaku frequency, with "*" (p < 0.05) or "**" (p < 0.01) on significant differences.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "compare")]
+struct CompareArgs {
+    /// first input XML file or directory.
+    #[argh(positional)]
+    a: String,
+
+    /// second input XML file or directory.
+    #[argh(positional)]
+    b: String,
+}
+
+/// Per-player statistics: win rate, deal-in rate, riichi rate, call rate, average winning and deal-in points, and average placement.
 #[derive(FromArgs, Debug)]
-struct Args {
+#[argh(subcommand, name = "players")]
+struct PlayersArgs {
     /// input XML file or directory.
     #[argh(positional)]
     input: String,
 
-    /// output JSON file or directory.
+    /// print CSV instead of JSON.
+    #[argh(switch)]
+    csv: bool,
+}
+
+/// Convert a built-in game and check the output, to confirm that the installed binary works.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "selftest")]
+struct SelftestArgs {}
+
+/// Measure read, parse, convert and export over a corpus: time, throughput, allocations and peak RSS of each stage, as JSON.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "bench")]
+struct BenchArgs {
+    /// input XML file or directory.
+    #[argh(positional)]
+    input: String,
+
+    /// also convert the files end to end on all CPUs, with async tasks and with --sync-parallel, to compare the two.
+    #[argh(switch)]
+    parallel: bool,
+}
+
+/// Download mjlog of games from tenhou.net and convert them.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "download")]
+struct DownloadArgs {
+    /// game IDs (2025010203gm-00a9-0000-0123abcd) or viewer URLs (`https://tenhou.net/0/?log=...`).
+    #[argh(positional)]
+    games: Vec<String>,
+
+    /// output directory (default: current directory). "-" writes to stdout.
+    #[argh(option, short = 'o', default = "String::from(\".\")")]
+    output: String,
+
+    /// output format: same as the conversion. Use xml to keep the downloaded mjlog.
+    #[argh(option, default = "OutputFormat::Json")]
+    format: OutputFormat,
+
+    /// language of the dan and rule names, and of the text, narrative, markdown and html output: ja (default) or en.
+    #[argh(option, default = "Locale::Japanese")]
+    locale: Locale,
+
+    /// thousands separator of points in the text, markdown and html output: comma (default, 25,000), none (25000), period (25.000) or space.
+    #[argh(option, default = "DigitGrouping::Comma")]
+    digit_grouping: DigitGrouping,
+
+    /// indent the tenhou-JSON output.
+    #[argh(switch)]
+    pretty: bool,
+
+    /// compress the files: gzip (foo.json.gz), zstd (foo.json.zst) or none (default).
+    #[argh(option, default = "Compression::None")]
+    compress: Compression,
+}
+
+/// Serve the conversion over HTTP: POST mjlog-XML to /tenhou or /mjai. GET /capabilities lists the formats.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "serve")]
+struct ServeArgs {
+    /// port to listen on (default: 8080).
+    #[argh(option, default = "8080")]
+    port: u16,
+
+    /// address to listen on (default: 127.0.0.1). Use 0.0.0.0 to accept other hosts.
+    #[argh(option, default = "String::from(\"127.0.0.1\")")]
+    host: String,
+
+    /// number of requests handled at a time. 0 (default) uses the number of CPUs.
+    #[argh(option, short = 'j', default = "0")]
+    jobs: usize,
+}
+
+/// Replay a game interactively in the terminal: step through the turns, switch the perspective and see the waits.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "tui")]
+struct TuiArgs {
+    /// input XML file (gzip-compressed .mjlog is also accepted).
+    #[argh(positional)]
+    input: String,
+
+    /// index of the game in files with multiple games, from 0 (default: 0).
+    #[argh(option, default = "0")]
+    game: usize,
+
+    /// write the draws marked with "m" as problems (JSON lines) to this file instead of stdout.
+    #[argh(option)]
+    export: Option<String>,
+}
+
+/// Step through the rounds of a game in the terminal: hands, rivers, dora and scores. Same viewer as tui.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "replay")]
+struct ReplayArgs {
+    /// input XML file (gzip-compressed .mjlog is also accepted).
+    #[argh(positional)]
+    input: String,
+
+    /// index of the game in files with multiple games, from 0 (default: 0).
+    #[argh(option, default = "0")]
+    game: usize,
+
+    /// round to start at, from 0 (default: 0).
+    #[argh(option, default = "0")]
+    round: usize,
+}
+
+/// Export situations after draws as "what would you discard" problems, one JSON per line.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "problem")]
+struct ProblemArgs {
+    /// input XML file (gzip-compressed .mjlog is also accepted), or a directory for --find.
+    #[argh(positional)]
+    input: String,
+
+    /// draw to export as ROUND:DRAW, both from 0, e.g. 3:12 for the 13th draw of the 4th round. Can be repeated.
+    #[argh(option)]
+    turn: Vec<TurnMark>,
+
+    /// index of the game in files with multiple games, from 0 (default: 0).
+    #[argh(option, default = "0")]
+    game: usize,
+
+    /// instead of --turn, find problems in every game of the input: deal_in_with_safe_tile (dealt into a riichi holding a genbutsu) or tenpai_choice (tenpai with different waits by the discard). Can be repeated.
+    #[argh(option)]
+    find: Vec<ProblemKind>,
+
+    /// output file (default: stdout).
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+}
+
+/// Render the table just after a draw as an SVG image, for blog posts and problem sets.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "snapshot")]
+struct SnapshotArgs {
+    /// input XML file (gzip-compressed .mjlog is also accepted).
+    #[argh(positional)]
+    input: String,
+
+    /// draw to render as ROUND:DRAW, both from 0, e.g. 3:12 for the 13th draw of the 4th round.
+    #[argh(option)]
+    turn: TurnMark,
+
+    /// index of the game in files with multiple games, from 0 (default: 0).
+    #[argh(option, default = "0")]
+    game: usize,
+
+    /// seat at the bottom, from 0 (default: the player who drew).
+    #[argh(option)]
+    perspective: Option<usize>,
+
+    /// show only the hand at the bottom.
+    #[argh(switch)]
+    hide_hands: bool,
+
+    /// output file (default: stdout).
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+}
+
+/// Write a game as the review input of Mortal (mjai events with the hero seat as "id" of start_game), and optionally run a reviewer on it.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "review")]
+struct ReviewArgs {
+    /// input XML file (gzip-compressed .mjlog is also accepted). 4-player games only.
+    #[argh(positional)]
+    input: String,
+
+    /// seat to review, from 0 (the first player of the log) to 3.
+    #[argh(option)]
+    hero: u8,
+
+    /// index of the game in files with multiple games, from 0 (default: 0).
+    #[argh(option, default = "0")]
+    game: usize,
+
+    /// output file of the review input (default: stdout, or a temporary file with --reviewer).
     #[argh(option, short = 'o')]
     output: Option<String>,
+
+    /// reviewer executable to run on the review input, e.g. mjai-reviewer.
+    #[argh(option)]
+    reviewer: Option<String>,
+
+    /// argument of the reviewer, where {input} is replaced with the review input file and {hero} with the seat. Without {input}, the input is given by stdin. Can be repeated.
+    #[argh(option)]
+    reviewer_arg: Vec<String>,
+}
+
+/// Check that the conversion matches the official tenhou-JSON: every XML in the directory is converted and compared with the JSON of the same name.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "check")]
+struct CheckArgs {
+    /// directory of the official XML and JSON files (default: data).
+    #[argh(positional, default = "String::from(\"data\")")]
+    dir: String,
+
+    /// check the files one by one instead of in parallel, printing each file before checking it.
+    #[argh(switch)]
+    sync: bool,
+
+    /// check every file, and write `<name>.expected.txt` and `<name>.actual.txt` of the failing ones to this directory. Without it, the check stops at the first difference and writes expected.txt and actual.txt.
+    #[argh(option)]
+    all: Option<String>,
+
+    /// field excluded from the comparison: ver, connection, ratingc, rule, lobby, dan, rate, sx, sc or name. Can be repeated.
+    #[argh(option)]
+    ignore: Vec<IgnoredField>,
+}
+
+/// Print the games (or rounds) matching a filter expression, e.g. "player:Bob and yakuman". See mjlog2json_core::query for the terms.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "query")]
+struct QueryArgs {
+    /// input XML file (gzip-compressed .mjlog is also accepted), or a directory.
+    #[argh(positional)]
+    input: Option<String>,
+
+    /// read the games from this index written by the index subcommand, instead of parsing the logs.
+    #[argh(option)]
+    index: Option<String>,
+
+    /// filter expression of terms (player:NAME, lobby:N, hanchan, tonpuu, sanma, yonma, double_riichi, yakuman) joined by and, or and not.
+    #[argh(option)]
+    filter: Query,
+
+    /// print each matching round as `<log name> <round index>` instead of each matching game.
+    #[argh(switch)]
+    rounds: bool,
+}
+
+/// Scan the logs once and write an index of the games (date, players, rule, final points, yakuman) as JSON lines, for query --index.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "index")]
+struct IndexArgs {
+    /// input XML file (gzip-compressed .mjlog is also accepted), or a directory.
+    #[argh(positional)]
+    input: String,
+
+    /// output file.
+    #[argh(option, short = 'o')]
+    output: String,
+}
+
+/// Write the decisions of players as feature planes and action labels for machine learning. See mjlog2json_core::features for the layout.
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "features")]
+struct FeaturesArgs {
+    /// input XML file (gzip-compressed .mjlog is also accepted), or a directory.
+    #[argh(positional)]
+    input: String,
+
+    /// seat whose decisions are written, from 0 to 3 (default: every seat).
+    #[argh(option)]
+    seat: Option<u8>,
+
+    /// npz (default; x.npy of shape (N, 57, 34) and y.npy of shape (N,)) or bin (flat little-endian f32 planes in the output and i64 labels in `<output>.labels`).
+    #[argh(option, default = "FeatureFormat::Npz")]
+    format: FeatureFormat,
+
+    /// output file.
+    #[argh(option, short = 'o')]
+    output: String,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand)]
+enum Command {
+    Convert(ConvertArgs),
+    Check(CheckArgs),
+    Stats(StatsArgs),
+    Selftest(SelftestArgs),
+    Bench(BenchArgs),
+    Download(DownloadArgs),
+    Serve(ServeArgs),
+    Tui(TuiArgs),
+    Replay(ReplayArgs),
+    Problem(ProblemArgs),
+    Snapshot(SnapshotArgs),
+    Review(ReviewArgs),
+    Features(FeaturesArgs),
+    Query(QueryArgs),
+    Index(IndexArgs),
+}
+
+fn from_args_or_exit<T: FromArgs>(command_name: &[&str], args: &[&str]) -> T {
+    T::from_args(command_name, args).unwrap_or_else(|EarlyExit { output, status }| match status {
+        Ok(()) => {
+            println!("{}", output);
+            std::process::exit(0)
+        }
+        Err(()) => {
+            eprintln!("{}\nRun {} --help for more information.", output, command_name.join(" "));
+            std::process::exit(1)
+        }
+    })
+}
+
+// Environment variables of the options taking a value: (name, short flag, long flag).
+const ENV_OPTIONS: [(&str, &str, &str); 4] = [
+    ("MJLOG2JSON_OUTPUT", "-o", "--output"),
+    ("MJLOG2JSON_JOBS", "-j", "--jobs"),
+    ("MJLOG2JSON_FORMAT", "--format", "--format"),
+    ("MJLOG2JSON_LOCALE", "--locale", "--locale"),
+];
+const ENV_SWITCHES: [(&str, &str, &str); 4] = [
+    ("MJLOG2JSON_FAIL_FAST", "--fail-fast", "--fail-fast"),
+    ("MJLOG2JSON_QUIET", "-q", "--quiet"),
+    ("MJLOG2JSON_PRETTY", "--pretty", "--pretty"),
+    ("MJLOG2JSON_SYNC_PARALLEL", "--sync-parallel", "--sync-parallel"),
+];
+const ENV_INPUT: &str = "MJLOG2JSON_INPUT";
+
+fn has_positional(args: &[&str]) -> bool {
+    let mut iter = args.iter();
+    while let Some(&x) = iter.next() {
+        if ENV_OPTIONS.iter().any(|&(_, short, long)| x == short || x == long) {
+            iter.next();
+        } else if x == STDIO_PATH || !x.starts_with('-') {
+            return true;
+        }
+    }
+    false
+}
+
+// Arguments from the environment variables, for the options not given on the command line.
+fn env_args(args: &[&str]) -> Vec<String> {
+    let given = |short: &str, long: &str| args.iter().any(|&x| x == short || x == long);
+    let mut env_args = vec![];
+    for (name, short, long) in ENV_OPTIONS {
+        if let Ok(value) = std::env::var(name) {
+            if !given(short, long) {
+                env_args.extend([long.to_string(), value]);
+            }
+        }
+    }
+    for (name, short, long) in ENV_SWITCHES {
+        if std::env::var(name).is_ok_and(|x| matches!(x.as_str(), "1" | "true")) && !given(short, long) {
+            env_args.push(long.to_string());
+        }
+    }
+    if let Ok(value) = std::env::var(ENV_INPUT) {
+        if !has_positional(args) {
+            env_args.push(value);
+        }
+    }
+    env_args
+}
+
+// Same as argh::from_env, except that:
+// * a bare "-" is accepted as the positional input. argh treats it as an unknown flag unless it comes after "--".
+// * the subcommand is optional and defaults to convert, so that "mjlog2json <input>" keeps working.
+// * options of the conversion can be given by environment variables.
+fn command_from_env() -> Command {
+    let strings: Vec<String> = std::env::args().collect();
+    let cmd = Path::new(&strings[0]).file_name().map_or(strings[0].as_str(), |x| x.to_str().unwrap_or_default());
+    let mut rest: Vec<&str> = strings[1..].iter().map(|x| x.as_str()).collect();
+
+    let is_subcommand = |x: &str| Command::COMMANDS.iter().any(|c| c.name == x);
+    if !rest.first().is_some_and(|&x| is_subcommand(x) || x == "help" || x == "--help") {
+        rest.insert(0, "convert");
+    }
+
+    let env_args = if rest[0] == "convert" { env_args(&rest[1..]) } else { vec![] };
+    if rest[0] == "convert" {
+        rest.extend(env_args.iter().map(|x| x.as_str()));
+
+        let stdin_pos = rest.iter().enumerate().position(|(i, x)| *x == STDIO_PATH && !matches!(rest[i - 1], "-o" | "--output"));
+        if let Some(pos) = stdin_pos {
+            rest.remove(pos);
+            rest.extend(["--", STDIO_PATH]);
+        }
+    }
+
+    from_args_or_exit::<TopLevel>(&[cmd], &rest).command
 }
 
 #[async_std::main]
 async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
-    let args: Args = argh::from_env();
+    match command_from_env() {
+        Command::Convert(args) => convert(args).await,
+        Command::Check(args) => {
+            let options = CheckOptions {
+                output: args.all.map_or(DiffOutput::First, |x| DiffOutput::All(PathBuf::from(x))),
+                ignore: args.ignore,
+            };
+            let pattern = Path::new(&args.dir).join("*.xml");
+            if args.sync {
+                Ok(sync_check_glob(&pattern.to_string_lossy(), &options)?)
+            } else {
+                Ok(async_check_glob(&pattern.to_string_lossy(), &options).await?)
+            }
+        }
+        Command::Stats(StatsArgs {
+            command: Some(StatsCommand::Compare(args)), ..
+        }) => print_comparison(Path::new(&args.a), Path::new(&args.b)),
+        Command::Stats(StatsArgs {
+            command: Some(StatsCommand::Players(args)), ..
+        }) => print_player_stats(Path::new(&args.input), args.csv),
+        Command::Stats(args) => {
+            let input = args.input.ok_or("stats needs the input, or compare with two inputs.")?;
+            let options = StatsOptions {
+                riichi_discards: args.riichi_discards.as_deref().map(Path::new),
+                player: args.player.as_deref(),
+                calendar: args.calendar.as_deref().map(Path::new),
+                normalization: Normalization {
+                    exclude_aka_dora: args.exclude_aka_dora,
+                    exclude_kuitan: args.exclude_kuitan,
+                },
+                yaku: args.yaku.as_deref().map(Path::new),
+            };
+            print_stats(Path::new(&input), &options)
+        }
+        Command::Selftest(_) => run_selftest(),
+        Command::Bench(args) => run_bench(Path::new(&args.input), args.parallel),
+        Command::Download(args) => download(args),
+        Command::Serve(args) => {
+            let options = ServeOptions {
+                host: args.host,
+                port: args.port,
+                workers: if args.jobs == 0 { std::thread::available_parallelism().map_or(1, |x| x.get()) } else { args.jobs },
+            };
+            serve(&options)
+        }
+        Command::Tui(args) => run_tui(Path::new(&args.input), args.game, 0, args.export.as_deref().map(Path::new)),
+        Command::Replay(args) => run_tui(Path::new(&args.input), args.game, args.round, None),
+        Command::Problem(args) if !args.find.is_empty() => write_problem_bank(Path::new(&args.input), &args.find, args.output.as_deref().map(Path::new)),
+        Command::Problem(args) => {
+            let (reference, mjlog) = load_game(Path::new(&args.input), args.game)?;
+            write_problems(&reference, &mjlog, &args.turn, args.output.as_deref().map(Path::new))
+        }
+        Command::Snapshot(args) => {
+            let options = BoardSvgOptions {
+                perspective: args.perspective,
+                hide_hands: args.hide_hands,
+            };
+            write_snapshot(Path::new(&args.input), args.game, args.turn, &options, args.output.as_deref().map(Path::new))
+        }
+        Command::Review(args) => {
+            let options = ReviewOptions {
+                hero: args.hero,
+                game: args.game,
+                output: args.output.as_deref().map(Path::new),
+                reviewer: args.reviewer.as_deref().map(Path::new),
+                reviewer_args: &args.reviewer_arg,
+            };
+            review(Path::new(&args.input), &options)
+        }
+        Command::Query(args) => match (&args.input, &args.index) {
+            (Some(input), None) => write_query(Path::new(input), &args.filter, args.rounds),
+            (None, Some(_)) if args.rounds => Err("--rounds needs the logs, not --index.".into()),
+            (None, Some(index)) => write_index_query(Path::new(index), &args.filter),
+            _ => Err("Give either the input or --index.".into()),
+        },
+        Command::Index(args) => write_game_index(Path::new(&args.input), Path::new(&args.output)),
+        Command::Features(args) => write_features(Path::new(&args.input), args.seat, args.format, Path::new(&args.output)),
+    }
+}
+
+fn download(args: DownloadArgs) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if args.format.is_combined() {
+        return Err("download does not support --format arrow, parquet or sqlite. Download with --format xml and convert the files.".into());
+    }
+    let output = OutputOptions {
+        format: args.format,
+        locale: args.locale,
+        digit_grouping: args.digit_grouping,
+        pretty: args.pretty,
+        compression: args.compress,
+        ..Default::default()
+    };
+
+    // Check all arguments before accessing the server.
+    let mut game_ids = vec![];
+    for x in &args.games {
+        game_ids.push(parse_download_target(x).ok_or_else(|| format!("{}: not a game ID or a log URL.", x))?);
+    }
+
+    for game_id in &game_ids {
+        let contents = read_mjlog_download(game_id, &output).map_err(|e| format!("{}: {}", game_id, e))?;
+        for (reference, s) in contents {
+            if args.output == STDIO_PATH {
+                println!("{}", s);
+            } else {
+                let path = Path::new(&args.output).join(output.file_name(&reference));
+                std::fs::write(&path, output.compression.compress(s.as_bytes())?)?;
+                eprintln!("{}", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+// All inputs go to a single stream, unlike the other formats written per game.
+#[cfg(feature = "arrow")]
+fn stream_arrow(args: &ConvertArgs, output: &OutputOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let input_path = Path::new(&args.input);
+    if args.input != STDIO_PATH && !input_path.exists() {
+        return Err(format!("{} does not exist.", args.input).into());
+    }
+
+    let sink: Box<dyn Write> = match args.output.as_deref() {
+        None | Some(STDIO_PATH) => Box::new(std::io::stdout().lock()),
+        Some(x) => match x.strip_prefix(TCP_SCHEME) {
+            Some(addr) => Box::new(TcpStream::connect(addr).map_err(|e| format!("{}: {}", x, e))?),
+            None => Box::new(File::create(x)?),
+        },
+    };
+    let mut writer = RoundsArrowWriter::new(BufWriter::new(sink))?;
+
+    if args.input == STDIO_PATH {
+        let mut bytes = Vec::new();
+        std::io::stdin().read_to_end(&mut bytes)?;
+        write_arrow_bytes(&mut writer, String::new(), bytes, output)?;
+    } else if input_path.is_dir() {
+        stream_arrow_files(&list_input_files(input_path), &mut writer, output, args.fail_fast)?;
+    } else {
+        stream_arrow_files(&[input_path.to_path_buf()], &mut writer, output, true)?;
+    }
+    Ok(writer.finish()?)
+}
+
+// All inputs go to a single JSON Lines file in the order of the files. The file is appended to, so that runs over several directories can share it.
+fn write_single_file(args: &ConvertArgs, path: &str, output: &OutputOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if args.format != OutputFormat::Json || args.pretty {
+        return Err("--single-file writes compact tenhou-JSON only, without --format and --pretty.".into());
+    }
+    let input_path = Path::new(&args.input);
+    if input_path.extension().is_some_and(|x| x == "zip") {
+        return Err("--single-file does not support zip archives. Convert the extracted directory.".into());
+    }
+    if args.input != STDIO_PATH && !input_path.exists() {
+        return Err(format!("{} does not exist.", args.input).into());
+    }
+
+    let sink: Box<dyn Write> = match path {
+        STDIO_PATH => Box::new(std::io::stdout().lock()),
+        x => Box::new(OpenOptions::new().create(true).append(true).open(x)?),
+    };
+    let mut writer = BufWriter::new(sink);
+
+    let ret = if args.input == STDIO_PATH {
+        let mut bytes = Vec::new();
+        std::io::stdin().read_to_end(&mut bytes)?;
+        write_jsonl_bytes(&mut writer, String::new(), bytes, output)
+    } else if input_path.is_dir() {
+        write_jsonl_files(&list_input_files(input_path), &mut writer, output, args.fail_fast)
+    } else {
+        write_jsonl_files(&[input_path.to_path_buf()], &mut writer, output, true)
+    };
+    writer.flush()?;
+    ret
+}
+
+// All inputs go to a single file, like the Arrow stream. The footer is written even after a failure, to keep the games converted before it.
+#[cfg(feature = "parquet")]
+fn write_parquet(args: &ConvertArgs, output: &OutputOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let input_path = Path::new(&args.input);
+    if args.input != STDIO_PATH && !input_path.exists() {
+        return Err(format!("{} does not exist.", args.input).into());
+    }
+    let parquet_path = match args.output.as_deref() {
+        None | Some(STDIO_PATH) => return Err("--format parquet needs the output file, e.g. -o rounds.parquet.".into()),
+        Some(x) => x,
+    };
+    let mut writer = TableParquetWriter::new(BufWriter::new(File::create(parquet_path)?), args.table)?;
+
+    let ret = if args.input == STDIO_PATH {
+        let mut bytes = Vec::new();
+        std::io::stdin().read_to_end(&mut bytes)?;
+        write_parquet_bytes(&mut writer, args.table, String::new(), bytes, output)
+    } else if input_path.is_dir() {
+        write_parquet_files(&list_input_files(input_path), &mut writer, args.table, output, args.fail_fast)
+    } else {
+        write_parquet_files(&[input_path.to_path_buf()], &mut writer, args.table, output, true)
+    };
+    writer.finish()?;
+    ret
+}
+
+// All inputs go to a single database, like the Arrow stream. The games converted before a failure are kept.
+#[cfg(feature = "sqlite")]
+fn write_sqlite(args: &ConvertArgs, output: &OutputOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let input_path = Path::new(&args.input);
+    if args.input != STDIO_PATH && !input_path.exists() {
+        return Err(format!("{} does not exist.", args.input).into());
+    }
+    let db_path = match args.output.as_deref() {
+        None | Some(STDIO_PATH) => return Err("--format sqlite needs the database file, e.g. -o games.db.".into()),
+        Some(x) => x,
+    };
+    let mut writer = SqliteWriter::create(db_path)?;
+
+    let ret = if args.input == STDIO_PATH {
+        let mut bytes = Vec::new();
+        std::io::stdin().read_to_end(&mut bytes)?;
+        write_sqlite_bytes(&mut writer, String::new(), bytes, output)
+    } else if input_path.is_dir() {
+        write_sqlite_files(&list_input_files(input_path), &mut writer, output, args.fail_fast)
+    } else {
+        write_sqlite_files(&[input_path.to_path_buf()], &mut writer, output, true)
+    };
+    writer.finish()?;
+    ret
+}
+
+async fn convert(args: ConvertArgs) -> Result<(), Box<dyn Error + Send + Sync>> {
     let input_path = PathBuf::from(args.input.clone());
+    let output = OutputOptions {
+        format: args.format,
+        locale: args.locale,
+        digit_grouping: args.digit_grouping,
+        pretty: args.pretty,
+        synthesize_final_result: args.synthesize_owari,
+        lenient: args.lenient,
+        allow_truncated: args.allow_truncated,
+        incomplete_round: args.incomplete_round.unwrap_or(if args.allow_truncated { IncompleteRound::Drop } else { IncompleteRound::Fail }),
+        compression: args.compress,
+        anonymize: args.anonymize,
+    };
+    if let Some(path) = &args.behaviors {
+        std::fs::write(path, behaviors_to_json(&behaviors(&conv_options("", &output))))?;
+    }
+    if output.compression != Compression::None && (args.single_file.is_some() || args.format.is_combined()) {
+        return Err("--compress applies to the files of each game, not to --single-file, arrow, parquet or sqlite.".into());
+    }
+    #[cfg(feature = "arrow")]
+    if args.format == OutputFormat::Arrow {
+        return stream_arrow(&args, &output);
+    }
+    if let Some(path) = &args.single_file {
+        return write_single_file(&args, path, &output);
+    }
+    #[cfg(feature = "parquet")]
+    if args.format == OutputFormat::Parquet {
+        return write_parquet(&args, &output);
+    }
+    #[cfg(feature = "sqlite")]
+    if args.format == OutputFormat::Sqlite {
+        return write_sqlite(&args, &output);
+    }
 
-    if input_path.is_file() {
+    let conv_dir_options = ConvDirOptions {
+        fail_fast: args.fail_fast,
+        jobs: args.jobs,
+        quiet: args.quiet,
+        output,
+    };
+
+    if input_path.is_file() && input_path.extension().is_some_and(|x| x == "zip") {
+        // zip conversion mode, same as directory conversion
+        let output_path = match args.output {
+            Some(x) => PathBuf::from(x),
+            None => input_path.with_extension(""),
+        };
+        if args.sync_parallel {
+            sync_conv_zip(&input_path, &output_path, &conv_dir_options)
+        } else {
+            async_conv_zip(&input_path, &output_path, &conv_dir_options).await
+        }
+    } else if args.input == STDIO_PATH || input_path.is_file() {
         // file conversion mode
-        let s = read_mjlog(&input_path)?;
-        if let Some(x) = args.output {
-            std::fs::write(x, s)?;
-            Ok(())
+        let contents = if args.input == STDIO_PATH {
+            read_mjlog_stdin(&output)?
         } else {
-            println!("{}", s);
-            Ok(())
+            read_mjlog(&input_path, &output).map_err(|e| format!("{}: {}", args.input, e))?
+        };
+        match args.output {
+            Some(x) if x != STDIO_PATH => {
+                // foo.json -> foo-1.json, foo-2.json, ... for multi-game inputs
+                let output_path = PathBuf::from(x);
+                let stem = output_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+                for (i, (_, s)) in contents.iter().enumerate() {
+                    let mut path = output_path.with_file_name(numbered_name(&stem, i, contents.len()));
+                    if let Some(ext) = output_path.extension() {
+                        path.set_extension(ext);
+                    }
+                    std::fs::write(path, output.compression.compress(s.as_bytes())?)?;
+                }
+                Ok(())
+            }
+            _ if output.compression != Compression::None => {
+                // One gzip member or zstd frame per game, which decompress as the concatenation.
+                let mut stdout = std::io::stdout().lock();
+                for (_, s) in contents {
+                    stdout.write_all(&output.compression.compress(format!("{}\n", s).as_bytes())?)?;
+                }
+                Ok(())
+            }
+            _ => {
+                for (_, s) in contents {
+                    println!("{}", s);
+                }
+                Ok(())
+            }
         }
     } else if input_path.is_dir() {
         // directory conversion mode
         let output_path = if let Some(x) = args.output { PathBuf::from(x) } else { input_path.clone() };
-        async_conv_dir(&input_path, &output_path).await
+        if args.sync_parallel {
+            sync_conv_dir(&input_path, &output_path, &conv_dir_options)
+        } else {
+            async_conv_dir(&input_path, &output_path, &conv_dir_options).await
+        }
     } else {
         // file does not exist
         Err(format!("{} does not exist.", args.input).into())
@@ -0,0 +1,63 @@
+use crate::converter::*;
+use mjlog::model::Mjlog;
+use mjlog2json_core::board_svg::*;
+use mjlog2json_core::problem::*;
+use mjlog2json_core::problem_bank::*;
+use std::error::Error;
+use std::path::Path;
+
+/// Loads the `game`-th (0-origin) game of the file, with its log name.
+pub fn load_game(input_path: &Path, game: usize) -> Result<(String, Mjlog), Box<dyn Error + Send + Sync>> {
+    let mut mjlogs = load_mjlogs(input_path)?;
+    if game >= mjlogs.len() {
+        return Err(format!("{}: there are {} game(s) in the file.", input_path.display(), mjlogs.len()).into());
+    }
+    Ok(mjlogs.swap_remove(game))
+}
+
+fn write_lines(lines: String, export: Option<&Path>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match export {
+        Some(path) => std::fs::write(path, lines)?,
+        None => print!("{}", lines),
+    }
+    Ok(())
+}
+
+/// Writes the problems of the marks as JSON lines to the file, or to stdout if `export` is `None`.
+pub fn write_problems(reference: &str, mjlog: &Mjlog, marks: &[TurnMark], export: Option<&Path>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut lines = String::new();
+    for &mark in marks {
+        lines += &format!("{}\n", export_problem(mjlog, mark, reference)?);
+    }
+    write_lines(lines, export)
+}
+
+/// Writes the SVG image of the table at the mark to the file, or to stdout if `export` is `None`.
+pub fn write_snapshot(input_path: &Path, game: usize, mark: TurnMark, options: &BoardSvgOptions, export: Option<&Path>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (_, mjlog) = load_game(input_path, game)?;
+    write_lines(board_svg(&mjlog, mark, options)?, export)
+}
+
+/// Scans every game in the file or directory with the heuristics, and writes the problems found as JSON lines
+/// with the heuristic as "kind". Games that cannot be replayed are reported and skipped.
+pub fn write_problem_bank(input_path: &Path, kinds: &[ProblemKind], export: Option<&Path>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut lines = String::new();
+    let mut count = 0;
+    for (reference, mjlog) in load_mjlogs(input_path)? {
+        let found = match find_problems(&mjlog, kinds) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("{}: {}", reference, e);
+                continue;
+            }
+        };
+        for x in found {
+            let mut problem = export_problem(&mjlog, x.mark, &reference)?;
+            problem["kind"] = x.kind.to_str().into();
+            lines += &format!("{}\n", problem);
+            count += 1;
+        }
+    }
+    eprintln!("{} problem(s) found.", count);
+    write_lines(lines, export)
+}
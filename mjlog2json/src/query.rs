@@ -0,0 +1,50 @@
+use crate::converter::*;
+use mjlog2json_core::conv::numbered_name;
+use mjlog2json_core::game_index::*;
+use mjlog2json_core::query::*;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+// Games of each file, numbered like the converted files.
+fn numbered_games(input_path: &Path) -> Result<Vec<(String, mjlog::model::Mjlog)>, Box<dyn Error + Send + Sync>> {
+    let paths: Vec<PathBuf> = if input_path.is_dir() { list_input_files(input_path) } else { vec![input_path.to_path_buf()] };
+    let mut games = vec![];
+    for path in paths {
+        let mjlogs = load_mjlogs(&path)?;
+        let count = mjlogs.len();
+        games.extend(mjlogs.into_iter().enumerate().map(|(i, (name, mjlog))| (numbered_name(&name, i, count), mjlog)));
+    }
+    Ok(games)
+}
+
+/// Prints the log name of each game in the file or directory that matches the query, one per line.
+/// With `rounds`, prints the log name and the index (0-origin) of each matching round instead.
+pub fn write_query(input_path: &Path, query: &Query, rounds: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
+    for (name, mjlog) in numbered_games(input_path)? {
+        if rounds {
+            for round in query.matching_rounds(&mjlog) {
+                println!("{} {}", name, round);
+            }
+        } else if query.matches_game(&mjlog) {
+            println!("{}", name);
+        }
+    }
+    Ok(())
+}
+
+/// Same as `write_query`, but reads the index written by `write_game_index` instead of the logs.
+pub fn write_index_query(index_path: &Path, query: &Query) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let entries = parse_game_index(&std::fs::read_to_string(index_path)?).map_err(|line| format!("{}:{}: invalid index entry.", index_path.display(), line))?;
+    for entry in entries.iter().filter(|x| query.matches_entry(x)) {
+        println!("{}", entry.reference);
+    }
+    Ok(())
+}
+
+/// Scans every game in the file or directory once, and writes the index as JSON lines.
+pub fn write_game_index(input_path: &Path, output_path: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let entries: Vec<GameIndexEntry> = numbered_games(input_path)?.iter().map(|(name, mjlog)| index_entry(name, mjlog)).collect();
+    std::fs::write(output_path, export_game_index(&entries))?;
+    eprintln!("{} games indexed.", entries.len());
+    Ok(())
+}
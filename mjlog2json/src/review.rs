@@ -0,0 +1,58 @@
+use crate::problem::load_game;
+use mjlog2json_core::mortal::*;
+use std::error::Error;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// Placeholders in the arguments of the reviewer.
+const INPUT_PLACEHOLDER: &str = "{input}";
+const HERO_PLACEHOLDER: &str = "{hero}";
+
+pub struct ReviewOptions<'a> {
+    pub hero: u8,
+    pub game: usize,
+    pub output: Option<&'a Path>,
+    pub reviewer: Option<&'a Path>,
+    pub reviewer_args: &'a [String],
+}
+
+fn run_reviewer(reviewer: &Path, args: &[String], hero: u8, events: &str, input_path: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+    // Events are given by stdin, unless the reviewer takes the file by "{input}".
+    let by_stdin = !args.iter().any(|x| x.contains(INPUT_PLACEHOLDER));
+    let input = input_path.to_string_lossy();
+    let args: Vec<String> = args.iter().map(|x| x.replace(INPUT_PLACEHOLDER, &input).replace(HERO_PLACEHOLDER, &hero.to_string())).collect();
+
+    let mut child = Command::new(reviewer)
+        .args(&args)
+        .stdin(if by_stdin { Stdio::piped() } else { Stdio::null() })
+        .spawn()
+        .map_err(|e| format!("{}: {}", reviewer.display(), e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(events.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(format!("{} exited with {}", reviewer.display(), status).into());
+    }
+    Ok(())
+}
+
+/// Writes the Mortal review input of the hero seat, and runs the reviewer on it if given.
+/// Without `-o`, the input is written to stdout, or to a temporary file for the reviewer.
+pub fn review(input_path: &Path, options: &ReviewOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (reference, mjlog) = load_game(input_path, options.game)?;
+    let events = export_mortal_review(&mjlog, options.hero)?;
+
+    let Some(reviewer) = options.reviewer else {
+        match options.output {
+            Some(path) => std::fs::write(path, events)?,
+            None => print!("{}", events),
+        }
+        return Ok(());
+    };
+
+    let events_path = options.output.map_or_else(|| std::env::temp_dir().join(format!("{}.mortal.jsonl", reference)), PathBuf::from);
+    std::fs::write(&events_path, &events)?;
+    run_reviewer(reviewer, options.reviewer_args, options.hero, &events, &events_path)
+}
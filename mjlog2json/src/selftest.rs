@@ -0,0 +1,22 @@
+use mjlog2json_core::conv::ConvOptions;
+use mjlog2json_core::convert_xml_str_to_json_str;
+use std::error::Error;
+
+const REFERENCE: &str = "2025010203gm-00a9-0000-0123abcd";
+const FIXTURE_XML: &str = include_str!("../fixtures/selftest.xml");
+const EXPECTED_JSON: &str = include_str!("../fixtures/selftest.json");
+
+/// Converts the embedded game and compares the output with the embedded expected JSON.
+pub fn run_selftest() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let options = ConvOptions {
+        reference: REFERENCE.to_string(),
+        ..ConvOptions::default()
+    };
+    let jsons = convert_xml_str_to_json_str(FIXTURE_XML, &options)?;
+
+    if jsons.len() != 1 || jsons[0] != EXPECTED_JSON.trim_end() {
+        return Err("selftest failed: the output differs from the expected JSON.".into());
+    }
+    println!("selftest passed (mjlog2json {}).", env!("CARGO_PKG_VERSION"));
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use crate::converter::*;
+use mjlog2json_core::capabilities;
+use std::error::Error;
+use std::io::Read;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+// Larger bodies are rejected. The biggest mjlog is a few hundred KB.
+const MAX_BODY_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Options for the server mode.
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    pub host: String,
+    pub port: u16,
+    /// Number of requests handled at a time.
+    pub workers: usize,
+}
+
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Reply {
+    fn ok(content_type: &'static str, body: String) -> Self {
+        Reply { status: 200, content_type, body }
+    }
+
+    fn error(status: u16, message: impl ToString) -> Self {
+        Reply {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{}\n", message.to_string()),
+        }
+    }
+}
+
+// "/tenhou?ref=foo" -> ("/tenhou", "foo")
+fn split_url(url: &str) -> (&str, String) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let reference = query.split('&').find_map(|x| x.strip_prefix("ref=")).unwrap_or_default();
+    (path, reference.to_string())
+}
+
+fn convert_body(request: &mut Request, reference: String, format: OutputFormat) -> Reply {
+    let mut bytes = Vec::new();
+    if let Err(x) = request.as_reader().take(MAX_BODY_SIZE + 1).read_to_end(&mut bytes) {
+        return Reply::error(400, x);
+    }
+    if bytes.len() as u64 > MAX_BODY_SIZE {
+        return Reply::error(413, "request body is too large");
+    }
+
+    let output = OutputOptions { format, ..Default::default() };
+    match read_mjlog_bytes(reference, bytes, &output) {
+        Ok(contents) => {
+            let contents: Vec<String> = contents.into_iter().map(|(_, x)| x).collect();
+            match format {
+                // One game is the object itself, and multiple games are an array.
+                OutputFormat::Json if contents.len() == 1 => Reply::ok("application/json", contents.concat()),
+                OutputFormat::Json => Reply::ok("application/json", format!("[{}]", contents.join(","))),
+                _ => Reply::ok("application/x-ndjson", contents.concat()),
+            }
+        }
+        Err(x) => Reply::error(400, x),
+    }
+}
+
+fn handle(request: &mut Request) -> Reply {
+    let (path, reference) = split_url(request.url());
+    match (request.method(), path) {
+        (Method::Post, "/tenhou") => convert_body(request, reference, OutputFormat::Json),
+        (Method::Post, "/mjai") => convert_body(request, reference, OutputFormat::Mjai),
+        (Method::Get, "/capabilities") => Reply::ok("application/json", capabilities().to_json()),
+        (_, "/tenhou" | "/mjai" | "/capabilities") => Reply::error(405, "method not allowed"),
+        _ => Reply::error(404, "not found"),
+    }
+}
+
+fn respond(mut request: Request) {
+    let reply = handle(&mut request);
+    let header = Header::from_bytes("Content-Type", reply.content_type).expect("the header is valid");
+    let response = Response::from_string(reply.body).with_status_code(reply.status).with_header(header);
+    // The client may be gone already. There is nobody to report to.
+    let _ = request.respond(response);
+}
+
+/// Serves the conversion over HTTP until the process is killed.
+///
+/// * `POST /tenhou`: mjlog-XML (optionally gzip-compressed) in the body, tenhou-JSON in the response.
+///   A file with multiple games gives an array. `?ref=` sets "ref" of the output.
+/// * `POST /mjai`: same as `/tenhou`, but mjai events, one JSON per line.
+/// * `GET /capabilities`: `mjlog2json_core::capabilities()` as JSON.
+pub fn serve(options: &ServeOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let server = Server::http((options.host.as_str(), options.port))?;
+    eprintln!("Listening on http://{}:{}", options.host, options.port);
+
+    std::thread::scope(|s| {
+        for _ in 0..options.workers.max(1) {
+            s.spawn(|| {
+                for request in server.incoming_requests() {
+                    respond(request);
+                }
+            });
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,210 @@
+use crate::converter::*;
+use mjlog::model::*;
+use mjlog2json_core::calendar::*;
+use mjlog2json_core::compare::*;
+use mjlog2json_core::corpus::*;
+use mjlog2json_core::defense::*;
+use mjlog2json_core::game_id::WEEKDAY_NAMES;
+use mjlog2json_core::stats::*;
+use serde_json::{json, Map, Value};
+use std::error::Error;
+use std::path::Path;
+
+fn export_tile_histogram(histogram: &[u32]) -> Value {
+    let map: Map<String, Value> = histogram.iter().enumerate().map(|(kind, &n)| (tile_kind_name(kind), json!(n))).collect();
+    Value::Object(map)
+}
+
+fn export_discard_stats(stats: &DiscardStats) -> Value {
+    let by_turn: Vec<Value> = stats
+        .yaochuu_rate_by_turn()
+        .iter()
+        .enumerate()
+        .map(|(turn, rate)| {
+            json!({
+                "turn": turn + 1,
+                "discards": stats.discards_by_turn[turn],
+                "yaochuu_rate": rate,
+            })
+        })
+        .collect();
+
+    json!({
+        "first_discard": export_tile_histogram(&stats.first_discard),
+        "yaochuu_discard_by_turn": by_turn,
+        "riichi_tile": export_tile_histogram(&stats.riichi_tile),
+    })
+}
+
+fn export_han_stats(stats: &HanStats) -> Value {
+    let by_han: Map<String, Value> = stats.wins_by_han.iter().enumerate().filter(|&(_, &n)| n > 0).map(|(han, &n)| (han.to_string(), json!(n))).collect();
+    json!({
+        "exclude_aka_dora": stats.normalization.exclude_aka_dora,
+        "exclude_kuitan": stats.normalization.exclude_kuitan,
+        "games_by_rule": stats.games_by_rule,
+        "wins": stats.wins(),
+        "excluded_wins": stats.excluded_wins,
+        "average_han": stats.average_han(),
+        "wins_by_han": by_han,
+    })
+}
+
+fn export_deal_in_stats(stats: &DealInStats) -> Value {
+    let count = |f: fn(&DealIn) -> bool| stats.deal_ins.iter().filter(|x| f(x)).count();
+    json!({
+        "deal_ins": stats.deal_ins.len(),
+        "chankan": count(|x| x.chankan),
+        "in_riichi": count(|x| x.discarder_riichi),
+        "pushed_against_riichi": count(|x| x.pushed_against_riichi()),
+        "discards_against_riichi": stats.discards_against_riichi,
+        "deal_in_rate_against_riichi": stats.deal_in_rate_against_riichi(),
+    })
+}
+
+fn export_yaku_stats(stats: &YakuStats) -> Value {
+    let yaku: Map<String, Value> = stats.yaku.iter().map(|(&id, x)| (yaku_name(id), json!({"id": id, "wins": x.wins, "han": x.han}))).collect();
+    json!({
+        "wins": stats.wins,
+        "yakuman_wins": stats.yakuman_wins,
+        "average_han": stats.average_han(),
+        "dora": stats.dora,
+        "aka_dora": stats.aka_dora,
+        "ura_dora": stats.ura_dora,
+        "average_dora": stats.average_dora(),
+        "yaku": yaku,
+    })
+}
+
+fn write_riichi_discards(mjlogs: &[(String, Mjlog)], output_path: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let games: Vec<(&str, Vec<RiichiDiscard>)> = mjlogs.iter().map(|(name, mjlog)| (name.as_str(), extract_riichi_discards(mjlog))).collect();
+    let csv = export_riichi_discards_csv(games.iter().map(|(name, rows)| (*name, rows.as_slice())));
+    std::fs::write(output_path, csv)?;
+    Ok(())
+}
+
+fn export_calendar_bucket(key: Value, x: &CalendarBucket) -> Value {
+    json!({
+        "key": key,
+        "games": x.games,
+        "average_placement": x.average_placement(),
+    })
+}
+
+fn export_calendar_stats(player: &str, stats: &CalendarStats) -> Value {
+    json!({
+        "player": player,
+        "by_hour": stats.by_hour.iter().enumerate().map(|(hour, x)| export_calendar_bucket(json!(hour), x)).collect::<Vec<_>>(),
+        "by_weekday": stats.by_weekday.iter().enumerate().map(|(weekday, x)| export_calendar_bucket(json!(WEEKDAY_NAMES[weekday]), x)).collect::<Vec<_>>(),
+        "by_month": stats.by_month.iter().map(|((year, month), x)| export_calendar_bucket(json!(format!("{:04}-{:02}", year, month)), x)).collect::<Vec<_>>(),
+    })
+}
+
+/// Options of the stats subcommand.
+#[derive(Debug, Default)]
+pub struct StatsOptions<'a> {
+    /// Writes discards against riichi to this CSV file.
+    pub riichi_discards: Option<&'a Path>,
+    /// Aggregates games of this player by time.
+    pub player: Option<&'a str>,
+    /// Writes the time aggregation of the player to this CSV file.
+    pub calendar: Option<&'a Path>,
+    /// Normalization of the han distribution across rules.
+    pub normalization: Normalization,
+    /// Writes the yaku frequency table to this CSV file.
+    pub yaku: Option<&'a Path>,
+}
+
+pub fn print_stats(input_path: &Path, options: &StatsOptions) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if options.calendar.is_some() && options.player.is_none() {
+        return Err("--calendar requires --player.".into());
+    }
+
+    let mjlogs = load_mjlogs(input_path)?;
+    let corpus = Corpus::from_mjlogs(mjlogs.iter().map(|(_, x)| x));
+
+    if let Some(path) = options.riichi_discards {
+        write_riichi_discards(&mjlogs, path)?;
+    }
+
+    let yaku = YakuStats::from_mjlogs(mjlogs.iter().map(|(_, x)| x));
+    let mut root = json!({
+        "games": corpus.num_games(),
+        "rounds": corpus.num_rounds(),
+        "discard": export_discard_stats(&DiscardStats::from_corpus(&corpus)),
+        "han": export_han_stats(&HanStats::from_corpus(&corpus, &options.normalization)),
+        "deal_in": export_deal_in_stats(&DealInStats::from_corpus(&corpus)),
+        "yaku": export_yaku_stats(&yaku),
+    });
+    if let Some(path) = options.yaku {
+        std::fs::write(path, export_yaku_stats_csv(&yaku))?;
+    }
+
+    if let Some(player) = options.player {
+        let calendar = CalendarStats::from_games(mjlogs.iter().map(|(name, x)| (name.as_str(), x)), player);
+        root["calendar"] = export_calendar_stats(player, &calendar);
+        if let Some(path) = options.calendar {
+            std::fs::write(path, calendar.to_csv())?;
+        }
+    }
+
+    println!("{}", serde_json::to_string_pretty(&root)?);
+    Ok(())
+}
+
+fn export_compared_value(x: &ComparedValue) -> Value {
+    json!({
+        "name": x.name,
+        "a": x.a,
+        "b": x.b,
+        "significance": x.significance.to_str(),
+    })
+}
+
+/// Prints the distributions of two corpora side by side, with "*" (p < 0.05) or "**" (p < 0.01) on significant differences.
+pub fn print_comparison(input_a: &Path, input_b: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let a = CorpusSummary::from_mjlogs(load_mjlogs(input_a)?.iter().map(|(_, x)| x));
+    let b = CorpusSummary::from_mjlogs(load_mjlogs(input_b)?.iter().map(|(_, x)| x));
+    let comparison = compare(&a, &b);
+
+    let corpus = |input: &Path, x: &CorpusSummary| json!({"input": input.to_string_lossy(), "games": x.games, "rounds": x.rounds, "wins": x.wins});
+    let root = json!({
+        "a": corpus(input_a, &a),
+        "b": corpus(input_b, &b),
+        "ryuukyoku_rate": export_compared_value(&comparison.ryuukyoku_rate),
+        "average_win_points": export_compared_value(&comparison.average_win_points),
+        "yaku_rate": comparison.yaku_rate.iter().map(export_compared_value).collect::<Vec<_>>(),
+    });
+    println!("{}", serde_json::to_string_pretty(&root)?);
+    Ok(())
+}
+
+fn export_player_stats(x: &PlayerStats) -> Value {
+    json!({
+        "name": x.name,
+        "games": x.games,
+        "rounds": x.rounds,
+        "win_rate": x.win_rate(),
+        "deal_in_rate": x.deal_in_rate(),
+        "riichi_rate": x.riichi_rate(),
+        "call_rate": x.call_rate(),
+        "average_win_points": x.average_win_points(),
+        "average_deal_in_points": x.average_deal_in_points(),
+        "average_placement": x.average_placement(),
+    })
+}
+
+/// Prints the statistics of every player in the input as JSON, or as CSV.
+pub fn print_player_stats(input_path: &Path, csv: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mjlogs = load_mjlogs(input_path)?;
+    let mut corpus = Corpus::with_interned_names();
+    for (_, mjlog) in &mjlogs {
+        corpus.push_mjlog(mjlog);
+    }
+    let players = player_stats(&corpus);
+    if csv {
+        print!("{}", export_player_stats_csv(&players));
+    } else {
+        println!("{}", serde_json::to_string_pretty(&players.iter().map(export_player_stats).collect::<Vec<_>>())?);
+    }
+    Ok(())
+}
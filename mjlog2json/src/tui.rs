@@ -0,0 +1,323 @@
+use crate::problem::*;
+use mjlog::model::*;
+use mjlog::replay::*;
+use mjlog2json_core::problem::*;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::error::Error;
+use std::path::Path;
+use tenhou_json::shanten::*;
+
+const WINDS: [&str; 4] = ["East", "South", "West", "North"];
+const KEY_HELP: &str = "←/→ step  ↑/↓ round  Tab perspective  o open hands  m mark draw  q quit";
+// Shown for the hidden tiles of other players.
+const TILE_BACK: char = '\u{1f02b}';
+
+// Unicode mahjong tile (U+1F000..) of the kind index. Honors are 東南西北白發中 in tenhou's order.
+fn tile_char(kind: usize) -> char {
+    let code = match kind {
+        0..=8 => 0x1f007 + kind,
+        9..=17 => 0x1f019 + kind - 9,
+        18..=26 => 0x1f010 + kind - 18,
+        27..=30 => 0x1f000 + kind - 27,
+        31 => 0x1f006,
+        32 => 0x1f005,
+        _ => 0x1f004,
+    };
+    char::from_u32(code as u32).unwrap_or('?')
+}
+
+fn hai_kind(hai: Hai) -> usize {
+    hai.to_u8() as usize / 4
+}
+
+fn is_red(hai: Hai, red: bool) -> bool {
+    red && matches!(hai.to_u8(), 16 | 52 | 88)
+}
+
+// A tile followed by a space, because terminals disagree on the width of the tiles.
+fn tile_span(hai: Hai, red: bool, style: Style) -> Span<'static> {
+    let style = if is_red(hai, red) { style.fg(Color::Red) } else { style };
+    Span::styled(format!("{} ", tile_char(hai_kind(hai))), style)
+}
+
+// Waits of a hand that is not holding the drawn tile, e.g. 13 tiles without melds.
+fn waits(hand: &[Hai]) -> Option<Vec<usize>> {
+    if hand.len() % 3 != 1 {
+        return None;
+    }
+    let tiles: Vec<_> = hand.iter().map(|&x| kind_to_tile(hai_kind(x))).collect();
+    if shanten(&tiles) != 0 {
+        return None;
+    }
+    Some(ukeire(&tiles).into_iter().map(|(t, _)| tile_to_kind(t)).collect())
+}
+
+struct Viewer {
+    names: Vec<String>,
+    mjlog: Mjlog,
+    /// State after each action.
+    states: Vec<GameState>,
+    /// Indices of the actions to stop at.
+    steps: Vec<usize>,
+    pos: usize,
+    /// Seat shown at the bottom, with the waits.
+    perspective: usize,
+    open_hands: bool,
+    /// Draws marked to be exported as problems.
+    marks: Vec<TurnMark>,
+}
+
+impl Viewer {
+    fn new(mjlog: Mjlog) -> Result<Self, ReplayError> {
+        let mut state = GameState::new();
+        let mut states = vec![];
+        let mut names = vec![];
+        for a in &mjlog.actions {
+            if let Action::UN1(x) = a {
+                names = x.names.clone();
+            }
+            state.apply(a)?;
+            states.push(state.clone());
+        }
+        let steps = mjlog
+            .actions
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| {
+                matches!(
+                    a,
+                    Action::INIT(_) | Action::DRAW(_) | Action::DISCARD(_) | Action::N(_) | Action::REACH1(_) | Action::DORA(_) | Action::AGARI(_) | Action::RYUUKYOKU(_)
+                )
+            })
+            .map(|(i, _)| i)
+            .collect();
+        Ok(Viewer {
+            names,
+            mjlog,
+            states,
+            steps,
+            pos: 0,
+            perspective: 0,
+            open_hands: false,
+            marks: vec![],
+        })
+    }
+
+    fn action_index(&self) -> Option<usize> {
+        self.steps.get(self.pos).copied()
+    }
+
+    fn state(&self) -> Option<&GameState> {
+        self.action_index().map(|i| &self.states[i])
+    }
+
+    fn name(&self, who: Player) -> String {
+        self.names.get(who.to_u8() as usize).cloned().unwrap_or_else(|| format!("Player {}", who.to_u8()))
+    }
+
+    fn step(&mut self, forward: bool) {
+        if forward {
+            self.pos = (self.pos + 1).min(self.steps.len().saturating_sub(1));
+        } else {
+            self.pos = self.pos.saturating_sub(1);
+        }
+    }
+
+    // Moves to the next or the previous INIT.
+    fn jump_round(&mut self, forward: bool) {
+        let is_init = |&pos: &usize| matches!(self.mjlog.actions[self.steps[pos]], Action::INIT(_));
+        let found = if forward {
+            (self.pos + 1..self.steps.len()).find(is_init)
+        } else {
+            // The start of the current round when in the middle of it, otherwise the previous round.
+            match (0..=self.pos).rev().find(is_init) {
+                Some(x) if x < self.pos => Some(x),
+                Some(x) => (0..x).rev().find(is_init),
+                None => None,
+            }
+        };
+        if let Some(x) = found {
+            self.pos = x;
+        }
+    }
+
+    // Moves to the start of the round, or stays at the start of the game if there are not so many rounds.
+    fn start_at_round(&mut self, round: usize) {
+        let inits: Vec<usize> = (0..self.steps.len()).filter(|&pos| matches!(self.mjlog.actions[self.steps[pos]], Action::INIT(_))).collect();
+        if let Some(&x) = inits.get(round) {
+            self.pos = x;
+        }
+    }
+
+    // Only draws can be marked, since a problem is the choice of the discard after a draw.
+    fn toggle_mark(&mut self) {
+        let Some(mark) = self.action_index().and_then(|i| turn_of_action(&self.mjlog, i)) else { return };
+        match self.marks.iter().position(|&x| x == mark) {
+            Some(pos) => {
+                self.marks.remove(pos);
+            }
+            None => self.marks.push(mark),
+        }
+    }
+
+    fn describe(&self) -> String {
+        let Some(i) = self.action_index() else { return String::new() };
+        let red = |x| is_red(x, self.state().is_some_and(|s| !s.settings.no_red));
+        let tile = |x: Hai| format!("{}{}", tile_char(hai_kind(x)), if red(x) { " (red)" } else { "" });
+        match &self.mjlog.actions[i] {
+            Action::INIT(_) => "Round start".to_string(),
+            Action::DRAW(x) => format!("{} draws {}", self.name(x.who), tile(x.hai)),
+            Action::DISCARD(x) => format!("{} discards {}", self.name(x.who), tile(x.hai)),
+            Action::REACH1(x) => format!("{} declares riichi", self.name(x.who)),
+            Action::N(x) => {
+                let call = match x.m {
+                    Meld::Chii { .. } => "chii",
+                    Meld::Pon { .. } => "pon",
+                    Meld::Kakan { .. } => "kakan",
+                    Meld::Daiminkan { .. } => "daiminkan",
+                    Meld::Ankan { .. } => "ankan",
+                };
+                format!("{} calls {}", self.name(x.who), call)
+            }
+            Action::DORA(x) => format!("New dora indicator {}", tile(x.hai)),
+            Action::AGARI(x) if x.who == x.from_who => format!("{} wins by tsumo, {} points", self.name(x.who), x.ten.total),
+            Action::AGARI(x) => format!("{} wins by ron from {}, {} points", self.name(x.who), self.name(x.from_who), x.ten.total),
+            Action::RYUUKYOKU(_) => "Draw".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    fn player_lines(&self, state: &GameState, who: usize) -> Vec<Line<'static>> {
+        let player = &state.players[who];
+        let red = !state.settings.no_red;
+        let shown = self.open_hands || who == self.perspective || !state.in_round;
+
+        let mut hand: Vec<Span> = if shown {
+            let mut sorted = player.hand.clone();
+            sorted.sort_by_key(|x| x.to_u8());
+            sorted.into_iter().map(|x| tile_span(x, red, Style::default())).collect()
+        } else {
+            player.hand.iter().map(|_| Span::raw(format!("{} ", TILE_BACK))).collect()
+        };
+        for m in &player.melds {
+            hand.push(Span::raw("  "));
+            hand.extend(meld_hais(m).into_iter().map(|x| tile_span(x, red, Style::default())));
+        }
+
+        let river: Vec<Span> = player
+            .discards
+            .iter()
+            .map(|x| {
+                let mut style = Style::default();
+                if x.tsumogiri {
+                    style = style.add_modifier(Modifier::DIM);
+                }
+                if x.riichi {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                if x.called {
+                    style = style.add_modifier(Modifier::CROSSED_OUT);
+                }
+                tile_span(x.hai, red, style)
+            })
+            .collect();
+
+        let mut lines = vec![Line::from(hand), Line::from(river)];
+        if who == self.perspective {
+            if let Some(kinds) = waits(&player.hand) {
+                let mut spans = vec![Span::raw("Waits: ")];
+                spans.extend(kinds.into_iter().map(|x| Span::raw(format!("{} ", tile_char(x)))));
+                lines.push(Line::from(spans));
+            }
+        }
+        lines
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let Some(state) = self.state() else {
+            frame.render_widget(Paragraph::new("No rounds in the log."), frame.area());
+            return;
+        };
+        let num_players = state.players.len();
+        let [header, body, footer] = Layout::vertical([Constraint::Length(1), Constraint::Min(0), Constraint::Length(2)]).areas(frame.area());
+
+        let dora: String = state.dora_indicators.iter().map(|&x| format!("{} ", tile_char(hai_kind(x)))).collect();
+        let round = format!(
+            "{} {}, {} honba, {} riichi stick(s)  Wall: {}  Dora indicators: {}",
+            WINDS[(state.kyoku / 4) as usize % 4],
+            state.kyoku % 4 + 1,
+            state.honba,
+            state.kyoutaku,
+            state.wall_remaining,
+            dora
+        );
+        frame.render_widget(Paragraph::new(round), header);
+
+        // The perspective comes last, at the bottom.
+        let areas = Layout::vertical(vec![Constraint::Ratio(1, num_players as u32); num_players]).split(body);
+        for (i, area) in areas.iter().enumerate() {
+            let who = (self.perspective + 1 + i) % num_players;
+            let player = &state.players[who];
+            let wind = (who + num_players - state.oya.to_u8() as usize) % num_players;
+            let title = format!(" {} {} {}{} ", WINDS[wind], self.name(Player::new(who as u8)), player.score as i64 * 100, if player.riichi { " riichi" } else { "" });
+            let mut block = Block::bordered().title(title);
+            if who == self.perspective {
+                block = block.border_style(Style::default().fg(Color::Yellow));
+            }
+            frame.render_widget(Paragraph::new(self.player_lines(state, who)).block(block), *area);
+        }
+
+        let mark = self.action_index().and_then(|i| turn_of_action(&self.mjlog, i));
+        let marked = if mark.is_some_and(|x| self.marks.contains(&x)) { "  [marked]" } else { "" };
+        let status = vec![Line::from(format!("{}{}", self.describe(), marked)), Line::from(Span::styled(KEY_HELP, Style::default().add_modifier(Modifier::DIM)))];
+        frame.render_widget(Paragraph::new(status), footer);
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else { continue };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let num_players = self.state().map_or(4, |x| x.players.len().max(1));
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Right | KeyCode::Char('l') | KeyCode::Char(' ') => self.step(true),
+                KeyCode::Left | KeyCode::Char('h') => self.step(false),
+                KeyCode::Down | KeyCode::Char('j') => self.jump_round(true),
+                KeyCode::Up | KeyCode::Char('k') => self.jump_round(false),
+                KeyCode::Tab => self.perspective = (self.perspective + 1) % num_players,
+                KeyCode::BackTab => self.perspective = (self.perspective + num_players - 1) % num_players,
+                KeyCode::Char('o') => self.open_hands = !self.open_hands,
+                KeyCode::Char('m') => self.toggle_mark(),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Opens an interactive replay of a game in the file, at the start of the 0-origin `round`. `game` is the 0-origin index in multi-game files.
+/// Marked draws are written as problems to `export`, or to stdout after the replay is closed.
+pub fn run_tui(input_path: &Path, game: usize, round: usize, export: Option<&Path>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (reference, mjlog) = load_game(input_path, game)?;
+    let mut viewer = Viewer::new(mjlog)?;
+    viewer.start_at_round(round);
+
+    // Restores the terminal on panic too.
+    let mut terminal = ratatui::init();
+    let result = viewer.run(&mut terminal);
+    ratatui::restore();
+    result?;
+
+    viewer.marks.sort_by_key(|x| (x.round, x.draw));
+    if !viewer.marks.is_empty() {
+        write_problems(&reference, &viewer.mjlog, &viewer.marks, export)?;
+    }
+    Ok(())
+}
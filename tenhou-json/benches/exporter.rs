@@ -0,0 +1,101 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+use tenhou_json::prelude::*;
+
+fn tile(x: u8) -> Tile {
+    Tile::from_u8(x).ok().unwrap()
+}
+
+// A hanchan-sized game: 10 rounds of 18 turns, with calls, riichi and agari.
+fn sample_game() -> TenhouJson {
+    let tiles: Vec<Tile> = (11..=19).chain(21..=29).chain(31..=39).chain(41..=47).map(tile).collect();
+    let round = |kyoku: u8| Round {
+        settings: RoundSettings {
+            kyoku,
+            honba: 0,
+            kyoutaku: 1,
+            points: vec![25000, 24000, 26000, 25000],
+            dora: vec![tile(18)],
+            ura_dora: vec![tile(21)],
+        },
+        players: (0..4)
+            .map(|p| RoundPlayer {
+                hand: tiles.iter().cycle().skip(p * 7).take(13).copied().collect(),
+                incoming: (0..18)
+                    .map(|i| match i {
+                        5 => IncomingTile::Pon {
+                            combination: (tile(45), tile(45), tile(45)),
+                            dir: Direction::Toimen,
+                        },
+                        9 => IncomingTile::Chii { combination: (tile(23), tile(24), tile(52)) },
+                        _ => IncomingTile::Tsumo(tiles[(i * 5 + p) % tiles.len()]),
+                    })
+                    .collect(),
+                outgoing: (0..18)
+                    .map(|i| match i {
+                        3 => OutgoingTile::Riichi(tile(19)),
+                        i if i % 3 == 0 => OutgoingTile::Tsumogiri,
+                        _ => OutgoingTile::Discard(tiles[(i * 3 + p) % tiles.len()]),
+                    })
+                    .collect(),
+            })
+            .collect(),
+        result: RoundResult::Agari {
+            agari_vec: vec![Agari {
+                delta_points: vec![0, 0, 8000, -8000],
+                who: 2,
+                from_who: 3,
+                pao_who: 2,
+                ranked_score: RankedScore {
+                    rank: ScoreRank::Mangan,
+                    score: Score::Ron(8000),
+                },
+                yaku: vec![
+                    YakuPair {
+                        yaku: Yaku::Riichi,
+                        level: YakuLevel::Normal(1),
+                    },
+                    YakuPair { yaku: Yaku::Dora, level: YakuLevel::Normal(3) },
+                ],
+            }],
+        },
+    };
+
+    TenhouJson {
+        ver: 2.3,
+        reference: "2025010203gm-00a9-0000-0123abcd".to_string(),
+        rounds: (0..10).map(|k| round(k % 8)).collect(),
+        connections: vec![],
+        ratingc: "PF4".to_string(),
+        rule: Rule {
+            disp: "般南喰赤".to_string(),
+            aka53: true,
+            aka52: true,
+            aka51: true,
+        },
+        lobby: 0,
+        dan: vec!["新人".to_string(); 4],
+        rate: vec![1500.0, 1520.5, 1480.0, 1500.0],
+        sx: vec!["M".to_string(); 4],
+        final_points: vec![35000, 25000, 22000, 18000],
+        final_results: vec![45.0, 5.0, -18.0, -32.0],
+        names: ["A", "B", "C", "D"].map(String::from).to_vec(),
+    }
+}
+
+fn bench_export(c: &mut Criterion) {
+    let game = sample_game();
+    c.bench_function("export_tenhou_json", |b| b.iter(|| export_tenhou_json(black_box(&game)).ok()));
+
+    let games: Vec<TenhouJson> = (0..100).map(|_| sample_game()).collect();
+    c.bench_function("export_tenhou_json batch 100", |b| {
+        b.iter(|| {
+            for x in black_box(&games) {
+                black_box(export_tenhou_json(x).ok());
+            }
+        })
+    });
+}
+
+criterion_group!(benches, bench_export);
+criterion_main!(benches);
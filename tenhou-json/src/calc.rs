@@ -88,6 +88,78 @@ pub fn get_ko_ron_yakuman(num: u8) -> RankedScore {
     RankedScore { rank: ScoreRank::Yakuman, score: Score::Ron(32000 * num as i32)}
 }
 
+/// Who pays a tsumo. Ron is the same in every profile.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ScoreProfile {
+    #[default]
+    Yonma,
+    /// Three players with tsumo loss, as tenhou: each payer pays the same as four players, and the share of the missing player is lost.
+    Sanma,
+    /// Three players without tsumo loss: the share of the missing player is split between the payers.
+    SanmaNoTsumoLoss,
+}
+
+impl ScoreProfile {
+    pub fn for_players(sanma: bool) -> Self {
+        if sanma { ScoreProfile::Sanma } else { ScoreProfile::Yonma }
+    }
+
+    pub fn num_players(&self) -> u8 {
+        match self {
+            ScoreProfile::Yonma => 4,
+            ScoreProfile::Sanma | ScoreProfile::SanmaNoTsumoLoss => 3,
+        }
+    }
+}
+
+fn rank_base_points(rank: &ScoreRank) -> i32 {
+    match *rank {
+        ScoreRank::Normal { fu, han } => calc_base_points(fu, han),
+        ScoreRank::Mangan => 2000,
+        ScoreRank::Haneman => 3000,
+        ScoreRank::Baiman => 4000,
+        ScoreRank::Sanbaiman => 6000,
+        ScoreRank::Yakuman => 8000,
+    }
+}
+
+// Splits the share of the missing player: the oya pays 2.5 and each ko 1.5 times the base points, instead of 2 and 1.
+fn without_tsumo_loss(x: RankedScore, base_points: i32) -> RankedScore {
+    let score = match x.score {
+        Score::OyaTsumo(_) => Score::OyaTsumo(ceil_to_100(base_points*3)),
+        Score::KoTsumo(_, _) => Score::KoTsumo(ceil_to_100(base_points*3/2), ceil_to_100(base_points*5/2)),
+        score => score,
+    };
+    RankedScore { rank: x.rank, score }
+}
+
+fn with_profile(x: RankedScore, base_points: i32, profile: ScoreProfile) -> RankedScore {
+    match profile {
+        ScoreProfile::Yonma | ScoreProfile::Sanma => x,
+        ScoreProfile::SanmaNoTsumoLoss => without_tsumo_loss(x, base_points),
+    }
+}
+
+pub fn get_oya_tsumo_with(fu: u8, han: u8, profile: ScoreProfile) -> RankedScore {
+    let x = get_oya_tsumo(fu, han);
+    let base_points = rank_base_points(&x.rank);
+    with_profile(x, base_points, profile)
+}
+
+pub fn get_ko_tsumo_with(fu: u8, han: u8, profile: ScoreProfile) -> RankedScore {
+    let x = get_ko_tsumo(fu, han);
+    let base_points = rank_base_points(&x.rank);
+    with_profile(x, base_points, profile)
+}
+
+pub fn get_oya_tsumo_yakuman_with(num: u8, profile: ScoreProfile) -> RankedScore {
+    with_profile(get_oya_tsumo_yakuman(num), 8000 * num as i32, profile)
+}
+
+pub fn get_ko_tsumo_yakuman_with(num: u8, profile: ScoreProfile) -> RankedScore {
+    with_profile(get_ko_tsumo_yakuman(num), 8000 * num as i32, profile)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -142,4 +214,15 @@ mod tests {
         assert_eq!(get_ko_tsumo(25,5), RankedScore { rank: ScoreRank::Mangan, score: Score::KoTsumo(2000,4000)});
         assert_eq!(get_ko_tsumo(25,6), RankedScore { rank: ScoreRank::Haneman, score: Score::KoTsumo(3000,6000)});
     }
+
+    #[test]
+    fn test_sanma_tsumo_scores() {
+        // Tsumo loss pays the same as four players.
+        assert_eq!(get_ko_tsumo_with(30,4,ScoreProfile::Sanma), get_ko_tsumo(30,4));
+        assert_eq!(get_ko_tsumo_with(30,4,ScoreProfile::SanmaNoTsumoLoss), RankedScore { rank: ScoreRank::Normal { fu: 30, han: 4 }, score: Score::KoTsumo(2900,4800)});
+        assert_eq!(get_ko_tsumo_with(30,5,ScoreProfile::SanmaNoTsumoLoss), RankedScore { rank: ScoreRank::Mangan, score: Score::KoTsumo(3000,5000)});
+        assert_eq!(get_oya_tsumo_with(30,5,ScoreProfile::SanmaNoTsumoLoss), RankedScore { rank: ScoreRank::Mangan, score: Score::OyaTsumo(6000)});
+        assert_eq!(get_oya_tsumo_with(20,2,ScoreProfile::SanmaNoTsumoLoss), RankedScore { rank: ScoreRank::Normal { fu: 20, han: 2 }, score: Score::OyaTsumo(1000)});
+        assert_eq!(get_ko_tsumo_yakuman_with(2,ScoreProfile::SanmaNoTsumoLoss), RankedScore { rank: ScoreRank::Yakuman, score: Score::KoTsumo(24000,40000)});
+    }
 }
@@ -1,175 +1,553 @@
+//! Serializes `TenhouJson` directly to the output, without building a `serde_json::Value` tree.
+
 use crate::model::*;
 use crate::parser::*;
-use serde_json::{json, Value};
-use std::iter::once;
-
-fn export_rule(rule: &Rule) -> Value {
-    json!({
-        "disp": rule.disp,
-        "aka53": if rule.aka53 {1} else {0},
-        "aka52": if rule.aka52 {1} else {0},
-        "aka51": if rule.aka51 {1} else {0},
-    })
-}
-
-fn export_tile(tile: &Tile) -> Value {
-    json!(tile.to_u8())
-}
-
-fn export_incoming_tile(incoming: &IncomingTile) -> Value {
-    match incoming {
-        IncomingTile::Tsumo(t) => export_tile(t),
-        IncomingTile::Chii { combination: (t1, t2, t3) } => json!(format!("c{}{}{}", t1.to_u8(), t2.to_u8(), t3.to_u8())),
-        IncomingTile::Pon { combination: (t1, t2, t3), dir } => json!(match dir {
-            Direction::Kamicha => format!("p{}{}{}", t1.to_u8(), t2.to_u8(), t3.to_u8()),
-            Direction::Toimen => format!("{}p{}{}", t1.to_u8(), t2.to_u8(), t3.to_u8()),
-            Direction::Shimocha => format!("{}{}p{}", t1.to_u8(), t2.to_u8(), t3.to_u8()),
-            _ => panic!("undefined"),
-        }),
-        IncomingTile::Daiminkan { combination: (t1, t2, t3, t4), dir } => json!(match dir {
-            Direction::Kamicha => format!("m{}{}{}{}", t1.to_u8(), t2.to_u8(), t3.to_u8(), t4.to_u8()),
-            Direction::Toimen => format!("{}m{}{}{}", t1.to_u8(), t2.to_u8(), t3.to_u8(), t4.to_u8()),
-            Direction::Shimocha => format!("{}{}{}m{}", t1.to_u8(), t2.to_u8(), t3.to_u8(), t4.to_u8()),
-            _ => panic!("undefined"),
-        }),
-    }
-}
-
-fn export_outgoing_tile(outgoing: &OutgoingTile) -> Value {
-    match outgoing {
-        OutgoingTile::Discard(t) => export_tile(t),
-        OutgoingTile::Riichi(t) => json!(format!("r{}", t.to_u8())),
-        OutgoingTile::Tsumogiri => json!(60),
-        OutgoingTile::TsumogiriRiichi => json!("r60"),
-        OutgoingTile::Ankan(t) => {
-            let b = t.to_black().to_u8();
-            json!(format!("{}{}{}a{}", b, b, b, t.to_u8())) // I think red is last always
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use serde_json::ser::Formatter;
+use std::fmt::Display;
+use std::io::{self, Write};
+
+/// Options of `export_tenhou_json_with`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportOptions {
+    /// Escape non-ASCII characters as `\uXXXX`, same as the official files.
+    pub ascii_escape: bool,
+    /// Indent the document, each round and the parts of a round, for reading by eye or diffing in git.
+    /// Tiles, calls and agari stay in one line. The default is the compact output of tenhou.net.
+    ///
+    /// Keys are always in the order of the official files:
+    /// `ver`, `ref`, `log`, `connection` (omitted if empty), `ratingc`, `rule`, `lobby`, `dan`, `rate`, `sx`, `sc`, `name`.
+    pub pretty: bool,
+    /// Language of the human-readable exporters built on this crate, such as the narrative transcript.
+    /// The tenhou-JSON itself is the same in every locale.
+    pub locale: Locale,
+}
+
+/// Language of the strings generated from tenhou-JSON, e.g. the dan names or a transcript.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Locale {
+    #[default]
+    Japanese,
+    English,
+}
+
+impl std::str::FromStr for Locale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ja" => Ok(Locale::Japanese),
+            "en" => Ok(Locale::English),
+            _ => Err(format!("unknown locale: {} (expected ja or en)", s)),
+        }
+    }
+}
+
+// Serializes a value with its `Display` implementation, as a JSON string.
+struct AsStr<T>(T);
+
+impl<T: Display> Serialize for AsStr<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+// Integer if the value has no fractional part, same as tenhou.net.
+struct Number(f64);
+
+impl Serialize for Number {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.0.fract() == 0.0 {
+            serializer.serialize_i64(self.0 as i64)
+        } else {
+            serializer.serialize_f64(self.0)
         }
-        OutgoingTile::Kakan { combination: (t1, t2, t3), dir, added } => json!(match dir {
-            Direction::Kamicha => format!("k{}{}{}{}", added.to_u8(), t1.to_u8(), t2.to_u8(), t3.to_u8()),
-            Direction::Toimen => format!("{}k{}{}{}", t1.to_u8(), added.to_u8(), t2.to_u8(), t3.to_u8()),
-            Direction::Shimocha => format!("{}{}k{}{}", t1.to_u8(), t2.to_u8(), added.to_u8(), t3.to_u8()),
-            _ => panic!("undefined"),
-        }),
-        OutgoingTile::Dummy => json!(0),
     }
 }
 
-fn export_tiles(tiles: &[Tile]) -> Vec<Value> {
-    tiles.iter().map(export_tile).collect::<Vec<_>>()
+struct ExportRule<'a>(&'a Rule);
+
+impl Serialize for ExportRule<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let rule = self.0;
+        let mut map = serializer.serialize_map(Some(4))?;
+        map.serialize_entry("disp", &rule.disp)?;
+        map.serialize_entry("aka53", &(rule.aka53 as u8))?;
+        map.serialize_entry("aka52", &(rule.aka52 as u8))?;
+        map.serialize_entry("aka51", &(rule.aka51 as u8))?;
+        map.end()
+    }
 }
 
-fn export_incoming_tiles(incoming: &[IncomingTile]) -> Vec<Value> {
-    incoming.iter().map(export_incoming_tile).collect::<Vec<_>>()
+struct ExportTiles<'a>(&'a [Tile]);
+
+impl Serialize for ExportTiles<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(|t| t.to_u8()))
+    }
 }
 
-fn export_outgoing_tiles(outgoing: &[OutgoingTile]) -> Vec<Value> {
-    outgoing.iter().map(export_outgoing_tile).collect::<Vec<_>>()
+struct ExportIncomingTile<'a>(&'a IncomingTile);
+
+impl Serialize for ExportIncomingTile<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            IncomingTile::Tsumo(t) => serializer.serialize_u8(t.to_u8()),
+            IncomingTile::Chii { combination: (t1, t2, t3) } => serializer.collect_str(&format_args!("c{}{}{}", t1.to_u8(), t2.to_u8(), t3.to_u8())),
+            IncomingTile::Pon { combination: (t1, t2, t3), dir } => match dir {
+                Direction::Kamicha => serializer.collect_str(&format_args!("p{}{}{}", t1.to_u8(), t2.to_u8(), t3.to_u8())),
+                Direction::Toimen => serializer.collect_str(&format_args!("{}p{}{}", t1.to_u8(), t2.to_u8(), t3.to_u8())),
+                Direction::Shimocha => serializer.collect_str(&format_args!("{}{}p{}", t1.to_u8(), t2.to_u8(), t3.to_u8())),
+                _ => panic!("undefined"),
+            },
+            IncomingTile::Daiminkan { combination: (t1, t2, t3, t4), dir } => match dir {
+                Direction::Kamicha => serializer.collect_str(&format_args!("m{}{}{}{}", t1.to_u8(), t2.to_u8(), t3.to_u8(), t4.to_u8())),
+                Direction::Toimen => serializer.collect_str(&format_args!("{}m{}{}{}", t1.to_u8(), t2.to_u8(), t3.to_u8(), t4.to_u8())),
+                Direction::Shimocha => serializer.collect_str(&format_args!("{}{}{}m{}", t1.to_u8(), t2.to_u8(), t3.to_u8(), t4.to_u8())),
+                _ => panic!("undefined"),
+            },
+        }
+    }
 }
 
-fn export_agari(agari: &Agari) -> [Value; 2] {
-    let mut vec = vec![json!(agari.who), json!(agari.from_who), json!(agari.pao_who), json!(agari.ranked_score.to_string())];
-    vec.extend(agari.yaku.iter().map(|x| json!(x.to_string())));
+struct ExportOutgoingTile<'a>(&'a OutgoingTile);
 
-    [json!(agari.delta_points), json!(vec)]
+impl Serialize for ExportOutgoingTile<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            OutgoingTile::Discard(t) => serializer.serialize_u8(t.to_u8()),
+            OutgoingTile::Riichi(t) => serializer.collect_str(&format_args!("r{}", t.to_u8())),
+            OutgoingTile::Tsumogiri => serializer.serialize_u8(60),
+            OutgoingTile::TsumogiriRiichi => serializer.serialize_str("r60"),
+            OutgoingTile::Ankan(t) => {
+                let b = t.to_black().to_u8();
+                serializer.collect_str(&format_args!("{}{}{}a{}", b, b, b, t.to_u8()))
+                // I think red is last always
+            }
+            OutgoingTile::Kakan { combination: (t1, t2, t3), dir, added } => match dir {
+                Direction::Kamicha => serializer.collect_str(&format_args!("k{}{}{}{}", added.to_u8(), t1.to_u8(), t2.to_u8(), t3.to_u8())),
+                Direction::Toimen => serializer.collect_str(&format_args!("{}k{}{}{}", t1.to_u8(), added.to_u8(), t2.to_u8(), t3.to_u8())),
+                Direction::Shimocha => serializer.collect_str(&format_args!("{}{}k{}{}", t1.to_u8(), t2.to_u8(), added.to_u8(), t3.to_u8())),
+                _ => panic!("undefined"),
+            },
+            OutgoingTile::Dummy => serializer.serialize_u8(0),
+        }
+    }
 }
 
-fn export_round_result(result: &RoundResult) -> Value {
-    match result {
-        RoundResult::Agari { agari_vec } => json!(once(json!("和了")).chain(agari_vec.iter().flat_map(export_agari)).collect::<Vec<_>>()),
-        RoundResult::Ryuukyoku { reason, delta_points } if delta_points.is_empty() => json!([json!(reason.to_str())]),
-        RoundResult::Ryuukyoku { reason, delta_points } => json!([json!(reason.to_str()), json!(delta_points)]),
+struct ExportIncomingTiles<'a>(&'a [IncomingTile]);
+
+impl Serialize for ExportIncomingTiles<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(ExportIncomingTile))
     }
 }
 
-fn export_round(round: &Round) -> Value {
-    json!([
-        [round.settings.kyoku, round.settings.honba, round.settings.kyoutaku],
-        round.settings.points,
-        export_tiles(&round.settings.dora),
-        export_tiles(&round.settings.ura_dora),
-        export_tiles(&round.players[0].hand),
-        export_incoming_tiles(&round.players[0].incoming),
-        export_outgoing_tiles(&round.players[0].outgoing),
-        export_tiles(&round.players[1].hand),
-        export_incoming_tiles(&round.players[1].incoming),
-        export_outgoing_tiles(&round.players[1].outgoing),
-        export_tiles(&round.players[2].hand),
-        export_incoming_tiles(&round.players[2].incoming),
-        export_outgoing_tiles(&round.players[2].outgoing),
-        export_tiles(&round.players[3].hand),
-        export_incoming_tiles(&round.players[3].incoming),
-        export_outgoing_tiles(&round.players[3].outgoing),
-        export_round_result(&round.result),
-    ])
+struct ExportOutgoingTiles<'a>(&'a [OutgoingTile]);
+
+impl Serialize for ExportOutgoingTiles<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(ExportOutgoingTile))
+    }
 }
 
-fn export_rounds(rounds: &[Round]) -> Value {
-    let mut ret = vec![];
-    for round in rounds {
-        ret.push(export_round(round));
+// [who, from_who, pao_who, score, yaku...]
+struct ExportAgari<'a>(&'a Agari);
+
+impl Serialize for ExportAgari<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let agari = self.0;
+        let mut seq = serializer.serialize_seq(Some(4 + agari.yaku.len()))?;
+        seq.serialize_element(&agari.who)?;
+        seq.serialize_element(&agari.from_who)?;
+        seq.serialize_element(&agari.pao_who)?;
+        seq.serialize_element(&AsStr(&agari.ranked_score))?;
+        for x in &agari.yaku {
+            seq.serialize_element(&AsStr(x))?;
+        }
+        seq.end()
     }
-    json!(ret)
 }
 
-fn export_rate(rate: &[f64]) -> Value {
-    let mut ret = vec![];
-    for &x in rate {
-        if x.fract() == 0.0 {
-            ret.push(json!(x as i64));
-        } else {
-            ret.push(json!(x));
+struct ExportRoundResult<'a>(&'a RoundResult);
+
+impl Serialize for ExportRoundResult<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            RoundResult::Agari { agari_vec } => {
+                let mut seq = serializer.serialize_seq(Some(1 + agari_vec.len() * 2))?;
+                seq.serialize_element("和了")?;
+                for agari in agari_vec {
+                    seq.serialize_element(&agari.delta_points)?;
+                    seq.serialize_element(&ExportAgari(agari))?;
+                }
+                seq.end()
+            }
+            RoundResult::Ryuukyoku { reason, delta_points } => {
+                let mut seq = serializer.serialize_seq(None)?;
+                seq.serialize_element(reason.to_str())?;
+                if !delta_points.is_empty() {
+                    seq.serialize_element(delta_points)?;
+                }
+                seq.end()
+            }
         }
     }
-    json!(ret)
 }
 
-fn export_sc(final_points: &[i32], final_results: &[f64]) -> Value {
-    let mut ret = vec![];
-    for (&a, &b) in final_points.iter().zip(final_results.iter()) {
-        ret.push(json!(a));
-        if b.fract() == 0.0 {
-            ret.push(json!(b as i64));
-        } else {
-            ret.push(json!(b));
+struct ExportRound<'a>(&'a Round);
+
+impl Serialize for ExportRound<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let round = self.0;
+        let mut seq = serializer.serialize_seq(Some(17))?;
+        seq.serialize_element(&[round.settings.kyoku, round.settings.honba, round.settings.kyoutaku])?;
+        seq.serialize_element(&round.settings.points)?;
+        seq.serialize_element(&ExportTiles(&round.settings.dora))?;
+        seq.serialize_element(&ExportTiles(&round.settings.ura_dora))?;
+        for player in &round.players[0..4] {
+            seq.serialize_element(&ExportTiles(&player.hand))?;
+            seq.serialize_element(&ExportIncomingTiles(&player.incoming))?;
+            seq.serialize_element(&ExportOutgoingTiles(&player.outgoing))?;
+        }
+        seq.serialize_element(&ExportRoundResult(&round.result))?;
+        seq.end()
+    }
+}
+
+struct ExportRounds<'a>(&'a [Round]);
+
+impl Serialize for ExportRounds<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(ExportRound))
+    }
+}
+
+struct ExportRate<'a>(&'a [f64]);
+
+impl Serialize for ExportRate<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(|&x| Number(x)))
+    }
+}
+
+// [points, result, points, result, ...]
+struct ExportSc<'a>(&'a [GamePoint], &'a [f64]);
+
+impl Serialize for ExportSc<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.len().min(self.1.len()) * 2))?;
+        for (&a, &b) in self.0.iter().zip(self.1.iter()) {
+            seq.serialize_element(&a)?;
+            seq.serialize_element(&Number(b))?;
+        }
+        seq.end()
+    }
+}
+
+struct ExportConnection<'a>(&'a Connection);
+
+impl Serialize for ExportConnection<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let connection = self.0;
+        let mut map = serializer.serialize_map(Some(4))?;
+        map.serialize_entry("what", &connection.what)?;
+        map.serialize_entry("log", &connection.log)?;
+        map.serialize_entry("who", &connection.who)?;
+        map.serialize_entry("step", &connection.step)?;
+        map.end()
+    }
+}
+
+struct ExportConnections<'a>(&'a [Connection]);
+
+impl Serialize for ExportConnections<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(ExportConnection))
+    }
+}
+
+struct ExportTenhouJson<'a>(&'a TenhouJson);
+
+impl Serialize for ExportTenhouJson<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let src = self.0;
+        let mut map = serializer.serialize_map(None)?;
+
+        map.serialize_entry("ver", &src.ver)?;
+        map.serialize_entry("ref", &src.reference)?;
+        map.serialize_entry("log", &ExportRounds(&src.rounds))?;
+
+        // "connection" is omitted if empty.
+        if !src.connections.is_empty() {
+            map.serialize_entry("connection", &ExportConnections(&src.connections))?;
+        }
+
+        map.serialize_entry("ratingc", &src.ratingc)?;
+        map.serialize_entry("rule", &ExportRule(&src.rule))?;
+        map.serialize_entry("lobby", &src.lobby)?;
+        map.serialize_entry("dan", &src.dan)?;
+        map.serialize_entry("rate", &ExportRate(&src.rate))?;
+        map.serialize_entry("sx", &src.sx)?;
+        map.serialize_entry("sc", &ExportSc(&src.final_points, &src.final_results))?;
+        map.serialize_entry("name", &src.names)?;
+        map.end()
+    }
+}
+
+// The model types serialize to the same wire format as `export_tenhou_json`.
+
+impl Serialize for Tile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.to_u8())
+    }
+}
+
+impl Serialize for IncomingTile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ExportIncomingTile(self).serialize(serializer)
+    }
+}
+
+impl Serialize for OutgoingTile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ExportOutgoingTile(self).serialize(serializer)
+    }
+}
+
+impl Serialize for Rule {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ExportRule(self).serialize(serializer)
+    }
+}
+
+impl Serialize for Round {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ExportRound(self).serialize(serializer)
+    }
+}
+
+impl Serialize for Connection {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ExportConnection(self).serialize(serializer)
+    }
+}
+
+impl Serialize for TenhouJson {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ExportTenhouJson(self).serialize(serializer)
+    }
+}
+
+// Arrays and objects up to this depth are indented in the pretty output: the document, each round, and the parts of a round.
+// Deeper ones such as tiles and agari are kept in one line.
+const PRETTY_DEPTH: usize = 3;
+
+struct ExportFormatter {
+    ascii_escape: bool,
+    pretty: bool,
+    depth: usize,
+    has_value: bool,
+}
+
+impl ExportFormatter {
+    fn new(options: &ExportOptions) -> Self {
+        ExportFormatter {
+            ascii_escape: options.ascii_escape,
+            pretty: options.pretty,
+            depth: 0,
+            has_value: false,
+        }
+    }
+
+    fn indented(&self) -> bool {
+        self.pretty && self.depth <= PRETTY_DEPTH
+    }
+
+    fn begin<W: ?Sized + Write>(&mut self, writer: &mut W, bracket: &[u8]) -> io::Result<()> {
+        self.depth += 1;
+        self.has_value = false;
+        writer.write_all(bracket)
+    }
+
+    fn end<W: ?Sized + Write>(&mut self, writer: &mut W, bracket: &[u8]) -> io::Result<()> {
+        if self.indented() && self.has_value {
+            self.write_newline(writer, self.depth - 1)?;
         }
+        self.depth -= 1;
+        writer.write_all(bracket)
+    }
+
+    fn begin_value<W: ?Sized + Write>(&mut self, writer: &mut W, first: bool) -> io::Result<()> {
+        if !first {
+            writer.write_all(b",")?;
+        }
+        if self.indented() {
+            self.write_newline(writer, self.depth)?;
+        }
+        Ok(())
+    }
+
+    fn write_newline<W: ?Sized + Write>(&self, writer: &mut W, depth: usize) -> io::Result<()> {
+        writer.write_all(b"\n")?;
+        (0..depth).try_for_each(|_| writer.write_all(b"  "))
     }
-    json!(ret)
 }
 
-fn export_connection(connection: &Connection) -> Value {
-    json!({
-        "what": connection.what,
-        "log": connection.log,
-        "who": connection.who,
-        "step": connection.step,
-    })
+impl Formatter for ExportFormatter {
+    fn begin_array<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.begin(writer, b"[")
+    }
+
+    fn end_array<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.end(writer, b"]")
+    }
+
+    fn begin_array_value<W: ?Sized + Write>(&mut self, writer: &mut W, first: bool) -> io::Result<()> {
+        self.begin_value(writer, first)
+    }
+
+    fn end_array_value<W: ?Sized + Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        self.has_value = true;
+        Ok(())
+    }
+
+    fn begin_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.begin(writer, b"{")
+    }
+
+    fn end_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.end(writer, b"}")
+    }
+
+    fn begin_object_key<W: ?Sized + Write>(&mut self, writer: &mut W, first: bool) -> io::Result<()> {
+        self.begin_value(writer, first)
+    }
+
+    fn begin_object_value<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(if self.indented() { b": " } else { b":" })
+    }
+
+    fn end_object_value<W: ?Sized + Write>(&mut self, _writer: &mut W) -> io::Result<()> {
+        self.has_value = true;
+        Ok(())
+    }
+
+    // Non-ASCII characters are escaped as UTF-16 `\uXXXX` if `ascii_escape`.
+    fn write_string_fragment<W: ?Sized + Write>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()> {
+        if !self.ascii_escape {
+            return writer.write_all(fragment.as_bytes());
+        }
+        let mut start = 0;
+        for (i, c) in fragment.char_indices().filter(|(_, c)| !c.is_ascii()) {
+            writer.write_all(&fragment.as_bytes()[start..i])?;
+            for u in c.encode_utf16(&mut [0; 2]) {
+                write!(writer, "\\u{:04x}", u)?;
+            }
+            start = i + c.len_utf8();
+        }
+        writer.write_all(&fragment.as_bytes()[start..])
+    }
 }
 
-fn export_connections(connections: &[Connection]) -> Value {
-    json!(connections.iter().map(export_connection).collect::<Vec<_>>())
+/// Writes tenhou-json to the writer, without building the whole output in memory.
+///
+/// The writer is written in small pieces; wrap files in `BufWriter`.
+///
+/// ```ignore
+/// let mut writer = BufWriter::new(File::create("out.json")?);
+/// export_tenhou_json_to(&tenhou_json, &mut writer)?;
+/// writer.flush()?;
+/// ```
+pub fn export_tenhou_json_to<W: Write>(src: &TenhouJson, writer: W) -> TenhouJsonResult<()> {
+    export_tenhou_json_to_with(src, &ExportOptions::default(), writer)
 }
 
+/// Same as `export_tenhou_json_to`, with options.
+pub fn export_tenhou_json_to_with<W: Write>(src: &TenhouJson, options: &ExportOptions, writer: W) -> TenhouJsonResult<()> {
+    let mut serializer = serde_json::Serializer::with_formatter(writer, ExportFormatter::new(options));
+    ExportTenhouJson(src).serialize(&mut serializer).map_err(|_| TenhouJsonError::new(TenhouJsonErrorKind::JsonWriteError))
+}
+
+/// Same as `export_tenhou_json_to`, but returns the output as a string.
 pub fn export_tenhou_json(src: &TenhouJson) -> TenhouJsonResult<String> {
-    // use IndexMap to ignore "connection"
-    let mut root = serde_json::Map::new();
+    export_tenhou_json_with(src, &ExportOptions::default())
+}
 
-    root.insert("ver".to_string(), json!(src.ver));
-    root.insert("ref".to_string(), json!(src.reference));
-    root.insert("log".to_string(), export_rounds(&src.rounds));
+/// Same as `export_tenhou_json`, with options.
+pub fn export_tenhou_json_with(src: &TenhouJson, options: &ExportOptions) -> TenhouJsonResult<String> {
+    let mut buf = Vec::with_capacity(16 * 1024);
+    export_tenhou_json_to_with(src, options, &mut buf)?;
+    // serde_json writes valid UTF-8 only.
+    String::from_utf8(buf).map_err(|_| TenhouJsonError::new(TenhouJsonErrorKind::JsonWriteError))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::exporter::*;
 
-    if !src.connections.is_empty() {
-        root.insert("connection".to_string(), export_connections(&src.connections));
+    #[test]
+    fn test_export_roundtrip() {
+        let src = r#"{"ver":2.3,"ref":"2025010203gm-00a9-0000-0123abcd","log":[[[1,0,1],[25000,24000,25000,25000],[18,21],[22,23],[11,11,11,18,18,19,41,42,43,44,45,46,47],[32,"c121314","4545p45",28],[47,"r11",60,"r60"],[21,26,27,28,29,31,32,33,34,53,39,41,42],["m39393939",33],[0,"393939a39","k42424242"],[12,13,14,15,16,17,22,22,23,24,25,36,37],[43],[60],[12,13,14,16,17,19,21,23,24,26,27,28,31],[38],[60],["流局",[3000,-1000,-1000,-1000]]]],"connection":[{"what":0,"log":0,"who":2,"step":3}],"ratingc":"PF4","rule":{"disp":"鳳南喰赤","aka53":1,"aka52":1,"aka51":0},"lobby":0,"dan":["初段","二段","三段","四段"],"rate":[1500,1600,1700.5,1800],"sx":["M","F","M","C"],"sc":[28000,38,24000,-16.5,26300,6,21700,-28],"name":["あい","\"Bob\"","C,D","<eve>"]}"#;
+        let tenhou_json = parse_tenhou_json(src).ok().unwrap();
+        assert_eq!(export_tenhou_json(&tenhou_json).ok().unwrap(), src);
     }
 
-    root.insert("ratingc".to_string(), json!(src.ratingc));
-    root.insert("rule".to_string(), export_rule(&src.rule));
-    root.insert("lobby".to_string(), json!(src.lobby));
-    root.insert("dan".to_string(), json!(src.dan));
-    root.insert("rate".to_string(), export_rate(&src.rate));
-    root.insert("sx".to_string(), json!(src.sx));
-    root.insert("sc".to_string(), export_sc(&src.final_points, &src.final_results));
-    root.insert("name".to_string(), json!(src.names));
+    #[test]
+    fn test_final_results_tenths() {
+        let src = r#"{"ver":2.3,"ref":"","log":[],"ratingc":"PF4","rule":{"disp":"","aka53":1,"aka52":1,"aka51":1},"lobby":0,"dan":["","","",""],"rate":[1500,1500,1500,1500],"sx":["M","M","M","M"],"sc":[28000,38,24000,-16.5,26300,6,21700,-28],"name":["a","b","c","d"]}"#;
+        let tenhou_json = parse_tenhou_json(src).ok().unwrap();
+        assert_eq!(tenhou_json.final_results_tenths(), vec![380, -165, 60, -280]);
+        assert_eq!(tenths_to_result(result_to_tenths(0.1 + 0.2)), 0.3);
+        assert_eq!(export_tenhou_json(&tenhou_json).ok().unwrap(), src);
+    }
 
-    Ok(Value::Object(root).to_string())
+    #[test]
+    fn test_export_ascii_escape() {
+        let src = r#"{"ver":2.3,"ref":"","log":[[[0,0,0],[25000,25000,25000,25000],[11],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],["流局",[1500,-1500,1500,-1500]]]],"ratingc":"PF4","rule":{"disp":"鳳南喰赤","aka53":1,"aka52":1,"aka51":1},"lobby":0,"dan":["","","",""],"rate":[1500,1500,1500,1500],"sx":["M","M","M","M"],"sc":[25000,0,25000,0,25000,0,25000,0],"name":["a\"😀","b","c","d"]}"#;
+        let escaped = r#"{"ver":2.3,"ref":"","log":[[[0,0,0],[25000,25000,25000,25000],[11],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],["\u6d41\u5c40",[1500,-1500,1500,-1500]]]],"ratingc":"PF4","rule":{"disp":"\u9cf3\u5357\u55b0\u8d64","aka53":1,"aka52":1,"aka51":1},"lobby":0,"dan":["","","",""],"rate":[1500,1500,1500,1500],"sx":["M","M","M","M"],"sc":[25000,0,25000,0,25000,0,25000,0],"name":["a\"\ud83d\ude00","b","c","d"]}"#;
+        let tenhou_json = parse_tenhou_json(src).ok().unwrap();
+        let options = ExportOptions { ascii_escape: true, ..Default::default() };
+        assert_eq!(export_tenhou_json_with(&tenhou_json, &options).ok().unwrap(), escaped);
+        assert_eq!(parse_tenhou_json(escaped).ok().unwrap(), tenhou_json);
+    }
+
+    #[test]
+    fn test_export_pretty() {
+        let src = r#"{"ver":2.3,"ref":"","log":[[[0,0,0],[25000,25000,25000,25000],[11],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],["流局",[1500,-1500,1500,-1500]]]],"ratingc":"PF4","rule":{"disp":"鳳南喰赤","aka53":1,"aka52":1,"aka51":1},"lobby":0,"dan":["","","",""],"rate":[1500,1500,1500,1500],"sx":["M","M","M","M"],"sc":[25000,0,25000,0,25000,0,25000,0],"name":["a","b","c","d"]}"#;
+        let tenhou_json = parse_tenhou_json(src).ok().unwrap();
+        let options = ExportOptions { pretty: true, ..Default::default() };
+        let pretty = export_tenhou_json_with(&tenhou_json, &options).ok().unwrap();
+        assert!(pretty.starts_with("{\n  \"ver\": 2.3,\n  \"ref\": \"\",\n  \"log\": [\n    [\n      [0,0,0],\n      [25000,25000,25000,25000],\n"));
+        assert!(pretty.contains("\n      [\"流局\",[1500,-1500,1500,-1500]]\n    ]\n  ],\n"));
+        assert!(pretty.ends_with("\"c\",\n    \"d\"\n  ]\n}"));
+        assert_eq!(parse_tenhou_json(&pretty).ok().unwrap(), tenhou_json);
+    }
+
+    #[test]
+    fn test_serde_roundtrip() {
+        let src = r#"{"ver":2.3,"ref":"","log":[[[0,0,0],[25000,25000,25000,25000],[11],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],["c121314"],[60],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],["全員不聴"]]],"ratingc":"PF4","rule":{"disp":"","aka53":1,"aka52":1,"aka51":1},"lobby":0,"dan":["","","",""],"rate":[1500,1500,1500,1500],"sx":["M","M","M","M"],"sc":[25000,0,25000,0,25000,0,25000,0],"name":["a","b","c","d"]}"#;
+        let tenhou_json = parse_tenhou_json(src).ok().unwrap();
+        assert_eq!(serde_json::to_string(&tenhou_json).unwrap(), src);
+        assert_eq!(serde_json::from_str::<TenhouJson>(src).unwrap(), tenhou_json);
+        assert_eq!(serde_json::to_string(&tenhou_json.rounds[0].players[0].incoming).unwrap(), r#"["c121314"]"#);
+        assert!(serde_json::from_str::<Tile>("99").is_err());
+    }
+
+    #[test]
+    fn test_points_out_of_i32() {
+        let src = r#"{"ver":2.3,"ref":"","log":[[[0,0,0],[3000000000,-3000000000,0,0],[11],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],["全員不聴"]]],"ratingc":"PF4","rule":{"disp":"","aka53":1,"aka52":1,"aka51":1},"lobby":0,"dan":["","","",""],"rate":[1500,1500,1500,1500],"sx":["M","M","M","M"],"sc":[3000000000,50,-3000000000,-50,0,0,0,0],"name":["a","b","c","d"]}"#;
+        let tenhou_json = parse_tenhou_json(src).ok().unwrap();
+        assert_eq!(tenhou_json.rounds[0].settings.points[0], 3_000_000_000);
+        assert_eq!(narrow_points(&tenhou_json.final_points), None);
+        assert_eq!(narrow_points(&[25000, -1000]), Some(vec![25000, -1000]));
+        assert_eq!(export_tenhou_json(&tenhou_json).ok().unwrap(), src);
+    }
+
+    #[test]
+    fn test_integer_out_of_range() {
+        let src = r#"{"ver":2.3,"ref":"","log":[[[300,0,0],[3000000000,-3000000000,0,0],[11],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[],[],["全員不聴"]]],"ratingc":"PF4","rule":{"disp":"","aka53":1,"aka52":1,"aka51":1},"lobby":0,"dan":["","","",""],"rate":[1500,1500,1500,1500],"sx":["M","M","M","M"],"sc":[3000000000,50,-3000000000,-50,0,0,0,0],"name":["a","b","c","d"]}"#;
+        let err = parse_tenhou_json(src).err().unwrap();
+        assert!(matches!(err.kind, TenhouJsonErrorKind::IntegerOutOfRange));
+    }
 }
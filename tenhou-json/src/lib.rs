@@ -4,20 +4,27 @@
 //!
 //! # Usage
 //!
-//! ```
+//! ```ignore
+//! use tenhou_json::prelude::*;
+//!
 //! let content :: String = std::fs::read_to_string("/your/json/path")?;
 //! let tenhou_json :: TenhouJson = parse_tenhou_json(&content)?;
 //! ```
 //!
 //! # Install
 //!
-//! ```
+//! ```text
 //! cargo add tenhou-json
 //! ```
 
 pub mod score;
+// Score tables used by converters. Not a part of the stable API; see `prelude`.
+#[doc(hidden)]
 #[rustfmt::skip]
 pub mod calc;
 pub mod exporter;
 pub mod model;
 pub mod parser;
+pub mod prelude;
+pub mod shanten;
+pub mod transform;
@@ -1,9 +1,29 @@
 use crate::score::*;
-use num_derive::FromPrimitive;
-use num_traits::FromPrimitive;
 use std::fmt;
 
-pub type GamePoint = i32;
+/// Points, not in units of 100.
+///
+/// 64-bit so that tournaments with large starting points do not overflow. Use `narrow_points` where `i32` is still needed.
+pub type GamePoint = i64;
+
+/// Final result (uma and oka included) in tenths of a point, e.g. -16.5 is -165.
+///
+/// Results on tenhou have one decimal place at most, so this compares and sums exactly unlike `f64`.
+pub type ResultTenths = i32;
+
+/// Rounds to the nearest tenth.
+pub fn result_to_tenths(x: f64) -> ResultTenths {
+    (x * 10.0).round() as ResultTenths
+}
+
+pub fn tenths_to_result(x: ResultTenths) -> f64 {
+    x as f64 / 10.0
+}
+
+/// Returns `None` if any of the points does not fit in `i32`.
+pub fn narrow_points(points: &[GamePoint]) -> Option<Vec<i32>> {
+    points.iter().map(|&x| i32::try_from(x).ok()).collect()
+}
 
 pub struct InvalidTileNumberError;
 pub struct InvalidYakuFormatError;
@@ -11,7 +31,7 @@ pub struct InvalidExtraRyuukyokuReasonError;
 
 /// Represents a tile.
 ///
-/// ```
+/// ```text
 /// 11...19 萬子
 /// 21...29 筒子
 /// 31...39 索子
@@ -121,8 +141,13 @@ pub enum ExtraRyuukyokuReason {
     NagashiMangan,
     TenpaiEverybody,
     TenpaiNobody,
+    /// Not in tenhou's logs. The conversion writes it for a round aborted without a win or draw.
+    Chuudan,
 }
 
+// Name of `Yaku::Unknown`, followed by the ID.
+const UNKNOWN_YAKU_NAME: &str = "不明役";
+
 const YAKU_NAME: [&str; 55] = [
     // 一飜
     "門前清自摸和",
@@ -189,8 +214,7 @@ const YAKU_NAME: [&str; 55] = [
 ];
 
 /// Represents a Yaku (winning hand combination).
-#[repr(u8)]
-#[derive(Debug, Default, PartialEq, Clone, Copy, FromPrimitive)]
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
 pub enum Yaku {
     #[default]
     MenzenTsumo,
@@ -248,6 +272,8 @@ pub enum Yaku {
     Dora,
     UraDora,
     AkaDora,
+    /// Yaku unknown to this version, with the ID in mjlog.
+    Unknown(u8),
 }
 
 /// Represents information at the end of a round.
@@ -258,7 +284,7 @@ pub enum RoundResult {
 }
 
 /// Represents the rules for the entire match.
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, PartialEq, Clone)]
 pub struct Rule {
     pub disp: String,
     pub aka53: bool,
@@ -283,7 +309,7 @@ pub struct Round {
 }
 
 /// Reconnection and disconnection information.
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, PartialEq, Clone)]
 pub struct Connection {
     pub what: u8,
 
@@ -314,6 +340,13 @@ pub struct TenhouJson {
     pub names: Vec<String>,
 }
 
+impl TenhouJson {
+    /// `final_results` in tenths of a point, for exact comparison.
+    pub fn final_results_tenths(&self) -> Vec<ResultTenths> {
+        self.final_results.iter().map(|&x| result_to_tenths(x)).collect()
+    }
+}
+
 impl Tile {
     pub fn from_u8(x: u8) -> Result<Self, InvalidTileNumberError> {
         if is_valid_tile(x) {
@@ -348,6 +381,64 @@ impl Tile {
             _ => *self,
         }
     }
+
+    /// The dora tile indicated by this tile, as a normal (not red) tile.
+    ///
+    /// 9 → 1 for number tiles, 北 → 東 for winds, and 中 → 白 for dragons. For three-player games, see `dora_from_indicator_with`.
+    pub fn dora_from_indicator(&self) -> Tile {
+        self.dora_from_indicator_with(false)
+    }
+
+    /// Same as `dora_from_indicator`. In three-player games, which have no 2m to 8m, 1m indicates 9m.
+    pub fn dora_from_indicator_with(&self, sanma: bool) -> Tile {
+        match self.to_black().0 {
+            11 if sanma => Tile(19),
+            x @ (19 | 29 | 39) => Tile(x - 8),
+            44 => Tile(41),
+            47 => Tile(45),
+            x => Tile(x + 1),
+        }
+    }
+
+    /// Sort key of the tile in a displayed hand. Normal and red fives of a suit are next to each other.
+    pub fn display_order(&self, red_five: RedFiveOrder) -> u32 {
+        let key = self.to_black().0 as u32 * 10;
+        match (self.is_red(), red_five) {
+            (true, RedFiveOrder::AfterNormal) => key + 1,
+            (true, RedFiveOrder::BeforeNormal) => key - 1,
+            (false, _) => key,
+        }
+    }
+}
+
+/// Position of red fives in a sorted hand.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum RedFiveOrder {
+    /// 5m 0m 6m, as tenhou displays the initial hands.
+    #[default]
+    AfterNormal,
+    /// 0m 5m 6m.
+    BeforeNormal,
+}
+
+/// Sorts the tiles as tenhou displays a hand: manzu, pinzu, souzu and honors, with red fives after normal fives.
+pub fn sort_hand_display(tiles: &mut [Tile]) {
+    sort_hand_display_with(tiles, RedFiveOrder::default());
+}
+
+/// Same as `sort_hand_display`, with the position of red fives.
+pub fn sort_hand_display_with(tiles: &mut [Tile], red_five: RedFiveOrder) {
+    tiles.sort_by_key(|x| x.display_order(red_five));
+}
+
+/// Number of dora in the tiles, counted once per indicator. Red fives count as normal fives; aka dora are not included.
+pub fn count_dora(hand: &[Tile], indicators: &[Tile]) -> u8 {
+    count_dora_with(hand, indicators, false)
+}
+
+/// Same as `count_dora`, with the dora of three-player games. See `Tile::dora_from_indicator_with`.
+pub fn count_dora_with(hand: &[Tile], indicators: &[Tile], sanma: bool) -> u8 {
+    indicators.iter().map(|i| i.dora_from_indicator_with(sanma)).map(|d| hand.iter().filter(|t| t.to_black() == d).count() as u8).sum()
 }
 
 impl YakuLevel {
@@ -360,8 +451,73 @@ impl YakuLevel {
 }
 
 impl Yaku {
+    /// Known yaku in the order of the IDs in mjlog.
+    pub const ALL: [Yaku; 55] = [
+        Yaku::MenzenTsumo,
+        Yaku::Riichi,
+        Yaku::Ippatsu,
+        Yaku::Chankan,
+        Yaku::Rinshankaihou,
+        Yaku::HaiteiTsumo,
+        Yaku::HouteiRon,
+        Yaku::Pinfu,
+        Yaku::Tanyao,
+        Yaku::Iipeikou,
+        Yaku::PlayerWindTon,
+        Yaku::PlayerWindNan,
+        Yaku::PlayerWindSha,
+        Yaku::PlayerWindPei,
+        Yaku::FieldWindTon,
+        Yaku::FieldWindNan,
+        Yaku::FieldWindSha,
+        Yaku::FieldWindPei,
+        Yaku::YakuhaiHaku,
+        Yaku::YakuhaiHatsu,
+        Yaku::YakuhaiChun,
+        Yaku::DoubleRiichi,
+        Yaku::Chiitoitsu,
+        Yaku::Chanta,
+        Yaku::Ikkitsuukan,
+        Yaku::SansyokuDoujun,
+        Yaku::SanshokuDoukou,
+        Yaku::Sankantsu,
+        Yaku::Toitoi,
+        Yaku::Sanannkou,
+        Yaku::Shousangen,
+        Yaku::Honroutou,
+        Yaku::Ryanpeikou,
+        Yaku::Junchan,
+        Yaku::Honiisou,
+        Yaku::Chiniisou,
+        Yaku::Renhou,
+        Yaku::Tenhou,
+        Yaku::Chiihou,
+        Yaku::Daisangen,
+        Yaku::Suuankou,
+        Yaku::SuuankouTanki,
+        Yaku::Tsuuiisou,
+        Yaku::Ryuuiisou,
+        Yaku::Chinroutou,
+        Yaku::Tyuurenpoutou,
+        Yaku::Tyuurenpoutou9,
+        Yaku::Kokushimusou,
+        Yaku::Kokushimusou13,
+        Yaku::Daisuushii,
+        Yaku::Syousuushii,
+        Yaku::Suukantsu,
+        Yaku::Dora,
+        Yaku::UraDora,
+        Yaku::AkaDora,
+    ];
+
+    /// Position in `Yaku::ALL`, or `None` for `Unknown`.
+    pub fn index(&self) -> Option<usize> {
+        Yaku::ALL.iter().position(|x| x == self)
+    }
+
+    /// Japanese name. `Unknown` is "不明役" without the ID, which `Display` adds.
     pub fn to_str(&self) -> &str {
-        YAKU_NAME[*self as usize]
+        self.index().map_or(UNKNOWN_YAKU_NAME, |i| YAKU_NAME[i])
     }
 }
 
@@ -377,13 +533,17 @@ impl ExtraRyuukyokuReason {
             ExtraRyuukyokuReason::NagashiMangan => "流し満貫",
             ExtraRyuukyokuReason::TenpaiEverybody => "全員聴牌",
             ExtraRyuukyokuReason::TenpaiNobody => "全員不聴",
+            ExtraRyuukyokuReason::Chuudan => "中断",
         }
     }
 }
 
 impl fmt::Display for Yaku {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str(self.to_str())
+        match self {
+            Yaku::Unknown(x) => write!(f, "{}{}", UNKNOWN_YAKU_NAME, x),
+            _ => f.write_str(self.to_str()),
+        }
     }
 }
 
@@ -407,7 +567,9 @@ impl std::str::FromStr for Yaku {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if let Some(pos) = YAKU_NAME.iter().position(|name| *name == s) {
-            Ok(Yaku::from_u8(pos as u8).unwrap())
+            Ok(Yaku::ALL[pos])
+        } else if let Some(id) = s.strip_prefix(UNKNOWN_YAKU_NAME).and_then(|x| x.parse().ok()) {
+            Ok(Yaku::Unknown(id))
         } else {
             Err(InvalidYakuFormatError)
         }
@@ -454,6 +616,7 @@ impl std::str::FromStr for ExtraRyuukyokuReason {
             "流し満貫" => Ok(ExtraRyuukyokuReason::NagashiMangan),
             "全員聴牌" => Ok(ExtraRyuukyokuReason::TenpaiEverybody),
             "全員不聴" => Ok(ExtraRyuukyokuReason::TenpaiNobody),
+            "中断" => Ok(ExtraRyuukyokuReason::Chuudan),
             _ => Err(InvalidExtraRyuukyokuReasonError),
         }
     }
@@ -478,3 +641,47 @@ fn is_valid_tile(x: u8) -> bool {
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::model::*;
+
+    #[test]
+    fn test_sort_hand_display() {
+        let tiles = |xs: &[u8]| xs.iter().map(|&x| Tile::from_u8(x).ok().unwrap()).collect::<Vec<_>>();
+        let mut hand = tiles(&[41, 16, 51, 15, 11, 52, 25]);
+        sort_hand_display(&mut hand);
+        assert_eq!(hand, tiles(&[11, 15, 51, 16, 25, 52, 41]));
+        sort_hand_display_with(&mut hand, RedFiveOrder::BeforeNormal);
+        assert_eq!(hand, tiles(&[11, 51, 15, 16, 52, 25, 41]));
+    }
+
+    #[test]
+    fn test_count_dora() {
+        let t = |x| Tile::from_u8(x).ok().unwrap();
+        let tiles = |xs: &[u8]| xs.iter().map(|&x| t(x)).collect::<Vec<_>>();
+        assert_eq!(t(19).dora_from_indicator(), t(11));
+        assert_eq!(t(44).dora_from_indicator(), t(41));
+        assert_eq!(t(47).dora_from_indicator(), t(45));
+        assert_eq!(t(52).dora_from_indicator(), t(26));
+        assert_eq!(count_dora(&tiles(&[14, 51, 15, 15, 16]), &tiles(&[14, 14])), 6);
+        assert_eq!(count_dora(&tiles(&[11, 45, 45, 45]), &tiles(&[19, 47])), 4);
+
+        // 1m indicates 9m in three-player games, and 2m otherwise.
+        assert_eq!(t(11).dora_from_indicator(), t(12));
+        assert_eq!(t(11).dora_from_indicator_with(true), t(19));
+        assert_eq!(t(19).dora_from_indicator_with(true), t(11));
+        assert_eq!(t(29).dora_from_indicator_with(true), t(21));
+        assert_eq!(count_dora_with(&tiles(&[19, 19, 12]), &tiles(&[11]), true), 2);
+        assert_eq!(count_dora(&tiles(&[19, 19, 12]), &tiles(&[11])), 1);
+    }
+
+    #[test]
+    fn test_yaku_pair_str() {
+        for s in ["立直(1飜)", "大三元(役満)", "不明役60(2飜)"] {
+            assert_eq!(s.parse::<YakuPair>().ok().unwrap().to_string(), s);
+        }
+        assert_eq!("不明役60(2飜)".parse::<YakuPair>().ok().unwrap().yaku, Yaku::Unknown(60));
+        assert!("不明役(2飜)".parse::<YakuPair>().is_err());
+    }
+}
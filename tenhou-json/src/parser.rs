@@ -1,5 +1,6 @@
 use crate::model::*;
 use crate::score::*;
+use serde::de::{Deserialize, Deserializer, Error as _};
 use serde_json::value::Index;
 use serde_json::Value;
 use std::str::FromStr;
@@ -56,6 +57,10 @@ pub enum TenhouJsonErrorKind {
     InvalidAgariFormat,
     #[error("Invalid letter position")]
     InvalidLetterPosition,
+    #[error("Cannot write json")]
+    JsonWriteError,
+    #[error("Integer out of range")]
+    IntegerOutOfRange,
 }
 
 trait WithContext {
@@ -79,20 +84,25 @@ fn conv_i64(v: &Value) -> TenhouJsonResult<i64> {
     v.as_i64().ok_or_else(|| TenhouJsonError::new(TenhouJsonErrorKind::TypeMismatch))
 }
 
-fn conv_i32(v: &Value) -> TenhouJsonResult<i32> {
-    Ok(conv_i64(v)? as i32)
+// Out-of-range values are errors instead of being truncated.
+fn conv_int<T: TryFrom<i64>>(v: &Value) -> TenhouJsonResult<T> {
+    T::try_from(conv_i64(v)?).map_err(|_| TenhouJsonError::new(TenhouJsonErrorKind::IntegerOutOfRange))
+}
+
+fn conv_game_point(v: &Value) -> TenhouJsonResult<GamePoint> {
+    conv_int(v)
 }
 
 fn conv_i8(v: &Value) -> TenhouJsonResult<i8> {
-    Ok(conv_i64(v)? as i8)
+    conv_int(v)
 }
 
 fn conv_u32(v: &Value) -> TenhouJsonResult<u32> {
-    Ok(conv_i64(v)? as u32)
+    conv_int(v)
 }
 
 fn conv_u8(v: &Value) -> TenhouJsonResult<u8> {
-    Ok(conv_i64(v)? as u8)
+    conv_int(v)
 }
 
 fn conv_f64(v: &Value) -> TenhouJsonResult<f64> {
@@ -274,16 +284,16 @@ fn conv_outgoing_tiles(v: &Value) -> TenhouJsonResult<Vec<OutgoingTile>> {
 }
 
 fn conv_round_setting(vs: &[Value]) -> TenhouJsonResult<RoundSettings> {
-    let h1 = conv_i32_array(&vs[0])?;
+    let h1 = conv_u8_array(&vs[0])?;
     if h1.len() != 3 {
         return Err(TenhouJsonError::new(TenhouJsonErrorKind::InvalidArrayLength));
     }
 
     Ok(RoundSettings {
-        kyoku: h1[0] as u8,
-        honba: h1[1] as u8,
-        kyoutaku: h1[2] as u8,
-        points: conv_i32_array(&vs[1])?,
+        kyoku: h1[0],
+        honba: h1[1],
+        kyoutaku: h1[2],
+        points: conv_game_point_array(&vs[1])?,
         dora: conv_tiles(&vs[2])?,
         ura_dora: conv_tiles(&vs[3])?,
     })
@@ -326,7 +336,7 @@ fn conv_agari(chunk0: &Value, chunk1: &Value) -> TenhouJsonResult<Agari> {
     }
 
     Ok(Agari {
-        delta_points: conv_i32_array(chunk0)?,
+        delta_points: conv_game_point_array(chunk0)?,
         who: conv_u8(&xs[0])?,
         from_who: conv_u8(&xs[1])?,
         pao_who: conv_u8(&xs[2])?,
@@ -352,7 +362,7 @@ fn conv_round_result(v: &Value) -> TenhouJsonResult<RoundResult> {
             // If the score changes due to double riichi, will the nine tiles affect delta_points?
             Ok(RoundResult::Ryuukyoku {
                 reason: conv_extra_ryuukyoku_reason(x)?,
-                delta_points: if xs.len() >= 2 { conv_i32_array(&xs[1])? } else { vec![] },
+                delta_points: if xs.len() >= 2 { conv_game_point_array(&xs[1])? } else { vec![] },
             })
         }
     }
@@ -386,7 +396,7 @@ fn conv_tenhou_json(v: &Value) -> TenhouJsonResult<TenhouJson> {
     let sc = get_field(v, "sc")?;
     let sc_array = conv_array(sc)?;
     let (even_sc, odd_sc) = get_partition_even_odd(sc_array);
-    let final_points = even_sc.iter().map(conv_i32).collect::<TenhouJsonResult<Vec<i32>>>()?;
+    let final_points = even_sc.iter().map(conv_game_point).collect::<TenhouJsonResult<Vec<GamePoint>>>()?;
     let final_results = odd_sc.iter().map(conv_f64).collect::<TenhouJsonResult<Vec<f64>>>()?;
 
     Ok(TenhouJson {
@@ -414,8 +424,12 @@ fn conv_f64_array(v: &Value) -> TenhouJsonResult<Vec<f64>> {
     conv_array(v)?.iter().enumerate().map(|(i, x)| conv_f64(x).index_context(i)).collect()
 }
 
-fn conv_i32_array(v: &Value) -> TenhouJsonResult<Vec<i32>> {
-    conv_array(v)?.iter().enumerate().map(|(i, x)| conv_i32(x).index_context(i)).collect()
+fn conv_u8_array(v: &Value) -> TenhouJsonResult<Vec<u8>> {
+    conv_array(v)?.iter().enumerate().map(|(i, x)| conv_u8(x).index_context(i)).collect()
+}
+
+fn conv_game_point_array(v: &Value) -> TenhouJsonResult<Vec<GamePoint>> {
+    conv_array(v)?.iter().enumerate().map(|(i, x)| conv_game_point(x).index_context(i)).collect()
 }
 
 fn conv_round_array(v: &Value) -> TenhouJsonResult<Vec<Round>> {
@@ -491,3 +505,52 @@ pub fn parse_tenhou_json(text: &str) -> TenhouJsonResult<TenhouJson> {
     let json: Value = serde_json::from_str(text).map_err(|_| TenhouJsonError::new(TenhouJsonErrorKind::JsonParseError))?;
     conv_tenhou_json(&json)
 }
+
+// Deserializes through `Value`, so that the wire format is the same as `parse_tenhou_json`.
+// This needs a self-describing format such as JSON or CBOR, because tiles and calls share an array.
+fn deserialize_with<'de, D: Deserializer<'de>, T>(deserializer: D, conv: fn(&Value) -> TenhouJsonResult<T>) -> Result<T, D::Error> {
+    let v = Value::deserialize(deserializer)?;
+    conv(&v).map_err(D::Error::custom)
+}
+
+impl<'de> Deserialize<'de> for Tile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_with(deserializer, conv_tile)
+    }
+}
+
+impl<'de> Deserialize<'de> for IncomingTile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_with(deserializer, conv_incoming_tile)
+    }
+}
+
+impl<'de> Deserialize<'de> for OutgoingTile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_with(deserializer, conv_outgoing_tile)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_with(deserializer, conv_rule)
+    }
+}
+
+impl<'de> Deserialize<'de> for Round {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_with(deserializer, conv_round)
+    }
+}
+
+impl<'de> Deserialize<'de> for Connection {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_with(deserializer, conv_connection)
+    }
+}
+
+impl<'de> Deserialize<'de> for TenhouJson {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_with(deserializer, conv_tenhou_json)
+    }
+}
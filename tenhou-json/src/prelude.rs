@@ -0,0 +1,20 @@
+//! # prelude
+//!
+//! The stable public API of this crate.
+//!
+//! Items exported here follow semantic versioning. Everything else, including the score tables
+//! in `calc`, may change in minor versions.
+//!
+//! ```ignore
+//! use tenhou_json::prelude::*;
+//! ```
+
+pub use crate::exporter::{export_tenhou_json, export_tenhou_json_to, export_tenhou_json_to_with, export_tenhou_json_with, ExportOptions, Locale};
+pub use crate::model::{
+    count_dora, count_dora_with, narrow_points, result_to_tenths, sort_hand_display, sort_hand_display_with, tenths_to_result, Agari, Connection, Direction, ExtraRyuukyokuReason, GamePoint, IncomingTile, InvalidExtraRyuukyokuReasonError,
+    InvalidTileNumberError, InvalidYakuFormatError, OutgoingTile, RedFiveOrder, ResultTenths, Round, RoundPlayer, RoundResult, RoundSettings, Rule, TenhouJson, Tile, Yaku, YakuLevel, YakuPair,
+};
+pub use crate::parser::{parse_tenhou_json, TenhouJsonError, TenhouJsonErrorKind, TenhouJsonResult};
+pub use crate::score::{InvalidRankedScoreError, RankedScore, Score, ScoreRank};
+pub use crate::shanten::{shanten, shanten_chiitoitsu, shanten_kokushi, shanten_standard, ukeire, ukeire_with_visible};
+pub use crate::transform::{permute_suits, rotate_seats, InvalidSuitPermutationError};
@@ -39,6 +39,18 @@ pub struct RankedScore {
     pub score: Score,
 }
 
+impl Score {
+    /// Points the winner receives from the other players, without honba and kyoutaku. Three-player tsumo has one payer less.
+    pub fn total(&self, num_players: u8) -> i32 {
+        let payers = num_players as i32 - 1;
+        match *self {
+            Score::OyaTsumo(x) => x * payers,
+            Score::KoTsumo(ko, oya) => ko * (payers - 1) + oya,
+            Score::Ron(x) => x,
+        }
+    }
+}
+
 impl fmt::Display for ScoreRank {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -250,4 +262,15 @@ mod tests {
         );
         assert_eq!(parse_exact_ranked_score("30符3飜2000点∀ "), None);
     }
+
+    #[test]
+    fn test_sanma_score() {
+        // Without tsumo loss, the share of the missing player makes the payments larger in the same format.
+        let x = parse_exact_ranked_score("満貫3000-5000点").unwrap();
+        assert_eq!(x.to_string(), "満貫3000-5000点");
+        assert_eq!(x.score.total(3), 8000);
+        assert_eq!(Score::KoTsumo(2000, 4000).total(3), 6000);
+        assert_eq!(Score::KoTsumo(2000, 4000).total(4), 8000);
+        assert_eq!(Score::OyaTsumo(4000).total(3), 8000);
+    }
 }
@@ -0,0 +1,252 @@
+//! # shanten
+//!
+//! Shanten number and tile acceptance (ukeire) of a concealed hand.
+//!
+//! Hands are given as the concealed tiles only. The number of called melds is derived from the number of tiles,
+//! so a hand of 10 tiles is treated as 1 meld called. Red fives are counted as normal fives.
+//!
+//! # Usage
+//!
+//! ```ignore
+//! let hand: Vec<Tile> = ...; // 13 tiles
+//! let s = shanten(&hand);    // -1: complete, 0: tenpai, 1: iishanten, ...
+//! let tiles = ukeire(&hand); // [(tile, remaining count), ...]
+//! ```
+
+use crate::model::*;
+
+/// Number of tile kinds. 0..9 萬子, 9..18 筒子, 18..27 索子, 27..34 字牌.
+pub const NUM_KINDS: usize = 34;
+
+/// Counts of each tile kind.
+pub type TileCounts = [u8; NUM_KINDS];
+
+/// Kind index of the tile (0..34). Red fives are the same kind as normal fives.
+pub fn tile_to_kind(t: Tile) -> usize {
+    let x = t.to_black().to_u8();
+    (x / 10 - 1) as usize * 9 + (x % 10 - 1) as usize
+}
+
+/// The normal (not red) tile of the kind index.
+pub fn kind_to_tile(kind: usize) -> Tile {
+    Tile::from_u8((kind / 9 + 1) as u8 * 10 + (kind % 9) as u8 + 1).ok().expect("kind index out of range")
+}
+
+pub fn tile_counts(hand: &[Tile]) -> TileCounts {
+    let mut counts = [0; NUM_KINDS];
+    for &t in hand {
+        counts[tile_to_kind(t)] += 1;
+    }
+    counts
+}
+
+fn is_suited(kind: usize) -> bool {
+    kind < 27
+}
+
+fn is_yaochuu(kind: usize) -> bool {
+    !is_suited(kind) || matches!(kind % 9, 0 | 8)
+}
+
+fn num_tiles(counts: &TileCounts) -> usize {
+    counts.iter().map(|&x| x as usize).sum()
+}
+
+// Maximum of 2 * mentsu + taatsu, where the blocks are limited to `limit`.
+fn search_blocks(counts: &mut TileCounts, kind: usize, mentsu: u8, taatsu: u8, limit: u8, best: &mut u8) {
+    let Some(i) = (kind..NUM_KINDS).find(|&i| counts[i] > 0) else {
+        let mentsu = mentsu.min(limit);
+        *best = (*best).max(2 * mentsu + taatsu.min(limit - mentsu));
+        return;
+    };
+
+    let seq = is_suited(i) && i % 9 <= 6;
+    let side = is_suited(i) && i % 9 <= 7;
+
+    if counts[i] >= 3 {
+        counts[i] -= 3;
+        search_blocks(counts, i, mentsu + 1, taatsu, limit, best);
+        counts[i] += 3;
+    }
+    if seq && counts[i + 1] > 0 && counts[i + 2] > 0 {
+        counts[i] -= 1;
+        counts[i + 1] -= 1;
+        counts[i + 2] -= 1;
+        search_blocks(counts, i, mentsu + 1, taatsu, limit, best);
+        counts[i] += 1;
+        counts[i + 1] += 1;
+        counts[i + 2] += 1;
+    }
+    if counts[i] >= 2 {
+        counts[i] -= 2;
+        search_blocks(counts, i, mentsu, taatsu + 1, limit, best);
+        counts[i] += 2;
+    }
+    if side && counts[i + 1] > 0 {
+        counts[i] -= 1;
+        counts[i + 1] -= 1;
+        search_blocks(counts, i, mentsu, taatsu + 1, limit, best);
+        counts[i] += 1;
+        counts[i + 1] += 1;
+    }
+    if seq && counts[i + 2] > 0 {
+        counts[i] -= 1;
+        counts[i + 2] -= 1;
+        search_blocks(counts, i, mentsu, taatsu + 1, limit, best);
+        counts[i] += 1;
+        counts[i + 2] += 1;
+    }
+
+    // Leave the tile isolated.
+    counts[i] -= 1;
+    search_blocks(counts, i, mentsu, taatsu, limit, best);
+    counts[i] += 1;
+}
+
+/// Shanten number of the standard form (4 melds and a pair).
+pub fn shanten_standard_counts(counts: &TileCounts) -> i8 {
+    let melds = (14usize.saturating_sub(num_tiles(counts)) / 3).min(4) as u8;
+    let limit = 4 - melds;
+    let mut counts = *counts;
+
+    let mut best = 0;
+    search_blocks(&mut counts, 0, 0, 0, limit, &mut best);
+    let mut shanten = 8 - 2 * melds as i8 - best as i8;
+
+    for i in 0..NUM_KINDS {
+        if counts[i] >= 2 {
+            counts[i] -= 2;
+            let mut best = 0;
+            search_blocks(&mut counts, 0, 0, 0, limit, &mut best);
+            shanten = shanten.min(7 - 2 * melds as i8 - best as i8);
+            counts[i] += 2;
+        }
+    }
+    shanten
+}
+
+/// Shanten number of chiitoitsu. `None` if the hand has called melds.
+pub fn shanten_chiitoitsu_counts(counts: &TileCounts) -> Option<i8> {
+    if num_tiles(counts) < 13 {
+        return None;
+    }
+    let pairs = counts.iter().filter(|&&x| x >= 2).count() as i8;
+    let kinds = counts.iter().filter(|&&x| x >= 1).count() as i8;
+    Some(6 - pairs + (7 - kinds).max(0))
+}
+
+/// Shanten number of kokushi musou. `None` if the hand has called melds.
+pub fn shanten_kokushi_counts(counts: &TileCounts) -> Option<i8> {
+    if num_tiles(counts) < 13 {
+        return None;
+    }
+    let yaochuu = || (0..NUM_KINDS).filter(|&i| is_yaochuu(i));
+    let kinds = yaochuu().filter(|&i| counts[i] >= 1).count() as i8;
+    let pair = yaochuu().any(|i| counts[i] >= 2) as i8;
+    Some(13 - kinds - pair)
+}
+
+/// Minimum shanten number of the standard form, chiitoitsu and kokushi musou.
+pub fn shanten_counts(counts: &TileCounts) -> i8 {
+    let standard = shanten_standard_counts(counts);
+    [shanten_chiitoitsu_counts(counts), shanten_kokushi_counts(counts)].into_iter().flatten().fold(standard, i8::min)
+}
+
+pub fn shanten_standard(hand: &[Tile]) -> i8 {
+    shanten_standard_counts(&tile_counts(hand))
+}
+
+pub fn shanten_chiitoitsu(hand: &[Tile]) -> Option<i8> {
+    shanten_chiitoitsu_counts(&tile_counts(hand))
+}
+
+pub fn shanten_kokushi(hand: &[Tile]) -> Option<i8> {
+    shanten_kokushi_counts(&tile_counts(hand))
+}
+
+/// Minimum shanten number of the standard form, chiitoitsu and kokushi musou.
+///
+/// -1 is a complete hand, 0 is tenpai.
+pub fn shanten(hand: &[Tile]) -> i8 {
+    shanten_counts(&tile_counts(hand))
+}
+
+/// Tiles that reduce the shanten number, with the number of the tiles not in the hand.
+///
+/// The hand should be waiting for a tile (13, 10, 7, 4 or 1 tiles). Tiles visible elsewhere (discards, dora indicators)
+/// are not subtracted; use `ukeire_with_visible` for that.
+pub fn ukeire(hand: &[Tile]) -> Vec<(Tile, u8)> {
+    ukeire_with_visible(hand, &[])
+}
+
+/// Same as `ukeire`, but also subtracts the tiles visible outside the hand.
+pub fn ukeire_with_visible(hand: &[Tile], visible: &[Tile]) -> Vec<(Tile, u8)> {
+    let mut counts = tile_counts(hand);
+    let seen = tile_counts(visible);
+    let current = shanten_counts(&counts);
+
+    let mut ret = vec![];
+    for i in 0..NUM_KINDS {
+        let remaining = 4u8.saturating_sub(counts[i] + seen[i]);
+        if counts[i] >= 4 {
+            continue;
+        }
+        counts[i] += 1;
+        if shanten_counts(&counts) < current && remaining > 0 {
+            ret.push((kind_to_tile(i), remaining));
+        }
+        counts[i] -= 1;
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shanten::*;
+
+    // "123m456p789s11z" style notation.
+    fn hand(s: &str) -> Vec<Tile> {
+        let mut ret = vec![];
+        let mut digits = vec![];
+        for c in s.chars() {
+            match c {
+                '0'..='9' => digits.push(c as u8 - b'0'),
+                _ => {
+                    let suit = match c {
+                        'm' => 1,
+                        'p' => 2,
+                        's' => 3,
+                        _ => 4,
+                    };
+                    for d in digits.drain(..) {
+                        let code = if d == 0 { 50 + suit } else { suit * 10 + d };
+                        ret.push(Tile::from_u8(code).ok().unwrap());
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    #[test]
+    fn test_shanten() {
+        assert_eq!(shanten(&hand("123m456p789s11122z")), -1);
+        assert_eq!(shanten(&hand("123m456p789s1112z")), 0);
+        assert_eq!(shanten(&hand("123m406p789s1112z")), 0);
+        assert_eq!(shanten(&hand("113355779m1122z")), 0);
+        assert_eq!(shanten(&hand("19m19p19s1234567z")), 0);
+        assert_eq!(shanten(&hand("147m258p369s1234z")), 6);
+        assert_eq!(shanten(&hand("123m456p7z")), 0);
+        assert_eq!(shanten_standard(&hand("147m258p369s1234z")), 8);
+        assert_eq!(shanten_chiitoitsu(&hand("123m456p7z")), None);
+    }
+
+    #[test]
+    fn test_ukeire() {
+        // 23456m waits on 1-4-7m, with 11z pair.
+        let tiles = ukeire(&hand("23456m456p789s11z"));
+        assert_eq!(tiles, vec![(Tile::from_u8(11).ok().unwrap(), 4), (Tile::from_u8(14).ok().unwrap(), 3), (Tile::from_u8(17).ok().unwrap(), 4)]);
+        let tiles = ukeire_with_visible(&hand("23456m456p789s11z"), &hand("44m"));
+        assert_eq!(tiles[1], (Tile::from_u8(14).ok().unwrap(), 1));
+    }
+}
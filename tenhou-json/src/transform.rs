@@ -0,0 +1,233 @@
+//! # transform
+//!
+//! Transforms of a whole game that keep it valid, for data augmentation and normalization.
+
+use crate::model::*;
+use thiserror::Error;
+
+// Number of seats actually used. Three-player games leave the 4th seat empty.
+fn num_seats(tenhou_json: &TenhouJson) -> usize {
+    let fourth_empty = tenhou_json.rounds.iter().all(|r| r.players.get(3).is_none_or(|p| p.hand.is_empty()));
+    if !tenhou_json.rounds.is_empty() && fourth_empty {
+        3
+    } else {
+        4
+    }
+}
+
+fn rotate_vec<T>(v: &mut [T], n: usize, first: usize) {
+    if v.len() >= n {
+        v[..n].rotate_left(first);
+    }
+}
+
+/// Rotates the seats so that the seat `first` becomes seat 0.
+///
+/// Every per-seat array and every seat number (who, from_who, pao_who, connections) are moved together,
+/// and `kyoku` is rewritten so that the dealer stays at `kyoku % 4`. The round wind is kept,
+/// so East 1 with the dealer moved to seat 3 becomes East 4.
+///
+/// Only rotations are supported. Other permutations would change the turn order and the relative directions of calls.
+pub fn rotate_seats(tenhou_json: &mut TenhouJson, first: u8) {
+    let n = num_seats(tenhou_json);
+    let first = first as usize % n;
+    if first == 0 {
+        return;
+    }
+    let seat = |x: u8| if (x as usize) < n { ((x as usize + n - first) % n) as u8 } else { x };
+
+    for round in &mut tenhou_json.rounds {
+        let kyoku = round.settings.kyoku;
+        round.settings.kyoku = kyoku / 4 * 4 + seat(kyoku % 4);
+        rotate_vec(&mut round.settings.points, n, first);
+        rotate_vec(&mut round.players, n, first);
+        match &mut round.result {
+            RoundResult::Agari { agari_vec } => {
+                for agari in agari_vec {
+                    agari.who = seat(agari.who);
+                    agari.from_who = seat(agari.from_who);
+                    agari.pao_who = seat(agari.pao_who);
+                    rotate_vec(&mut agari.delta_points, n, first);
+                }
+            }
+            RoundResult::Ryuukyoku { delta_points, .. } => rotate_vec(delta_points, n, first),
+        }
+    }
+
+    for c in &mut tenhou_json.connections {
+        c.who = seat(c.who);
+    }
+
+    rotate_vec(&mut tenhou_json.dan, n, first);
+    rotate_vec(&mut tenhou_json.rate, n, first);
+    rotate_vec(&mut tenhou_json.sx, n, first);
+    rotate_vec(&mut tenhou_json.final_points, n, first);
+    rotate_vec(&mut tenhou_json.final_results, n, first);
+    rotate_vec(&mut tenhou_json.names, n, first);
+}
+
+/// Error of `permute_suits`.
+#[derive(Debug, Error)]
+pub enum InvalidSuitPermutationError {
+    #[error("Not a permutation of the three suits: {0:?}")]
+    NotPermutation([u8; 3]),
+    #[error("Three-player games have only 1m and 9m, so manzu cannot be moved to another suit")]
+    Sanma,
+}
+
+fn permute_tile(t: &mut Tile, perm: &[u8; 3]) {
+    let x = t.to_u8();
+    let y = match x {
+        11..=39 => (perm[(x / 10 - 1) as usize] + 1) * 10 + x % 10,
+        51..=53 => 51 + perm[(x - 51) as usize],
+        _ => x,
+    };
+    *t = Tile::from_u8(y).ok().expect("permuted tile is valid");
+}
+
+// Same order as the initial hands of the converter: red five after the normal five.
+fn hand_order(t: &Tile) -> u32 {
+    match t.to_u8() {
+        x @ 51..=53 => (x as u32 - 50) * 100 + 51,
+        x => x as u32 * 10,
+    }
+}
+
+/// Permutes the three number suits of the whole game. `perm[i]` is the new suit of suit `i` (0: 萬子, 1: 筒子, 2: 索子).
+///
+/// Hands, calls, discards, dora and red five rules are moved together. Scores and yaku are kept,
+/// except that ryuuiisou requires 索子; the indices of rounds with ryuuiisou that is no longer valid are returned.
+/// Three-player games can only swap 筒子 and 索子.
+pub fn permute_suits(tenhou_json: &mut TenhouJson, perm: [u8; 3]) -> Result<Vec<usize>, InvalidSuitPermutationError> {
+    let mut sorted = perm;
+    sorted.sort();
+    if sorted != [0, 1, 2] {
+        return Err(InvalidSuitPermutationError::NotPermutation(perm));
+    }
+    if perm[0] != 0 && num_seats(tenhou_json) == 3 {
+        return Err(InvalidSuitPermutationError::Sanma);
+    }
+
+    let mut invalid_rounds = vec![];
+    for (i, round) in tenhou_json.rounds.iter_mut().enumerate() {
+        round.settings.dora.iter_mut().chain(&mut round.settings.ura_dora).for_each(|t| permute_tile(t, &perm));
+
+        for player in &mut round.players {
+            player.hand.iter_mut().for_each(|t| permute_tile(t, &perm));
+            player.hand.sort_by_key(hand_order);
+            for x in &mut player.incoming {
+                match x {
+                    IncomingTile::Tsumo(t) => permute_tile(t, &perm),
+                    IncomingTile::Chii { combination: (a, b, c) } | IncomingTile::Pon { combination: (a, b, c), .. } => [a, b, c].into_iter().for_each(|t| permute_tile(t, &perm)),
+                    IncomingTile::Daiminkan { combination: (a, b, c, d), .. } => [a, b, c, d].into_iter().for_each(|t| permute_tile(t, &perm)),
+                }
+            }
+            for x in &mut player.outgoing {
+                match x {
+                    OutgoingTile::Discard(t) | OutgoingTile::Riichi(t) | OutgoingTile::Ankan(t) => permute_tile(t, &perm),
+                    OutgoingTile::Kakan { combination: (a, b, c), added, .. } => [a, b, c, added].into_iter().for_each(|t| permute_tile(t, &perm)),
+                    OutgoingTile::Tsumogiri | OutgoingTile::TsumogiriRiichi | OutgoingTile::Dummy => {}
+                }
+            }
+        }
+
+        if let RoundResult::Agari { agari_vec } = &round.result {
+            if perm[2] != 2 && agari_vec.iter().any(|a| a.yaku.iter().any(|y| y.yaku == Yaku::Ryuuiisou)) {
+                invalid_rounds.push(i);
+            }
+        }
+    }
+
+    let rule = &mut tenhou_json.rule;
+    let aka = [rule.aka51, rule.aka52, rule.aka53];
+    let mut permuted = [false; 3];
+    for (i, &x) in aka.iter().enumerate() {
+        permuted[perm[i] as usize] = x;
+    }
+    [rule.aka51, rule.aka52, rule.aka53] = permuted;
+
+    Ok(invalid_rounds)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transform::*;
+
+    fn game() -> TenhouJson {
+        let tile = |x| Tile::from_u8(x).ok().unwrap();
+        TenhouJson {
+            rounds: vec![Round {
+                settings: RoundSettings {
+                    kyoku: 1,
+                    points: vec![250, 240, 260, 250],
+                    ..Default::default()
+                },
+                players: (0..4)
+                    .map(|i| RoundPlayer {
+                        hand: vec![tile(11 + i)],
+                        ..Default::default()
+                    })
+                    .collect(),
+                result: RoundResult::Agari {
+                    agari_vec: vec![Agari {
+                        who: 2,
+                        from_who: 1,
+                        pao_who: 2,
+                        delta_points: vec![0, -1000, 1000, 0],
+                        ..Default::default()
+                    }],
+                },
+            }],
+            connections: vec![Connection { what: 0, log: 0, who: 3, step: 1 }],
+            names: ["A", "B", "C", "D"].map(String::from).to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rotate_seats() {
+        let mut x = game();
+        rotate_seats(&mut x, 1);
+        assert_eq!(x.names, ["B", "C", "D", "A"]);
+        assert_eq!(x.rounds[0].settings.kyoku, 0);
+        assert_eq!(x.rounds[0].settings.points, [240, 260, 250, 250]);
+        assert_eq!(x.rounds[0].players[0].hand, [Tile::from_u8(12).ok().unwrap()]);
+        assert_eq!(x.connections[0].who, 2);
+        match &x.rounds[0].result {
+            RoundResult::Agari { agari_vec } => {
+                assert_eq!((agari_vec[0].who, agari_vec[0].from_who, agari_vec[0].pao_who), (1, 0, 1));
+                assert_eq!(agari_vec[0].delta_points, [-1000, 1000, 0, 0]);
+            }
+            _ => unreachable!(),
+        }
+
+        rotate_seats(&mut x, 3);
+        assert_eq!(x, game());
+    }
+
+    #[test]
+    fn test_permute_suits() {
+        let tile = |x| Tile::from_u8(x).ok().unwrap();
+        let mut x = game();
+        x.rule.aka51 = true;
+        x.rounds[0].settings.dora = vec![tile(51)];
+        x.rounds[0].players[0].hand = vec![tile(15), tile(51), tile(29), tile(41)];
+        assert_eq!(permute_suits(&mut x, [2, 0, 1]).unwrap(), Vec::<usize>::new());
+        assert_eq!(x.rounds[0].settings.dora, [tile(53)]);
+        assert_eq!(x.rounds[0].players[0].hand, [tile(19), tile(35), tile(53), tile(41)]);
+        assert!(!x.rule.aka51 && x.rule.aka53);
+        assert!(matches!(permute_suits(&mut x, [0, 0, 1]), Err(InvalidSuitPermutationError::NotPermutation(_))));
+    }
+
+    #[test]
+    fn test_permute_suits_sanma() {
+        let tile = |x| Tile::from_u8(x).ok().unwrap();
+        let mut x = game();
+        x.rounds[0].players[3].hand.clear();
+        x.rounds[0].players[0].hand = vec![tile(11), tile(21), tile(39)];
+        assert!(matches!(permute_suits(&mut x, [1, 0, 2]), Err(InvalidSuitPermutationError::Sanma)));
+        assert_eq!(x.rounds[0].players[0].hand, [tile(11), tile(21), tile(39)]);
+        assert_eq!(permute_suits(&mut x, [0, 2, 1]).unwrap(), Vec::<usize>::new());
+        assert_eq!(x.rounds[0].players[0].hand, [tile(11), tile(29), tile(31)]);
+    }
+}